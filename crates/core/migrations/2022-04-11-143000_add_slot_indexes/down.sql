@@ -0,0 +1,3 @@
+drop index if exists token_accounts_slot_idx;
+
+drop index if exists twitter_handle_name_services_slot_idx;
\ No newline at end of file
@@ -0,0 +1,5 @@
+create index if not exists token_accounts_slot_idx on
+  token_accounts (slot);
+
+create index if not exists twitter_handle_name_services_slot_idx on
+  twitter_handle_name_services (slot);
\ No newline at end of file
@@ -0,0 +1,3 @@
+drop index if exists attr_trait_type_normalized_value_index;
+
+alter table attributes drop column trait_type_normalized;
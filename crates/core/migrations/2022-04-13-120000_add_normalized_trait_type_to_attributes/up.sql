@@ -0,0 +1,8 @@
+alter table attributes add column trait_type_normalized text null;
+
+update attributes
+set trait_type_normalized = lower(regexp_replace(trait_type, '^\s+|\s+$', '', 'g'))
+where trait_type is not null;
+
+create index if not exists attr_trait_type_normalized_value_index
+on attributes (trait_type_normalized, value);
@@ -0,0 +1 @@
+drop table if exists collection_jsons;
\ No newline at end of file
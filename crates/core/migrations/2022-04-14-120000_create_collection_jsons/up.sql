@@ -0,0 +1,7 @@
+create table if not exists collection_jsons (
+  collection_mint varchar(48) primary key,
+  fetch_uri text not null,
+  name text null,
+  image text null,
+  description text null
+);
\ No newline at end of file
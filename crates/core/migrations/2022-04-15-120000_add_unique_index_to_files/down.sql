@@ -0,0 +1 @@
+drop index if exists files_metadata_address_uri_index;
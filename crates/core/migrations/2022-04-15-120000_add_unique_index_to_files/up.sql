@@ -0,0 +1,8 @@
+delete from files a
+using files b
+where a.metadata_address = b.metadata_address
+  and a.uri = b.uri
+  and a.id < b.id;
+
+create unique index if not exists files_metadata_address_uri_index
+on files (metadata_address, uri);
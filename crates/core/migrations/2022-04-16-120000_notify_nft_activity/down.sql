@@ -0,0 +1,5 @@
+drop trigger if exists bid_receipts_notify_nft_activity on bid_receipts;
+drop trigger if exists purchase_receipts_notify_nft_activity on purchase_receipts;
+drop trigger if exists listing_receipts_notify_nft_activity on listing_receipts;
+
+drop function if exists notify_nft_activity();
@@ -0,0 +1,41 @@
+create or replace function notify_nft_activity() returns trigger as $$
+declare
+  activity_type text;
+  wallets varchar[];
+begin
+  if tg_table_name = 'listing_receipts' then
+    activity_type := 'listing';
+    wallets := array[new.seller];
+  elsif tg_table_name = 'purchase_receipts' then
+    activity_type := 'purchase';
+    wallets := array[new.seller, new.buyer];
+  else
+    activity_type := 'bid';
+    wallets := array[new.buyer];
+  end if;
+
+  perform pg_notify('nft_activity', json_build_object(
+    'address', new.address,
+    'metadata', new.metadata,
+    'auction_house', new.auction_house,
+    'price', new.price,
+    'created_at', new.created_at,
+    'wallets', wallets,
+    'activity_type', activity_type
+  )::text);
+
+  return null;
+end;
+$$ language plpgsql;
+
+create trigger listing_receipts_notify_nft_activity
+after insert on listing_receipts
+for each row execute procedure notify_nft_activity();
+
+create trigger purchase_receipts_notify_nft_activity
+after insert on purchase_receipts
+for each row execute procedure notify_nft_activity();
+
+create trigger bid_receipts_notify_nft_activity
+after insert on bid_receipts
+for each row execute procedure notify_nft_activity();
@@ -0,0 +1 @@
+drop index if exists metadatas_name_trgm_idx;
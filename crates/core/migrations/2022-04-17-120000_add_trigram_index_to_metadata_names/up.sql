@@ -0,0 +1,5 @@
+-- Supports fuzzy NFT name search with pg_trgm's word similarity operator
+create extension if not exists pg_trgm;
+
+create index if not exists metadatas_name_trgm_idx on
+  metadatas using gin (name gin_trgm_ops);
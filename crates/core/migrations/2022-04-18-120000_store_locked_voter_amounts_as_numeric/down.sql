@@ -0,0 +1,6 @@
+-- Fails if any amount has been indexed above the range of a bigint
+alter table escrows
+  alter column amount type bigint using amount::bigint;
+
+alter table lockers
+  alter column locked_supply type bigint using locked_supply::bigint;
@@ -0,0 +1,7 @@
+-- Raw token amounts are u64s and may exceed the range of a bigint.  Existing
+-- values are all non-negative and convert exactly.
+alter table escrows
+  alter column amount type numeric(20, 0) using amount::numeric(20, 0);
+
+alter table lockers
+  alter column locked_supply type numeric(20, 0) using locked_supply::numeric(20, 0);
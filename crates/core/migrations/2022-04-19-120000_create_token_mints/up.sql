@@ -0,0 +1,7 @@
+-- Decimals of fungible SPL token mints, used to display prices in auction
+-- house treasury tokens.  NFT mints (zero decimals and a supply of at most one)
+-- are not indexed.
+create table if not exists token_mints (
+  address varchar(48) primary key,
+  decimals smallint not null
+);
@@ -0,0 +1,30 @@
+create or replace function notify_table_change() returns trigger as $$
+declare
+  row jsonb;
+begin
+  if tg_op = 'DELETE' then
+    row := to_jsonb(old);
+  else
+    row := to_jsonb(new);
+  end if;
+
+  perform pg_notify('table_changes', json_build_object(
+    'table', tg_table_name,
+    'key', row ->> tg_argv[0]
+  )::text);
+
+  return null;
+end;
+$$ language plpgsql;
+
+create trigger listing_receipts_notify_table_change
+after insert or update or delete on listing_receipts
+for each row execute procedure notify_table_change('auction_house');
+
+create trigger purchase_receipts_notify_table_change
+after insert or update or delete on purchase_receipts
+for each row execute procedure notify_table_change('auction_house');
+
+create trigger bid_receipts_notify_table_change
+after insert or update or delete on bid_receipts
+for each row execute procedure notify_table_change('auction_house');
@@ -0,0 +1,14 @@
+-- Trait rarity of NFTs within their collection, as identified by the first
+-- verified creator.  A collection's scores and ranks are recomputed shortly
+-- after the metadata JSON of any of its NFTs is indexed, so they stay current
+-- while the collection is still minting.
+create table if not exists rarity_scores (
+  metadata_address varchar(48) primary key,
+  first_verified_creator varchar(48) not null,
+  score double precision not null,
+  rank bigint not null,
+  updated_at timestamp not null
+);
+
+create index if not exists rarity_scores_first_verified_creator_idx
+on rarity_scores (first_verified_creator);
@@ -0,0 +1,254 @@
+//! Helpers for writing many rows in as few statements as possible
+
+use diesel::QueryResult;
+
+use super::models;
+use crate::prelude::*;
+
+/// The maximum number of bind parameters Postgres accepts in one statement
+pub const MAX_BIND_PARAMS: usize = u16::MAX as usize;
+
+/// A row type that can be written with [`upsert_batch`]
+pub trait BatchRow {
+    /// The number of bind parameters inserting one row uses, i.e. the number
+    /// of columns in its `Insertable` implementation
+    const PARAMS: usize;
+}
+
+macro_rules! batch_rows {
+    ($($ty:ident => $params:literal),* $(,)?) => {
+        $(impl BatchRow for models::$ty<'_> {
+            const PARAMS: usize = $params;
+        })*
+    };
+}
+
+batch_rows! {
+    AuctionHouse => 14,
+    ListingReceipt => 13,
+    Metadata => 12,
+    MetadataAttributeWrite => 5,
+    MetadataJson => 10,
+    PurchaseReceipt => 10,
+}
+
+/// Compute the number of rows with the given number of bind parameters each
+/// that fit in one statement
+fn chunk_size(params_per_row: usize) -> Result<usize> {
+    if params_per_row == 0 || params_per_row > MAX_BIND_PARAMS {
+        bail!(
+            "Invalid parameter count {} per row for batch upsert",
+            params_per_row
+        );
+    }
+
+    Ok(MAX_BIND_PARAMS / params_per_row)
+}
+
+/// Upsert rows in chunks small enough to respect [`MAX_BIND_PARAMS`], returning
+/// the total number of rows affected.
+///
+/// `upsert` should issue a single `INSERT ... ON CONFLICT DO UPDATE` statement
+/// for the chunk it is given.  Because every row in a chunk shares one
+/// statement, the update should be written in terms of
+/// [`excluded`](super::excluded) values rather than a single row's
+/// `AsChangeset`, and no two rows in `rows` may share a conflict target, as
+/// Postgres refuses to update the same row twice in one statement.
+///
+/// Chunks are written in order and are not wrapped in a transaction, so chunks
+/// preceding a failed chunk remain written.
+///
+/// # Errors
+/// This function fails if any chunk fails to upsert, in which case the error
+/// identifies the range of rows in the failed chunk.
+pub fn upsert_batch<R: BatchRow>(
+    conn: &super::Connection,
+    rows: &[R],
+    upsert: impl FnMut(&super::Connection, &[R]) -> QueryResult<usize>,
+) -> Result<usize> {
+    upsert_chunks(conn, rows, chunk_size(R::PARAMS)?, upsert)
+}
+
+fn upsert_chunks<R>(
+    conn: &super::Connection,
+    rows: &[R],
+    chunk_size: usize,
+    mut upsert: impl FnMut(&super::Connection, &[R]) -> QueryResult<usize>,
+) -> Result<usize> {
+    let mut affected = 0;
+
+    for (i, chunk) in rows.chunks(chunk_size).enumerate() {
+        let start = i * chunk_size;
+
+        affected += upsert(conn, chunk).with_context(|| {
+            format!(
+                "Failed to upsert rows {}..{} of batch",
+                start,
+                start + chunk.len()
+            )
+        })?;
+    }
+
+    Ok(affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        excluded, insert_into,
+        models::Metadata,
+        tables::{
+            attributes, auction_houses, listing_receipts, metadata_jsons, metadatas,
+            purchase_receipts,
+        },
+        testing,
+    };
+
+    #[test]
+    fn chunk_sizes() {
+        assert_eq!(chunk_size(1).unwrap(), MAX_BIND_PARAMS);
+        assert_eq!(chunk_size(5).unwrap(), 13_107);
+        assert_eq!(chunk_size(20).unwrap(), 3_276);
+        assert_eq!(chunk_size(MAX_BIND_PARAMS).unwrap(), 1);
+        assert!(chunk_size(0).is_err());
+        assert!(chunk_size(MAX_BIND_PARAMS + 1).is_err());
+
+        for params in [1, 3, 7, 20, 1000] {
+            assert!(chunk_size(params).unwrap() * params <= MAX_BIND_PARAMS);
+        }
+    }
+
+    /// Count the columns named by a rendered single-row `INSERT`
+    fn columns(sql: impl std::fmt::Display) -> usize {
+        let sql = sql.to_string();
+        let (_, rest) = sql.split_once('(').unwrap();
+        let (list, _) = rest.split_once(") VALUES").unwrap();
+
+        list.split(", ").count()
+    }
+
+    fn params<R: BatchRow>(_: &R) -> usize {
+        R::PARAMS
+    }
+
+    macro_rules! assert_params {
+        ($table:path, $row:expr) => {{
+            let row = $row;
+            let sql = debug_query::<Pg, _>(&insert_into($table).values(&row));
+
+            assert_eq!(params(&row), columns(sql), "{}", stringify!($table));
+        }};
+    }
+
+    #[test]
+    fn params_match_insert_columns() {
+        let now = NaiveDateTime::from_timestamp(0, 0);
+
+        assert_params!(auction_houses::table, testing::auction_house("ah", "mint"));
+        assert_params!(
+            listing_receipts::table,
+            testing::listing_receipt("l", "ah", "md", "seller", 1, now)
+        );
+        assert_params!(metadatas::table, testing::metadata("md", "mint", false));
+        assert_params!(attributes::table, models::MetadataAttributeWrite {
+            metadata_address: Borrowed("md"),
+            value: None,
+            trait_type: None,
+            first_verified_creator: None,
+            trait_type_normalized: None,
+        });
+        assert_params!(metadata_jsons::table, testing::metadata_json("md", "img"));
+        assert_params!(
+            purchase_receipts::table,
+            testing::purchase_receipt("p", "ah", "md", ("seller", "buyer"), 1, now)
+        );
+    }
+
+    fn metas(range: std::ops::Range<usize>, name: &'static str) -> Vec<Metadata<'static>> {
+        range
+            .map(|i| Metadata {
+                address: Owned(format!("batch-md{}", i)),
+                name: Borrowed(name),
+                mint_address: Owned(format!("batch-mint{}", i)),
+                edition_pda: Owned(format!("batch-md{}-edition", i)),
+                ..testing::metadata("", "", false)
+            })
+            .collect()
+    }
+
+    fn upsert(conn: &super::super::Connection, rows: &[Metadata]) -> QueryResult<usize> {
+        insert_into(metadatas::table)
+            .values(rows)
+            .on_conflict(metadatas::address)
+            .do_update()
+            .set(metadatas::name.eq(excluded(metadatas::name)))
+            .execute(conn)
+    }
+
+    #[test]
+    fn overlapping_upsert() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+
+        assert_eq!(
+            upsert_batch(&conn, &metas(0..5000, "v1"), upsert).unwrap(),
+            5000
+        );
+
+        // The second half of these rows overlap the rows written above
+        assert_eq!(
+            upsert_batch(&conn, &metas(2500..7500, "v2"), upsert).unwrap(),
+            5000
+        );
+
+        let counts: Vec<(String, i64)> = metadatas::table
+            .filter(metadatas::address.like("batch-md%"))
+            .group_by(metadatas::name)
+            .select((metadatas::name, diesel::dsl::count_star()))
+            .order(metadatas::name)
+            .load(&conn)
+            .unwrap();
+
+        assert_eq!(counts, vec![("v1".into(), 2500), ("v2".into(), 5000)]);
+    }
+
+    #[test]
+    fn chunks_are_written_in_order() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+
+        let mut chunks = vec![];
+        let affected = upsert_chunks(&conn, &metas(0..5000, "v1"), 2000, |c, rows| {
+            chunks.push(rows.len());
+            upsert(c, rows)
+        })
+        .unwrap();
+
+        assert_eq!(affected, 5000);
+        assert_eq!(chunks, vec![2000, 2000, 1000]);
+    }
+
+    #[test]
+    fn failed_chunks_are_identified() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+
+        // Two rows in one statement may not update the same row
+        let mut rows = metas(0..2, "v1");
+        rows[1].address = Borrowed("batch-md0");
+
+        let err = upsert_batch(&conn, &rows, upsert).unwrap_err();
+
+        assert!(err.to_string().contains("rows 0..2"), "{}", err);
+    }
+}
@@ -1,12 +1,15 @@
-//! Includes `WhitelistMintMode` enum and `EndSettingType` enum
+//! Includes `WhitelistMintMode` enum, `EndSettingType` enum, and `VoteSide` enum,
+//! as well as the `NumericU64` wrapper for amounts stored as `NUMERIC`
 
 use std::io::Write;
 
+use bigdecimal::{BigDecimal, ToPrimitive};
 use diesel::{
     deserialize::{self, FromSql},
     not_none,
     pg::Pg,
     serialize::{self, IsNull, Output, ToSql},
+    sql_types::{Numeric, SmallInt},
     AsExpression, FromSqlRow, SqlType,
 };
 
@@ -38,10 +41,16 @@ impl ToSql<SettingType, Pg> for EndSettingType {
 
 impl FromSql<SettingType, Pg> for EndSettingType {
     fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
-        match not_none!(bytes) {
-            b"date" => Ok(EndSettingType::Date),
-            b"amount" => Ok(EndSettingType::Amount),
-            _ => Err("Unrecognized enum variant".into()),
+        let bytes = not_none!(bytes);
+
+        // The labels of `settingtype` are capitalized, as written by `ToSql`,
+        // but older rows may have been written in lowercase
+        if bytes.eq_ignore_ascii_case(b"date") {
+            Ok(EndSettingType::Date)
+        } else if bytes.eq_ignore_ascii_case(b"amount") {
+            Ok(EndSettingType::Amount)
+        } else {
+            Err("Unrecognized enum variant".into())
         }
     }
 }
@@ -123,3 +132,314 @@ impl FromSql<TokenStandard, Pg> for TokenStandardEnum {
         }
     }
 }
+
+#[derive(Debug, PartialEq, Eq, FromSqlRow, AsExpression, Clone, Copy)]
+#[sql_type = "SmallInt"]
+/// `VoteSide` enum in Tribeca `Vote` accounts, stored as its discriminant
+pub enum VoteSide {
+    /// The vote has not been cast
+    Pending,
+    /// A vote against the proposal
+    Against,
+    /// A vote for the proposal
+    For,
+    /// An abstention
+    Abstain,
+}
+
+impl From<VoteSide> for i16 {
+    fn from(side: VoteSide) -> Self {
+        match side {
+            VoteSide::Pending => 0,
+            VoteSide::Against => 1,
+            VoteSide::For => 2,
+            VoteSide::Abstain => 3,
+        }
+    }
+}
+
+impl From<i16> for VoteSide {
+    /// Map a Tribeca `VoteSide` discriminant, treating unrecognized values as
+    /// a vote which has not yet been cast
+    fn from(side: i16) -> Self {
+        match side {
+            1 => VoteSide::Against,
+            2 => VoteSide::For,
+            3 => VoteSide::Abstain,
+            _ => VoteSide::Pending,
+        }
+    }
+}
+
+impl ToSql<SmallInt, Pg> for VoteSide {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<SmallInt, Pg>::to_sql(&i16::from(*self), out)
+    }
+}
+
+impl FromSql<SmallInt, Pg> for VoteSide {
+    /// Read a stored discriminant, mapping unrecognized values the same way
+    /// as when they were indexed
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        <i16 as FromSql<SmallInt, Pg>>::from_sql(bytes).map(Self::from)
+    }
+}
+
+#[derive(
+    Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, FromSqlRow, AsExpression, Clone, Copy,
+)]
+#[sql_type = "Numeric"]
+/// An unsigned 64-bit token amount stored as a Postgres `NUMERIC`
+///
+/// Raw SPL token amounts are `u64`s, which do not fit in a `bigint` above
+/// [`i64::MAX`].  Columns holding such amounts should be declared as
+/// `numeric(20, 0)`, which holds every `u64` exactly, and read and written
+/// through this type.
+pub struct NumericU64(pub u64);
+
+impl From<u64> for NumericU64 {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<NumericU64> for u64 {
+    fn from(NumericU64(value): NumericU64) -> Self {
+        value
+    }
+}
+
+impl TryFrom<i64> for NumericU64 {
+    type Error = std::num::TryFromIntError;
+
+    /// Convert an amount previously stored as a `bigint`
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        value.try_into().map(Self)
+    }
+}
+
+impl From<NumericU64> for BigDecimal {
+    fn from(NumericU64(value): NumericU64) -> Self {
+        value.into()
+    }
+}
+
+impl TryFrom<&BigDecimal> for NumericU64 {
+    type Error = &'static str;
+
+    /// Convert a decimal, failing if it is fractional, negative, or greater
+    /// than [`u64::MAX`]
+    fn try_from(value: &BigDecimal) -> Result<Self, Self::Error> {
+        if value.with_scale(0) != *value {
+            return Err("Numeric amount is not an integer");
+        }
+
+        value
+            .to_u64()
+            .map(Self)
+            .ok_or("Numeric amount out of range for u64")
+    }
+}
+
+impl ToSql<Numeric, Pg> for NumericU64 {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<Numeric, Pg>::to_sql(&BigDecimal::from(*self), out)
+    }
+}
+
+impl FromSql<Numeric, Pg> for NumericU64 {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let value = <BigDecimal as FromSql<Numeric, Pg>>::from_sql(bytes)?;
+
+        Self::try_from(&value).map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::prelude::*;
+
+    use super::*;
+    use crate::db::testing;
+
+    const VOTE_SIDES: [VoteSide; 4] = [
+        VoteSide::Pending,
+        VoteSide::Against,
+        VoteSide::For,
+        VoteSide::Abstain,
+    ];
+
+    #[test]
+    fn vote_side_discriminants_round_trip() {
+        for side in VOTE_SIDES {
+            assert_eq!(VoteSide::from(i16::from(side)), side);
+
+            let bytes = i16::from(side).to_be_bytes();
+
+            assert_eq!(
+                <VoteSide as FromSql<SmallInt, Pg>>::from_sql(Some(&bytes)).unwrap(),
+                side
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_vote_sides_are_pending() {
+        for raw in [-1_i16, 4, 255] {
+            assert_eq!(VoteSide::from(raw), VoteSide::Pending);
+            assert_eq!(
+                <VoteSide as FromSql<SmallInt, Pg>>::from_sql(Some(&raw.to_be_bytes())).unwrap(),
+                VoteSide::Pending
+            );
+        }
+    }
+
+    #[test]
+    fn vote_sides_round_trip_through_postgres() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+
+        for side in VOTE_SIDES {
+            let read: VoteSide = diesel::select(side.into_sql::<SmallInt>())
+                .get_result(&conn)
+                .unwrap();
+
+            assert_eq!(read, side);
+        }
+
+        let unknown: VoteSide = diesel::select(7_i16.into_sql::<SmallInt>())
+            .get_result(&conn)
+            .unwrap();
+
+        assert_eq!(unknown, VoteSide::Pending);
+    }
+
+    #[test]
+    fn enum_labels_round_trip_through_postgres() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+
+        // Each label written by `ToSql` must be accepted by the Postgres enum
+        // and parse back to the same value through `FromSql`
+        for value in [EndSettingType::Date, EndSettingType::Amount] {
+            let read: EndSettingType = diesel::select(value.into_sql::<SettingType>())
+                .get_result(&conn)
+                .unwrap();
+
+            assert_eq!(read, value);
+        }
+
+        for value in [
+            WhitelistMintMode::BurnEveryTime,
+            WhitelistMintMode::NeverBurn,
+        ] {
+            let read: WhitelistMintMode = diesel::select(value.into_sql::<Mode>())
+                .get_result(&conn)
+                .unwrap();
+
+            assert_eq!(read, value);
+        }
+
+        for value in [
+            TokenStandardEnum::NonFungible,
+            TokenStandardEnum::FungibleAsset,
+            TokenStandardEnum::Fungible,
+            TokenStandardEnum::NonFungibleEdition,
+        ] {
+            let read: TokenStandardEnum = diesel::select(value.into_sql::<TokenStandard>())
+                .get_result(&conn)
+                .unwrap();
+
+            assert_eq!(read, value);
+        }
+    }
+
+    #[test]
+    fn end_setting_labels_ignore_case() {
+        for (label, value) in [
+            (&b"Date"[..], EndSettingType::Date),
+            (b"date", EndSettingType::Date),
+            (b"Amount", EndSettingType::Amount),
+            (b"amount", EndSettingType::Amount),
+        ] {
+            assert_eq!(
+                <EndSettingType as FromSql<SettingType, Pg>>::from_sql(Some(label)).unwrap(),
+                value
+            );
+        }
+
+        assert!(<EndSettingType as FromSql<SettingType, Pg>>::from_sql(Some(b"never")).is_err());
+    }
+
+    /// Audit of the other enum mappings: `WhitelistMintMode` and
+    /// `TokenStandardEnum` read the same labels they write, which match those
+    /// of the `mode` and `token_standard` Postgres enums exactly, so unlike
+    /// `EndSettingType` they need no case folding.
+    #[test]
+    fn other_enum_labels_match_migrations() {
+        for (label, value) in [
+            (&b"BurnEveryTime"[..], WhitelistMintMode::BurnEveryTime),
+            (b"NeverBurn", WhitelistMintMode::NeverBurn),
+        ] {
+            assert_eq!(
+                <WhitelistMintMode as FromSql<Mode, Pg>>::from_sql(Some(label)).unwrap(),
+                value
+            );
+        }
+
+        for (label, value) in [
+            (&b"NonFungible"[..], TokenStandardEnum::NonFungible),
+            (b"FungibleAsset", TokenStandardEnum::FungibleAsset),
+            (b"Fungible", TokenStandardEnum::Fungible),
+            (b"NonFungibleEdition", TokenStandardEnum::NonFungibleEdition),
+        ] {
+            assert_eq!(
+                <TokenStandardEnum as FromSql<TokenStandard, Pg>>::from_sql(Some(label)).unwrap(),
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn numeric_u64_from_decimal() {
+        let convert = |s: &str| NumericU64::try_from(&s.parse::<BigDecimal>().unwrap());
+        let above_i64 = u64::try_from(i64::MAX).unwrap() + 1;
+
+        assert_eq!(convert("0"), Ok(NumericU64(0)));
+        assert_eq!(convert("42.000"), Ok(NumericU64(42)));
+        assert_eq!(convert("9223372036854775808"), Ok(NumericU64(above_i64)));
+        assert_eq!(convert("18446744073709551615"), Ok(NumericU64(u64::MAX)));
+
+        assert!(convert("18446744073709551616").is_err());
+        assert!(convert("1.5").is_err());
+        assert!(convert("0.000001").is_err());
+        assert!(convert("-1").is_err());
+    }
+
+    #[test]
+    fn numeric_u64_round_trips_through_postgres() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+
+        for value in [0, u64::try_from(i64::MAX).unwrap() + 1, u64::MAX] {
+            let read: NumericU64 = diesel::select(NumericU64(value).into_sql::<Numeric>())
+                .get_result(&conn)
+                .unwrap();
+
+            assert_eq!(read, NumericU64(value));
+        }
+
+        let fractional: Result<NumericU64, _> =
+            diesel::select(diesel::dsl::sql::<Numeric>("1.5::numeric")).get_result(&conn);
+        assert!(fractional.is_err());
+    }
+}
@@ -1,10 +1,15 @@
 //! Interface with the indexer database
 
+mod batch;
 pub mod custom_types;
 pub mod models;
 pub mod queries;
+mod replicas;
+mod rollback;
 #[allow(missing_docs, unused_imports)]
 mod schema;
+pub mod testing;
+mod upsert;
 
 pub mod tables {
     //! Diesel schema DSLs
@@ -14,6 +19,7 @@ pub mod tables {
 
 use std::env;
 
+pub use batch::{upsert_batch, BatchRow, MAX_BIND_PARAMS};
 pub use diesel::{
     backend::Backend,
     debug_query, delete, expression, insert_into,
@@ -26,8 +32,11 @@ use diesel::{pg, r2d2};
 pub use diesel_full_text_search::{
     websearch_to_tsquery, TsQuery, TsQueryExtensions, TsVector, TsVectorExtensions,
 };
+pub use replicas::{ReplicaHealth, ReplicaPool, RETRY_AFTER};
+pub use rollback::{rollback_above_slot, RollbackCounts};
+pub use upsert::{upsert_if_newer, UpsertIfNewer};
 
-use crate::prelude::*;
+use crate::{prelude::*, PoolConfig};
 
 embed_migrations!("migrations");
 
@@ -82,26 +91,19 @@ impl From<ConnectMode> for ConnectionType {
 /// if Diesel fails to construct a connection pool, or if any pending database
 /// migrations fail to run.
 pub fn connect(mode: ConnectMode) -> Result<(Pool, ConnectionType)> {
-    let mode_env = match mode {
-        ConnectMode::Read => "DATABASE_READ_URL",
-        ConnectMode::Write => "DATABASE_WRITE_URL",
-    };
-
-    let (ty, url) = env::var_os(mode_env)
-        .map(|v| (mode.into(), v))
-        .or_else(|| env::var_os("DATABASE_URL").map(|v| (ConnectionType::Default, v)))
-        .ok_or_else(|| anyhow!("No value found for {} or DATABASE_URL", mode_env))?;
-    let url = url.to_string_lossy().into_owned();
-
-    debug!("Connecting to db: {:?}", url);
+    connect_with(mode, PoolConfig::default())
+}
 
-    let man = ConnectionManager::new(url);
-    let pool = Pool::builder()
-        .max_size(num_cpus::get().try_into().unwrap_or(u32::MAX))
-        .min_idle(Some(1))
-        .idle_timeout(Some(std::time::Duration::from_secs(60)))
-        .build(man)
-        .context("Failed to create database connection pool")?;
+/// Create a pooled connection to the Postgres database as with [`connect`],
+/// using the given pool configuration.  Connections requested from the pool
+/// fail with a timeout error if one cannot be acquired within the configured
+/// acquisition timeout.
+///
+/// # Errors
+/// This function fails for the same reasons as [`connect`].
+pub fn connect_with(mode: ConnectMode, config: PoolConfig) -> Result<(Pool, ConnectionType)> {
+    let (ty, url) = database_url(mode)?;
+    let pool = build_pool(url, config)?;
 
     let mut out = vec![];
 
@@ -133,3 +135,182 @@ pub fn connect(mode: ConnectMode) -> Result<(Pool, ConnectionType)> {
 
     Ok((pool, ty))
 }
+
+/// Create an additional pooled connection to the Postgres database with the
+/// given pool configuration.  The database URL is resolved the same way as
+/// [`connect`], but no migrations are run.
+///
+/// # Errors
+/// This function fails if no database URL environment variable is found, or if
+/// Diesel fails to construct a connection pool.
+pub fn connect_pool(mode: ConnectMode, config: PoolConfig) -> Result<Pool> {
+    let (_ty, url) = database_url(mode)?;
+
+    build_pool(url, config)
+}
+
+/// Create a set of read-only connection pools spread over the database's read
+/// replicas, each with the given pool configuration.  Replica URLs are read
+/// from the whitespace-separated list `DATABASE_READ_URLS`, or else a single
+/// URL is resolved for [`ConnectMode::Read`] the same way as [`connect`].  No
+/// migrations are run.
+///
+/// # Errors
+/// This function fails if no database URL environment variable is found, or if
+/// Diesel fails to construct any of the connection pools.
+pub fn connect_replicas(config: PoolConfig) -> Result<ReplicaPool> {
+    let urls: Vec<String> = match env::var("DATABASE_READ_URLS") {
+        Ok(v) if !v.trim().is_empty() => v.split_whitespace().map(ToOwned::to_owned).collect(),
+        _ => vec![database_url(ConnectMode::Read)?.1],
+    };
+
+    ReplicaPool::new(
+        urls.into_iter()
+            .enumerate()
+            .map(|(i, url)| {
+                let name = replica_name(&url).unwrap_or_else(|| format!("replica {}", i));
+
+                build_pool(url, config).map(|p| (name, p))
+            })
+            .collect::<Result<Vec<_>>>()?,
+    )
+}
+
+/// Identify a replica by the host and port of its URL, omitting credentials
+fn replica_name(url: &str) -> Option<String> {
+    let url = url::Url::parse(url).ok()?;
+    let host = url.host_str()?;
+
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_owned(),
+    })
+}
+
+/// Get the Postgres connection URL for the given mode, resolved the same way
+/// as [`connect`], for clients that need a dedicated non-pooled connection
+///
+/// # Errors
+/// This function fails if no database URL environment variable is found.
+pub fn connection_url(mode: ConnectMode) -> Result<String> {
+    database_url(mode).map(|(_, url)| url)
+}
+
+fn database_url(mode: ConnectMode) -> Result<(ConnectionType, String)> {
+    let mode_env = match mode {
+        ConnectMode::Read => "DATABASE_READ_URL",
+        ConnectMode::Write => "DATABASE_WRITE_URL",
+    };
+
+    let (ty, url) = env::var_os(mode_env)
+        .map(|v| (mode.into(), v))
+        .or_else(|| env::var_os("DATABASE_URL").map(|v| (ConnectionType::Default, v)))
+        .ok_or_else(|| anyhow!("No value found for {} or DATABASE_URL", mode_env))?;
+
+    Ok((ty, url.to_string_lossy().into_owned()))
+}
+
+/// Connection customizer limiting the run time of every statement executed
+/// on a connection, set once when the connection is established
+#[derive(Debug, Clone, Copy)]
+struct StatementTimeout(std::time::Duration);
+
+impl r2d2::CustomizeConnection<Connection, r2d2::Error> for StatementTimeout {
+    fn on_acquire(&self, conn: &mut Connection) -> Result<(), r2d2::Error> {
+        sql_query(format!("set statement_timeout = {}", self.0.as_millis()))
+            .execute(conn)
+            .map(|_| ())
+            .map_err(r2d2::Error::QueryError)
+    }
+}
+
+fn build_pool(
+    url: String,
+    PoolConfig {
+        max_size,
+        min_idle,
+        acquire_timeout,
+        statement_timeout,
+    }: PoolConfig,
+) -> Result<Pool> {
+    debug!("Connecting to db: {:?}", url);
+
+    let man = ConnectionManager::new(url);
+    let max_size = max_size.unwrap_or_else(|| num_cpus::get().try_into().unwrap_or(u32::MAX));
+
+    let mut builder = Pool::builder()
+        .max_size(max_size)
+        .min_idle(Some(min_idle.min(max_size)))
+        .connection_timeout(acquire_timeout)
+        .idle_timeout(Some(std::time::Duration::from_secs(60)));
+
+    if let Some(timeout) = statement_timeout {
+        builder = builder.connection_customizer(Box::new(StatementTimeout(timeout)));
+    }
+
+    builder
+        .build(man)
+        .context("Failed to create database connection pool")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn test_url() -> Option<String> {
+        env::var(testing::TEST_DATABASE_URL)
+            .ok()
+            .filter(|u| !u.trim().is_empty())
+    }
+
+    #[test]
+    fn statement_timeout_cancels_slow_statements() {
+        let url = match test_url() {
+            Some(u) => u,
+            None => return,
+        };
+        let pool = build_pool(url, PoolConfig {
+            max_size: Some(1),
+            min_idle: 0,
+            statement_timeout: Some(Duration::from_millis(100)),
+            ..PoolConfig::default()
+        })
+        .unwrap();
+        let conn = pool.get().unwrap();
+
+        assert!(sql_query("select pg_sleep(0.01)").execute(&conn).is_ok());
+
+        let err = sql_query("select pg_sleep(2)").execute(&conn).unwrap_err();
+
+        assert!(err.to_string().contains("statement timeout"), "{}", err);
+    }
+
+    #[test]
+    fn bounded_pool_times_out_under_contention() {
+        let url = match test_url() {
+            Some(u) => u,
+            None => return,
+        };
+        let pool = build_pool(url, PoolConfig {
+            max_size: Some(1),
+            min_idle: 0,
+            acquire_timeout: Duration::from_millis(200),
+            ..PoolConfig::default()
+        })
+        .unwrap();
+
+        let held = pool.get().unwrap();
+        let start = std::time::Instant::now();
+        let err = pool.get().unwrap_err();
+        let waited = start.elapsed();
+
+        assert!(err.to_string().contains("timed out"), "{}", err);
+        assert!(waited >= Duration::from_millis(200), "{:?}", waited);
+        assert!(waited < Duration::from_secs(5), "{:?}", waited);
+
+        drop(held);
+        assert!(pool.get().is_ok());
+    }
+}
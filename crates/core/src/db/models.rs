@@ -11,18 +11,21 @@ use super::schema::{
     attributes, auction_caches, auction_datas, auction_datas_ext, auction_houses, bid_receipts,
     bids, candy_machine_collection_pdas, candy_machine_config_lines, candy_machine_creators,
     candy_machine_datas, candy_machine_end_settings, candy_machine_gate_keeper_configs,
-    candy_machine_hidden_settings, candy_machine_whitelist_mint_settings, candy_machines, editions,
-    escrows, files, governance_parameters, governors, graph_connections,
-    ins_buffer_bundle_ins_keys, ins_buffer_bundle_instructions, ins_buffer_bundles,
-    instruction_buffers, listing_metadatas, listing_receipts, locker_params,
+    candy_machine_hidden_settings, candy_machine_whitelist_mint_settings, candy_machines,
+    collection_jsons, editions, escrows, files, governance_parameters, governors,
+    graph_connections, ins_buffer_bundle_ins_keys, ins_buffer_bundle_instructions,
+    ins_buffer_bundles, instruction_buffers, listing_metadatas, listing_receipts, locker_params,
     locker_whitelist_entries, lockers, master_editions, metadata_collection_keys,
     metadata_collections, metadata_creators, metadata_jsons, metadatas, proposal_account_metas,
-    proposal_instructions, proposal_metas, proposals, purchase_receipts, smart_wallet_owners,
-    smart_wallets, store_config_jsons, store_configs, store_creators, storefronts, stores,
-    sub_account_infos, token_accounts, transactions, twitter_handle_name_services,
-    tx_instruction_keys, tx_instructions, votes, whitelisted_creators,
+    proposal_instructions, proposal_metas, proposals, purchase_receipts, rarity_scores,
+    smart_wallet_owners, smart_wallets, store_config_jsons, store_configs, store_creators,
+    storefronts, stores, sub_account_infos, token_accounts, token_mints, transactions,
+    twitter_handle_name_services, tx_instruction_keys, tx_instructions, votes,
+    whitelisted_creators,
+};
+use crate::db::custom_types::{
+    EndSettingType, NumericU64, TokenStandardEnum, VoteSide, WhitelistMintMode,
 };
-use crate::db::custom_types::{EndSettingType, TokenStandardEnum, WhitelistMintMode};
 
 /// A row in the `bids` table
 #[derive(Debug, Clone, Queryable, Insertable, AsChangeset, Associations)]
@@ -178,6 +181,18 @@ pub struct TokenAccount<'a> {
     pub slot: Option<i64>,
 }
 
+/// A row in the `token_mints` table, holding the decimals of a fungible SPL
+/// token mint
+#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[diesel(treat_none_as_null = true)]
+pub struct TokenMint<'a> {
+    /// The address of the mint
+    pub address: Cow<'a, str>,
+    /// Number of base-10 digits to the right of the decimal point in a UI
+    /// amount of the token
+    pub decimals: i16,
+}
+
 /// A row in the `metadatas` table
 #[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
 #[diesel(treat_none_as_null = true)]
@@ -270,7 +285,21 @@ pub struct Nft {
     pub image: Option<String>,
 }
 
-/// Union of `listing_receipts` and `purchase_receipts` for an `NFTActivity`
+/// A row in a `metadatas::sample_owned_by` query, representing an NFT held by
+/// one of the requested wallets
+#[derive(Debug, Clone, QueryableByName)]
+pub struct OwnedNft {
+    /// The wallet holding the NFT
+    #[sql_type = "VarChar"]
+    pub owner: String,
+
+    /// The NFT held
+    #[diesel(embed)]
+    pub nft: Nft,
+}
+
+/// Union of `listing_receipts`, `purchase_receipts`, and (for some queries)
+/// `bid_receipts` for an `NFTActivity`
 #[derive(Debug, Clone, Queryable, QueryableByName)]
 pub struct NftActivity {
     /// The address of the activity
@@ -293,11 +322,14 @@ pub struct NftActivity {
     #[sql_type = "Timestamp"]
     pub created_at: NaiveDateTime,
 
-    /// The wallet address asociated to the activity [seller, buyer]
+    /// The wallet addresses associated with the activity: `[seller]` for
+    /// listings, `[buyer, seller]` for purchases (`[seller, buyer]` from
+    /// [`activities`](crate::db::queries::metadatas::activities)), and
+    /// `[buyer]` for bids
     #[sql_type = "Array<VarChar>"]
     pub wallets: Vec<String>,
 
-    /// Listing/Purchase created time
+    /// The kind of activity, e.g. `listing` or `purchase`
     #[sql_type = "Text"]
     pub activity_type: String,
 }
@@ -408,7 +440,8 @@ pub struct MetadataJson<'a> {
     pub category: Option<Cow<'a, str>>,
     /// Metadata URI raw JSON
     pub raw_content: Cow<'a, serde_json::Value>,
-    /// Model the JSON was parsed with
+    /// Schema the JSON was detected as conforming to, one of `metaplex`,
+    /// `metaplex-v1.1`, `candy-machine`, or `unknown`
     pub model: Option<Cow<'a, str>>,
 }
 
@@ -437,6 +470,8 @@ pub struct MetadataAttributeWrite<'a> {
     pub trait_type: Option<Cow<'a, str>>,
     /// Address of metadata first verified creator
     pub first_verified_creator: Option<Cow<'a, str>>,
+    /// Attribute trait type, trimmed and lowercased for filtering and grouping
+    pub trait_type_normalized: Option<Cow<'a, str>>,
 }
 
 /// A row in the `attributes` table
@@ -452,6 +487,8 @@ pub struct MetadataAttribute<'a> {
     pub id: Cow<'a, uuid::Uuid>,
     /// Address of metadata first verified creator
     pub first_verified_creator: Option<Cow<'a, str>>,
+    /// Attribute trait type, trimmed and lowercased for filtering and grouping
+    pub trait_type_normalized: Option<Cow<'a, str>>,
 }
 
 /// A row in the `metadata_collections` table
@@ -527,6 +564,22 @@ pub struct StoreConfigJson<'a> {
     pub store_address: Option<Cow<'a, str>>,
 }
 
+/// A row in the `collection_jsons` table
+#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[diesel(treat_none_as_null = true)]
+pub struct CollectionJson<'a> {
+    /// The mint of the collection NFT this record refers to
+    pub collection_mint: Cow<'a, str>,
+    /// The URI the JSON was fetched from
+    pub fetch_uri: Cow<'a, str>,
+    /// Collection name
+    pub name: Option<Cow<'a, str>>,
+    /// Collection image URL
+    pub image: Option<Cow<'a, str>>,
+    /// Collection description
+    pub description: Option<Cow<'a, str>>,
+}
+
 /// A row in the `auction_houses` table
 #[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
 #[diesel(treat_none_as_null = true)]
@@ -605,8 +658,9 @@ pub struct BidReceipt<'a> {
 }
 
 /// A row in the `listing_receipts` table
-#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[derive(Debug, Clone, Queryable, QueryableByName, Insertable, AsChangeset)]
 #[diesel(treat_none_as_null = true)]
+#[table_name = "listing_receipts"]
 pub struct ListingReceipt<'a> {
     /// ListingReceipt account pubkey
     pub address: Cow<'a, str>,
@@ -662,6 +716,24 @@ pub struct PurchaseReceipt<'a> {
     pub created_at: NaiveDateTime,
 }
 
+/// A row in the `rarity_scores` table, holding the trait rarity of an NFT
+/// within its collection
+#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[diesel(treat_none_as_null = true)]
+pub struct RarityScore<'a> {
+    /// The address of the NFT's metadata
+    pub metadata_address: Cow<'a, str>,
+    /// The first verified creator identifying the NFT's collection
+    pub first_verified_creator: Cow<'a, str>,
+    /// Sum over the NFT's attributes of the collection size divided by the
+    /// number of NFTs in the collection sharing the attribute
+    pub score: f64,
+    /// One-based rank of the NFT's score within its collection, rarest first
+    pub rank: i64,
+    /// Time the score was last computed
+    pub updated_at: NaiveDateTime,
+}
+
 /// A row in the `store_creators` table
 #[derive(Debug, Clone, Queryable, Insertable, AsChangeset, QueryableByName)]
 #[diesel(treat_none_as_null = true)]
@@ -852,6 +924,10 @@ pub struct MintStats<'a> {
     /// The floor price in this token
     #[sql_type = "Nullable<Int8>"]
     pub floor: Option<i64>,
+    /// The floor price in this token of listings whose primary sale has
+    /// already happened
+    #[sql_type = "Nullable<Int8>"]
+    pub secondary_floor: Option<i64>,
     /// The average price in this token
     #[sql_type = "Nullable<Int8>"]
     pub average: Option<i64>,
@@ -860,6 +936,53 @@ pub struct MintStats<'a> {
     pub volume_24hr: Option<i64>,
 }
 
+/// A row in a `stats::activity` query, counting auction house activity by type
+#[derive(Debug, Clone, Copy, QueryableByName)]
+pub struct ActivitySummary {
+    /// Number of listings created
+    #[sql_type = "Int8"]
+    pub listings: i64,
+    /// Number of sales
+    #[sql_type = "Int8"]
+    pub sales: i64,
+    /// Number of listings canceled
+    #[sql_type = "Int8"]
+    pub cancellations: i64,
+    /// Number of bids placed
+    #[sql_type = "Int8"]
+    pub bids: i64,
+}
+
+/// A row in a `token_accounts::top_holders` query, representing the balance of
+/// a single wallet
+#[derive(Debug, Clone, QueryableByName)]
+pub struct TokenHolder {
+    /// The wallet holding the tokens
+    #[sql_type = "VarChar"]
+    pub owner_address: String,
+    /// The total balance held across the wallet's token accounts
+    #[sql_type = "Int8"]
+    pub amount: i64,
+    /// The total balance held across all wallets
+    #[sql_type = "Int8"]
+    pub total: i64,
+    /// The twitter handle of the wallet, if any
+    #[sql_type = "Nullable<Text>"]
+    pub twitter_handle: Option<String>,
+}
+
+/// A row in a `data_quality::report` query, identifying a row which failed a
+/// consistency check
+#[derive(Debug, Clone, QueryableByName)]
+pub struct DataQualityAnomaly {
+    /// The address of the offending account
+    #[sql_type = "VarChar"]
+    pub address: String,
+    /// A description of how the invariant was violated
+    #[sql_type = "Text"]
+    pub detail: String,
+}
+
 /// A join of `graph_connections` and `twitter_handle_name_services` for connections that include twitter handle of wallets
 #[derive(Debug, Clone, QueryableByName)]
 pub struct TwitterEnrichedGraphConnection {
@@ -880,6 +1003,21 @@ pub struct TwitterEnrichedGraphConnection {
     pub to_twitter_handle: Option<String>,
 }
 
+/// A row in a `graph_connection::counts` query, counting the connections of a
+/// single wallet
+#[derive(Debug, Clone, QueryableByName)]
+pub struct ConnectionCounts {
+    /// The wallet whose connections were counted
+    #[sql_type = "Text"]
+    pub wallet: String,
+    /// Number of wallets following this wallet
+    #[sql_type = "Int8"]
+    pub followers: i64,
+    /// Number of wallets this wallet follows
+    #[sql_type = "Int8"]
+    pub following: i64,
+}
+
 /// A row in a `metadatas::count_by_marketplace` query, representing stats for
 /// a single marketplace
 #[derive(Debug, Clone, QueryableByName)]
@@ -893,6 +1031,36 @@ pub struct MarketStats<'a> {
     pub nfts: Option<i64>,
 }
 
+/// A row in a `stats::marketplace_summary` query, summarizing trading in a
+/// single marketplace
+#[derive(Debug, Clone, Copy, QueryableByName)]
+pub struct MarketplaceSummary {
+    /// The lowest price of an active listing
+    #[sql_type = "Nullable<Int8>"]
+    pub floor: Option<i64>,
+    /// Total price of sales within the window
+    #[sql_type = "Nullable<Int8>"]
+    pub volume: Option<i64>,
+    /// Number of active listings
+    #[sql_type = "Int8"]
+    pub listed: i64,
+    /// Number of distinct wallets holding an NFT in the marketplace
+    #[sql_type = "Int8"]
+    pub holders: i64,
+}
+
+/// A row in a `stats::floor_history` query, representing the floor of a
+/// collection over a single bucket of time
+#[derive(Debug, Clone, Copy, QueryableByName)]
+pub struct FloorPoint {
+    /// The start of the bucket
+    #[sql_type = "Timestamp"]
+    pub timestamp: NaiveDateTime,
+    /// The lowest price of a listing active during the bucket
+    #[sql_type = "Nullable<Int8>"]
+    pub floor: Option<i64>,
+}
+
 /// A row in the `twitter_handle_name_services` table
 #[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
 #[diesel(treat_none_as_null = true)]
@@ -935,7 +1103,7 @@ pub struct Locker<'a> {
     /// Mint of the token that must be locked in the [Locker].
     pub token_mint: Cow<'a, str>,
     /// Total number of tokens locked in [Escrow]s.
-    pub locked_supply: i64,
+    pub locked_supply: NumericU64,
     /// Governor associated with the [Locker].
     pub governor: Cow<'a, str>,
 }
@@ -992,7 +1160,7 @@ pub struct Escrow<'a> {
     /// The token account holding the escrow tokens.
     pub tokens: Cow<'a, str>,
     /// Amount of tokens staked.
-    pub amount: i64,
+    pub amount: NumericU64,
     /// When the [Escrow::owner] started their escrow.
     pub escrow_started_at: i64,
     /// When the escrow unlocks; i.e. the [Escrow::owner] is scheduled to be allowed to withdraw their tokens.
@@ -1045,8 +1213,9 @@ pub struct GovernanceParameter<'a> {
 
 /// `Tribeca` Govern program account
 /// A row in the `proposals` table
-#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[derive(Debug, Clone, Queryable, QueryableByName, Insertable, AsChangeset)]
 #[diesel(treat_none_as_null = true)]
+#[table_name = "proposals"]
 pub struct Proposal<'a> {
     /// Proposal account pubkey
     pub address: Cow<'a, str>,
@@ -1083,6 +1252,28 @@ pub struct Proposal<'a> {
     pub queued_transaction: Cow<'a, str>,
 }
 
+/// A join of `proposals`, `proposal_metas`, and `transactions`, with the
+/// proposer's Twitter handle
+#[derive(Debug, Clone, QueryableByName)]
+pub struct ProposalWithMeta<'a> {
+    /// The proposal
+    #[diesel(embed)]
+    pub proposal: Proposal<'a>,
+    /// Title of the proposal, if its metadata has been indexed
+    #[sql_type = "Nullable<Text>"]
+    pub title: Option<String>,
+    /// Link to a description of the proposal, if its metadata has been indexed
+    #[sql_type = "Nullable<Text>"]
+    pub description_link: Option<String>,
+    /// The timestamp the proposal's queued smart wallet transaction was
+    /// executed at, if it has been indexed
+    #[sql_type = "Nullable<Int8>"]
+    pub executed_at: Option<i64>,
+    /// The Twitter handle of the proposer
+    #[sql_type = "Nullable<Text>"]
+    pub twitter_handle: Option<String>,
+}
+
 /// A row in the `proposal_instructions` table
 #[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
 #[diesel(treat_none_as_null = true)]
@@ -1142,7 +1333,7 @@ pub struct Vote<'a> {
     /// Bump seed
     pub bump: i16,
     /// The side of the vote taken.
-    pub side: i16,
+    pub side: VoteSide,
     /// The number of votes this vote holds.
     pub weight: i64,
 }
@@ -0,0 +1,138 @@
+//! Query utilities for looking up bid receipts
+
+use anyhow::Context;
+use diesel::prelude::*;
+
+use super::slot;
+use crate::{
+    db::{
+        models::BidReceipt,
+        tables::{bid_receipts, metadatas, token_accounts},
+        Connection,
+    },
+    error::Result,
+};
+
+/// Load the active offers, i.e. those neither purchased nor canceled, made by
+/// a wallet, newest first
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn made_by(
+    conn: &Connection,
+    buyer: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<BidReceipt<'static>>> {
+    bid_receipts::table
+        .inner_join(metadatas::table.on(metadatas::address.eq(bid_receipts::metadata)))
+        .filter(bid_receipts::buyer.eq(buyer))
+        .filter(bid_receipts::canceled_at.is_null())
+        .filter(bid_receipts::purchase_receipt.is_null())
+        .select(bid_receipts::all_columns)
+        .order((bid_receipts::created_at.desc(), bid_receipts::address.asc()))
+        .limit(limit)
+        .offset(offset)
+        .load(conn)
+        .context("Failed to load offers made")
+}
+
+/// Load the active offers, i.e. those neither purchased nor canceled, made on
+/// NFTs currently held by a wallet, newest first.  Only token accounts
+/// confirmed at or before `max_slot` are considered when determining which
+/// NFTs the wallet holds.
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn received_by(
+    conn: &Connection,
+    seller: &str,
+    max_slot: i64,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<BidReceipt<'static>>> {
+    bid_receipts::table
+        .inner_join(metadatas::table.on(metadatas::address.eq(bid_receipts::metadata)))
+        .inner_join(
+            token_accounts::table.on(metadatas::mint_address.eq(token_accounts::mint_address)),
+        )
+        .filter(token_accounts::owner_address.eq(seller))
+        .filter(token_accounts::amount.eq(1))
+        .filter(slot::coalesce_slot(token_accounts::slot, 0).le(max_slot))
+        .filter(bid_receipts::canceled_at.is_null())
+        .filter(bid_receipts::purchase_receipt.is_null())
+        .select(bid_receipts::all_columns)
+        .order((bid_receipts::created_at.desc(), bid_receipts::address.asc()))
+        .limit(limit)
+        .offset(offset)
+        .load(conn)
+        .context("Failed to load offers received")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow::Borrowed;
+
+    use chrono::{Duration, Local};
+
+    use super::*;
+    use crate::db::{insert_into, testing};
+
+    fn addresses<'a>(offers: &'a [BidReceipt<'a>]) -> Vec<&'a str> {
+        offers.iter().map(|o| o.address.as_ref()).collect()
+    }
+
+    #[test]
+    fn active_and_cancelled_offers() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+        let now = Local::now().naive_utc();
+        let hours_ago = |h| now - Duration::hours(h);
+
+        insert_into(metadatas::table)
+            .values(&[
+                testing::metadata("nft1", "mint1", true),
+                testing::metadata("nft2", "mint2", true),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(token_accounts::table)
+            .values(&[
+                testing::token_account("account1", "mint1", "seller", 1),
+                testing::token_account("account2", "mint2", "seller", 1),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(bid_receipts::table)
+            .values(&[
+                testing::bid_receipt("older", "house", "nft1", "buyer", 100, hours_ago(3)),
+                testing::bid_receipt("newer", "house", "nft2", "buyer", 200, hours_ago(2)),
+                testing::bid_receipt("other", "house", "nft1", "other", 150, hours_ago(1)),
+                BidReceipt {
+                    canceled_at: Some(now),
+                    ..testing::bid_receipt("canceled", "house", "nft1", "buyer", 300, now)
+                },
+                BidReceipt {
+                    purchase_receipt: Some(Borrowed("purchase")),
+                    ..testing::bid_receipt("accepted", "house", "nft2", "buyer", 300, now)
+                },
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        assert_eq!(addresses(&made_by(&conn, "buyer", 10, 0).unwrap()), vec![
+            "newer", "older"
+        ]);
+        assert_eq!(addresses(&made_by(&conn, "buyer", 10, 1).unwrap()), vec![
+            "older"
+        ]);
+        assert_eq!(
+            addresses(&received_by(&conn, "seller", 0, 10, 0).unwrap()),
+            vec!["other", "newer", "older"]
+        );
+        assert!(received_by(&conn, "buyer", 0, 10, 0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+//! Consistency checks over indexed data, used to find indexing bugs.
+//!
+//! New checks are added by appending to [`CHECKS`].  Each check's query must
+//! select an `address` and a human-readable `detail` column for every
+//! offending row, and accept a row limit as its only parameter.
+
+use anyhow::Context;
+use diesel::{prelude::*, sql_types::Integer};
+
+use crate::{
+    db::{models::DataQualityAnomaly, Connection},
+    error::Result,
+};
+
+/// The kind of data a consistency check covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Scope {
+    /// Candy machine configuration and progress
+    CandyMachines,
+    /// Master edition and edition supplies
+    Editions,
+    /// Metadata creator lists
+    Creators,
+}
+
+/// A single consistency check
+#[derive(Debug, Clone, Copy)]
+pub struct Check {
+    /// A unique name identifying the invariant being checked
+    pub name: &'static str,
+    /// The kind of data this check covers
+    pub scope: Scope,
+    query: &'static str,
+}
+
+/// The full set of consistency checks
+pub const CHECKS: &[Check] = &[
+    Check {
+        name: "candy_machine_redeemed_within_available",
+        scope: Scope::CandyMachines,
+        query: r"
+select cm.address,
+    format('items_redeemed (%s) exceeds items_available (%s)',
+        cm.items_redeemed, cmd.items_available) as detail
+from candy_machines cm
+    inner join candy_machine_datas cmd
+        on (cmd.candy_machine_address = cm.address)
+
+where cm.items_redeemed > cmd.items_available
+limit $1;
+ -- $1: limit::integer",
+    },
+    Check {
+        name: "master_edition_supply_within_max_supply",
+        scope: Scope::Editions,
+        query: r"
+select address,
+    format('supply (%s) exceeds max_supply (%s)', supply, max_supply) as detail
+from master_editions
+
+where max_supply is not null and supply > max_supply
+limit $1;
+ -- $1: limit::integer",
+    },
+    Check {
+        name: "edition_number_within_max_supply",
+        scope: Scope::Editions,
+        query: r"
+select e.address,
+    format('edition number (%s) exceeds max_supply (%s) of master edition %s',
+        e.edition, me.max_supply, me.address) as detail
+from editions e
+    inner join master_editions me
+        on (me.address = e.parent_address)
+
+where me.max_supply is not null and e.edition > me.max_supply
+limit $1;
+ -- $1: limit::integer",
+    },
+    Check {
+        name: "creator_shares_sum_to_100",
+        scope: Scope::Creators,
+        query: r"
+select metadata_address as address,
+    format('creator shares sum to %s', sum(share)) as detail
+from metadata_creators
+
+group by metadata_address
+having sum(share) <> 100
+limit $1;
+ -- $1: limit::integer",
+    },
+];
+
+/// Run every check, or only the checks covering the given scopes, returning
+/// up to `limit` anomalies per check
+///
+/// # Errors
+/// This function fails if any of the underlying SQL queries return an error
+pub fn report(
+    conn: &Connection,
+    scopes: Option<&[Scope]>,
+    limit: i32,
+) -> Result<Vec<(&'static Check, DataQualityAnomaly)>> {
+    let mut anomalies = Vec::new();
+
+    for check in CHECKS
+        .iter()
+        .filter(|c| scopes.map_or(true, |s| s.contains(&c.scope)))
+    {
+        let rows: Vec<DataQualityAnomaly> = diesel::sql_query(check.query)
+            .bind::<Integer, _>(limit)
+            .load(conn)
+            .with_context(|| format!("Failed to run data quality check {}", check.name))?;
+
+        anomalies.extend(rows.into_iter().map(|r| (check, r)));
+    }
+
+    Ok(anomalies)
+}
@@ -8,7 +8,10 @@ use diesel::{
 };
 
 use crate::{
-    db::{models::TwitterEnrichedGraphConnection, Connection},
+    db::{
+        models::{ConnectionCounts, TwitterEnrichedGraphConnection},
+        Connection,
+    },
     error::Result,
     prelude::*,
 };
@@ -46,3 +49,25 @@ pub fn list(
         .load(conn)
         .context("failed to load twitter enriched graph connections")
 }
+
+const COUNTS_QUERY: &str = r"
+SELECT w.address AS wallet,
+    (SELECT COUNT(*) FROM graph_connections WHERE to_account = w.address)::bigint AS followers,
+    (SELECT COUNT(*) FROM graph_connections WHERE from_account = w.address)::bigint AS following
+    FROM UNNEST($1::text[]) AS w(address);
+ -- $1: wallets::text[]
+ ";
+
+/// Count the followers and followed wallets of each of the given wallets
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn counts(
+    conn: &Connection,
+    wallets: impl ToSql<Array<Text>, Pg>,
+) -> Result<Vec<ConnectionCounts>> {
+    sql_query(COUNTS_QUERY)
+        .bind(wallets)
+        .load(conn)
+        .context("failed to load graph connection counts")
+}
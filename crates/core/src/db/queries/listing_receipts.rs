@@ -0,0 +1,256 @@
+//! Query utilities for looking up listing receipts
+
+use anyhow::Context;
+use diesel::{
+    pg::Pg,
+    prelude::*,
+    serialize::ToSql,
+    sql_types::{BigInt, Bool, Integer, Nullable, Text},
+};
+
+use crate::{
+    db::{models::ListingReceipt, Connection},
+    error::Result,
+};
+
+const BELOW_FLOOR_QUERY: &str = r"
+with active_listings as (
+    select lr.*
+    from listing_receipts lr
+        inner join metadata_creators mc
+            on (mc.metadata_address = lr.metadata)
+        inner join metadatas md
+            on (md.address = lr.metadata)
+
+    where lr.auction_house = $2
+        and mc.creator_address = $1
+        and mc.verified
+        and lr.purchase_receipt is null
+        and lr.canceled_at is null
+        and (md.primary_sale_happened or not $5)
+        and not exists (select from listing_denylist ld
+            where ld.listing_address = lr.address)
+)
+select
+    al.address, al.trade_state, al.bookkeeper, al.auction_house, al.seller,
+    al.metadata, al.purchase_receipt, al.price, al.token_size, al.bump,
+    al.trade_state_bump, al.created_at, al.canceled_at
+from active_listings al
+    cross join (select min(price) as price from active_listings) floor
+
+where al.price <= floor.price + (floor.price * $3 / 100)
+order by al.price asc
+limit $4;
+ -- $1: collection creator::text
+ -- $2: auction house::text
+ -- $3: percentage above floor::integer
+ -- $4: limit::integer
+ -- $5: secondary only::boolean";
+
+/// Load active listings for a collection, identified by its verified creator,
+/// priced within `percent` percent of the collection's floor on the given
+/// auction house, cheapest first.  If `secondary_only` is set, listings whose
+/// primary sale has not happened are ignored.
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn below_floor(
+    conn: &Connection,
+    collection: impl ToSql<Text, Pg>,
+    auction_house: impl ToSql<Text, Pg>,
+    percent: impl ToSql<Integer, Pg>,
+    limit: impl ToSql<Integer, Pg>,
+    secondary_only: bool,
+) -> Result<Vec<ListingReceipt<'static>>> {
+    diesel::sql_query(BELOW_FLOOR_QUERY)
+        .bind(collection)
+        .bind(auction_house)
+        .bind(percent)
+        .bind(limit)
+        .bind::<Bool, _>(secondary_only)
+        .load(conn)
+        .context("Failed to load listings below floor")
+}
+
+const ACTIVE_QUERY: &str = r"
+select
+    lr.address, lr.trade_state, lr.bookkeeper, lr.auction_house, lr.seller,
+    lr.metadata, lr.purchase_receipt, lr.price, lr.token_size, lr.bump,
+    lr.trade_state_bump, lr.created_at, lr.canceled_at
+from listing_receipts lr
+    inner join metadatas md
+        on (md.address = lr.metadata)
+
+where lr.purchase_receipt is null
+    and lr.canceled_at is null
+    and ($1::text is null or lr.auction_house = $1)
+    and ($2::bigint is null or lr.price >= $2)
+    and ($3::bigint is null or lr.price <= $3)
+    and not exists (select from listing_denylist ld
+        where ld.listing_address = lr.address)
+order by
+    case when $4 = 'price_asc' then lr.price end asc,
+    case when $4 = 'price_desc' then lr.price end desc,
+    lr.created_at desc,
+    lr.address asc
+limit $5
+offset $6;
+ -- $1: auction house::text
+ -- $2: minimum price::bigint
+ -- $3: maximum price::bigint
+ -- $4: sort order::text
+ -- $5: limit::bigint
+ -- $6: offset::bigint";
+
+/// Order in which [`active`] returns listings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum ListingSort {
+    /// Cheapest listings first
+    PriceAsc,
+    /// Most expensive listings first
+    PriceDesc,
+    /// Most recently created listings first
+    Newest,
+}
+
+/// Options for [`active`]
+#[derive(Debug)]
+pub struct ActiveOptions {
+    /// Only return listings made on this auction house
+    pub auction_house: Option<String>,
+    /// Only return listings priced at or above this many lamports
+    pub min_price: Option<i64>,
+    /// Only return listings priced at or below this many lamports
+    pub max_price: Option<i64>,
+    /// Order to return listings in
+    pub sort: ListingSort,
+    /// Maximum number of listings to return
+    pub limit: i64,
+    /// Number of listings to skip
+    pub offset: i64,
+}
+
+/// Load active listings, i.e. those which have been neither purchased nor
+/// canceled, optionally filtered by auction house and price.  Ties in the
+/// requested order are broken by recency.
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn active(
+    conn: &Connection,
+    ActiveOptions {
+        auction_house,
+        min_price,
+        max_price,
+        sort,
+        limit,
+        offset,
+    }: ActiveOptions,
+) -> Result<Vec<ListingReceipt<'static>>> {
+    let sort: &'static str = sort.into();
+
+    diesel::sql_query(ACTIVE_QUERY)
+        .bind::<Nullable<Text>, _>(auction_house)
+        .bind::<Nullable<BigInt>, _>(min_price)
+        .bind::<Nullable<BigInt>, _>(max_price)
+        .bind::<Text, _>(sort)
+        .bind::<BigInt, _>(limit)
+        .bind::<BigInt, _>(offset)
+        .load(conn)
+        .context("Failed to load active listings")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow::Borrowed;
+
+    use chrono::{Duration, Local};
+
+    use super::*;
+    use crate::db::{
+        insert_into,
+        tables::{listing_receipts, metadatas},
+        testing,
+    };
+
+    fn addresses<'a>(listings: &'a [ListingReceipt<'a>]) -> Vec<&'a str> {
+        listings.iter().map(|l| l.address.as_ref()).collect()
+    }
+
+    #[test]
+    fn active_listings() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+        let now = Local::now().naive_utc();
+        let hours_ago = |h| now - Duration::hours(h);
+
+        insert_into(metadatas::table)
+            .values(&[
+                testing::metadata("nft1", "mint1", false),
+                testing::metadata("nft2", "mint2", false),
+                testing::metadata("nft3", "mint3", false),
+                testing::metadata("nft4", "mint4", false),
+                testing::metadata("nft5", "mint5", false),
+                testing::metadata("nft6", "mint6", false),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(listing_receipts::table)
+            .values(&[
+                testing::listing_receipt("cheap", "house", "nft1", "seller", 100, hours_ago(5)),
+                testing::listing_receipt("mid", "house", "nft2", "seller", 200, hours_ago(4)),
+                testing::listing_receipt("dear", "house", "nft3", "seller", 300, hours_ago(3)),
+                testing::listing_receipt("other", "other", "nft4", "seller", 150, hours_ago(2)),
+                ListingReceipt {
+                    canceled_at: Some(hours_ago(1)),
+                    ..testing::listing_receipt("canceled", "house", "nft5", "seller", 250, now)
+                },
+                ListingReceipt {
+                    purchase_receipt: Some(Borrowed("purchase")),
+                    ..testing::listing_receipt("sold", "house", "nft6", "seller", 250, now)
+                },
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        let load = |auction_house: Option<&str>, min_price, max_price, sort, offset| {
+            active(&conn, ActiveOptions {
+                auction_house: auction_house.map(ToOwned::to_owned),
+                min_price,
+                max_price,
+                sort,
+                limit: 10,
+                offset,
+            })
+            .unwrap()
+        };
+
+        assert_eq!(
+            addresses(&load(None, None, None, ListingSort::Newest, 0)),
+            vec!["other", "dear", "mid", "cheap"]
+        );
+        assert_eq!(
+            addresses(&load(Some("house"), None, None, ListingSort::PriceDesc, 0)),
+            vec!["dear", "mid", "cheap"]
+        );
+        assert_eq!(
+            addresses(&load(None, Some(150), Some(250), ListingSort::PriceAsc, 0)),
+            vec!["other", "mid"]
+        );
+        assert_eq!(
+            addresses(&load(
+                Some("house"),
+                Some(150),
+                None,
+                ListingSort::PriceAsc,
+                1
+            )),
+            vec!["dear"]
+        );
+        assert!(load(None, Some(301), None, ListingSort::PriceAsc, 0).is_empty());
+    }
+}
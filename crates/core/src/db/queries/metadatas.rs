@@ -1,24 +1,65 @@
 //! Query utilities for looking up  metadatas
 
+use chrono::NaiveDateTime;
 use diesel::{
     pg::Pg,
     prelude::*,
     serialize::ToSql,
-    sql_types::{Array, Text},
+    sql_types::{Array, BigInt, Integer, Nullable, Text, Timestamp},
 };
 
+use super::slot;
 use crate::{
     db::{
         any,
-        models::{Nft, NftActivity},
+        custom_types::TokenStandardEnum,
+        models::{Nft, NftActivity, OwnedNft},
         tables::{
-            attributes, bid_receipts, listing_receipts, metadata_creators, metadata_jsons,
-            metadatas, token_accounts,
+            attributes, bid_receipts, listing_receipts, metadata_collection_keys,
+            metadata_creators, metadata_jsons, metadatas, token_accounts,
         },
         Connection,
     },
     error::prelude::*,
 };
+/// Normalize an attribute trait type for filtering and grouping by trimming
+/// surrounding whitespace and lowercasing it
+#[must_use]
+pub fn normalize_trait_type(trait_type: &str) -> String {
+    trait_type.trim().to_lowercase()
+}
+
+/// Combine filters naming the same trait type once normalized, so that their
+/// values are matched with OR rather than AND.  The trait types of the
+/// returned filters are normalized.
+fn merge_attribute_filters(filters: Vec<AttributeFilter>) -> Vec<AttributeFilter> {
+    let mut merged: Vec<AttributeFilter> = Vec::with_capacity(filters.len());
+
+    for AttributeFilter { trait_type, values } in filters {
+        let trait_type = normalize_trait_type(&trait_type);
+
+        match merged.iter_mut().find(|f| f.trait_type == trait_type) {
+            Some(existing) => existing.values.extend(values),
+            None => merged.push(AttributeFilter { trait_type, values }),
+        }
+    }
+
+    merged
+}
+
+/// Whitespace stripped from trait types by [`normalize_trait_type`]
+const TRAIT_TYPE_WHITESPACE: &str = " \t\r\n";
+
+sql_function! {
+    /// Lowercase a string
+    fn lower(s: Nullable<Text>) -> Nullable<Text>;
+}
+
+sql_function! {
+    /// Strip any of the given characters from both ends of a string
+    fn btrim(s: Nullable<Text>, characters: Text) -> Nullable<Text>;
+}
+
 /// Format for incoming filters on attributes
 #[derive(Debug)]
 pub struct AttributeFilter {
@@ -41,6 +82,10 @@ pub struct ListQueryOptions {
     pub attributes: Option<Vec<AttributeFilter>>,
     /// nft listed with auction house
     pub listed: Option<Vec<String>>,
+    /// nft token standard
+    pub token_standard: Option<TokenStandardEnum>,
+    /// only return nfts whose metadata address sorts after this one
+    pub after: Option<String>,
     /// limit to apply to query
     pub limit: i64,
     /// offset to apply to query
@@ -49,6 +94,10 @@ pub struct ListQueryOptions {
 
 /// Handles queries for NFTs
 ///
+/// Attribute filters are ANDed across distinct trait types and ORed across
+/// the values given for each trait type.  When creators are also given,
+/// attributes are matched only within those creators' collections.
+///
 /// # Errors
 /// returns an error when the underlying queries throw an error
 pub fn list(
@@ -59,6 +108,8 @@ pub fn list(
         offerers,
         attributes,
         listed,
+        token_standard,
+        after,
         limit,
         offset,
     }: ListQueryOptions,
@@ -69,7 +120,7 @@ pub fn list(
         && offerers.is_none()
         && listed.is_none()
     {
-        let query = metadatas::table
+        let mut query = metadatas::table
             .inner_join(
                 metadata_creators::table
                     .on(metadatas::address.eq(metadata_creators::metadata_address)),
@@ -79,6 +130,17 @@ pub fn list(
             )
             .filter(metadata_creators::creator_address.eq(any(creators.unwrap_or_else(Vec::new))))
             .filter(metadata_creators::verified.eq(true))
+            .into_boxed();
+
+        if let Some(token_standard) = token_standard {
+            query = query.filter(metadatas::token_standard.eq(token_standard));
+        }
+
+        if let Some(after) = after {
+            query = query.filter(metadatas::address.gt(after));
+        }
+
+        let query = query
             .select((
                 metadatas::address,
                 metadatas::name,
@@ -115,20 +177,32 @@ pub fn list(
         .into_boxed();
 
     if let Some(attributes) = attributes {
-        query =
-            attributes
-                .into_iter()
-                .fold(query, |acc, AttributeFilter { trait_type, values }| {
-                    let sub = attributes::table
-                        .select(attributes::metadata_address)
-                        .filter(
-                            attributes::trait_type
-                                .eq(trait_type)
-                                .and(attributes::value.eq(any(values))),
-                        );
-
-                    acc.filter(metadatas::address.eq(any(sub)))
-                });
+        query = merge_attribute_filters(attributes).into_iter().fold(
+            query,
+            |acc, AttributeFilter { trait_type, values }| {
+                let mut sub = attributes::table
+                    .select(attributes::metadata_address)
+                    .filter(
+                        attributes::trait_type_normalized
+                            .eq(&trait_type)
+                            .or(attributes::trait_type_normalized.is_null().and(
+                                lower(btrim(attributes::trait_type, TRAIT_TYPE_WHITESPACE))
+                                    .eq(&trait_type),
+                            ))
+                            .and(attributes::value.eq(any(values))),
+                    )
+                    .into_boxed();
+
+                // Scoping to the collection lets the subquery use the
+                // first_verified_creator index rather than scanning every
+                // attribute with a matching trait
+                if let Some(ref creators) = creators {
+                    sub = sub.filter(attributes::first_verified_creator.eq(any(creators.clone())));
+                }
+
+                acc.filter(metadatas::address.eq(any(sub)))
+            },
+        );
     }
 
     if let Some(creators) = creators {
@@ -156,6 +230,14 @@ pub fn list(
             .filter(listing_receipts::canceled_at.is_null());
     }
 
+    if let Some(token_standard) = token_standard {
+        query = query.filter(metadatas::token_standard.eq(token_standard));
+    }
+
+    if let Some(after) = after {
+        query = query.filter(metadatas::address.gt(after));
+    }
+
     let rows: Vec<Nft> = query
         .select((
             metadatas::address,
@@ -176,6 +258,164 @@ pub fn list(
     Ok(rows)
 }
 
+const SAMPLE_OWNED_QUERY: &str = r"
+select owner, address, name, seller_fee_basis_points, mint_address,
+    primary_sale_happened, description, image
+from (
+    select ta.owner_address as owner,
+        md.address, md.name, md.seller_fee_basis_points, md.mint_address,
+        md.primary_sale_happened, mj.description, mj.image,
+        row_number() over (partition by ta.owner_address order by md.address) as rank
+    from metadatas md
+        inner join metadata_jsons mj
+            on (mj.metadata_address = md.address)
+        inner join token_accounts ta
+            on (ta.mint_address = md.mint_address)
+
+    where ta.owner_address = any($1)
+        and ta.amount = 1
+        and coalesce(ta.slot, 0) <= $2
+) owned
+where rank <= $3
+order by owner, address;
+ -- $1: owners::text[]
+ -- $2: max slot::bigint
+ -- $3: NFTs per owner::bigint";
+
+/// Load the first `per_owner` NFTs held by each of several wallets, ordered by
+/// metadata address.  NFTs are selected as in [`owned_by`].
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn sample_owned_by(
+    conn: &Connection,
+    owners: impl ToSql<Array<Text>, Pg>,
+    max_slot: i64,
+    per_owner: i64,
+) -> Result<Vec<OwnedNft>> {
+    diesel::sql_query(SAMPLE_OWNED_QUERY)
+        .bind(owners)
+        .bind::<BigInt, _>(max_slot)
+        .bind::<BigInt, _>(per_owner)
+        .load(conn)
+        .context("Failed to load sample of owned NFTs")
+}
+
+/// Load the NFTs held by a wallet, ordered by metadata address.  Only token
+/// accounts with a balance of exactly one are considered, so fungible
+/// balances are excluded, and only accounts confirmed at or before `max_slot`
+/// are considered.
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn owned_by(
+    conn: &Connection,
+    owner: impl ToSql<Text, Pg>,
+    max_slot: i64,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Nft>> {
+    metadatas::table
+        .inner_join(
+            metadata_jsons::table.on(metadatas::address.eq(metadata_jsons::metadata_address)),
+        )
+        .inner_join(
+            token_accounts::table.on(metadatas::mint_address.eq(token_accounts::mint_address)),
+        )
+        .filter(token_accounts::owner_address.eq(owner))
+        .filter(token_accounts::amount.eq(1))
+        .filter(slot::coalesce_slot(token_accounts::slot, 0).le(max_slot))
+        .select((
+            metadatas::address,
+            metadatas::name,
+            metadatas::seller_fee_basis_points,
+            metadatas::mint_address,
+            metadatas::primary_sale_happened,
+            metadata_jsons::description,
+            metadata_jsons::image,
+        ))
+        .distinct()
+        .order(metadatas::address.asc())
+        .limit(limit)
+        .offset(offset)
+        .load(conn)
+        .context("Failed to load owned NFTs")
+}
+
+/// Load the verified members of a collection, identified by the mint address
+/// of its collection NFT, ordered by metadata address.  Members whose link to
+/// the collection has not been verified by its authority are excluded.
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn collection_members(
+    conn: &Connection,
+    collection: impl ToSql<Text, Pg>,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Nft>> {
+    metadatas::table
+        .inner_join(
+            metadata_jsons::table.on(metadatas::address.eq(metadata_jsons::metadata_address)),
+        )
+        .inner_join(
+            metadata_collection_keys::table
+                .on(metadatas::address.eq(metadata_collection_keys::metadata_address)),
+        )
+        .filter(metadata_collection_keys::collection_address.eq(collection))
+        .filter(metadata_collection_keys::verified)
+        .select((
+            metadatas::address,
+            metadatas::name,
+            metadatas::seller_fee_basis_points,
+            metadatas::mint_address,
+            metadatas::primary_sale_happened,
+            metadata_jsons::description,
+            metadata_jsons::image,
+        ))
+        .order(metadatas::address.asc())
+        .limit(limit)
+        .offset(offset)
+        .load(conn)
+        .context("Failed to load collection members")
+}
+
+const SEARCH_QUERY: &str = r"
+select
+    md.address, md.name, md.seller_fee_basis_points, md.mint_address,
+    md.primary_sale_happened, mj.description, mj.image
+from metadatas md
+    inner join metadata_jsons mj
+        on (mj.metadata_address = md.address)
+
+where $1 <% md.name
+order by word_similarity($1, md.name) desc, similarity($1, md.name) desc, md.address
+limit $2;
+ -- $1: search term::text
+ -- $2: limit::integer";
+
+/// Search for NFTs whose name fuzzily matches the given term, best matches
+/// first.  Names are ranked by `pg_trgm` word similarity, so a term matching a
+/// whole word of a name ranks above partial matches, with ties broken in favor
+/// of names closer to the term overall.
+///
+/// This relies on the `metadatas_name_trgm_idx` trigram index to avoid
+/// scanning every metadata row.
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn search(
+    conn: &Connection,
+    term: impl ToSql<Text, Pg>,
+    limit: impl ToSql<Integer, Pg>,
+) -> Result<Vec<Nft>> {
+    diesel::sql_query(SEARCH_QUERY)
+        .bind(term)
+        .bind(limit)
+        .load(conn)
+        .context("Failed to search NFTs")
+}
+
 const ACTIVITES_QUERY: &str = r"
     SELECT address, metadata, auction_house, price, auction_house, created_at, array[seller::text] as wallets, 'listing' as activity_type
         FROM listing_receipts WHERE metadata = ANY($1)
@@ -198,3 +438,317 @@ pub fn activities(
         .load(conn)
         .context("Failed to load nft(s) activities")
 }
+
+const ACTIVITY_PAGE_QUERY: &str = r"
+select address, metadata, auction_house, price, created_at, wallets, activity_type
+from (
+    select address, metadata, auction_house, price, created_at,
+        array[seller::text] as wallets, 'listing' as activity_type
+    from listing_receipts where metadata = $1
+    union all
+    select address, metadata, auction_house, price, created_at,
+        array[buyer::text, seller::text] as wallets, 'purchase' as activity_type
+    from purchase_receipts where metadata = $1
+    union all
+    select address, metadata, auction_house, price, created_at,
+        array[buyer::text] as wallets, 'bid' as activity_type
+    from bid_receipts where metadata = $1
+    union all
+    select address, metadata, auction_house, price, canceled_at as created_at,
+        array[buyer::text] as wallets, 'bid_cancelled' as activity_type
+    from bid_receipts where metadata = $1 and canceled_at is not null
+) a
+
+where ($2::timestamp is null or (a.created_at, a.address || ':' || a.activity_type) < ($2, $3))
+    and ($5::text[] is null or a.activity_type = any($5))
+order by a.created_at desc, a.address || ':' || a.activity_type desc
+limit $4;
+ -- $1: address::text
+ -- $2: cursor created_at::timestamp
+ -- $3: cursor activity key::text
+ -- $4: limit::integer
+ -- $5: activity types::text[]";
+
+/// Kinds of activity returned by [`activity_page`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumString, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum ActivityType {
+    /// An NFT was listed for sale
+    Listing,
+    /// An NFT was sold
+    Purchase,
+    /// A bid was placed on an NFT
+    Bid,
+    /// A bid on an NFT was cancelled
+    BidCancelled,
+}
+
+/// Key identifying an activity among activities created at the same time
+///
+/// A bid and its cancellation share an address, and may share a timestamp,
+/// so the key also includes the activity type.
+#[must_use]
+pub fn activity_key(activity: &NftActivity) -> String {
+    format!("{}:{}", activity.address, activity.activity_type)
+}
+
+/// Load a page of listing, sale, and bid activity for an NFT, newest first.
+///
+/// If `after` is given, only activities strictly after the `(created_at,
+/// key)` keyset position it names are returned, with keys given by
+/// [`activity_key`], so pages remain stable as new activity is inserted.  If
+/// `types` is given, only activities of those types are returned.
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn activity_page(
+    conn: &Connection,
+    address: impl ToSql<Text, Pg>,
+    after: Option<(NaiveDateTime, String)>,
+    limit: impl ToSql<Integer, Pg>,
+    types: Option<&[ActivityType]>,
+) -> Result<Vec<NftActivity>> {
+    let (after_created_at, after_address) = match after {
+        Some((created_at, address)) => (Some(created_at), Some(address)),
+        None => (None, None),
+    };
+    let types: Option<Vec<&'static str>> = types.map(|t| t.iter().map(|&t| t.into()).collect());
+
+    diesel::sql_query(ACTIVITY_PAGE_QUERY)
+        .bind(address)
+        .bind::<Nullable<Timestamp>, _>(after_created_at)
+        .bind::<Nullable<Text>, _>(after_address)
+        .bind(limit)
+        .bind::<Nullable<Array<Text>>, _>(types)
+        .load(conn)
+        .context("Failed to load NFT activity page")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(trait_type: &str, values: &[&str]) -> AttributeFilter {
+        AttributeFilter {
+            trait_type: trait_type.into(),
+            values: values.iter().map(|&v| v.into()).collect(),
+        }
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(normalize_trait_type("  Background\t"), "background");
+        assert_eq!(normalize_trait_type("EYES"), "eyes");
+    }
+
+    #[test]
+    fn merge_same_trait_type() {
+        let merged = merge_attribute_filters(vec![
+            filter(" Background", &["Blue"]),
+            filter("Eyes", &["Laser"]),
+            filter("background ", &["Red"]),
+        ]);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].trait_type, "background");
+        assert_eq!(merged[0].values, vec!["Blue", "Red"]);
+        assert_eq!(merged[1].trait_type, "eyes");
+        assert_eq!(merged[1].values, vec!["Laser"]);
+    }
+
+    #[test]
+    fn merge_nothing() {
+        assert!(merge_attribute_filters(vec![]).is_empty());
+    }
+
+    #[test]
+    fn bid_and_cancellation_keys_differ() {
+        let activity = |activity_type: &str| NftActivity {
+            address: "bid".into(),
+            metadata: "metadata".into(),
+            auction_house: "auction_house".into(),
+            price: 1,
+            created_at: NaiveDateTime::from_timestamp(1_650_000_000, 0),
+            wallets: vec!["buyer".into()],
+            activity_type: activity_type.into(),
+        };
+
+        assert_eq!(activity_key(&activity("bid")), "bid:bid");
+        assert_eq!(
+            activity_key(&activity("bid_cancelled")),
+            "bid:bid_cancelled"
+        );
+    }
+
+    fn at(secs: i64) -> NaiveDateTime {
+        NaiveDateTime::from_timestamp(1_650_000_000 + secs, 0)
+    }
+
+    /// Seed one listing, one sale, and one bid which was later cancelled, a
+    /// second apart in that order
+    fn seed_activity(conn: &Connection) {
+        use crate::db::{insert_into, tables::purchase_receipts, testing};
+
+        insert_into(listing_receipts::table)
+            .values(&testing::listing_receipt(
+                "listing",
+                "house",
+                "nft",
+                "seller",
+                100,
+                at(0),
+            ))
+            .execute(conn)
+            .unwrap();
+        insert_into(purchase_receipts::table)
+            .values(&testing::purchase_receipt(
+                "purchase",
+                "house",
+                "nft",
+                ("seller", "buyer"),
+                100,
+                at(1),
+            ))
+            .execute(conn)
+            .unwrap();
+        insert_into(bid_receipts::table)
+            .values(&crate::db::models::BidReceipt {
+                canceled_at: Some(at(3)),
+                ..testing::bid_receipt("bid", "house", "nft", "bidder", 90, at(2))
+            })
+            .execute(conn)
+            .unwrap();
+    }
+
+    fn summarize(activities: Vec<NftActivity>) -> Vec<(String, String, Vec<String>)> {
+        activities
+            .into_iter()
+            .map(|a| (a.address, a.activity_type, a.wallets))
+            .collect()
+    }
+
+    fn row(address: &str, ty: &str, wallets: &[&str]) -> (String, String, Vec<String>) {
+        (
+            address.into(),
+            ty.into(),
+            wallets.iter().map(|&w| w.into()).collect(),
+        )
+    }
+
+    #[test]
+    fn listing_and_sale_activities() {
+        let pool = match crate::db::testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+        seed_activity(&conn);
+
+        assert_eq!(summarize(activities(&conn, vec!["nft"]).unwrap()), vec![
+            row("purchase", "purchase", &["seller", "buyer"]),
+            row("listing", "listing", &["seller"]),
+        ]);
+    }
+
+    #[test]
+    fn activity_pages() {
+        let pool = match crate::db::testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+        seed_activity(&conn);
+
+        let all = activity_page(&conn, "nft", None, 10, None).unwrap();
+        let cursor = (all[1].created_at, activity_key(&all[1]));
+
+        assert_eq!(summarize(all), vec![
+            row("bid", "bid_cancelled", &["bidder"]),
+            row("bid", "bid", &["bidder"]),
+            row("purchase", "purchase", &["buyer", "seller"]),
+            row("listing", "listing", &["seller"]),
+        ]);
+
+        assert_eq!(
+            summarize(activity_page(&conn, "nft", Some(cursor), 10, None).unwrap()),
+            vec![
+                row("purchase", "purchase", &["buyer", "seller"]),
+                row("listing", "listing", &["seller"]),
+            ]
+        );
+
+        assert_eq!(
+            summarize(
+                activity_page(
+                    &conn,
+                    "nft",
+                    None,
+                    10,
+                    Some(&[ActivityType::Bid, ActivityType::Listing])
+                )
+                .unwrap()
+            ),
+            vec![
+                row("bid", "bid", &["bidder"]),
+                row("listing", "listing", &["seller"]),
+            ]
+        );
+
+        assert_eq!(activity_page(&conn, "nft", None, 1, None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn owned_nfts_exclude_fungible_balances() {
+        use crate::db::{insert_into, testing};
+
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+
+        insert_into(metadatas::table)
+            .values(&[
+                testing::metadata("a", "mint_a", true),
+                testing::metadata("b", "mint_b", true),
+                testing::metadata("fungible", "mint_f", true),
+                testing::metadata("other", "mint_o", true),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(metadata_jsons::table)
+            .values(&[
+                testing::metadata_json("a", "a.png"),
+                testing::metadata_json("b", "b.png"),
+                testing::metadata_json("fungible", "f.png"),
+                testing::metadata_json("other", "o.png"),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(token_accounts::table)
+            .values(&[
+                testing::token_account("ta", "mint_a", "wallet", 1),
+                testing::token_account("tb", "mint_b", "wallet", 1),
+                testing::token_account("tf", "mint_f", "wallet", 500),
+                testing::token_account("to", "mint_o", "someone", 1),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        let addresses = |nfts: Vec<Nft>| nfts.into_iter().map(|n| n.address).collect::<Vec<_>>();
+
+        assert_eq!(
+            addresses(owned_by(&conn, "wallet", i64::MAX, 10, 0).unwrap()),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            addresses(owned_by(&conn, "wallet", i64::MAX, 1, 1).unwrap()),
+            vec!["b"]
+        );
+        assert!(
+            owned_by(&conn, "nobody", i64::MAX, 10, 0)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
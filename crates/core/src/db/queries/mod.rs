@@ -1,10 +1,19 @@
 //! Reusable query operations for common or complicated queries.
 
+pub mod bid_receipts;
+pub mod data_quality;
 pub mod graph_connection;
 pub mod listing_denylist;
+pub mod listing_receipts;
 pub mod metadata_edition;
 pub mod metadatas;
 pub mod nft_count;
+pub mod proposals;
+pub mod purchase_receipts;
+pub mod rarity_scores;
+pub mod slot;
 pub mod stats;
 pub mod store_denylist;
+pub mod token_accounts;
 pub mod twitter_handle_name_service;
+pub mod wash_trading;
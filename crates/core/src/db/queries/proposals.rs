@@ -0,0 +1,193 @@
+//! Query utilities for looking up Tribeca governance proposals
+
+use anyhow::Context;
+use diesel::{
+    pg::Pg,
+    prelude::*,
+    serialize::ToSql,
+    sql_types::{Array, BigInt, Integer, Nullable, Text},
+};
+
+use crate::{
+    db::{models::ProposalWithMeta, Connection},
+    error::Result,
+};
+
+const GET_QUERY: &str = r"
+select
+    p.address, p.governor, p.index, p.bump, p.proposer, p.quorum_votes,
+    p.for_votes, p.against_votes, p.abstain_votes, p.canceled_at, p.created_at,
+    p.activated_at, p.voting_ends_at, p.queued_at, p.queued_transaction,
+    pm.title, pm.description_link, t.executed_at,
+    (select th.twitter_handle from twitter_handle_name_services th
+        where th.wallet_address = p.proposer and th.slot <= $2
+        limit 1) as twitter_handle
+from proposals p
+    left join proposal_metas pm
+        on (pm.proposal = p.address)
+    left join transactions t
+        on (t.address = p.queued_transaction)
+
+where p.address = $1;
+ -- $1: address::text
+ -- $2: max slot::bigint";
+
+/// Load a single proposal, along with its metadata and the proposer's Twitter
+/// handle
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn get(
+    conn: &Connection,
+    address: impl ToSql<Text, Pg>,
+    max_slot: i64,
+) -> Result<Option<ProposalWithMeta<'static>>> {
+    let mut rows: Vec<ProposalWithMeta> = diesel::sql_query(GET_QUERY)
+        .bind(address)
+        .bind::<BigInt, _>(max_slot)
+        .load(conn)
+        .context("Failed to load proposal")?;
+
+    Ok(rows.pop())
+}
+
+const BY_PROPOSER_QUERY: &str = r"
+select
+    p.address, p.governor, p.index, p.bump, p.proposer, p.quorum_votes,
+    p.for_votes, p.against_votes, p.abstain_votes, p.canceled_at, p.created_at,
+    p.activated_at, p.voting_ends_at, p.queued_at, p.queued_transaction,
+    pm.title, pm.description_link, t.executed_at,
+    (select th.twitter_handle from twitter_handle_name_services th
+        where th.wallet_address = p.proposer and th.slot <= $5
+        limit 1) as twitter_handle
+from proposals p
+    left join proposal_metas pm
+        on (pm.proposal = p.address)
+    left join transactions t
+        on (t.address = p.queued_transaction)
+
+where p.proposer = $1
+    and ($2::bigint is null or (p.created_at, p.address) < ($2, $3))
+order by p.created_at desc, p.address desc
+limit $4;
+ -- $1: proposer::text
+ -- $2: cursor created_at::bigint
+ -- $3: cursor address::text
+ -- $4: limit::integer
+ -- $5: max slot::bigint";
+
+/// Load proposals created by the given wallet, newest first, along with their
+/// metadata and the proposer's Twitter handle.
+///
+/// If `after` is given, only proposals strictly after the `(created_at,
+/// address)` keyset position it names are returned.
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn by_proposer(
+    conn: &Connection,
+    proposer: impl ToSql<Text, Pg>,
+    after: Option<(i64, String)>,
+    limit: impl ToSql<Integer, Pg>,
+    max_slot: i64,
+) -> Result<Vec<ProposalWithMeta<'static>>> {
+    let (after_created_at, after_address) = match after {
+        Some((created_at, address)) => (Some(created_at), Some(address)),
+        None => (None, None),
+    };
+
+    diesel::sql_query(BY_PROPOSER_QUERY)
+        .bind(proposer)
+        .bind::<Nullable<BigInt>, _>(after_created_at)
+        .bind::<Nullable<Text>, _>(after_address)
+        .bind(limit)
+        .bind::<BigInt, _>(max_slot)
+        .load(conn)
+        .context("Failed to load proposals by proposer")
+}
+
+const BY_GOVERNOR_QUERY: &str = r"
+select
+    p.address, p.governor, p.index, p.bump, p.proposer, p.quorum_votes,
+    p.for_votes, p.against_votes, p.abstain_votes, p.canceled_at, p.created_at,
+    p.activated_at, p.voting_ends_at, p.queued_at, p.queued_transaction,
+    pm.title, pm.description_link, t.executed_at,
+    (select th.twitter_handle from twitter_handle_name_services th
+        where th.wallet_address = p.proposer and th.slot <= $6
+        limit 1) as twitter_handle
+from proposals p
+    left join proposal_metas pm
+        on (pm.proposal = p.address)
+    left join transactions t
+        on (t.address = p.queued_transaction)
+
+where p.governor = $1
+    and ($2::text[] is null or (case
+        when p.canceled_at > 0 then 'canceled'
+        when p.activated_at = 0 then 'draft'
+        when $3 < p.voting_ends_at then 'active'
+        when p.for_votes < p.quorum_votes or p.for_votes <= p.against_votes then 'defeated'
+        when coalesce(t.executed_at, 0) > 0 then 'executed'
+        when p.queued_at > 0 then 'queued'
+        else 'succeeded'
+    end) = any($2))
+order by p.index desc
+limit $4
+offset $5;
+ -- $1: governor::text
+ -- $2: states::text[]
+ -- $3: current unix timestamp::bigint
+ -- $4: limit::integer
+ -- $5: offset::integer
+ -- $6: max slot::bigint";
+
+/// Lifecycle states of a proposal, as computed by Tribeca's
+/// `Proposal::get_state`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum ProposalState {
+    /// The proposal has not been activated
+    Draft,
+    /// The proposal is being voted on
+    Active,
+    /// The proposal was canceled
+    Canceled,
+    /// Voting ended without the proposal reaching quorum or a majority
+    Defeated,
+    /// Voting ended with the proposal passing
+    Succeeded,
+    /// The proposal passed and its transaction has been queued
+    Queued,
+    /// The proposal's queued transaction has been executed
+    Executed,
+}
+
+/// Load proposals of the given governor with the highest index first, along
+/// with their metadata and the proposer's Twitter handle.
+///
+/// If `states` is given, only proposals whose state at the unix timestamp
+/// `now` is one of `states` are returned.
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn by_governor(
+    conn: &Connection,
+    governor: impl ToSql<Text, Pg>,
+    states: Option<&[ProposalState]>,
+    now: i64,
+    limit: impl ToSql<Integer, Pg>,
+    offset: impl ToSql<Integer, Pg>,
+    max_slot: i64,
+) -> Result<Vec<ProposalWithMeta<'static>>> {
+    let states: Option<Vec<&'static str>> = states.map(|s| s.iter().map(|&s| s.into()).collect());
+
+    diesel::sql_query(BY_GOVERNOR_QUERY)
+        .bind(governor)
+        .bind::<Nullable<Array<Text>>, _>(states)
+        .bind::<BigInt, _>(now)
+        .bind(limit)
+        .bind(offset)
+        .bind::<BigInt, _>(max_slot)
+        .load(conn)
+        .context("Failed to load proposals by governor")
+}
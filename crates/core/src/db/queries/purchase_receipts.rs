@@ -0,0 +1,88 @@
+//! Query utilities for looking up purchase receipts
+
+use anyhow::Context;
+use diesel::prelude::*;
+
+use crate::{
+    db::{
+        models::PurchaseReceipt,
+        tables::{metadatas, purchase_receipts},
+        Connection,
+    },
+    error::Result,
+};
+
+/// Load every recorded sale of an NFT, identified by its metadata address,
+/// oldest first
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn sales_history(conn: &Connection, metadata: &str) -> Result<Vec<PurchaseReceipt<'static>>> {
+    purchase_receipts::table
+        .inner_join(metadatas::table.on(metadatas::address.eq(purchase_receipts::metadata)))
+        .filter(purchase_receipts::metadata.eq(metadata))
+        .select(purchase_receipts::all_columns)
+        .order((
+            purchase_receipts::created_at.asc(),
+            purchase_receipts::address.asc(),
+        ))
+        .load(conn)
+        .context("Failed to load sales history")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Local};
+
+    use super::*;
+    use crate::db::{insert_into, testing};
+
+    #[test]
+    fn sales_are_chronological() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+        let now = Local::now().naive_utc();
+        let hours_ago = |h| now - Duration::hours(h);
+
+        insert_into(metadatas::table)
+            .values(&[
+                testing::metadata("nft", "mint", true),
+                testing::metadata("other", "other_mint", true),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        let sale = |address, metadata, parties, price, hours| {
+            testing::purchase_receipt(address, "house", metadata, parties, price, hours_ago(hours))
+        };
+
+        // Inserted out of order to check sorting
+        insert_into(purchase_receipts::table)
+            .values(&[
+                sale("second", "nft", ("bob", "carol"), 200, 2),
+                sale("third", "nft", ("carol", "dave"), 300, 1),
+                sale("first", "nft", ("alice", "bob"), 100, 3),
+                sale("unrelated", "other", ("eve", "frank"), 50, 2),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        let sales = sales_history(&conn, "nft").unwrap();
+
+        assert_eq!(
+            sales
+                .iter()
+                .map(|s| (&*s.address, &*s.seller, &*s.buyer, s.price))
+                .collect::<Vec<_>>(),
+            vec![
+                ("first", "alice", "bob", 100),
+                ("second", "bob", "carol", 200),
+                ("third", "carol", "dave", 300),
+            ]
+        );
+        assert!(sales_history(&conn, "missing").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+//! Query utilities for NFT trait rarity within a collection
+
+use anyhow::Context;
+use diesel::{prelude::*, sql_types::Text, Connection as _};
+
+use crate::{db::Connection, error::Result};
+
+const RECOMPUTE_QUERY: &str = r"
+with collection_attributes as (
+    select
+        metadata_address,
+        coalesce(trait_type_normalized, lower(btrim(trait_type))) as trait_type,
+        value
+    from attributes
+    where first_verified_creator = $1
+), collection_size as (
+    select count(distinct metadata_address)::double precision as n
+    from collection_attributes
+), trait_counts as (
+    select trait_type, value, count(*) as n
+    from collection_attributes
+    group by trait_type, value
+), scores as (
+    select a.metadata_address, sum(cs.n / tc.n) as score
+    from collection_attributes a
+    inner join trait_counts tc
+        on (tc.trait_type is not distinct from a.trait_type
+            and tc.value is not distinct from a.value)
+    cross join collection_size cs
+    group by a.metadata_address
+), deleted as (
+    delete from rarity_scores
+    where first_verified_creator = $1
+        and metadata_address not in (select metadata_address from scores)
+)
+insert into rarity_scores (metadata_address, first_verified_creator, score, rank, updated_at)
+select metadata_address, $1, score, rank() over (order by score desc), now()
+from scores
+order by metadata_address
+on conflict (metadata_address) do update set
+    first_verified_creator = excluded.first_verified_creator,
+    score = excluded.score,
+    rank = excluded.rank,
+    updated_at = excluded.updated_at
+ -- $1: first_verified_creator::text";
+
+/// Recompute the rarity score and rank of every NFT in the collection with
+/// the given first verified creator, returning the number of NFTs scored.
+///
+/// Each attribute of an NFT scores the collection size divided by the number
+/// of NFTs in the collection sharing its trait type and value, and an NFT's
+/// score is the sum of its attributes' scores.  Trait types are compared
+/// once normalized, so differently cased or padded spellings of the same
+/// trait type count as one.  NFTs no longer in the collection have their
+/// scores removed.
+///
+/// The collection is rescored inside a transaction holding an advisory lock
+/// on its creator, so concurrent recomputes of the same collection run one
+/// after another rather than contending for its rows.
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn recompute(conn: &Connection, first_verified_creator: &str) -> Result<usize> {
+    conn.transaction(|| {
+        diesel::sql_query("select pg_advisory_xact_lock(hashtext($1))")
+            .bind::<Text, _>(first_verified_creator)
+            .execute(conn)?;
+
+        diesel::sql_query(RECOMPUTE_QUERY)
+            .bind::<Text, _>(first_verified_creator)
+            .execute(conn)
+    })
+    .context("Failed to recompute rarity scores")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow::Borrowed;
+
+    use super::*;
+    use crate::db::{
+        insert_into,
+        models::MetadataAttributeWrite,
+        tables::{attributes, rarity_scores},
+        testing,
+    };
+
+    fn attribute(
+        metadata_address: &'static str,
+        creator: &'static str,
+        trait_type: &'static str,
+        value: &'static str,
+    ) -> MetadataAttributeWrite<'static> {
+        MetadataAttributeWrite {
+            metadata_address: Borrowed(metadata_address),
+            value: Some(Borrowed(value)),
+            trait_type: Some(Borrowed(trait_type)),
+            first_verified_creator: Some(Borrowed(creator)),
+            trait_type_normalized: None,
+        }
+    }
+
+    #[test]
+    fn five_nft_collection() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+
+        insert_into(attributes::table)
+            .values(&[
+                attribute("nft1", "creator", "background", "red"),
+                attribute("nft1", "creator", "eyes", "laser"),
+                attribute("nft1", "creator", "hat", "crown"),
+                // Other spellings of the same trait type, with and without a
+                // stored normalized form
+                attribute("nft2", "creator", "Background ", "red"),
+                attribute("nft2", "creator", "eyes", "plain"),
+                MetadataAttributeWrite {
+                    trait_type_normalized: Some(Borrowed("background")),
+                    ..attribute("nft3", "creator", "BACKGROUND", "red")
+                },
+                attribute("nft3", "creator", "eyes", "plain"),
+                attribute("nft4", "creator", "background", "red"),
+                attribute("nft4", "creator", "eyes", "plain"),
+                attribute("nft5", "creator", "background", "gold"),
+                attribute("nft5", "creator", "eyes", "plain"),
+                attribute("other", "other_creator", "background", "gold"),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        assert_eq!(recompute(&conn, "creator").unwrap(), 5);
+
+        let scores: Vec<(String, f64, i64)> = rarity_scores::table
+            .filter(rarity_scores::first_verified_creator.eq("creator"))
+            .select((
+                rarity_scores::metadata_address,
+                rarity_scores::score,
+                rarity_scores::rank,
+            ))
+            .order(rarity_scores::metadata_address)
+            .load(&conn)
+            .unwrap();
+
+        // Background red is shared by 4 of 5, gold by 1; laser eyes by 1 and
+        // plain eyes by 4; only nft1 has a hat.  nft2 and nft3 score the same
+        // as nft4 despite spelling their background trait type differently.
+        assert_eq!(scores, vec![
+            ("nft1".into(), 5.0 / 4.0 + 5.0 + 5.0, 1),
+            ("nft2".into(), 5.0 / 4.0 + 5.0 / 4.0, 3),
+            ("nft3".into(), 5.0 / 4.0 + 5.0 / 4.0, 3),
+            ("nft4".into(), 5.0 / 4.0 + 5.0 / 4.0, 3),
+            ("nft5".into(), 5.0 + 5.0 / 4.0, 2),
+        ]);
+    }
+}
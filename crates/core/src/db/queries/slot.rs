@@ -0,0 +1,62 @@
+//! Query utilities for limiting served rows to a confirmation depth.
+//!
+//! Queries honoring the confirmation depth only return rows whose `slot` is at
+//! least `depth` slots behind the latest slot known to the indexer.  Rows
+//! without a slot are always returned.  This currently applies to:
+//!  - [`twitter_handle_name_service::get`](super::twitter_handle_name_service::get)
+//!    and [`get_wallet`](super::twitter_handle_name_service::get_wallet)
+//!  - [`token_accounts::top_holders`](super::token_accounts::top_holders)
+//!  - [`bid_receipts::received_by`](super::bid_receipts::received_by)
+//!  - [`metadatas::owned_by`](super::metadatas::owned_by) and
+//!    [`sample_owned_by`](super::metadatas::sample_owned_by)
+//!  - GraphQL lookups of NFT owners (`token_accounts`) and of Twitter handles
+//!    for wallets, creators, and profiles (`twitter_handle_name_services`)
+
+use anyhow::Context;
+use diesel::{
+    dsl::max,
+    prelude::*,
+    sql_types::{BigInt, Nullable},
+};
+
+use crate::{
+    db::{
+        tables::{token_accounts, twitter_handle_name_services},
+        Connection,
+    },
+    error::Result,
+};
+
+sql_function! {
+    /// Return the slot of a row, treating rows without a slot as slot zero so
+    /// they are never filtered out by a confirmation depth
+    #[sql_name = "coalesce"]
+    fn coalesce_slot(slot: Nullable<BigInt>, default: BigInt) -> BigInt;
+}
+
+/// Return the highest slot that has been confirmed to the given depth, or
+/// [`i64::MAX`] if the depth is zero and all rows should be served.
+///
+/// # Errors
+/// This function fails if the underlying queries fail to execute.
+pub fn max_confirmed(conn: &Connection, depth: u64) -> Result<i64> {
+    if depth == 0 {
+        return Ok(i64::MAX);
+    }
+
+    let tokens: Option<i64> = token_accounts::table
+        .select(max(token_accounts::slot))
+        .first(conn)
+        .context("Failed to load latest token account slot")?;
+    let handles: Option<i64> = twitter_handle_name_services::table
+        .select(max(twitter_handle_name_services::slot))
+        .first(conn)
+        .context("Failed to load latest Twitter handle slot")?;
+
+    let latest = match tokens.max(handles) {
+        Some(s) => s,
+        None => return Ok(i64::MAX),
+    };
+
+    Ok(latest.saturating_sub(depth.try_into().unwrap_or(i64::MAX)))
+}
@@ -11,7 +11,7 @@ use diesel::{
 
 use crate::{
     db::{
-        models::{MarketStats, MintStats},
+        models::{ActivitySummary, FloorPoint, MarketStats, MarketplaceSummary, MintStats},
         Connection,
     },
     error::Result,
@@ -23,6 +23,7 @@ select
     auction_house,
     mint,
     min(listing_price) filter (where listing_canceled_at is null and listing_purchase_receipt is null)::bigint as floor,
+    min(listing_price) filter (where listing_canceled_at is null and listing_purchase_receipt is null and secondary)::bigint as secondary_floor,
     round(avg(purchase_price))::bigint as average,
     sum(purchase_price) filter (where ($2 - purchased_at) < interval '24 hr')::bigint as volume_24hr
 
@@ -32,10 +33,13 @@ from (select lr.auction_house as auction_house,
         lr.created_at as listed_at,
         lr.purchase_receipt as listing_purchase_receipt,
         lr.canceled_at as listing_canceled_at,
+        coalesce(md.primary_sale_happened, true) as secondary,
         ah.treasury_mint as mint
 from listing_receipts lr
     inner join auction_houses ah
         on (lr.auction_house = ah.address)
+    left join metadatas md
+        on (lr.metadata = md.address)
     left join purchase_receipts pr
         on (lr.purchase_receipt = pr.address)
 
@@ -87,11 +91,72 @@ pub fn marketplace(
         .context("Failed to load marketplace stats")
 }
 
+const MARKET_SUMMARY_QUERY: &str = r"
+with items as (
+    select distinct mc.metadata_address as metadata
+    from store_creators sc
+        inner join metadata_creators mc
+            on (mc.creator_address = sc.creator_address)
+
+    where sc.store_config_address = $1 and mc.verified
+), house as (
+    select auction_house_address as address
+    from store_config_jsons
+    where config_address = $1
+), listings as (
+    select lr.price
+    from listing_receipts lr
+        inner join items
+            on (items.metadata = lr.metadata)
+        inner join house
+            on (house.address = lr.auction_house)
+
+    where lr.purchase_receipt is null and lr.canceled_at is null
+)
+select
+    (select min(price) from listings)::bigint as floor,
+    (select sum(pr.price)
+        from purchase_receipts pr
+            inner join items
+                on (items.metadata = pr.metadata)
+            inner join house
+                on (house.address = pr.auction_house)
+        where pr.created_at >= $2)::bigint as volume,
+    (select count(*) from listings)::bigint as listed,
+    (select count(distinct ta.owner_address)
+        from token_accounts ta
+            inner join metadatas md
+                on (md.mint_address = ta.mint_address)
+            inner join items
+                on (items.metadata = md.address)
+        where ta.amount > 0)::bigint as holders;
+ -- $1: store config address::text
+ -- $2: window start::timestamp";
+
+/// Summarize the floor price, sale volume since the given time, number of
+/// active listings, and number of holders of a marketplace.  Only listings
+/// and sales on the marketplace's own auction house are counted.
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn marketplace_summary(
+    conn: &Connection,
+    store_config: impl ToSql<Text, Pg>,
+    since: NaiveDateTime,
+) -> Result<MarketplaceSummary> {
+    diesel::sql_query(MARKET_SUMMARY_QUERY)
+        .bind(store_config)
+        .bind::<Timestamp, _>(since)
+        .get_result(conn)
+        .context("Failed to load marketplace summary")
+}
+
 const COLLECTION_QUERY: &str = r"
 select
     auction_house,
     mint,
     min(listing_price) filter (where listing_canceled_at is null and listing_purchase_receipt is null)::bigint as floor,
+    min(listing_price) filter (where listing_canceled_at is null and listing_purchase_receipt is null and secondary)::bigint as secondary_floor,
     round(avg(purchase_price))::bigint as average,
     sum(purchase_price) filter (where ($3 - purchased_at) < interval '24 hr')::bigint as volume_24hr
 
@@ -103,6 +168,7 @@ from (
         lr.created_at as listed_at,
         lr.purchase_receipt as listing_purchase_receipt,
         lr.canceled_at as listing_canceled_at,
+        coalesce(md.primary_sale_happened, true) as secondary,
         ah.treasury_mint as mint
 from listing_receipts lr
     inner join auction_houses ah
@@ -139,3 +205,338 @@ pub fn collection(
         .load(conn)
         .context("Failed to load collection mint stats")
 }
+
+const ACTIVITY_QUERY: &str = r"
+select
+    (select count(*) from listing_receipts
+        where auction_house = $1 and created_at >= $2)::bigint as listings,
+    (select count(*) from purchase_receipts
+        where auction_house = $1 and created_at >= $2)::bigint as sales,
+    (select count(*) from listing_receipts
+        where auction_house = $1 and canceled_at >= $2)::bigint as cancellations,
+    (select count(*) from bid_receipts
+        where auction_house = $1 and created_at >= $2)::bigint as bids;
+ -- $1: auction house address::text
+ -- $2: window start::timestamp";
+
+/// Count listings, sales, cancellations, and bids on the given auction house
+/// since the given time
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn activity(
+    conn: &Connection,
+    auction_house: impl ToSql<Text, Pg>,
+    since: NaiveDateTime,
+) -> Result<ActivitySummary> {
+    diesel::sql_query(ACTIVITY_QUERY)
+        .bind(auction_house)
+        .bind::<Timestamp, _>(since)
+        .get_result(conn)
+        .context("Failed to load activity summary")
+}
+
+/// Width of the buckets in a time series
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Interval {
+    /// One-hour buckets
+    Hour,
+    /// One-day buckets
+    Day,
+}
+
+impl Interval {
+    /// The length of a single bucket
+    #[must_use]
+    pub fn duration(self) -> chrono::Duration {
+        match self {
+            Self::Hour => chrono::Duration::hours(1),
+            Self::Day => chrono::Duration::days(1),
+        }
+    }
+}
+
+const FLOOR_HISTORY_QUERY: &str = r"
+with items as (
+    select distinct metadata_address as metadata
+    from attributes
+    where first_verified_creator = $1
+), listings as (
+    select lr.price, lr.created_at as listed_at,
+        least(lr.canceled_at, pr.created_at) as closed_at
+    from listing_receipts lr
+        inner join items
+            on (items.metadata = lr.metadata)
+        left join purchase_receipts pr
+            on (pr.address = lr.purchase_receipt)
+
+    where lr.created_at < $3
+), buckets as (
+    select start
+    from generate_series(date_trunc($4, $2), $3, ('1 ' || $4)::interval) start
+    where start < $3
+)
+select b.start as timestamp, min(l.price)::bigint as floor
+from buckets b
+    left join listings l
+        on (l.listed_at < b.start + ('1 ' || $4)::interval
+            and (l.closed_at is null or l.closed_at >= b.start))
+group by b.start
+order by b.start;
+ -- $1: collection creator::text
+ -- $2: start time::timestamp
+ -- $3: end time::timestamp
+ -- $4: interval::text";
+
+/// Compute the floor of a collection, identified by its first verified
+/// creator, over each bucket of the given width between `from` and `to`.
+/// The floor of a bucket is the lowest price of any listing active at some
+/// point during it, or `None` if there were no active listings.
+///
+/// This scans every listing of the collection created before `to` once per
+/// bucket, so it is relatively expensive for large collections or long
+/// ranges.  If it becomes a bottleneck, the hourly floors could be
+/// precomputed into a materialized view refreshed on a schedule, from which
+/// daily floors can be rolled up.
+///
+/// Collections are matched using the `first_verified_creator` of NFT
+/// attributes, so NFTs whose JSON has no attributes are not counted.
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn floor_history(
+    conn: &Connection,
+    creator: impl ToSql<Text, Pg>,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+    interval: Interval,
+) -> Result<Vec<FloorPoint>> {
+    diesel::sql_query(FLOOR_HISTORY_QUERY)
+        .bind(creator)
+        .bind::<Timestamp, _>(from)
+        .bind::<Timestamp, _>(to)
+        .bind::<Text, _>(interval.to_string())
+        .load(conn)
+        .context("Failed to load collection floor history")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        insert_into,
+        models::{ListingReceipt, MetadataAttributeWrite},
+        tables::{
+            attributes, auction_houses, listing_receipts, metadata_creators, metadatas,
+            purchase_receipts, store_config_jsons, store_creators, token_accounts,
+        },
+        testing,
+    };
+
+    #[test]
+    fn secondary_floors_agree() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+        let now = Local::now().naive_utc();
+
+        insert_into(auction_houses::table)
+            .values(&testing::auction_house("house", "sol"))
+            .execute(&conn)
+            .unwrap();
+        insert_into(metadatas::table)
+            .values(&[
+                testing::metadata("primary", "primary_mint", false),
+                testing::metadata("secondary", "secondary_mint", true),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(metadata_creators::table)
+            .values(&[
+                testing::metadata_creator("primary", "creator", true),
+                testing::metadata_creator("secondary", "creator", true),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(listing_receipts::table)
+            .values(&[
+                testing::listing_receipt("l1", "house", "primary", "seller", 100, now),
+                testing::listing_receipt("l2", "house", "secondary", "seller", 200, now),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        let houses = vec!["house"];
+        let by_mint = mint(&conn, &houses).unwrap();
+        let by_collection = collection(&conn, &houses, "creator").unwrap();
+
+        for stats in [by_mint, by_collection] {
+            assert_eq!(stats.len(), 1);
+            assert_eq!(stats[0].mint, "sol");
+            assert_eq!(stats[0].floor, Some(100));
+            assert_eq!(stats[0].secondary_floor, Some(200));
+        }
+    }
+
+    #[test]
+    fn marketplace_summary_counts_its_own_trades() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+        let now = Local::now().naive_utc();
+        let hours_ago = |h| now - chrono::Duration::hours(h);
+
+        insert_into(store_config_jsons::table)
+            .values(&testing::store_config_json("config", "market", "house"))
+            .execute(&conn)
+            .unwrap();
+        insert_into(store_creators::table)
+            .values(&testing::store_creator("config", "creator"))
+            .execute(&conn)
+            .unwrap();
+        insert_into(metadatas::table)
+            .values(&[
+                testing::metadata("a", "mint_a", true),
+                testing::metadata("b", "mint_b", true),
+                testing::metadata("c", "mint_c", true),
+                testing::metadata("unverified", "mint_u", true),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(metadata_creators::table)
+            .values(&[
+                testing::metadata_creator("a", "creator", true),
+                testing::metadata_creator("b", "creator", true),
+                testing::metadata_creator("c", "creator", true),
+                testing::metadata_creator("unverified", "creator", false),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        let canceled = ListingReceipt {
+            canceled_at: Some(hours_ago(1)),
+            ..testing::listing_receipt("l3", "house", "c", "seller", 50, hours_ago(2))
+        };
+        insert_into(listing_receipts::table)
+            .values(&[
+                testing::listing_receipt("l1", "house", "a", "seller", 100, hours_ago(2)),
+                testing::listing_receipt("l2", "house", "b", "seller", 300, hours_ago(2)),
+                canceled,
+                testing::listing_receipt("l4", "other", "a", "seller", 10, hours_ago(2)),
+                testing::listing_receipt("l5", "house", "unverified", "seller", 5, hours_ago(2)),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(purchase_receipts::table)
+            .values(&[
+                testing::purchase_receipt("p1", "house", "a", ("x", "y"), 500, hours_ago(1)),
+                testing::purchase_receipt("p2", "house", "b", ("x", "y"), 700, hours_ago(72)),
+                testing::purchase_receipt("p3", "other", "c", ("x", "y"), 200, hours_ago(1)),
+                testing::purchase_receipt("p4", "house", "unverified", ("x", "y"), 900, now),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(token_accounts::table)
+            .values(&[
+                testing::token_account("ta", "mint_a", "alice", 1),
+                testing::token_account("tb", "mint_b", "bob", 1),
+                testing::token_account("tb2", "mint_b", "alice", 1),
+                testing::token_account("tc", "mint_c", "carol", 0),
+                testing::token_account("tu", "mint_u", "dave", 1),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        let summary = marketplace_summary(&conn, "config", hours_ago(24)).unwrap();
+
+        assert_eq!(summary.floor, Some(100));
+        assert_eq!(summary.volume, Some(500));
+        assert_eq!(summary.listed, 2);
+        assert_eq!(summary.holders, 2);
+
+        let summary = marketplace_summary(&conn, "config", hours_ago(96)).unwrap();
+        assert_eq!(summary.volume, Some(1200));
+
+        let empty = marketplace_summary(&conn, "missing", hours_ago(24)).unwrap();
+        assert_eq!(
+            (empty.floor, empty.volume, empty.listed, empty.holders),
+            (None, None, 0, 0)
+        );
+    }
+
+    #[test]
+    fn daily_floor_history() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+        let day = |d, h| NaiveDate::from_ymd(2022, 4, d).and_hms(h, 0, 0);
+        let attribute = |metadata, creator| MetadataAttributeWrite {
+            metadata_address: Borrowed(metadata),
+            value: Some(Borrowed("Gold")),
+            trait_type: Some(Borrowed("Background")),
+            first_verified_creator: Some(Borrowed(creator)),
+            trait_type_normalized: Some(Borrowed("background")),
+        };
+
+        insert_into(attributes::table)
+            .values(&[
+                attribute("a", "creator"),
+                attribute("b", "creator"),
+                attribute("c", "creator"),
+                attribute("other", "someone_else"),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        let canceled = ListingReceipt {
+            canceled_at: Some(day(2, 5)),
+            ..testing::listing_receipt("lb", "house", "b", "seller", 100, day(2, 2))
+        };
+        let sold = ListingReceipt {
+            purchase_receipt: Some(Borrowed("pc")),
+            ..testing::listing_receipt("lc", "house", "c", "seller", 50, day(4, 5))
+        };
+        insert_into(listing_receipts::table)
+            .values(&[
+                testing::listing_receipt("la", "house", "a", "seller", 300, day(3, 1)),
+                canceled,
+                sold,
+                testing::listing_receipt("lo", "house", "other", "seller", 10, day(2, 1)),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(purchase_receipts::table)
+            .values(&testing::purchase_receipt(
+                "pc",
+                "house",
+                "c",
+                ("buyer", "seller"),
+                50,
+                day(4, 6),
+            ))
+            .execute(&conn)
+            .unwrap();
+
+        let history = floor_history(&conn, "creator", day(1, 3), day(5, 0), Interval::Day).unwrap();
+
+        assert_eq!(
+            history
+                .into_iter()
+                .map(|FloorPoint { timestamp, floor }| (timestamp, floor))
+                .collect::<Vec<_>>(),
+            vec![
+                (day(1, 0), None),
+                (day(2, 0), Some(100)),
+                (day(3, 0), Some(300)),
+                (day(4, 0), Some(50)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Query utilities for looking up token accounts
+
+use anyhow::Context;
+use chrono::NaiveDateTime;
+use diesel::{
+    pg::Pg,
+    prelude::*,
+    serialize::ToSql,
+    sql_types::{Integer, Text},
+};
+
+use super::slot;
+use crate::{
+    db::{
+        models::TokenHolder,
+        tables::{metadatas, purchase_receipts, token_accounts},
+        Connection,
+    },
+    error::Result,
+};
+
+const TOP_HOLDERS_QUERY: &str = r"
+with holders as (
+    select owner_address, sum(amount)::bigint as amount
+    from token_accounts
+    where mint_address = $1
+        and amount > 0
+        and coalesce(slot, 0) <= $3
+    group by owner_address
+)
+select
+    h.owner_address,
+    h.amount,
+    t.total,
+    (select th.twitter_handle from twitter_handle_name_services th
+        where th.wallet_address = h.owner_address and th.slot <= $3
+        limit 1) as twitter_handle
+from holders h
+    cross join (select coalesce(sum(amount), 0)::bigint as total from holders) t
+
+order by h.amount desc, h.owner_address asc
+limit $2;
+ -- $1: mint address::text
+ -- $2: limit::integer
+ -- $3: max slot::bigint";
+
+/// Load the wallets holding the largest balances of the given mint, along
+/// with the total amount held across all wallets
+///
+/// # Errors
+/// This function fails if the underlying SQL query returns an error
+pub fn top_holders(
+    conn: &Connection,
+    mint: impl ToSql<Text, Pg>,
+    limit: impl ToSql<Integer, Pg>,
+    max_slot: i64,
+) -> Result<Vec<TokenHolder>> {
+    diesel::sql_query(TOP_HOLDERS_QUERY)
+        .bind(mint)
+        .bind(limit)
+        .bind::<diesel::sql_types::BigInt, _>(max_slot)
+        .load(conn)
+        .context("Failed to load top token holders")
+}
+
+/// The way an NFT came into the custody of a wallet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OwnershipEventKind {
+    /// The wallet is the earliest known holder of the NFT
+    Mint,
+    /// The wallet bought the NFT from the previous holder on an auction house
+    Sale,
+    /// The NFT was transferred from the previous holder outside of a sale
+    Transfer,
+}
+
+/// A change in the custody of an NFT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnershipEvent {
+    /// The wallet receiving the NFT
+    pub owner: String,
+    /// The wallet the NFT was received from, if any
+    pub from: Option<String>,
+    /// The approximate time of the change, if known
+    pub timestamp: Option<NaiveDateTime>,
+    /// How the NFT changed hands
+    pub kind: OwnershipEventKind,
+}
+
+/// Reconstruct the chain of custody of an NFT from its token accounts and
+/// purchase receipts, oldest first
+///
+/// Token accounts are ordered by the slot they were last written at, so each
+/// account emptied when the NFT left it is followed by the account it moved
+/// to.  Consecutive accounts with the same owner are merged, and a change of
+/// owner is reported as a sale if a purchase receipt matches its seller and
+/// buyer.  Because only the latest state of each account is indexed, the time
+/// of a transfer is approximated by when the previous account was last
+/// written, and the time of the mint is unknown.
+///
+/// # Errors
+/// This function fails if an underlying query fails to execute.
+pub fn ownership_history(
+    conn: &Connection,
+    mint: &str,
+    max_slot: i64,
+) -> Result<Vec<OwnershipEvent>> {
+    let accounts: Vec<(String, Option<i64>, NaiveDateTime)> = token_accounts::table
+        .filter(token_accounts::mint_address.eq(mint))
+        .filter(slot::coalesce_slot(token_accounts::slot, 0).le(max_slot))
+        .select((
+            token_accounts::owner_address,
+            token_accounts::slot,
+            token_accounts::updated_at,
+        ))
+        .load(conn)
+        .context("Failed to load NFT token accounts")?;
+
+    let sales: Vec<(String, String, NaiveDateTime)> = purchase_receipts::table
+        .inner_join(metadatas::table.on(metadatas::address.eq(purchase_receipts::metadata)))
+        .filter(metadatas::mint_address.eq(mint))
+        .select((
+            purchase_receipts::seller,
+            purchase_receipts::buyer,
+            purchase_receipts::created_at,
+        ))
+        .order(purchase_receipts::created_at.asc())
+        .load(conn)
+        .context("Failed to load NFT purchase receipts")?;
+
+    Ok(ownership_events(accounts, sales))
+}
+
+/// Build the chain of custody for [`ownership_history`] from the owner, slot,
+/// and last write time of each token account of an NFT, and the seller, buyer,
+/// and time of each of its sales, oldest first
+fn ownership_events(
+    mut accounts: Vec<(String, Option<i64>, NaiveDateTime)>,
+    mut sales: Vec<(String, String, NaiveDateTime)>,
+) -> Vec<OwnershipEvent> {
+    // Accounts written before slots were indexed sort first
+    accounts.sort_by_key(|(_, slot, updated_at)| (slot.unwrap_or(0), *updated_at));
+
+    let mut events: Vec<OwnershipEvent> = Vec::new();
+    let mut left_at = None;
+
+    for (owner, _, updated_at) in accounts {
+        let from = match events.last() {
+            Some(e) if e.owner == owner => {
+                left_at = Some(updated_at);
+                continue;
+            },
+            Some(e) => Some(e.owner.clone()),
+            None => None,
+        };
+
+        let event = match from {
+            None => OwnershipEvent {
+                owner,
+                from: None,
+                timestamp: None,
+                kind: OwnershipEventKind::Mint,
+            },
+            Some(from) => {
+                match sales
+                    .iter()
+                    .position(|(seller, buyer, _)| *seller == from && *buyer == owner)
+                {
+                    Some(i) => {
+                        let (_, _, created_at) = sales.remove(i);
+
+                        OwnershipEvent {
+                            owner,
+                            from: Some(from),
+                            timestamp: Some(created_at),
+                            kind: OwnershipEventKind::Sale,
+                        }
+                    },
+                    None => OwnershipEvent {
+                        owner,
+                        from: Some(from),
+                        timestamp: left_at,
+                        kind: OwnershipEventKind::Transfer,
+                    },
+                }
+            },
+        };
+
+        events.push(event);
+        left_at = Some(updated_at);
+    }
+
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32) -> NaiveDateTime {
+        NaiveDateTime::from_timestamp(1_650_000_000, 0) + chrono::Duration::hours(hour.into())
+    }
+
+    fn account(owner: &str, slot: i64, hour: u32) -> (String, Option<i64>, NaiveDateTime) {
+        (owner.to_owned(), Some(slot), at(hour))
+    }
+
+    fn event(
+        owner: &str,
+        from: Option<&str>,
+        timestamp: Option<NaiveDateTime>,
+        kind: OwnershipEventKind,
+    ) -> OwnershipEvent {
+        OwnershipEvent {
+            owner: owner.to_owned(),
+            from: from.map(ToOwned::to_owned),
+            timestamp,
+            kind,
+        }
+    }
+
+    #[test]
+    fn mint_sale_and_transfer() {
+        let accounts = vec![
+            account("carol", 30, 9),
+            account("alice", 10, 2),
+            account("bob", 20, 5),
+            // A second account of the same owner is not a change of custody
+            account("alice", 12, 3),
+        ];
+        let sales = vec![
+            ("alice".to_owned(), "bob".to_owned(), at(4)),
+            ("eve".to_owned(), "mallory".to_owned(), at(6)),
+        ];
+
+        assert_eq!(ownership_events(accounts, sales), vec![
+            event("alice", None, None, OwnershipEventKind::Mint),
+            event("bob", Some("alice"), Some(at(4)), OwnershipEventKind::Sale),
+            event(
+                "carol",
+                Some("bob"),
+                Some(at(5)),
+                OwnershipEventKind::Transfer
+            ),
+        ]);
+    }
+
+    #[test]
+    fn no_accounts() {
+        assert_eq!(
+            ownership_events(vec![], vec![("alice".to_owned(), "bob".to_owned(), at(0))]),
+            vec![]
+        );
+    }
+}
@@ -14,11 +14,16 @@ use crate::{
     prelude::*,
 };
 
-/// Return twitter handle linked to the provide wallet address
+/// Return twitter handle linked to the provide wallet address, ignoring
+/// handles indexed after `max_slot`
 ///
 /// # Errors
 /// This function fails if the underlying query fails to execute.
-pub fn get<A: AsExpression<Text>>(conn: &Connection, address: A) -> Result<Option<String>>
+pub fn get<A: AsExpression<Text>>(
+    conn: &Connection,
+    address: A,
+    max_slot: i64,
+) -> Result<Option<String>>
 where
     A::Expression: NonAggregate
         + QueryId
@@ -27,8 +32,86 @@ where
 {
     twitter_handle_name_services::table
         .filter(twitter_handle_name_services::wallet_address.eq(address))
+        .filter(twitter_handle_name_services::slot.le(max_slot))
         .select(twitter_handle_name_services::twitter_handle)
         .first(conn)
         .optional()
         .context("Failed to load twitter handle")
 }
+
+/// Return the wallet address linked to the provided twitter handle, ignoring
+/// handles indexed after `max_slot`.  If the handle has been registered more
+/// than once, the registration with the highest slot is preferred.
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn get_wallet<H: AsExpression<Text>>(
+    conn: &Connection,
+    handle: H,
+    max_slot: i64,
+) -> Result<Option<String>>
+where
+    H::Expression: NonAggregate
+        + QueryId
+        + QueryFragment<Pg>
+        + AppearsOnTable<twitter_handle_name_services::table>,
+{
+    twitter_handle_name_services::table
+        .filter(twitter_handle_name_services::twitter_handle.eq(handle))
+        .filter(twitter_handle_name_services::slot.le(max_slot))
+        .select(twitter_handle_name_services::wallet_address)
+        .order(twitter_handle_name_services::slot.desc())
+        .first(conn)
+        .optional()
+        .context("Failed to load wallet for twitter handle")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_into, models::TwitterHandle, testing};
+
+    fn handle(address: &'static str, wallet: &'static str, slot: i64) -> TwitterHandle<'static> {
+        TwitterHandle {
+            address: Borrowed(address),
+            wallet_address: Borrowed(wallet),
+            twitter_handle: Borrowed("degods"),
+            slot,
+        }
+    }
+
+    #[test]
+    fn latest_registration_wins() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+
+        insert_into(twitter_handle_name_services::table)
+            .values(&[
+                handle("old", "old_wallet", 10),
+                handle("new", "new_wallet", 20),
+                handle("unconfirmed", "unconfirmed_wallet", 30),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        assert_eq!(
+            get_wallet(&conn, "degods", 25).unwrap().as_deref(),
+            Some("new_wallet")
+        );
+        assert_eq!(
+            get_wallet(&conn, "degods", 15).unwrap().as_deref(),
+            Some("old_wallet")
+        );
+        assert_eq!(get_wallet(&conn, "degods", 5).unwrap(), None);
+        assert_eq!(get_wallet(&conn, "missing", 25).unwrap(), None);
+
+        assert_eq!(
+            get(&conn, "new_wallet", 25).unwrap().as_deref(),
+            Some("degods")
+        );
+        assert_eq!(get(&conn, "unconfirmed_wallet", 25).unwrap(), None);
+    }
+}
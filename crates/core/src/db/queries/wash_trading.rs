@@ -0,0 +1,192 @@
+//! Heuristics for flagging possible wash trading in an NFT's sale history.
+
+use std::collections::HashSet;
+
+use anyhow::Context;
+use chrono::Duration;
+use diesel::prelude::*;
+
+use crate::{
+    db::{models::PurchaseReceipt, tables::purchase_receipts, Connection},
+    error::Result,
+};
+
+/// Thresholds used to flag suspicious sales
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    /// Length of the window of preceding sales each sale is compared against
+    pub window: Duration,
+    /// Sales within a window trading between at most this many distinct
+    /// wallets are considered a closed cycle
+    pub max_wallets: usize,
+    /// Minimum number of sales within a window for it to be considered a cycle
+    pub min_sales: usize,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            window: Duration::days(7),
+            max_wallets: 3,
+            min_sales: 3,
+        }
+    }
+}
+
+/// A sale flagged as a possible wash trade
+#[derive(Debug, Clone)]
+pub struct SuspiciousTrade {
+    /// The flagged sale
+    pub purchase: PurchaseReceipt<'static>,
+    /// A human-readable explanation of why the sale was flagged
+    pub reason: String,
+}
+
+/// Flag sales of the NFT with the given metadata address which appear to be
+/// part of a trading cycle between a small set of wallets.
+///
+/// A sale is flagged if, within the preceding window:
+///  - the buyer previously sold the NFT to the current seller, or
+///  - at least `min_sales` sales (including this one) changed hands between at
+///    most `max_wallets` distinct wallets.
+///
+/// # Errors
+/// This function fails if the underlying query fails to execute.
+pub fn suspicious_trades(
+    conn: &Connection,
+    metadata: &str,
+    thresholds: Thresholds,
+) -> Result<Vec<SuspiciousTrade>> {
+    let sales: Vec<PurchaseReceipt<'static>> = purchase_receipts::table
+        .filter(purchase_receipts::metadata.eq(metadata))
+        .order(purchase_receipts::created_at.asc())
+        .load(conn)
+        .context("Failed to load sale history")?;
+
+    Ok(flag_sales(&sales, thresholds))
+}
+
+/// Apply the heuristics described in [`suspicious_trades`] to a sale history
+/// sorted oldest first
+fn flag_sales(sales: &[PurchaseReceipt<'static>], thresholds: Thresholds) -> Vec<SuspiciousTrade> {
+    let Thresholds {
+        window,
+        max_wallets,
+        min_sales,
+    } = thresholds;
+
+    let mut flagged = Vec::new();
+
+    for (i, sale) in sales.iter().enumerate() {
+        let earlier: Vec<_> = sales[..i]
+            .iter()
+            .rev()
+            .take_while(|s| sale.created_at - s.created_at <= window)
+            .collect();
+
+        let reason = if earlier
+            .iter()
+            .any(|s| s.seller == sale.buyer && s.buyer == sale.seller)
+        {
+            Some(format!(
+                "Buyer {} previously sold this NFT to seller {} within {} hours",
+                sale.buyer,
+                sale.seller,
+                window.num_hours()
+            ))
+        } else {
+            let wallets: HashSet<_> = earlier
+                .iter()
+                .copied()
+                .chain(Some(sale))
+                .flat_map(|s| [&s.buyer, &s.seller])
+                .collect();
+            let count = earlier.len() + 1;
+
+            (count >= min_sales && wallets.len() <= max_wallets).then(|| {
+                format!(
+                    "{} sales between {} wallets within {} hours",
+                    count,
+                    wallets.len(),
+                    window.num_hours()
+                )
+            })
+        };
+
+        if let Some(reason) = reason {
+            flagged.push(SuspiciousTrade {
+                purchase: sale.clone(),
+                reason,
+            });
+        }
+    }
+
+    flagged
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use chrono::{Duration, NaiveDate};
+
+    use super::{flag_sales, PurchaseReceipt, Thresholds};
+
+    fn sale(seller: &'static str, buyer: &'static str, day: u32) -> PurchaseReceipt<'static> {
+        PurchaseReceipt {
+            address: Cow::Owned(format!("{}-{}-{}", seller, buyer, day)),
+            bookkeeper: Cow::Borrowed("bookkeeper"),
+            buyer: Cow::Borrowed(buyer),
+            seller: Cow::Borrowed(seller),
+            auction_house: Cow::Borrowed("house"),
+            metadata: Cow::Borrowed("metadata"),
+            token_size: 1,
+            price: 1_000_000_000,
+            bump: 255,
+            created_at: NaiveDate::from_ymd(2022, 4, day).and_hms(0, 0, 0),
+        }
+    }
+
+    #[test]
+    fn flags_sale_back_to_previous_seller() {
+        let sales = [sale("a", "b", 1), sale("b", "a", 2)];
+        let flagged = flag_sales(&sales, Thresholds::default());
+
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].purchase.address, "b-a-2");
+        assert!(flagged[0].reason.contains("previously sold"));
+    }
+
+    #[test]
+    fn flags_closed_cycle() {
+        let sales = [sale("a", "b", 1), sale("b", "c", 2), sale("c", "a", 3)];
+        let flagged = flag_sales(&sales, Thresholds::default());
+
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].purchase.address, "c-a-3");
+        assert!(flagged[0].reason.starts_with("3 sales between 3 wallets"));
+    }
+
+    #[test]
+    fn ignores_distinct_buyers() {
+        let sales = [
+            sale("a", "b", 1),
+            sale("b", "c", 2),
+            sale("c", "d", 3),
+            sale("d", "e", 4),
+        ];
+
+        assert!(flag_sales(&sales, Thresholds::default()).is_empty());
+    }
+
+    #[test]
+    fn ignores_sales_outside_window() {
+        let sales = [sale("a", "b", 1), sale("b", "a", 20)];
+        let thresholds = Thresholds {
+            window: Duration::days(7),
+            ..Thresholds::default()
+        };
+
+        assert!(flag_sales(&sales, thresholds).is_empty());
+    }
+}
@@ -0,0 +1,275 @@
+//! Read connection pools spread across database replicas
+//!
+//! A [`ReplicaPool`] holds one connection pool per read replica and hands out
+//! connections from each in turn, preferring any replica with an idle
+//! connection over waiting on a busy one.  A replica whose pool fails to
+//! produce a connection while holding no connections at all cannot be reached,
+//! so it is marked unhealthy and skipped until [`RETRY_AFTER`] has passed,
+//! after which it is tried again.  A replica which merely has all of its
+//! connections in use is not marked unhealthy.  If every replica is unhealthy
+//! they are all still tried, so reads are never refused without an attempt.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use diesel::r2d2::PoolError;
+
+use super::{Pool, PooledConnection};
+use crate::prelude::*;
+
+/// Time an unhealthy replica is skipped for before it is tried again
+pub const RETRY_AFTER: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+struct Replica {
+    name: String,
+    pool: Pool,
+    down_since: Mutex<Option<Instant>>,
+}
+
+impl Replica {
+    fn down_since(&self) -> std::sync::MutexGuard<Option<Instant>> {
+        self.down_since.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.down_since()
+            .map_or(true, |since| since.elapsed() >= RETRY_AFTER)
+    }
+
+    fn mark_up(&self) {
+        if self.down_since().take().is_some() {
+            info!("Database replica {} recovered", self.name);
+        }
+    }
+
+    fn mark_down(&self, err: &PoolError) {
+        let mut down_since = self.down_since();
+
+        if down_since.is_none() {
+            warn!("Database replica {} is unavailable: {}", self.name, err);
+        }
+
+        *down_since = Some(Instant::now());
+    }
+}
+
+/// Health of a single replica in a [`ReplicaPool`]
+#[derive(Debug, Clone)]
+pub struct ReplicaHealth {
+    /// Name identifying the replica in logs, usually its host and port
+    pub name: String,
+    /// Whether the replica is currently receiving reads
+    pub healthy: bool,
+    /// Number of connections held by the replica's pool
+    pub connections: u32,
+    /// Number of idle connections held by the replica's pool
+    pub idle_connections: u32,
+}
+
+/// A set of connection pools for read replicas, handing out connections from
+/// each healthy replica in turn
+#[derive(Debug)]
+pub struct ReplicaPool {
+    replicas: Vec<Replica>,
+    next: AtomicUsize,
+}
+
+impl ReplicaPool {
+    /// Group connection pools for several replicas, each paired with a name
+    /// identifying it in logs and health reports
+    ///
+    /// # Errors
+    /// This function fails if no pools are given.
+    pub fn new(pools: impl IntoIterator<Item = (String, Pool)>) -> Result<Self> {
+        let replicas: Vec<_> = pools
+            .into_iter()
+            .map(|(name, pool)| Replica {
+                name,
+                pool,
+                down_since: Mutex::new(None),
+            })
+            .collect();
+
+        if replicas.is_empty() {
+            bail!("No database replicas given");
+        }
+
+        Ok(Self {
+            replicas,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Get a connection from the next healthy replica, waiting up to the
+    /// pools' configured timeout in total
+    ///
+    /// # Errors
+    /// This function fails if no replica can provide a connection.
+    pub fn get(&self) -> Result<PooledConnection> {
+        self.get_timeout(self.replicas[0].pool.connection_timeout())
+    }
+
+    /// Get a connection from the next healthy replica, waiting up to
+    /// `timeout` in total
+    ///
+    /// Replicas with an idle connection are used first.  Failing that, the
+    /// remaining time is shared between the replicas, each waited on in turn.
+    ///
+    /// # Errors
+    /// This function fails if no replica can provide a connection.
+    pub fn get_timeout(&self, timeout: Duration) -> Result<PooledConnection> {
+        let deadline = Instant::now() + timeout;
+        let len = self.replicas.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+
+        // Try healthy replicas in round-robin order, then unhealthy ones
+        let mut order: Vec<_> = (0..len)
+            .map(|i| &self.replicas[(start + i) % len])
+            .collect();
+        order.sort_by_key(|r| !r.is_healthy());
+
+        if let Some((replica, conn)) = order.iter().find_map(|r| r.pool.try_get().map(|c| (r, c))) {
+            replica.mark_up();
+
+            return Ok(conn);
+        }
+
+        let mut last_err = None;
+
+        for (i, replica) in order.iter().enumerate() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+
+            if remaining == Duration::ZERO && last_err.is_some() {
+                break;
+            }
+
+            // Divide the remaining time evenly between the replicas left, so
+            // one busy replica cannot use up the whole timeout
+            let share = remaining / u32::try_from(len - i).unwrap_or(u32::MAX);
+
+            match replica.pool.get_timeout(share) {
+                Ok(conn) => {
+                    replica.mark_up();
+
+                    return Ok(conn);
+                },
+                Err(e) => {
+                    // A pool holding connections is reachable but busy, so
+                    // only a pool with none is taken to be down
+                    if replica.pool.state().connections == 0 {
+                        replica.mark_down(&e);
+                    }
+
+                    last_err = Some(e);
+                },
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| unreachable!()))
+            .context("Timed out waiting for a connection from any database replica")
+    }
+
+    /// Report the health of each replica
+    #[must_use]
+    pub fn health(&self) -> Vec<ReplicaHealth> {
+        self.replicas
+            .iter()
+            .map(|r| {
+                let state = r.pool.state();
+
+                ReplicaHealth {
+                    name: r.name.clone(),
+                    healthy: r.is_healthy(),
+                    connections: state.connections,
+                    idle_connections: state.idle_connections,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{testing::TEST_DATABASE_URL, ConnectionManager};
+
+    fn pool(url: &str, max_size: u32) -> Pool {
+        Pool::builder()
+            .max_size(max_size)
+            .min_idle(Some(0))
+            .connection_timeout(Duration::from_millis(500))
+            .build_unchecked(ConnectionManager::new(url))
+    }
+
+    /// A pool for a port nothing should be listening on
+    fn unreachable_pool() -> Pool {
+        pool("postgres://127.0.0.1:1/unreachable", 1)
+    }
+
+    #[test]
+    fn unreachable_replicas_are_marked_down() {
+        let replicas = ReplicaPool::new([
+            ("a".to_owned(), unreachable_pool()),
+            ("b".to_owned(), unreachable_pool()),
+        ])
+        .unwrap();
+
+        let start = Instant::now();
+        assert!(replicas.get_timeout(Duration::from_millis(400)).is_err());
+
+        // Both replicas share the timeout rather than each waiting for it
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(replicas.health().iter().all(|h| !h.healthy));
+    }
+
+    #[test]
+    fn distribution_and_failover() {
+        let url = match std::env::var(TEST_DATABASE_URL) {
+            Ok(u) if !u.trim().is_empty() => u,
+            _ => return,
+        };
+
+        let replicas = ReplicaPool::new([
+            ("a".to_owned(), pool(&url, 1)),
+            ("b".to_owned(), pool(&url, 1)),
+        ])
+        .unwrap();
+
+        // Each single-connection replica serves one of two held connections
+        let first = replicas.get().unwrap();
+        let second = replicas.get().unwrap();
+        let health = replicas.health();
+        assert!(
+            health
+                .iter()
+                .all(|h| h.connections == 1 && h.idle_connections == 0)
+        );
+
+        // A busy replica is waited on but not marked down
+        assert!(replicas.get_timeout(Duration::from_millis(100)).is_err());
+        assert!(replicas.health().iter().all(|h| h.healthy));
+
+        drop((first, second));
+
+        let replicas = ReplicaPool::new([
+            ("down".to_owned(), unreachable_pool()),
+            ("up".to_owned(), pool(&url, 1)),
+        ])
+        .unwrap();
+
+        for _ in 0..3 {
+            drop(replicas.get().unwrap());
+        }
+
+        let health = replicas.health();
+        assert_eq!(health[0].name, "down");
+        assert!(!health[0].healthy);
+        assert!(health[1].healthy);
+    }
+}
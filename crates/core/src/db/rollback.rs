@@ -0,0 +1,122 @@
+//! Removal of rows written at slots discarded by a fork
+
+use diesel::prelude::*;
+
+use super::{
+    delete,
+    tables::{token_accounts, twitter_handle_name_services},
+    Connection,
+};
+use crate::prelude::*;
+
+/// Number of rows removed from each slot-tracked table by
+/// [`rollback_above_slot`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RollbackCounts {
+    /// Rows removed from `token_accounts`
+    pub token_accounts: usize,
+    /// Rows removed from `twitter_handle_name_services`
+    pub twitter_handle_name_services: usize,
+}
+
+/// Delete every row in a slot-tracked table written at a slot above `slot`
+///
+/// The tables affected are `token_accounts` and
+/// `twitter_handle_name_services`.  Previous versions of rows are not
+/// retained, so rolled-back rows are deleted rather than reverted and will be
+/// restored when the accounts are next indexed.  Rows without a slot are left
+/// untouched.  All deletes run in a single transaction.
+///
+/// # Errors
+/// This function fails if any delete fails, in which case no rows are removed.
+pub fn rollback_above_slot(conn: &Connection, slot: i64) -> Result<RollbackCounts> {
+    conn.transaction(|| {
+        let token_accounts = delete(token_accounts::table.filter(token_accounts::slot.gt(slot)))
+            .execute(conn)
+            .context("Failed to roll back token accounts")?;
+
+        let twitter_handle_name_services = delete(
+            twitter_handle_name_services::table.filter(twitter_handle_name_services::slot.gt(slot)),
+        )
+        .execute(conn)
+        .context("Failed to roll back Twitter handles")?;
+
+        Ok(RollbackCounts {
+            token_accounts,
+            twitter_handle_name_services,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow::Borrowed;
+
+    use super::*;
+    use crate::db::{
+        insert_into,
+        models::{TokenAccount, TwitterHandle},
+        testing,
+    };
+
+    fn token_account(address: &'static str, slot: Option<i64>) -> TokenAccount<'static> {
+        TokenAccount {
+            address: Borrowed(address),
+            mint_address: Borrowed("mint"),
+            owner_address: Borrowed("owner"),
+            amount: 1,
+            slot,
+        }
+    }
+
+    fn twitter_handle(address: &'static str, slot: i64) -> TwitterHandle<'static> {
+        TwitterHandle {
+            address: Borrowed(address),
+            wallet_address: Borrowed("wallet"),
+            twitter_handle: Borrowed(address),
+            slot,
+        }
+    }
+
+    #[test]
+    fn removes_rows_above_slot() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+
+        insert_into(token_accounts::table)
+            .values(&[
+                token_account("below", Some(90)),
+                token_account("at", Some(100)),
+                token_account("above", Some(101)),
+                token_account("unslotted", None),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        insert_into(twitter_handle_name_services::table)
+            .values(&[twitter_handle("kept", 100), twitter_handle("removed", 150)])
+            .execute(&conn)
+            .unwrap();
+
+        assert_eq!(rollback_above_slot(&conn, 100).unwrap(), RollbackCounts {
+            token_accounts: 1,
+            twitter_handle_name_services: 1,
+        });
+
+        let accounts: Vec<String> = token_accounts::table
+            .select(token_accounts::address)
+            .order(token_accounts::address)
+            .load(&conn)
+            .unwrap();
+        let handles: Vec<String> = twitter_handle_name_services::table
+            .select(twitter_handle_name_services::address)
+            .load(&conn)
+            .unwrap();
+
+        assert_eq!(accounts, vec!["at", "below", "unslotted"]);
+        assert_eq!(handles, vec!["kept"]);
+    }
+}
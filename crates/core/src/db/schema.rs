@@ -9,6 +9,7 @@ table! {
         trait_type -> Nullable<Text>,
         id -> Uuid,
         first_verified_creator -> Nullable<Varchar>,
+        trait_type_normalized -> Nullable<Text>,
     }
 }
 
@@ -241,6 +242,20 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{SettingType as Settingtype, Mode, TokenStandard as Token_standard};
+
+    collection_jsons (collection_mint) {
+        collection_mint -> Varchar,
+        fetch_uri -> Text,
+        name -> Nullable<Text>,
+        image -> Nullable<Text>,
+        description -> Nullable<Text>,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -264,7 +279,7 @@ table! {
         owner -> Varchar,
         bump -> Int2,
         tokens -> Varchar,
-        amount -> Int8,
+        amount -> Numeric,
         escrow_started_at -> Int8,
         escrow_ends_at -> Int8,
         vote_delegate -> Varchar,
@@ -461,7 +476,7 @@ table! {
         base -> Varchar,
         bump -> Int2,
         token_mint -> Varchar,
-        locked_supply -> Int8,
+        locked_supply -> Numeric,
         governor -> Varchar,
     }
 }
@@ -639,6 +654,20 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{SettingType as Settingtype, Mode, TokenStandard as Token_standard};
+
+    rarity_scores (metadata_address) {
+        metadata_address -> Varchar,
+        first_verified_creator -> Varchar,
+        score -> Float8,
+        rank -> Int8,
+        updated_at -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -792,6 +821,17 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
+    use crate::db::custom_types::{SettingType as Settingtype, Mode, TokenStandard as Token_standard};
+
+    token_mints (address) {
+        address -> Varchar,
+        decimals -> Int2,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector, TsQuery as Tsquery};
@@ -894,6 +934,7 @@ allow_tables_to_appear_in_same_query!(
     candy_machine_hidden_settings,
     candy_machine_whitelist_mint_settings,
     candy_machines,
+    collection_jsons,
     editions,
     escrows,
     files,
@@ -921,6 +962,7 @@ allow_tables_to_appear_in_same_query!(
     proposal_metas,
     proposals,
     purchase_receipts,
+    rarity_scores,
     smart_wallet_owners,
     smart_wallets,
     store_config_jsons,
@@ -932,6 +974,7 @@ allow_tables_to_appear_in_same_query!(
     sub_account_infos,
     temp_attributes,
     token_accounts,
+    token_mints,
     transactions,
     twitter_handle_name_services,
     tx_instruction_keys,
@@ -0,0 +1,318 @@
+//! Transactional database pools for exercising queries against a scratch
+//! database
+//!
+//! A pool returned by [`rollback_pool`] holds a single connection whose
+//! changes are never committed: the connection opens a transaction as soon as
+//! it is established, so every query run through the pool, including pending
+//! migrations and any rows inserted to set up fixtures, is rolled back when
+//! the pool is dropped.  Because there is only one connection, queries made
+//! through the pool from different places (for instance by dataloaders and
+//! the code seeding their rows) all observe each other's writes.
+//!
+//! Tests needing a database use [`test_pool`] or [`test_replicas`], which
+//! connect to the scratch database named by [`TEST_DATABASE_URL`] and return
+//! `None` if it is unset, so that such tests are skipped rather than failed
+//! on machines without Postgres.  Constructors for commonly seeded rows,
+//! filled in with placeholder values, are provided alongside them.
+
+use std::env;
+
+use diesel::{
+    r2d2::{self, CustomizeConnection},
+    Connection as _,
+};
+
+use super::{embedded_migrations, models, Connection, ConnectionManager, Pool, ReplicaPool};
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy)]
+struct BeginTestTransaction;
+
+impl CustomizeConnection<Connection, r2d2::Error> for BeginTestTransaction {
+    fn on_acquire(&self, conn: &mut Connection) -> Result<(), r2d2::Error> {
+        conn.begin_test_transaction()
+            .map_err(r2d2::Error::QueryError)
+    }
+}
+
+/// Create a single-connection pool for the database at `url` whose writes are
+/// rolled back when the pool is dropped.  Pending migrations are run inside
+/// the uncommitted transaction, so the database need not be migrated
+/// beforehand.
+///
+/// # Errors
+/// This function fails if the database cannot be connected to or if the
+/// migrations fail to run.
+pub fn rollback_pool(url: impl Into<String>) -> Result<Pool> {
+    let pool = Pool::builder()
+        .max_size(1)
+        .connection_customizer(Box::new(BeginTestTransaction))
+        .build(ConnectionManager::new(url))
+        .context("Failed to create rollback connection pool")?;
+
+    embedded_migrations::run(&pool.get().context("Failed to connect to the database")?)
+        .context("Failed to run database migrations")?;
+
+    Ok(pool)
+}
+
+/// Wrap a pool created by [`rollback_pool`] as a replica set with a single
+/// member, for consumers such as the GraphQL server's dataloaders which read
+/// through a [`ReplicaPool`]
+///
+/// # Errors
+/// This function fails for the same reasons as [`rollback_pool`].
+pub fn rollback_replicas(url: impl Into<String>) -> Result<ReplicaPool> {
+    ReplicaPool::new([("rollback".to_owned(), rollback_pool(url)?)])
+}
+
+/// Environment variable naming the scratch database used by [`test_pool`] and
+/// [`test_replicas`]
+pub const TEST_DATABASE_URL: &str = "TEST_DATABASE_URL";
+
+fn test_url() -> Option<String> {
+    let url = env::var(TEST_DATABASE_URL)
+        .ok()
+        .filter(|u| !u.trim().is_empty());
+
+    if url.is_none() {
+        warn!("{} is not set, skipping database test", TEST_DATABASE_URL);
+    }
+
+    url
+}
+
+/// Create a rollback pool for the database named by [`TEST_DATABASE_URL`], or
+/// return `None` if it is not set
+///
+/// # Panics
+/// This function panics if the variable is set but [`rollback_pool`] fails.
+#[must_use]
+pub fn test_pool() -> Option<Pool> {
+    test_url().map(|u| rollback_pool(u).expect("Failed to open test database"))
+}
+
+/// Create a rollback replica set for the database named by
+/// [`TEST_DATABASE_URL`], or return `None` if it is not set
+///
+/// # Panics
+/// This function panics if the variable is set but [`rollback_replicas`]
+/// fails.
+#[must_use]
+pub fn test_replicas() -> Option<ReplicaPool> {
+    test_url().map(|u| rollback_replicas(u).expect("Failed to open test database"))
+}
+
+/// A metadata row for the given mint with placeholder values, whose primary
+/// sale has happened if `secondary` is set
+#[must_use]
+pub fn metadata(
+    address: &'static str,
+    mint_address: &'static str,
+    secondary: bool,
+) -> models::Metadata<'static> {
+    models::Metadata {
+        address: Borrowed(address),
+        name: Borrowed(address),
+        symbol: Borrowed(""),
+        uri: Borrowed(""),
+        seller_fee_basis_points: 0,
+        update_authority_address: Borrowed("update_authority"),
+        mint_address: Borrowed(mint_address),
+        primary_sale_happened: secondary,
+        is_mutable: false,
+        edition_nonce: None,
+        edition_pda: Owned(format!("{}-edition", address)),
+        token_standard: None,
+    }
+}
+
+/// Off-chain JSON for the given metadata with the given image and no other
+/// details
+#[must_use]
+pub fn metadata_json(
+    metadata_address: &'static str,
+    image: &'static str,
+) -> models::MetadataJson<'static> {
+    models::MetadataJson {
+        metadata_address: Borrowed(metadata_address),
+        fingerprint: Owned(metadata_address.as_bytes().to_vec()),
+        updated_at: NaiveDateTime::from_timestamp(0, 0),
+        description: Some(Borrowed("")),
+        image: Some(Borrowed(image)),
+        animation_url: None,
+        external_url: None,
+        category: None,
+        raw_content: Owned(serde_json::Value::Object(serde_json::Map::new())),
+        model: None,
+    }
+}
+
+/// A creator of the given metadata at the first position
+#[must_use]
+pub fn metadata_creator(
+    metadata_address: &'static str,
+    creator_address: &'static str,
+    verified: bool,
+) -> models::MetadataCreator<'static> {
+    models::MetadataCreator {
+        metadata_address: Borrowed(metadata_address),
+        creator_address: Borrowed(creator_address),
+        share: 100,
+        verified,
+        position: Some(0),
+    }
+}
+
+/// An auction house trading in the given treasury mint, with placeholder
+/// addresses for its other accounts
+#[must_use]
+pub fn auction_house(
+    address: &'static str,
+    treasury_mint: &'static str,
+) -> models::AuctionHouse<'static> {
+    models::AuctionHouse {
+        address: Borrowed(address),
+        treasury_mint: Borrowed(treasury_mint),
+        auction_house_treasury: Owned(format!("{}-treasury", address)),
+        treasury_withdrawal_destination: Borrowed("withdrawal"),
+        fee_withdrawal_destination: Borrowed("fee_withdrawal"),
+        authority: Borrowed("authority"),
+        creator: Borrowed("creator"),
+        bump: 0,
+        treasury_bump: 0,
+        fee_payer_bump: 0,
+        seller_fee_basis_points: 0,
+        requires_sign_off: false,
+        can_change_sale_price: false,
+        auction_house_fee_account: Owned(format!("{}-fee", address)),
+    }
+}
+
+/// An open listing of the given metadata by `seller`
+#[must_use]
+pub fn listing_receipt(
+    address: &'static str,
+    auction_house: &'static str,
+    metadata: &'static str,
+    seller: &'static str,
+    price: i64,
+    created_at: NaiveDateTime,
+) -> models::ListingReceipt<'static> {
+    models::ListingReceipt {
+        address: Borrowed(address),
+        trade_state: Owned(format!("{}-trade-state", address)),
+        bookkeeper: Borrowed("bookkeeper"),
+        auction_house: Borrowed(auction_house),
+        seller: Borrowed(seller),
+        metadata: Borrowed(metadata),
+        purchase_receipt: None,
+        price,
+        token_size: 1,
+        bump: 0,
+        trade_state_bump: 0,
+        created_at,
+        canceled_at: None,
+    }
+}
+
+/// An open offer on the given metadata by `buyer`
+#[must_use]
+pub fn bid_receipt(
+    address: &'static str,
+    auction_house: &'static str,
+    metadata: &'static str,
+    buyer: &'static str,
+    price: i64,
+    created_at: NaiveDateTime,
+) -> models::BidReceipt<'static> {
+    models::BidReceipt {
+        address: Borrowed(address),
+        trade_state: Owned(format!("{}-trade-state", address)),
+        bookkeeper: Borrowed("bookkeeper"),
+        auction_house: Borrowed(auction_house),
+        buyer: Borrowed(buyer),
+        metadata: Borrowed(metadata),
+        token_account: None,
+        purchase_receipt: None,
+        price,
+        token_size: 1,
+        bump: 0,
+        trade_state_bump: 0,
+        created_at,
+        canceled_at: None,
+    }
+}
+
+/// A sale of the given metadata from `seller` to `buyer`
+#[must_use]
+pub fn purchase_receipt(
+    address: &'static str,
+    auction_house: &'static str,
+    metadata: &'static str,
+    (seller, buyer): (&'static str, &'static str),
+    price: i64,
+    created_at: NaiveDateTime,
+) -> models::PurchaseReceipt<'static> {
+    models::PurchaseReceipt {
+        address: Borrowed(address),
+        bookkeeper: Borrowed("bookkeeper"),
+        buyer: Borrowed(buyer),
+        seller: Borrowed(seller),
+        auction_house: Borrowed(auction_house),
+        metadata: Borrowed(metadata),
+        token_size: 1,
+        price,
+        bump: 0,
+        created_at,
+    }
+}
+
+/// A token account holding `amount` of the given mint, with no slot
+#[must_use]
+pub fn token_account(
+    address: &'static str,
+    mint_address: &'static str,
+    owner_address: &'static str,
+    amount: i64,
+) -> models::TokenAccount<'static> {
+    models::TokenAccount {
+        address: Borrowed(address),
+        mint_address: Borrowed(mint_address),
+        owner_address: Borrowed(owner_address),
+        amount,
+        slot: None,
+    }
+}
+
+/// A marketplace at the given subdomain trading on the given auction house
+#[must_use]
+pub fn store_config_json(
+    config_address: &'static str,
+    subdomain: &'static str,
+    auction_house_address: &'static str,
+) -> models::StoreConfigJson<'static> {
+    models::StoreConfigJson {
+        config_address: Borrowed(config_address),
+        name: Borrowed(subdomain),
+        description: Borrowed(""),
+        logo_url: Borrowed(""),
+        banner_url: Borrowed(""),
+        subdomain: Borrowed(subdomain),
+        owner_address: Borrowed("owner"),
+        auction_house_address: Borrowed(auction_house_address),
+        store_address: None,
+    }
+}
+
+/// A creator whitelisted by the given marketplace
+#[must_use]
+pub fn store_creator(
+    store_config_address: &'static str,
+    creator_address: &'static str,
+) -> models::StoreCreator<'static> {
+    models::StoreCreator {
+        store_config_address: Borrowed(store_config_address),
+        creator_address: Borrowed(creator_address),
+    }
+}
@@ -0,0 +1,173 @@
+//! Slot-aware upserts for tables tracking the slot a row was written at
+
+use diesel::{
+    pg::Pg,
+    query_builder::{AstPass, QueryFragment, QueryId},
+    query_dsl::RunQueryDsl,
+    query_source::{QuerySource, Table},
+    QueryResult,
+};
+
+/// An `INSERT ... ON CONFLICT DO UPDATE` statement whose update is skipped if
+/// the stored row was written at a later slot than the incoming row
+///
+/// Construct with [`upsert_if_newer`].
+#[derive(Debug, Clone, Copy)]
+pub struct UpsertIfNewer<T, Q> {
+    table: T,
+    statement: Q,
+}
+
+/// Guard an upsert into a table with a `slot` column so that messages
+/// arriving out of order cannot overwrite newer data with older data.
+///
+/// `statement` must be an `INSERT ... ON CONFLICT ... DO UPDATE SET ...`
+/// statement into `table`.  The update is applied only if the incoming slot is
+/// greater than or equal to the stored slot, or if the stored slot is null.
+/// Executing the returned statement yields the number of rows inserted or
+/// updated, which is zero if the update was skipped.
+///
+/// ```ignore
+/// upsert_if_newer(
+///     token_accounts::table,
+///     insert_into(token_accounts::table)
+///         .values(&row)
+///         .on_conflict(token_accounts::address)
+///         .do_update()
+///         .set(&row),
+/// )
+/// .execute(db)?;
+/// ```
+pub fn upsert_if_newer<T: Table, Q>(table: T, statement: Q) -> UpsertIfNewer<T, Q> {
+    UpsertIfNewer { table, statement }
+}
+
+impl<T: Table, Q: QueryFragment<Pg>> QueryFragment<Pg> for UpsertIfNewer<T, Q>
+where
+    T::FromClause: QueryFragment<Pg>,
+{
+    fn walk_ast(&self, mut out: AstPass<Pg>) -> QueryResult<()> {
+        self.statement.walk_ast(out.reborrow())?;
+
+        out.push_sql(" WHERE ");
+        self.table.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql(".slot IS NULL OR excluded.slot >= ");
+        self.table.from_clause().walk_ast(out.reborrow())?;
+        out.push_sql(".slot");
+
+        Ok(())
+    }
+}
+
+impl<T, Q> QueryId for UpsertIfNewer<T, Q> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<T, Q> RunQueryDsl<super::Connection> for UpsertIfNewer<T, Q> {}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow::Borrowed;
+
+    use diesel::{debug_query, prelude::*};
+
+    use super::*;
+    use crate::db::{insert_into, models::TokenAccount, tables::token_accounts, testing};
+
+    fn account(amount: i64, slot: Option<i64>) -> TokenAccount<'static> {
+        TokenAccount {
+            address: Borrowed("account"),
+            mint_address: Borrowed("mint"),
+            owner_address: Borrowed("owner"),
+            amount,
+            slot,
+        }
+    }
+
+    fn upsert(conn: &super::super::Connection, row: &TokenAccount) -> usize {
+        upsert_if_newer(
+            token_accounts::table,
+            insert_into(token_accounts::table)
+                .values(row)
+                .on_conflict(token_accounts::address)
+                .do_update()
+                .set(row),
+        )
+        .execute(conn)
+        .unwrap()
+    }
+
+    fn stored(conn: &super::super::Connection) -> (i64, Option<i64>) {
+        token_accounts::table
+            .filter(token_accounts::address.eq("account"))
+            .select((token_accounts::amount, token_accounts::slot))
+            .first(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn guard_sql() {
+        let row = account(1, Some(1));
+        let query = upsert_if_newer(
+            token_accounts::table,
+            insert_into(token_accounts::table)
+                .values(&row)
+                .on_conflict(token_accounts::address)
+                .do_update()
+                .set(&row),
+        );
+        let sql = debug_query::<Pg, _>(&query).to_string();
+
+        assert!(
+            sql.starts_with(r#"INSERT INTO "token_accounts""#),
+            "{}",
+            sql
+        );
+        assert!(
+            sql.contains(r#"ON CONFLICT ("address") DO UPDATE SET "#),
+            "{}",
+            sql
+        );
+        assert!(
+            sql.contains(
+                r#" WHERE "token_accounts".slot IS NULL OR excluded.slot >= "token_accounts".slot"#
+            ),
+            "{}",
+            sql
+        );
+    }
+
+    #[test]
+    fn older_slots_are_ignored() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+
+        assert_eq!(upsert(&conn, &account(1, Some(100))), 1);
+        assert_eq!(upsert(&conn, &account(2, Some(90))), 0);
+        assert_eq!(stored(&conn), (1, Some(100)));
+
+        assert_eq!(upsert(&conn, &account(3, Some(100))), 1);
+        assert_eq!(stored(&conn), (3, Some(100)));
+
+        assert_eq!(upsert(&conn, &account(4, Some(110))), 1);
+        assert_eq!(stored(&conn), (4, Some(110)));
+    }
+
+    #[test]
+    fn unslotted_rows_are_overwritten() {
+        let pool = match testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+
+        assert_eq!(upsert(&conn, &account(1, None)), 1);
+        assert_eq!(upsert(&conn, &account(2, Some(5))), 1);
+        assert_eq!(stored(&conn), (2, Some(5)));
+    }
+}
@@ -0,0 +1,267 @@
+//! Offline decoding of instructions for well-known programs
+//!
+//! Instructions stored by the indexer, such as those attached to governance
+//! proposals, are raw program IDs and data.  [`decode`] recognizes the
+//! instruction formats of the System, SPL Token, and Token Metadata programs,
+//! reading the instruction name and any simple arguments without consulting
+//! an RPC node.
+
+use solana_sdk::{pubkey::Pubkey, system_program};
+
+use crate::pubkeys;
+
+/// A decoded instruction for a known program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedInstruction {
+    /// Human-readable name of the program
+    pub program: &'static str,
+    /// Name of the instruction
+    pub name: &'static str,
+    /// Named arguments of the instruction, formatted as strings
+    pub args: Vec<(&'static str, String)>,
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4)?.try_into().ok().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.take(8)?.try_into().ok().map(u64::from_le_bytes)
+    }
+
+    fn pubkey(&mut self) -> Option<Pubkey> {
+        self.take(32)?.try_into().ok().map(Pubkey::new_from_array)
+    }
+
+    /// Read a `COption<Pubkey>` as laid out by the SPL Token program
+    fn token_option_pubkey(&mut self) -> Option<Option<Pubkey>> {
+        match self.u8()? {
+            0 => Some(None),
+            1 => self.pubkey().map(Some),
+            _ => None,
+        }
+    }
+}
+
+fn decode_system(data: &[u8]) -> Option<(&'static str, Vec<(&'static str, String)>)> {
+    let mut r = Reader(data);
+
+    Some(match r.u32()? {
+        0 => ("CreateAccount", vec![
+            ("lamports", r.u64()?.to_string()),
+            ("space", r.u64()?.to_string()),
+            ("owner", r.pubkey()?.to_string()),
+        ]),
+        1 => ("Assign", vec![("owner", r.pubkey()?.to_string())]),
+        2 => ("Transfer", vec![("lamports", r.u64()?.to_string())]),
+        3 => ("CreateAccountWithSeed", vec![]),
+        4 => ("AdvanceNonceAccount", vec![]),
+        5 => ("WithdrawNonceAccount", vec![(
+            "lamports",
+            r.u64()?.to_string(),
+        )]),
+        6 => ("InitializeNonceAccount", vec![(
+            "authority",
+            r.pubkey()?.to_string(),
+        )]),
+        7 => ("AuthorizeNonceAccount", vec![(
+            "authority",
+            r.pubkey()?.to_string(),
+        )]),
+        8 => ("Allocate", vec![("space", r.u64()?.to_string())]),
+        9 => ("AllocateWithSeed", vec![]),
+        10 => ("AssignWithSeed", vec![]),
+        11 => ("TransferWithSeed", vec![("lamports", r.u64()?.to_string())]),
+        _ => return None,
+    })
+}
+
+fn decode_token(data: &[u8]) -> Option<(&'static str, Vec<(&'static str, String)>)> {
+    let mut r = Reader(data);
+
+    let amount = |r: &mut Reader| r.u64().map(|a| vec![("amount", a.to_string())]);
+    let checked = |r: &mut Reader| {
+        Some(vec![
+            ("amount", r.u64()?.to_string()),
+            ("decimals", r.u8()?.to_string()),
+        ])
+    };
+
+    Some(match r.u8()? {
+        0 => {
+            let mut args = vec![
+                ("decimals", r.u8()?.to_string()),
+                ("mintAuthority", r.pubkey()?.to_string()),
+            ];
+
+            if let Some(freeze) = r.token_option_pubkey()? {
+                args.push(("freezeAuthority", freeze.to_string()));
+            }
+
+            ("InitializeMint", args)
+        },
+        1 => ("InitializeAccount", vec![]),
+        2 => ("InitializeMultisig", vec![("m", r.u8()?.to_string())]),
+        3 => ("Transfer", amount(&mut r)?),
+        4 => ("Approve", amount(&mut r)?),
+        5 => ("Revoke", vec![]),
+        6 => {
+            let authority_type = match r.u8()? {
+                0 => "MintTokens",
+                1 => "FreezeAccount",
+                2 => "AccountOwner",
+                3 => "CloseAccount",
+                _ => return None,
+            };
+            let mut args = vec![("authorityType", authority_type.to_owned())];
+
+            if let Some(new) = r.token_option_pubkey()? {
+                args.push(("newAuthority", new.to_string()));
+            }
+
+            ("SetAuthority", args)
+        },
+        7 => ("MintTo", amount(&mut r)?),
+        8 => ("Burn", amount(&mut r)?),
+        9 => ("CloseAccount", vec![]),
+        10 => ("FreezeAccount", vec![]),
+        11 => ("ThawAccount", vec![]),
+        12 => ("TransferChecked", checked(&mut r)?),
+        13 => ("ApproveChecked", checked(&mut r)?),
+        14 => ("MintToChecked", checked(&mut r)?),
+        15 => ("BurnChecked", checked(&mut r)?),
+        16 => ("InitializeAccount2", vec![(
+            "owner",
+            r.pubkey()?.to_string(),
+        )]),
+        17 => ("SyncNative", vec![]),
+        18 => ("InitializeAccount3", vec![(
+            "owner",
+            r.pubkey()?.to_string(),
+        )]),
+        _ => return None,
+    })
+}
+
+fn decode_metadata(data: &[u8]) -> Option<(&'static str, Vec<(&'static str, String)>)> {
+    let name = match *data.first()? {
+        0 => "CreateMetadataAccount",
+        1 => "UpdateMetadataAccount",
+        2 => "DeprecatedCreateMasterEdition",
+        3 => "DeprecatedMintNewEditionFromMasterEditionViaPrintingToken",
+        4 => "UpdatePrimarySaleHappenedViaToken",
+        5 => "DeprecatedSetReservationList",
+        6 => "DeprecatedCreateReservationList",
+        7 => "SignMetadata",
+        8 => "DeprecatedMintPrintingTokensViaToken",
+        9 => "DeprecatedMintPrintingTokens",
+        10 => "CreateMasterEdition",
+        11 => "MintNewEditionFromMasterEditionViaToken",
+        12 => "ConvertMasterEditionV1ToV2",
+        13 => "MintNewEditionFromMasterEditionViaVaultProxy",
+        14 => "PuffMetadata",
+        15 => "UpdateMetadataAccountV2",
+        16 => "CreateMetadataAccountV2",
+        17 => "CreateMasterEditionV3",
+        18 => "VerifyCollection",
+        19 => "Utilize",
+        20 => "ApproveUseAuthority",
+        21 => "RevokeUseAuthority",
+        22 => "UnverifyCollection",
+        23 => "ApproveCollectionAuthority",
+        24 => "RevokeCollectionAuthority",
+        25 => "SetAndVerifyCollection",
+        26 => "FreezeDelegatedAccount",
+        27 => "ThawDelegatedAccount",
+        28 => "RemoveCreatorVerification",
+        _ => return None,
+    };
+
+    Some((name, vec![]))
+}
+
+/// Decode an instruction given the address of the program executing it and
+/// its data.  Returns `None` if the program is not recognized or the data is
+/// not a valid instruction for it.
+#[must_use]
+pub fn decode(program_id: &str, data: &[u8]) -> Option<DecodedInstruction> {
+    let program_id: Pubkey = program_id.parse().ok()?;
+
+    let (program, (name, args)) = if program_id == system_program::id() {
+        ("System", decode_system(data)?)
+    } else if program_id == pubkeys::token() {
+        ("SPL Token", decode_token(data)?)
+    } else if program_id == pubkeys::metadata() {
+        ("Token Metadata", decode_metadata(data)?)
+    } else {
+        return None;
+    };
+
+    Some(DecodedInstruction {
+        program,
+        name,
+        args,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spl_transfer() {
+        let mut data = vec![3];
+        data.extend_from_slice(&1_500_u64.to_le_bytes());
+
+        assert_eq!(
+            decode(&pubkeys::token().to_string(), &data),
+            Some(DecodedInstruction {
+                program: "SPL Token",
+                name: "Transfer",
+                args: vec![("amount", "1500".to_owned())],
+            })
+        );
+
+        let mut data = vec![12];
+        data.extend_from_slice(&1_500_u64.to_le_bytes());
+        data.push(6);
+
+        assert_eq!(
+            decode(&pubkeys::token().to_string(), &data).map(|i| (i.name, i.args)),
+            Some(("TransferChecked", vec![
+                ("amount", "1500".to_owned()),
+                ("decimals", "6".to_owned()),
+            ]))
+        );
+
+        // Truncated amount
+        assert_eq!(decode(&pubkeys::token().to_string(), &[3, 1, 2]), None);
+    }
+
+    #[test]
+    fn unknown_programs() {
+        let data = [3, 0, 0, 0, 0, 0, 0, 0, 0];
+
+        assert_eq!(decode(&Pubkey::new_unique().to_string(), &data), None);
+        assert_eq!(decode("not a pubkey", &data), None);
+        assert_eq!(decode(&pubkeys::token().to_string(), &[200]), None);
+    }
+}
@@ -29,7 +29,13 @@ pub mod db;
 pub mod error;
 pub mod hash;
 #[cfg(feature = "solana")]
+pub mod instructions;
+pub mod logging;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "solana")]
 pub mod pubkeys;
+pub mod reporting;
 pub mod util;
 
 /// Commonly used utilities
@@ -47,6 +53,7 @@ pub mod prelude {
         expression_methods::*,
         pg::Pg,
         query_dsl::{BelongingToDsl, GroupByDsl, JoinOnDsl, QueryDsl, RunQueryDsl, SaveChangesDsl},
+        Connection as _,
     };
     #[cfg(feature = "db")]
     pub use diesel_full_text_search::{TsQueryExtensions, TsVectorExtensions};
@@ -58,6 +65,7 @@ pub mod prelude {
 use std::{
     net::SocketAddr,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use anyhow::Context;
@@ -81,28 +89,96 @@ pub struct ServerOpts {
     /// Overrides the port of the provided binding address
     #[clap(short, long, env)]
     port: Option<u16>,
+
+    /// Maximum number of connections in the main database connection pool.
+    /// Defaults to the number of CPUs.
+    #[clap(long, env)]
+    db_pool_size: Option<u32>,
+
+    /// Size of a dedicated database connection pool for heavy queries.  If
+    /// unset, heavy queries share the main connection pool.
+    #[clap(long, env)]
+    heavy_pool_size: Option<u32>,
+
+    /// Minimum number of idle connections to keep open in each database
+    /// connection pool
+    #[clap(long, env, default_value_t = 1)]
+    db_min_idle: u32,
+
+    /// Maximum time, in milliseconds, to wait for a connection from a database
+    /// connection pool before failing
+    #[clap(long, env, default_value_t = 5_000)]
+    db_acquire_timeout: u64,
+}
+
+/// Sizing and timeout options for a database connection pool
+#[derive(Debug, Clone, Copy)]
+pub struct PoolConfig {
+    /// Maximum number of connections to hold, or `None` to use the number of
+    /// CPUs
+    pub max_size: Option<u32>,
+    /// Minimum number of idle connections to keep open
+    pub min_idle: u32,
+    /// Maximum time to wait for a connection before failing
+    pub acquire_timeout: Duration,
+    /// Maximum time a single statement may run for on connections from the
+    /// pool, or `None` for no limit
+    pub statement_timeout: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_size: None,
+            min_idle: 1,
+            acquire_timeout: Duration::from_secs(30),
+            statement_timeout: None,
+        }
+    }
 }
 
 impl ServerOpts {
-    /// Process and expose the server options
+    /// Process and expose the server options, returning the bind address and
+    /// the configuration of the main database pool and of the heavy query
+    /// pool, if one was requested
     #[must_use]
-    pub fn into_parts(self) -> (SocketAddr,) {
-        let Self { mut address, port } = self;
+    pub fn into_parts(self) -> (SocketAddr, PoolConfig, Option<PoolConfig>) {
+        let Self {
+            mut address,
+            port,
+            db_pool_size,
+            heavy_pool_size,
+            db_min_idle,
+            db_acquire_timeout,
+        } = self;
 
         if let Some(port) = port {
             address.set_port(port);
         }
 
-        (address,)
+        let pool = PoolConfig {
+            max_size: db_pool_size,
+            min_idle: db_min_idle,
+            acquire_timeout: Duration::from_millis(db_acquire_timeout),
+            statement_timeout: None,
+        };
+        let heavy_pool = heavy_pool_size.map(|size| PoolConfig {
+            max_size: Some(size),
+            ..pool
+        });
+
+        (address, pool, heavy_pool)
     }
 }
 
-/// Process environment variables, initialize logging, and then execute the
-/// provided closure and handle its result before exiting.
+/// Process environment variables, parse command-line options, initialize
+/// logging and error reporting with the parsed [`LogOpts`](logging::LogOpts),
+/// and then execute the provided closure and handle its result before exiting.
+/// Errors returned by the closure are reported to Sentry if it is enabled.
 ///
 /// # Panics
 /// This function panics if dotenv fails to load a .env file
-pub fn run(main: impl FnOnce() -> Result<()>) -> ! {
+pub fn run<O: clap::Parser + AsRef<logging::LogOpts>>(main: impl FnOnce(O) -> Result<()>) -> ! {
     [
         ".env.local",
         if cfg!(debug_assertions) {
@@ -120,20 +196,21 @@ pub fn run(main: impl FnOnce() -> Result<()>) -> ! {
     })
     .expect("Failed to load .env files");
 
-    env_logger::builder()
-        .filter_level(if cfg!(debug_assertions) {
-            log::LevelFilter::Debug
-        } else {
-            log::LevelFilter::Warn
-        })
-        .parse_default_env()
-        .init();
+    let opts = O::parse();
+    let log_opts = opts.as_ref();
+    logging::init(log_opts.log_format);
+    let reporting = reporting::init(log_opts.sentry_dsn.clone());
 
-    std::process::exit(match main() {
+    let code = match main(opts) {
         Ok(()) => 0,
         Err(e) => {
             log::error!("{:?}", e);
+            reporting::capture_error(&e);
             1
         },
-    });
+    };
+
+    // Exiting skips destructors, so flush pending reports first
+    std::mem::drop(reporting);
+    std::process::exit(code);
 }
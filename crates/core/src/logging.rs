@@ -0,0 +1,197 @@
+//! Log output formatting and per-task log fields
+//!
+//! Logs are written in a human-readable format by default, or as one JSON
+//! object per line if [`LogFormat::Json`] is selected.  JSON log lines carry
+//! the timestamp, level, target, and message of each record, as well as any
+//! fields attached to the code emitting it with [`with_fields`] or
+//! [`WithFieldsExt::with_log_fields`], such as the ID of the message being
+//! processed.
+
+use std::{
+    cell::RefCell,
+    future::Future,
+    io::Write,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use chrono::{SecondsFormat, Utc};
+
+/// A list of named values attached to log records
+pub type Fields = Vec<(&'static str, String)>;
+
+thread_local! {
+    static FIELDS: RefCell<Fields> = RefCell::new(Vec::new());
+}
+
+/// Format of log output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ArgEnum)]
+pub enum LogFormat {
+    /// Human-readable log lines
+    Pretty,
+    /// One JSON object per line
+    Json,
+}
+
+/// Logging options for all binaries
+#[derive(Debug, Clone, clap::Args)]
+pub struct LogOpts {
+    /// The format to write logs in
+    #[clap(long, env, arg_enum, default_value = "pretty")]
+    pub log_format: LogFormat,
+
+    /// An optional Sentry DSN to report errors and panics to
+    #[clap(long, env)]
+    pub sentry_dsn: Option<crate::reporting::Dsn>,
+}
+
+struct FieldsGuard(usize);
+
+impl Drop for FieldsGuard {
+    fn drop(&mut self) {
+        FIELDS.with(|f| f.borrow_mut().truncate(self.0));
+    }
+}
+
+/// Attach the given fields to all log records emitted by `f`
+pub fn with_fields<R>(fields: &[(&'static str, String)], f: impl FnOnce() -> R) -> R {
+    let _guard = FIELDS.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        let len = stack.len();
+        stack.extend_from_slice(fields);
+
+        FieldsGuard(len)
+    });
+
+    f()
+}
+
+/// Get the fields attached to the code currently running on this thread
+#[must_use]
+pub fn current_fields() -> Fields {
+    FIELDS.with(|f| f.borrow().clone())
+}
+
+/// A future with fields attached to all log records it emits, created by
+/// [`WithFieldsExt::with_log_fields`]
+#[derive(Debug)]
+pub struct WithFields<F> {
+    fields: Fields,
+    inner: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for WithFields<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let Self { fields, inner } = self.get_mut();
+
+        with_fields(fields, || inner.as_mut().poll(cx))
+    }
+}
+
+/// Extension trait for attaching log fields to futures
+pub trait WithFieldsExt: Future + Sized {
+    /// Attach the given fields to all log records emitted while this future
+    /// is polled, even if it moves between threads
+    fn with_log_fields(self, fields: Fields) -> WithFields<Self> {
+        WithFields {
+            fields,
+            inner: Box::pin(self),
+        }
+    }
+}
+
+impl<F: Future> WithFieldsExt for F {}
+
+fn format_json(buf: &mut env_logger::fmt::Formatter, record: &log::Record) -> std::io::Result<()> {
+    write_json(buf, record)
+}
+
+fn write_json(buf: &mut impl Write, record: &log::Record) -> std::io::Result<()> {
+    let mut line = serde_json::Map::new();
+
+    line.insert(
+        "timestamp".into(),
+        Utc::now()
+            .to_rfc3339_opts(SecondsFormat::Millis, true)
+            .into(),
+    );
+    line.insert("level".into(), record.level().as_str().into());
+    line.insert("target".into(), record.target().into());
+    line.insert("message".into(), record.args().to_string().into());
+
+    FIELDS.with(|f| {
+        let f = f.borrow();
+
+        if !f.is_empty() {
+            line.insert(
+                "fields".into(),
+                f.iter()
+                    .map(|(k, v)| ((*k).to_owned(), v.clone().into()))
+                    .collect::<serde_json::Map<_, _>>()
+                    .into(),
+            );
+        }
+    });
+
+    writeln!(buf, "{}", serde_json::Value::Object(line))
+}
+
+pub(crate) fn init(format: LogFormat) {
+    let mut builder = env_logger::builder();
+
+    builder.filter_level(if cfg!(debug_assertions) {
+        log::LevelFilter::Debug
+    } else {
+        log::LevelFilter::Warn
+    });
+
+    if format == LogFormat::Json {
+        builder.format(format_json);
+    }
+
+    builder.parse_default_env().init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(level: log::Level, message: &str) -> serde_json::Value {
+        let mut buf = Vec::new();
+
+        write_json(
+            &mut buf,
+            &log::Record::builder()
+                .level(level)
+                .target("indexer_core::logging")
+                .args(format_args!("{}", message))
+                .build(),
+        )
+        .unwrap();
+
+        let line = String::from_utf8(buf).unwrap();
+        assert_eq!(line.matches('\n').count(), 1);
+
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn json_lines_parse() {
+        let line = capture(log::Level::Warn, "a \"quoted\"\nmessage");
+
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["target"], "indexer_core::logging");
+        assert_eq!(line["message"], "a \"quoted\"\nmessage");
+        assert!(line["timestamp"].as_str().unwrap().ends_with('Z'));
+        assert!(line.get("fields").is_none());
+
+        let line = with_fields(&[("message_id", "42".to_owned())], || {
+            capture(log::Level::Info, "processing")
+        });
+
+        assert_eq!(line["fields"]["message_id"], "42");
+        assert!(current_fields().is_empty());
+    }
+}
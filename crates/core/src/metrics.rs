@@ -0,0 +1,50 @@
+//! A shared Prometheus registry for exporting service metrics
+
+use lazy_static::lazy_static;
+pub use prometheus;
+use prometheus::{core::Collector, Encoder, Registry, TextEncoder};
+
+use crate::prelude::*;
+
+lazy_static! {
+    static ref REGISTRY: Registry = Registry::new();
+}
+
+/// Get the registry all service metrics are recorded in
+#[must_use]
+pub fn registry() -> &'static Registry {
+    &REGISTRY
+}
+
+/// Register a metric with the shared registry, returning it for convenience
+///
+/// # Panics
+/// This function panics if a metric with the same name is already registered.
+pub fn register<C: Collector + Clone + 'static>(metric: C) -> C {
+    REGISTRY
+        .register(Box::new(metric.clone()))
+        .unwrap_or_else(|e| panic!("Failed to register metric: {}", e));
+
+    metric
+}
+
+/// The MIME type of the output of [`encode`]
+#[must_use]
+pub fn content_type() -> String {
+    TextEncoder::new().format_type().to_owned()
+}
+
+/// Encode the current value of all registered metrics in the Prometheus text
+/// exposition format
+///
+/// # Errors
+/// This function fails if the metrics cannot be encoded.
+pub fn encode() -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buf)
+        .context("Failed to encode metrics")?;
+
+    Ok(buf)
+}
@@ -0,0 +1,171 @@
+//! Optional error and panic reporting to Sentry
+//!
+//! Reporting is enabled by passing a DSN with the `--sentry-dsn` option
+//! defined on [`LogOpts`](crate::logging::LogOpts).  Once enabled, panics are
+//! reported automatically and errors passed to [`capture_error`] are reported
+//! tagged with the log fields attached to the code reporting them, such as
+//! the type of message being processed.  URLs in reported messages are
+//! reduced to their scheme and host before being sent, as they may carry
+//! credentials or otherwise identify users.
+
+use std::sync::Arc;
+
+pub use sentry::types::Dsn;
+use sentry::{protocol::Event, ClientInitGuard, ClientOptions};
+use url::Url;
+
+use crate::{logging, prelude::*};
+
+/// Replace the path, query, fragment, and any credentials of each URL in `s`
+/// with a placeholder
+fn scrub_urls(s: &str) -> String {
+    s.split_inclusive(char::is_whitespace)
+        .map(|word| {
+            let trimmed = word.trim_matches(|c: char| {
+                c.is_whitespace() || matches!(c, '"' | '\'' | '`' | '(' | ')' | '<' | '>' | ',')
+            });
+
+            match Url::parse(trimmed) {
+                Ok(url) if url.has_host() => word.replacen(
+                    trimmed,
+                    &format!(
+                        "{}://{}/[redacted]",
+                        url.scheme(),
+                        url.host_str().unwrap_or_default()
+                    ),
+                    1,
+                ),
+                _ => word.to_owned(),
+            }
+        })
+        .collect()
+}
+
+fn scrub(mut event: Event<'static>) -> Option<Event<'static>> {
+    event.message = event.message.map(|m| scrub_urls(&m));
+
+    for exc in &mut event.exception.values {
+        exc.value = exc.value.as_deref().map(scrub_urls);
+    }
+
+    for value in event.tags.values_mut() {
+        *value = scrub_urls(value);
+    }
+
+    if let Some(ref mut req) = event.request {
+        req.url = None;
+        req.query_string = None;
+        req.cookies = None;
+    }
+
+    Some(event)
+}
+
+/// Client options for reporting, less the DSN
+fn options() -> ClientOptions {
+    ClientOptions {
+        release: sentry::release_name!(),
+        environment: Some(
+            if cfg!(debug_assertions) {
+                "development"
+            } else {
+                "production"
+            }
+            .into(),
+        ),
+        before_send: Some(Arc::new(scrub)),
+        ..ClientOptions::default()
+    }
+}
+
+/// Initialize the Sentry client if a DSN was given, returning a guard which
+/// flushes pending events when dropped
+pub(crate) fn init(dsn: Option<Dsn>) -> Option<ClientInitGuard> {
+    let dsn = dsn?;
+
+    Some(sentry::init(ClientOptions {
+        dsn: Some(dsn),
+        ..options()
+    }))
+}
+
+/// Report an error to Sentry, tagged with the log fields attached to the
+/// calling code.  Does nothing if reporting is not enabled.
+pub fn capture_error(err: &Error) {
+    let fields = logging::current_fields();
+
+    sentry::with_scope(
+        |scope| {
+            for (key, value) in fields {
+                scope.set_tag(key, value);
+            }
+        },
+        || sentry::integrations::anyhow::capture_anyhow(err),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use sentry::test::with_captured_events_options;
+
+    use super::*;
+
+    #[test]
+    fn scrub_urls() {
+        assert_eq!(
+            super::scrub_urls("Failed to fetch \"https://user:pw@example.com/a.json?key=1\""),
+            "Failed to fetch \"https://example.com/[redacted]\""
+        );
+        assert_eq!(
+            super::scrub_urls("Invalid value (https://arweave.net/abc) for field: image"),
+            "Invalid value (https://arweave.net/[redacted]) for field: image"
+        );
+        assert_eq!(super::scrub_urls("no links here"), "no links here");
+    }
+
+    #[test]
+    fn errors_are_tagged_and_scrubbed() {
+        let events = with_captured_events_options(
+            || {
+                logging::with_fields(
+                    &[
+                        ("message", "MetadataJson".to_owned()),
+                        (
+                            "uri",
+                            "https://example.com/private.json?token=secret".to_owned(),
+                        ),
+                    ],
+                    || {
+                        capture_error(
+                            &anyhow!("HTTP status 404")
+                                .context("Failed to fetch https://example.com/1.json?token=secret"),
+                        );
+                    },
+                );
+            },
+            options(),
+        );
+
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+
+        assert_eq!(
+            event.tags.get("message").map(String::as_str),
+            Some("MetadataJson")
+        );
+        assert_eq!(
+            event.tags.get("uri").map(String::as_str),
+            Some("https://example.com/[redacted]")
+        );
+
+        let values: Vec<_> = event
+            .exception
+            .values
+            .iter()
+            .filter_map(|e| e.value.as_deref())
+            .collect();
+
+        assert!(values.contains(&"Failed to fetch https://example.com/[redacted]"));
+        assert!(values.iter().all(|v| !v.contains("secret")));
+    }
+}
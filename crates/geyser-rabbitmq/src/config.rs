@@ -31,6 +31,11 @@ pub struct Amqp {
 
     #[serde_as(as = "serde_with::DisplayFromStr")]
     pub network: indexer_rabbitmq::geyser::Network,
+
+    /// Codec used to compress message bodies, defaults to `none`
+    #[serde(default)]
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub compression: indexer_rabbitmq::Compression,
 }
 
 #[derive(Debug, Deserialize)]
@@ -61,7 +61,11 @@ impl Inner {
         )
         .await?;
 
-        Producer::new(&conn, QueueType::new(amqp.network, startup_type, None)).await
+        Ok(
+            Producer::new(&conn, QueueType::new(amqp.network, startup_type, None))
+                .await?
+                .with_compression(amqp.compression),
+        )
     }
 
     async fn connect<'a>(
@@ -0,0 +1,355 @@
+//! Optional API key authentication with per-key rate limits
+//!
+//! Keys are loaded from a JSON file mapping each key to its rate limit, e.g.
+//!
+//! ```json
+//! {
+//!   "keys": {
+//!     "some-key": { "requestsPerMinute": 600, "burst": 100 },
+//!     "ops-key": { "requestsPerMinute": 60, "admin": true }
+//!   },
+//!   "anonymous": { "requestsPerMinute": 60 }
+//! }
+//! ```
+//!
+//! Clients pass their key in an `X-Api-Key` header or as an `Authorization:
+//! Bearer` token.  Each key draws from its own token bucket, and requests
+//! without a key draw from a single shared anonymous bucket if one is
+//! configured or are rejected otherwise.  Unknown keys are always rejected.
+//!
+//! Requests made with a key marked `admin` are granted [`Access::admin`],
+//! which is required by administrative queries such as `dataQualityReport`.
+
+use std::{
+    future::Future,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse},
+    http::{
+        header::{self, HeaderMap},
+        StatusCode,
+    },
+    HttpMessage, HttpResponse, ResponseError,
+};
+use indexer_core::{hash::HashMap, prelude::*};
+use serde::Deserialize;
+
+/// Name of the header carrying an API key
+pub const HEADER: &str = "x-api-key";
+
+/// The rate limit applied to a key
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Limit {
+    /// Sustained number of requests allowed per minute
+    requests_per_minute: u32,
+    /// Number of requests that may be made at once after a period of
+    /// inactivity.  Defaults to the per-minute rate.
+    burst: Option<u32>,
+    /// Whether requests made with this key may run administrative queries
+    #[serde(default)]
+    admin: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeysFile {
+    keys: HashMap<String, Limit>,
+    anonymous: Option<Limit>,
+}
+
+/// The privileges granted to an authenticated request, stored in the
+/// request's extensions by [`middleware`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Access {
+    /// Whether the request may run administrative queries
+    pub admin: bool,
+}
+
+/// A token bucket refilled continuously at a fixed rate
+#[derive(Debug)]
+struct Bucket {
+    /// Tokens added per second
+    rate: f64,
+    capacity: f64,
+    access: Access,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl Bucket {
+    fn new(limit: Limit) -> Self {
+        let rate = f64::from(limit.requests_per_minute) / 60.0;
+        let capacity = f64::from(limit.burst.unwrap_or(limit.requests_per_minute).max(1));
+
+        Self {
+            rate,
+            capacity,
+            access: Access { admin: limit.admin },
+            state: Mutex::new((capacity, Instant::now())),
+        }
+    }
+
+    /// Take a token from the bucket, or return the time until one will be
+    /// available if it is empty
+    fn take(&self) -> Result<(), Duration> {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let (ref mut tokens, ref mut updated) = *state;
+        let now = Instant::now();
+
+        *tokens =
+            (*tokens + now.duration_since(*updated).as_secs_f64() * self.rate).min(self.capacity);
+        *updated = now;
+
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+
+            Ok(())
+        } else if self.rate > 0.0 {
+            Err(Duration::from_secs_f64((1.0 - *tokens) / self.rate))
+        } else {
+            Err(Duration::MAX)
+        }
+    }
+}
+
+/// Reason a request was refused by the API key middleware
+#[derive(Debug, thiserror::Error)]
+pub enum Rejection {
+    /// No key was sent and anonymous requests are not allowed
+    #[error("An API key is required")]
+    MissingKey,
+    /// The key sent is not recognized
+    #[error("Invalid API key")]
+    InvalidKey,
+    /// The key's rate limit has been exceeded
+    #[error("Rate limit exceeded")]
+    RateLimited {
+        /// Time until the next request will be accepted
+        retry_after: Duration,
+    },
+}
+
+impl ResponseError for Rejection {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::MissingKey | Self::InvalidKey => StatusCode::UNAUTHORIZED,
+            Self::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut res = HttpResponse::build(self.status_code());
+
+        if let Self::RateLimited { retry_after } = self {
+            // Round up so clients never retry before a token is available
+            let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+            res.insert_header((header::RETRY_AFTER, secs.to_string()));
+        }
+
+        res.json(serde_json::json!({
+            "errors": [{ "message": self.to_string() }],
+        }))
+    }
+}
+
+/// The set of accepted API keys and their rate limit state
+#[derive(Debug)]
+pub struct ApiKeys {
+    keys: HashMap<String, Bucket>,
+    anonymous: Option<Bucket>,
+}
+
+impl ApiKeys {
+    /// Load API keys and their rate limits from a JSON file
+    ///
+    /// # Errors
+    /// This function fails if the file cannot be read or is malformed.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::read(path)
+            .with_context(|| format!("Failed to read API keys file {:?}", path))?;
+        let file = serde_json::from_slice(&file)
+            .with_context(|| format!("Failed to parse API keys file {:?}", path))?;
+
+        Ok(Self::new(file))
+    }
+
+    fn new(KeysFile { keys, anonymous }: KeysFile) -> Self {
+        Self {
+            keys: keys.into_iter().map(|(k, l)| (k, Bucket::new(l))).collect(),
+            anonymous: anonymous.map(Bucket::new),
+        }
+    }
+
+    fn key(headers: &HeaderMap) -> Option<&str> {
+        headers
+            .get(HEADER)
+            .and_then(|v| v.to_str().ok())
+            .or_else(|| {
+                headers
+                    .get(header::AUTHORIZATION)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.strip_prefix("Bearer "))
+            })
+            .map(str::trim)
+    }
+
+    /// Check a request's API key and charge it against the key's rate limit,
+    /// returning the privileges granted to the key
+    ///
+    /// # Errors
+    /// This function fails if the request is unauthenticated and anonymous
+    /// requests are not allowed, if its key is unknown, or if the applicable
+    /// rate limit has been exceeded.
+    pub fn check(&self, headers: &HeaderMap) -> Result<Access, Rejection> {
+        let bucket = match Self::key(headers) {
+            Some(key) => self.keys.get(key).ok_or(Rejection::InvalidKey)?,
+            None => self.anonymous.as_ref().ok_or(Rejection::MissingKey)?,
+        };
+
+        bucket
+            .take()
+            .map(|()| bucket.access)
+            .map_err(|retry_after| Rejection::RateLimited { retry_after })
+    }
+}
+
+/// Middleware authenticating each request against the given keys, if any are
+/// configured, and recording the [`Access`] granted to it.  Requests are
+/// never granted administrative access if no keys are configured.
+pub fn middleware<S, B>(
+    keys: Option<&ApiKeys>,
+    req: ServiceRequest,
+    srv: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, actix_web::Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    let res = match keys.map_or(Ok(Access::default()), |k| k.check(req.headers())) {
+        Ok(access) => {
+            req.extensions_mut().insert(access);
+            Ok(srv.call(req))
+        },
+        Err(e) => {
+            debug!("Rejecting request: {}", e);
+            Err(e)
+        },
+    };
+
+    async move { res?.await }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::header::{HeaderName, HeaderValue};
+
+    use super::*;
+
+    fn keys(json: serde_json::Value) -> ApiKeys {
+        ApiKeys::new(serde_json::from_value(json).unwrap())
+    }
+
+    fn headers(name: &'static str, value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            HeaderName::from_static(name),
+            HeaderValue::from_static(value),
+        );
+
+        headers
+    }
+
+    #[test]
+    fn valid_key() {
+        let keys = keys(serde_json::json!({
+            "keys": {
+                "user": { "requestsPerMinute": 60 },
+                "ops": { "requestsPerMinute": 60, "admin": true },
+            },
+        }));
+
+        assert_eq!(keys.check(&headers(HEADER, "user")).unwrap(), Access {
+            admin: false
+        });
+        assert_eq!(
+            keys.check(&headers("authorization", "Bearer ops")).unwrap(),
+            Access { admin: true }
+        );
+        assert!(matches!(
+            keys.check(&headers(HEADER, "other")),
+            Err(Rejection::InvalidKey)
+        ));
+    }
+
+    #[test]
+    fn over_limit() {
+        let keys = keys(serde_json::json!({
+            "keys": { "user": { "requestsPerMinute": 60, "burst": 2 } },
+        }));
+        let user = headers(HEADER, "user");
+
+        assert!(keys.check(&user).is_ok());
+        assert!(keys.check(&user).is_ok());
+
+        let err = keys.check(&user).unwrap_err();
+        let retry_after = match err {
+            Rejection::RateLimited { retry_after } => retry_after,
+            ref e => panic!("Unexpected rejection {:?}", e),
+        };
+
+        assert!(retry_after > Duration::ZERO && retry_after <= Duration::from_secs(1));
+
+        let res = err.error_response();
+
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers().get(header::RETRY_AFTER).unwrap(), "1");
+    }
+
+    #[test]
+    fn missing_key() {
+        let closed = keys(serde_json::json!({ "keys": {} }));
+
+        assert!(matches!(
+            closed.check(&HeaderMap::new()),
+            Err(Rejection::MissingKey)
+        ));
+        assert_eq!(
+            Rejection::MissingKey.error_response().status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        let open = keys(serde_json::json!({
+            "keys": {},
+            "anonymous": { "requestsPerMinute": 60, "burst": 1 },
+        }));
+
+        assert_eq!(open.check(&HeaderMap::new()).unwrap(), Access::default());
+        assert!(matches!(
+            open.check(&HeaderMap::new()),
+            Err(Rejection::RateLimited { .. })
+        ));
+    }
+
+    #[test]
+    fn bucket_refills() {
+        let bucket = Bucket::new(Limit {
+            requests_per_minute: 6000,
+            burst: Some(1),
+            admin: false,
+        });
+
+        assert!(bucket.take().is_ok());
+        assert!(bucket.take().is_err());
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(bucket.take().is_ok());
+    }
+}
@@ -0,0 +1,295 @@
+//! URL rewriting for the sharded asset proxy
+//!
+//! The proxy endpoint is a URL template containing the placeholder `[n]`,
+//! e.g. `https://assets[n].example.com/`, which is replaced with a shard
+//! number from `1` to `count - 1`, or removed for shard zero.  Assets are
+//! assigned to shards by hashing a stable key for the source asset, so a
+//! given asset is always served (and cached) by the same proxy host.
+//!
+//! Images may additionally be resized or re-encoded by the proxy, as described
+//! by an [`ImageTransform`].
+
+use base64::display::Base64Display;
+use indexer_core::{
+    assets::{AssetHint, AssetIdentifier},
+    prelude::*,
+    url::Url,
+};
+
+use crate::SharedData;
+
+/// Placeholder in the proxy endpoint replaced with the shard number
+pub const SHARD_PLACEHOLDER: &str = "[n]";
+
+/// Largest width or height an image may be resized to by the proxy
+pub const MAX_DIMENSION: u32 = 2048;
+
+/// Encoding of an image served by the proxy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, juniper::GraphQLEnum)]
+pub enum ImageFormat {
+    /// WebP
+    Webp,
+    /// PNG
+    Png,
+    /// JPEG
+    Jpeg,
+}
+
+impl ImageFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Webp => "webp",
+            Self::Png => "png",
+            Self::Jpeg => "jpeg",
+        }
+    }
+}
+
+/// Resizing and re-encoding to apply to an image served by the proxy.  Unset
+/// fields leave the corresponding property of the source image unchanged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImageTransform {
+    /// Width to resize the image to, clamped to [`MAX_DIMENSION`]
+    pub width: Option<u32>,
+    /// Height to resize the image to, clamped to [`MAX_DIMENSION`]
+    pub height: Option<u32>,
+    /// Format to re-encode the image as
+    pub format: Option<ImageFormat>,
+}
+
+impl ImageTransform {
+    /// Return the proxy query parameters requesting this transform
+    #[must_use]
+    pub fn query(&self) -> Vec<(&'static str, String)> {
+        let Self {
+            width,
+            height,
+            format,
+        } = *self;
+
+        width
+            .map(|w| ("width", w.min(MAX_DIMENSION).to_string()))
+            .into_iter()
+            .chain(height.map(|h| ("height", h.min(MAX_DIMENSION).to_string())))
+            .chain(format.map(|f| ("format", f.as_str().to_owned())))
+            .collect()
+    }
+}
+
+/// Select the shard for an asset from its key, using the first byte of the
+/// key's MD5 hash.  A `count` of zero is treated the same as a count of one,
+/// always selecting shard zero.
+#[must_use]
+pub fn shard(key: impl AsRef<[u8]>, count: u8) -> u8 {
+    md5::compute(key)[0] % count.max(1)
+}
+
+/// Render a shard number in place of [`SHARD_PLACEHOLDER`].  Shard zero is
+/// rendered as an empty string, so its host carries no number.
+fn shard_label(shard: u8) -> String {
+    if shard == 0 {
+        String::new()
+    } else {
+        shard.to_string()
+    }
+}
+
+/// Rewrite a source asset to its URL on the proxy, appending the given path
+/// segments and query parameters.  `key` identifies the source asset, e.g.
+/// its URL or content fingerprint, and determines which shard serves it.
+///
+/// # Errors
+/// This function fails if the endpoint is not a valid base URL.
+pub fn proxy_url<'a>(
+    endpoint: &str,
+    count: u8,
+    key: impl AsRef<[u8]>,
+    path: impl IntoIterator<Item = &'a str>,
+    query: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Result<Url> {
+    let shard = shard(key, count);
+
+    let mut url = Url::parse(&endpoint.replace(SHARD_PLACEHOLDER, &shard_label(shard)))
+        .context("Failed to parse asset proxy endpoint")?;
+
+    url.path_segments_mut()
+        .map_err(|()| anyhow!("Asset proxy endpoint {:?} is not a base URL", endpoint))?
+        .pop_if_empty()
+        .extend(path);
+    url.query_pairs_mut().extend_pairs(query);
+
+    Ok(url)
+}
+
+/// Rewrite an Arweave or IPFS asset URL to its URL on the proxy, applying the
+/// given transform.  Returns `None` if the source URL does not unambiguously
+/// identify an Arweave or IPFS asset, in which case it should be served as-is.
+///
+/// # Errors
+/// This function fails if the endpoint is not a valid base URL.
+pub fn proxy_asset(
+    endpoint: &str,
+    count: u8,
+    source: &Url,
+    transform: &ImageTransform,
+) -> Result<Option<Url>> {
+    let id = AssetIdentifier::new(source);
+    let transform = transform.query();
+    let transform = transform.iter().map(|(k, v)| (*k, &**v));
+
+    match (id.arweave, &id.ipfs) {
+        (Some(_), Some(_)) | (None, None) => Ok(None),
+        (Some(txid), None) => {
+            let txid = Base64Display::with_config(&txid.0, base64::URL_SAFE_NO_PAD).to_string();
+
+            proxy_url(
+                endpoint,
+                count,
+                id.fingerprint(Some(AssetHint::Arweave))
+                    .unwrap_or_else(|| unreachable!()),
+                ["arweave", &txid],
+                transform,
+            )
+            .map(Some)
+        },
+        (None, Some((cid, path))) => {
+            let cid = cid.to_string();
+
+            proxy_url(
+                endpoint,
+                count,
+                id.fingerprint(Some(AssetHint::Ipfs))
+                    .unwrap_or_else(|| unreachable!()),
+                ["ipfs", &cid],
+                transform.chain(if path.is_empty() {
+                    None
+                } else {
+                    Some(("path", &**path))
+                }),
+            )
+            .map(Some)
+        },
+    }
+}
+
+/// Serve an image through the configured asset proxy, returning the source
+/// unchanged if it is not a valid URL for an Arweave or IPFS asset
+///
+/// # Errors
+/// This function fails if the configured endpoint is not a valid base URL.
+pub fn proxy_image(
+    shared: &SharedData,
+    source: &str,
+    transform: &ImageTransform,
+) -> Result<String> {
+    let url = match Url::parse(source) {
+        Ok(u) => u,
+        Err(_) => return Ok(source.to_owned()),
+    };
+
+    Ok(proxy_asset(
+        &shared.asset_proxy_endpoint,
+        shared.asset_proxy_count,
+        &url,
+        transform,
+    )?
+    .map_or_else(|| source.to_owned(), |u| u.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENDPOINT: &str = "https://assets[n].holaplex.com/";
+
+    #[test]
+    fn shards_are_stable() {
+        // md5("") begins with 0xd4 and md5("a") with 0x0c
+        assert_eq!(shard("", 3), 212 % 3);
+        assert_eq!(shard("a", 5), 12 % 5);
+        assert_eq!(shard("a", 5), shard("a", 5));
+        assert_eq!(shard("a", 255), 12);
+    }
+
+    #[test]
+    fn zero_or_one_shard() {
+        for count in [0, 1] {
+            assert_eq!(shard("", count), 0);
+            assert_eq!(shard("a", count), 0);
+
+            assert_eq!(
+                proxy_url(
+                    ENDPOINT,
+                    count,
+                    "a",
+                    ["arweave", "txid"],
+                    None::<(&str, &str)>
+                )
+                .unwrap()
+                .as_str(),
+                "https://assets.holaplex.com/arweave/txid"
+            );
+        }
+    }
+
+    #[test]
+    fn shard_number_in_host() {
+        assert_eq!(
+            proxy_url(ENDPOINT, 3, "", ["ipfs", "cid"], Some(("width", "400")))
+                .unwrap()
+                .as_str(),
+            "https://assets2.holaplex.com/ipfs/cid?width=400"
+        );
+    }
+
+    #[test]
+    fn transform_query() {
+        assert!(ImageTransform::default().query().is_empty());
+
+        assert_eq!(
+            ImageTransform {
+                width: Some(400),
+                height: None,
+                format: Some(ImageFormat::Webp),
+            }
+            .query(),
+            vec![("width", "400".to_owned()), ("format", "webp".to_owned())]
+        );
+
+        assert_eq!(
+            ImageTransform {
+                width: Some(10_000),
+                height: Some(MAX_DIMENSION + 1),
+                format: Some(ImageFormat::Jpeg),
+            }
+            .query(),
+            vec![
+                ("width", MAX_DIMENSION.to_string()),
+                ("height", MAX_DIMENSION.to_string()),
+                ("format", "jpeg".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn transformed_assets() {
+        let source =
+            Url::parse("https://ipfs.io/ipfs/QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG/1.png")
+                .unwrap();
+
+        let plain = proxy_asset(ENDPOINT, 1, &source, &ImageTransform::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(plain.query(), Some("path=1.png"));
+
+        let resized = proxy_asset(ENDPOINT, 1, &source, &ImageTransform {
+            width: Some(4096),
+            height: None,
+            format: Some(ImageFormat::Png),
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(resized.path(), plain.path());
+        assert_eq!(resized.query(), Some("width=2048&format=png&path=1.png"));
+    }
+}
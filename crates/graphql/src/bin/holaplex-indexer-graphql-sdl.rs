@@ -0,0 +1,26 @@
+//! Print the GraphQL server's schema in the GraphQL schema definition language
+//! without starting the server
+
+use std::{io::Write, path::PathBuf};
+
+use indexer_core::{clap, clap::Parser, prelude::*};
+
+#[derive(Parser)]
+struct Opts {
+    /// File to write the schema to, instead of standard output
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn main() -> Result<()> {
+    let Opts { output } = Opts::parse();
+    let sdl = holaplex_indexer_graphql::schema_sdl();
+
+    match output {
+        Some(path) => std::fs::write(&path, sdl)
+            .with_context(|| format!("Failed to write schema to {:?}", path)),
+        None => std::io::stdout()
+            .write_all(sdl.as_bytes())
+            .context("Failed to write schema"),
+    }
+}
@@ -0,0 +1,168 @@
+//! CORS policy for the GraphQL server
+
+use std::sync::Arc;
+
+use actix_cors::Cors;
+use actix_web::http;
+use indexer_core::url::Url;
+
+use crate::{api_keys, request_id};
+
+/// Check an origin against an allowlist entry.  Entries may omit the scheme
+/// to match any scheme, and may begin the host with `*.` to match any
+/// subdomain of the given domain (but not the domain itself).
+fn origin_matches(pattern: &str, origin: &Url) -> bool {
+    let (scheme, host) = match pattern.split_once("://") {
+        Some((s, h)) => (Some(s), h),
+        None => (None, pattern),
+    };
+    let host = host.trim_end_matches('/');
+
+    if scheme.map_or(false, |s| !s.eq_ignore_ascii_case(origin.scheme())) {
+        return false;
+    }
+
+    let origin_host = match (origin.host_str(), origin.port()) {
+        (Some(h), Some(p)) => format!("{}:{}", h, p),
+        (Some(h), None) => h.to_owned(),
+        (None, _) => return false,
+    };
+
+    match host.strip_prefix("*.") {
+        Some(domain) => origin_host
+            .to_ascii_lowercase()
+            .strip_suffix(&domain.to_ascii_lowercase())
+            .map_or(false, |sub| sub.len() > 1 && sub.ends_with('.')),
+        None => origin_host.eq_ignore_ascii_case(host),
+    }
+}
+
+/// Construct the CORS middleware, restricting requests to the given origins
+/// or allowing any origin if none are given
+pub fn build(allowed_origins: &Arc<Vec<String>>) -> Cors {
+    let cors = if allowed_origins.is_empty() {
+        Cors::default().allow_any_origin()
+    } else {
+        let allowed_origins = Arc::clone(allowed_origins);
+
+        Cors::default().allowed_origin_fn(move |origin, _| {
+            let origin = match origin.to_str().ok().and_then(|o| Url::parse(o).ok()) {
+                Some(o) => o,
+                None => return false,
+            };
+
+            allowed_origins.iter().any(|p| origin_matches(p, &origin))
+        })
+    };
+
+    cors.allowed_methods(vec!["GET", "POST"])
+        .allowed_headers(vec![http::header::AUTHORIZATION, http::header::ACCEPT])
+        .allowed_header(http::header::CONTENT_TYPE)
+        .allowed_header(api_keys::HEADER)
+        .expose_headers(vec![
+            http::header::RETRY_AFTER,
+            http::header::HeaderName::from_static(request_id::HEADER),
+        ])
+        .max_age(3600)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{dev::Service, test, web, App, HttpResponse};
+
+    use super::*;
+
+    fn matches(pattern: &str, origin: &str) -> bool {
+        origin_matches(pattern, &Url::parse(origin).unwrap())
+    }
+
+    #[test]
+    fn allowed_origins() {
+        assert!(matches("https://holaplex.com", "https://holaplex.com"));
+        assert!(matches("https://holaplex.com/", "https://HOLAPLEX.com"));
+        assert!(matches("holaplex.com", "http://holaplex.com"));
+        assert!(matches("holaplex.com", "https://holaplex.com"));
+        assert!(matches("localhost:3000", "http://localhost:3000"));
+    }
+
+    #[test]
+    fn disallowed_origins() {
+        assert!(!matches("https://holaplex.com", "http://holaplex.com"));
+        assert!(!matches("holaplex.com", "https://evil.com"));
+        assert!(!matches("holaplex.com", "https://holaplex.com.evil.com"));
+        assert!(!matches("holaplex.com", "https://www.holaplex.com"));
+        assert!(!matches("localhost:3000", "http://localhost:3001"));
+        assert!(!matches("localhost:3000", "http://localhost"));
+    }
+
+    #[test]
+    fn wildcard_origins() {
+        assert!(matches("*.holaplex.com", "https://www.holaplex.com"));
+        assert!(matches("*.holaplex.com", "https://a.b.holaplex.com"));
+        assert!(matches(
+            "https://*.holaplex.com",
+            "https://store.holaplex.com"
+        ));
+        assert!(!matches(
+            "https://*.holaplex.com",
+            "http://store.holaplex.com"
+        ));
+        assert!(!matches("*.holaplex.com", "https://holaplex.com"));
+        assert!(!matches("*.holaplex.com", "https://evilholaplex.com"));
+        assert!(!matches("*.holaplex.com", "https://holaplex.com.evil.com"));
+    }
+
+    #[test]
+    fn middleware_checks_origins() {
+        actix_web::rt::System::new().block_on(async {
+            let app = test::init_service(
+                App::new()
+                    .wrap(build(&Arc::new(vec!["*.holaplex.com".to_owned()])))
+                    .route("/", web::get().to(HttpResponse::Ok)),
+            )
+            .await;
+
+            let allowed_origin = |origin: &'static str| {
+                let req = test::TestRequest::get()
+                    .uri("/")
+                    .insert_header((http::header::ORIGIN, origin))
+                    .to_request();
+                let res = app.call(req);
+
+                async move {
+                    res.await.ok().and_then(|res| {
+                        res.headers()
+                            .get(http::header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                            .cloned()
+                    })
+                }
+            };
+
+            assert_eq!(
+                allowed_origin("https://www.holaplex.com").await.unwrap(),
+                "https://www.holaplex.com"
+            );
+            assert_eq!(allowed_origin("https://evil.com").await, None);
+
+            let res = app
+                .call(
+                    test::TestRequest::get()
+                        .uri("/")
+                        .insert_header((http::header::ORIGIN, "https://www.holaplex.com"))
+                        .to_request(),
+                )
+                .await
+                .unwrap();
+            let exposed = res
+                .headers()
+                .get(http::header::ACCESS_CONTROL_EXPOSE_HEADERS)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_ascii_lowercase();
+
+            assert!(exposed.contains("retry-after"));
+            assert!(exposed.contains(request_id::HEADER));
+        });
+    }
+}
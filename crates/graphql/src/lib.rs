@@ -0,0 +1,718 @@
+//! GraphQL server to read from `holaplex-indexer`
+
+#![deny(
+    clippy::suspicious,
+    clippy::style,
+    missing_debug_implementations,
+    missing_copy_implementations
+)]
+#![warn(clippy::pedantic, clippy::cargo, missing_docs)]
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    middleware, web, App, Error, HttpMessage, HttpRequest, HttpResponse, HttpServer,
+};
+use futures_util::{future, StreamExt};
+use indexer_core::{
+    clap,
+    clap::Parser,
+    db,
+    db::{models, ReplicaPool},
+    logging::LogOpts,
+    prelude::*,
+    ServerOpts,
+};
+use juniper::http::{graphiql::graphiql_source, GraphQLRequest};
+use juniper_actix::subscriptions::subscriptions_handler;
+use juniper_graphql_ws::ConnectionConfig;
+
+use crate::{
+    api_keys::{Access, ApiKeys},
+    persisted_queries::PersistedQueries,
+    request_id::RequestId,
+    schema::{
+        AppContext, LruCache, NftActivitySender, QueryAnalyzer, Revalidate, Schema, TtlCache,
+        TwitterProfileCache, TwitterRateLimit,
+    },
+};
+
+mod api_keys;
+mod asset_proxy;
+mod cors;
+mod metrics;
+mod notify;
+mod persisted_queries;
+mod request_id;
+mod schema;
+mod subscription_guard;
+#[cfg(test)]
+mod testing;
+
+#[derive(Parser)]
+struct Opts {
+    #[clap(flatten)]
+    server: ServerOpts,
+
+    #[clap(flatten)]
+    log: LogOpts,
+
+    /// Comma-separated list of origins allowed to make cross-origin requests,
+    /// e.g. `https://holaplex.com,https://*.holaplex.com`.  A `*.` prefix on
+    /// the host matches any subdomain.  Any origin is allowed if this is
+    /// empty.
+    #[clap(long, env, use_value_delimiter(true))]
+    allowed_origins: Option<Vec<String>>,
+
+    #[clap(long, env)]
+    twitter_bearer_token: Option<String>,
+
+    /// Base URL of the Twitter API
+    #[clap(long, env, default_value = "https://api.twitter.com")]
+    twitter_api_endpoint: String,
+
+    /// Maximum number of Twitter profiles to cache.  A value of 0 disables
+    /// the cache.
+    #[clap(long, env, default_value_t = 10_000)]
+    twitter_cache_capacity: usize,
+
+    /// Time, in seconds, to cache Twitter profiles for
+    #[clap(long, env, default_value_t = 3600)]
+    twitter_cache_ttl: u64,
+
+    /// Time, in seconds, to cache the absence of a Twitter user for.  This is
+    /// shorter than the profile TTL so newly registered handles are found.
+    #[clap(long, env, default_value_t = 300)]
+    twitter_missing_cache_ttl: u64,
+
+    /// Base URL of the asset proxy, with `[n]` standing in for the shard
+    /// number, e.g. `https://assets[n].holaplex.com/`.  The placeholder is
+    /// removed for shard zero.
+    #[clap(long, env)]
+    asset_proxy_endpoint: String,
+
+    /// Number of asset proxy shards, numbered from zero
+    #[clap(long, env)]
+    asset_proxy_count: u8,
+
+    /// Auction house to prefer when resolving the current listing of an NFT
+    #[clap(long, env)]
+    default_auction_house: Option<String>,
+
+    /// How to choose between several active listings of the same NFT
+    #[clap(long, env, arg_enum, default_value = "default-house")]
+    listing_tie_break: ListingTieBreak,
+
+    /// Only serve slot-bearing rows at least this many slots behind the latest
+    /// indexed slot
+    #[clap(long, env, default_value_t = 0)]
+    confirmation_depth: u64,
+
+    /// Time, in seconds, to cache the results of aggregate queries for
+    #[clap(long, env, default_value_t = 60)]
+    aggregate_cache_ttl: u64,
+
+    /// Time, in seconds, past its TTL that a cached aggregate may still be
+    /// served while it is recomputed
+    #[clap(long, env, default_value_t = 300)]
+    aggregate_cache_stale_ttl: u64,
+
+    /// How to recompute stale cached aggregates
+    #[clap(long, env, arg_enum, default_value = "background")]
+    aggregate_cache_revalidate: Revalidate,
+
+    /// Maximum time, in milliseconds, a single dataloader batch may run for.
+    /// Every key of a batch still running at the deadline resolves to an
+    /// error.  A value of 0 disables the limit.
+    #[clap(long, env, default_value_t = 30_000)]
+    batch_timeout: u64,
+
+    /// Maximum time, in milliseconds, any single database statement may run
+    /// for.  This is set on every connection of the main and heavy pools, so
+    /// it bounds all queries made by the server, including those a timed-out
+    /// batch leaves running.  A value of 0 disables the limit.
+    #[clap(long, env, default_value_t = 30_000)]
+    statement_timeout: u64,
+
+    /// Maximum number of `nftFull` fields a single request may resolve
+    #[clap(long, env, default_value_t = 5)]
+    max_nft_full_per_request: u32,
+
+    /// Maximum nesting depth of fields in a query.  A value of 0 disables the
+    /// limit.
+    #[clap(long, env, default_value_t = 12)]
+    max_query_depth: usize,
+
+    /// Maximum estimated cost of a query, counting each selected field once
+    /// and multiplying the fields under a list by its expected length.  A
+    /// value of 0 disables the limit.
+    #[clap(long, env, default_value_t = 50_000)]
+    max_query_cost: u64,
+
+    /// Maximum number of automatic persisted queries to cache.  A value of 0
+    /// disables persisted queries.
+    #[clap(long, env, default_value_t = 10_000)]
+    persisted_query_cache_capacity: usize,
+
+    /// Time, in seconds, to cache automatic persisted queries for
+    #[clap(long, env, default_value_t = 86_400)]
+    persisted_query_cache_ttl: u64,
+
+    /// Path to a JSON file of API keys and their rate limits.  If given,
+    /// GraphQL requests must carry a listed key unless the file also sets an
+    /// anonymous rate limit.
+    #[clap(long, env)]
+    api_keys_file: Option<PathBuf>,
+
+    /// Reject introspection queries selecting `__schema` or `__type`
+    #[clap(long, env, parse(try_from_str), default_value = "false")]
+    disable_introspection: bool,
+
+    /// Serve the GraphiQL IDE at `/graphiql`
+    #[clap(long, env, parse(try_from_str), default_value = "false")]
+    enable_graphiql: bool,
+}
+
+impl AsRef<LogOpts> for Opts {
+    fn as_ref(&self) -> &LogOpts {
+        &self.log
+    }
+}
+
+/// Strategy for picking an NFT's current listing when it is listed on more
+/// than one auction house
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ArgEnum)]
+pub(crate) enum ListingTieBreak {
+    /// Prefer listings on the default auction house, then the lowest price
+    DefaultHouse,
+    /// Prefer the lowest price, then listings on the default auction house
+    LowestPrice,
+}
+
+struct GraphiqlData {
+    uri: String,
+}
+
+struct RedirectData {
+    route: &'static str,
+    new_route: &'static str,
+}
+
+pub(crate) struct SharedData {
+    schema: Arc<Schema>,
+    query_analyzer: QueryAnalyzer,
+    persisted_queries: PersistedQueries,
+    pub db: Arc<ReplicaPool>,
+    pub heavy_db: Arc<ReplicaPool>,
+    pub asset_proxy_endpoint: String,
+    pub asset_proxy_count: u8,
+    pub twitter_bearer_token: String,
+    pub twitter_api_endpoint: String,
+    pub twitter_profile_cache: Arc<TwitterProfileCache>,
+    pub twitter_missing_cache_ttl: Duration,
+    pub twitter_rate_limit: Arc<TwitterRateLimit>,
+    pub default_auction_house: Option<String>,
+    pub listing_tie_break: ListingTieBreak,
+    pub confirmation_depth: u64,
+    pub activity_summary_cache: TtlCache<(String, i32), models::ActivitySummary>,
+    pub marketplace_stats_cache: TtlCache<(String, i32), models::MarketplaceSummary>,
+    pub batch_timeout: Option<Duration>,
+    pub max_nft_full_per_request: u32,
+    pub nft_activity: NftActivitySender,
+}
+
+impl SharedData {
+    /// Evict cached values made stale by a write to the database
+    fn invalidate(&self, change: &notify::TableChange) {
+        let table = change.table.as_str();
+
+        if let ("listing_receipts" | "purchase_receipts" | "bid_receipts", Some(auction_house)) =
+            (table, change.key.as_deref())
+        {
+            self.activity_summary_cache
+                .remove_where(|(a, _)| a == auction_house);
+        }
+
+        // Marketplace summaries are keyed by store config rather than auction
+        // house, so any listing or sale evicts all of them
+        if matches!(table, "listing_receipts" | "purchase_receipts") {
+            self.marketplace_stats_cache.remove_where(|_| true);
+        }
+    }
+}
+
+#[allow(clippy::unused_async)]
+async fn graphiql(data: web::Data<GraphiqlData>) -> HttpResponse {
+    let html = graphiql_source(&data.uri, None);
+
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(html)
+}
+
+#[allow(clippy::unused_async)]
+async fn redirect_version(data: web::Data<RedirectData>) -> HttpResponse {
+    HttpResponse::MovedPermanently()
+        .insert_header(("Location", data.new_route))
+        .body(format!(
+            "API route {} deprecated, please use {}",
+            data.route, data.new_route
+        ))
+}
+
+/// Access log format, extending the default with the request's correlation
+/// ID as returned in its response headers
+const LOG_FORMAT: &str = r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T %{x-request-id}o"#;
+
+/// Maximum time the readiness check may take to acquire a connection and run
+/// its query before the server is reported unavailable
+const READINESS_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[allow(clippy::unused_async)]
+async fn health() -> HttpResponse {
+    HttpResponse::Ok().body("ok")
+}
+
+async fn readiness(data: web::Data<SharedData>) -> HttpResponse {
+    let db = data.db.clone();
+    let check = web::block(move || -> Result<()> {
+        let conn = db
+            .get_timeout(READINESS_TIMEOUT)
+            .context("Failed to acquire database connection")?;
+
+        db::sql_query("select 1")
+            .execute(&conn)
+            .context("Failed to query database")?;
+
+        Ok(())
+    });
+
+    match actix_web::rt::time::timeout(READINESS_TIMEOUT, check).await {
+        Ok(Ok(Ok(()))) => HttpResponse::Ok().body("ok"),
+        Ok(Ok(Err(e))) => {
+            warn!("Readiness check failed: {:?}", e);
+            HttpResponse::ServiceUnavailable().body("database unavailable")
+        },
+        Ok(Err(e)) => {
+            error!("Readiness check task failed: {:?}", e);
+            HttpResponse::ServiceUnavailable().body("database unavailable")
+        },
+        Err(_) => {
+            warn!("Readiness check timed out");
+            HttpResponse::ServiceUnavailable().body("database unavailable")
+        },
+    }
+}
+
+async fn graphql(
+    req: HttpRequest,
+    data: web::Data<SharedData>,
+    body: web::Json<serde_json::Value>,
+) -> Result<HttpResponse, Error> {
+    let request_id = req.extensions().get::<RequestId>().cloned();
+    let access = req
+        .extensions()
+        .get::<Access>()
+        .copied()
+        .unwrap_or_default();
+    let mut body = body.into_inner();
+
+    if let Err(e) = data.persisted_queries.resolve(&mut body) {
+        return Ok(HttpResponse::Ok().json(e.to_response()));
+    }
+
+    if let Err(message) = data.query_analyzer.check(&body) {
+        return Ok(HttpResponse::Ok().json(serde_json::json!({
+            "errors": [{ "message": message }],
+        })));
+    }
+
+    let req: GraphQLRequest =
+        serde_json::from_value(body).map_err(actix_web::error::ErrorBadRequest)?;
+    let ctx = AppContext::new(data.clone().into_inner(), request_id, access);
+    let resp = req.execute(&data.schema, &ctx).await;
+
+    Ok(HttpResponse::Ok().json(&resp))
+}
+
+/// Interval between keep-alive messages sent to subscription clients
+const SUBSCRIPTION_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+async fn subscriptions(
+    req: HttpRequest,
+    payload: web::Payload,
+    data: web::Data<SharedData>,
+) -> Result<HttpResponse, Error> {
+    let request_id = req.extensions().get::<RequestId>().cloned();
+    let access = req
+        .extensions()
+        .get::<Access>()
+        .copied()
+        .unwrap_or_default();
+    let ctx = AppContext::new(data.clone().into_inner(), request_id, access);
+    let config = ConnectionConfig::new(ctx).with_keep_alive_interval(SUBSCRIPTION_KEEP_ALIVE);
+
+    let payload = subscription_guard::guard(&req, payload, {
+        let data = data.clone();
+        move |body| data.query_analyzer.check(body)
+    })
+    .await?;
+
+    subscriptions_handler(req, payload, Arc::clone(&data.schema), config).await
+}
+
+/// Path prefix of the current API version
+const VERSION_EXTENSION: &str = "/v1";
+
+/// Construct the server application with all of its middleware and routes
+fn app(
+    shared: &web::Data<SharedData>,
+    api_keys: &Arc<Option<ApiKeys>>,
+    allowed_origins: &Arc<Vec<String>>,
+    enable_graphiql: bool,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    let redirect_data = web::Data::new(RedirectData {
+        route: "/v0",
+        new_route: VERSION_EXTENSION,
+    });
+
+    let graphiql_data = web::Data::new(GraphiqlData {
+        uri: VERSION_EXTENSION.to_owned(),
+    });
+
+    App::new()
+        .wrap(middleware::Compress::default())
+        .wrap_fn(request_id::middleware)
+        .wrap(middleware::Logger::new(LOG_FORMAT))
+        .wrap(cors::build(allowed_origins))
+        .service(
+            web::resource(VERSION_EXTENSION)
+                .app_data(shared.clone())
+                .wrap_fn({
+                    let keys = api_keys.clone();
+                    move |req, srv| api_keys::middleware(Option::as_ref(&keys), req, srv)
+                })
+                .route(web::post().to(graphql)),
+        )
+        .service(
+            web::resource(format!("{}/subscriptions", VERSION_EXTENSION))
+                .app_data(shared.clone())
+                .wrap_fn({
+                    let keys = api_keys.clone();
+                    move |req, srv| api_keys::middleware(Option::as_ref(&keys), req, srv)
+                })
+                .route(web::get().to(subscriptions)),
+        )
+        .service(
+            web::resource(redirect_data.route)
+                .app_data(redirect_data)
+                .to(redirect_version),
+        )
+        .service(web::resource("/health").route(web::get().to(health)))
+        .service(
+            web::resource("/metrics")
+                .app_data(shared.clone())
+                .route(web::get().to(metrics::serve)),
+        )
+        .service(
+            web::resource("/readiness")
+                .app_data(shared.clone())
+                .route(web::get().to(readiness)),
+        )
+        .configure(|cfg| {
+            if enable_graphiql {
+                cfg.service(
+                    web::resource("/graphiql")
+                        .app_data(graphiql_data)
+                        .route(web::get().to(graphiql)),
+                );
+            }
+        })
+}
+
+/// Print the GraphQL schema in the GraphQL schema definition language
+#[must_use]
+pub fn schema_sdl() -> String {
+    schema::create().as_schema_language()
+}
+
+/// Parse command-line options and run the GraphQL server until it exits
+pub fn run() {
+    indexer_core::run(|opts: Opts| {
+        let Opts {
+            server,
+            log: _,
+            allowed_origins,
+            twitter_bearer_token,
+            twitter_api_endpoint,
+            twitter_cache_capacity,
+            twitter_cache_ttl,
+            twitter_missing_cache_ttl,
+            asset_proxy_endpoint,
+            asset_proxy_count,
+            default_auction_house,
+            listing_tie_break,
+            confirmation_depth,
+            aggregate_cache_ttl,
+            aggregate_cache_stale_ttl,
+            aggregate_cache_revalidate,
+            batch_timeout,
+            statement_timeout,
+            max_nft_full_per_request,
+            max_query_depth,
+            max_query_cost,
+            persisted_query_cache_capacity,
+            persisted_query_cache_ttl,
+            api_keys_file,
+            disable_introspection,
+            enable_graphiql,
+        } = opts;
+
+        let (addr, mut pool_config, mut heavy_pool_config) = server.into_parts();
+        info!("Listening on {}", addr);
+
+        let statement_timeout =
+            Some(Duration::from_millis(statement_timeout)).filter(|d| *d != Duration::ZERO);
+        pool_config.statement_timeout = statement_timeout;
+
+        if let Some(ref mut config) = heavy_pool_config {
+            config.statement_timeout = statement_timeout;
+        }
+
+        let twitter_bearer_token = twitter_bearer_token.unwrap_or_else(String::new);
+
+        let db =
+            Arc::new(db::connect_replicas(pool_config).context("Failed to connect to Postgres")?);
+        let heavy_db = match heavy_pool_config {
+            Some(config) => Arc::new(
+                db::connect_replicas(config)
+                    .context("Failed to connect heavy query pool to Postgres")?,
+            ),
+            None => db.clone(),
+        };
+
+        let api_keys = Arc::new(
+            api_keys_file
+                .map(ApiKeys::load)
+                .transpose()
+                .context("Failed to load API keys")?,
+        );
+
+        metrics::init();
+
+        let schema = schema::create();
+        let query_analyzer = QueryAnalyzer::new(
+            &schema,
+            Some(max_query_depth).filter(|d| *d != 0),
+            Some(max_query_cost).filter(|c| *c != 0),
+            !disable_introspection,
+        )
+        .context("Failed to initialize query analyzer")?;
+
+        // Notifications are only delivered by the primary, not read replicas
+        let notify_url = db::connection_url(db::ConnectMode::Write)
+            .context("Failed to get notification listener database URL")?;
+        let nft_activity = schema::nft_activity_channel();
+
+        let shared = web::Data::new(SharedData {
+            schema: Arc::new(schema),
+            query_analyzer,
+            persisted_queries: PersistedQueries::new(
+                persisted_query_cache_capacity,
+                Duration::from_secs(persisted_query_cache_ttl),
+            ),
+            db,
+            heavy_db,
+            asset_proxy_endpoint,
+            asset_proxy_count,
+            twitter_bearer_token,
+            twitter_api_endpoint,
+            twitter_profile_cache: Arc::new(LruCache::new(
+                twitter_cache_capacity,
+                Duration::from_secs(twitter_cache_ttl),
+            )),
+            twitter_missing_cache_ttl: Duration::from_secs(twitter_missing_cache_ttl),
+            twitter_rate_limit: Arc::new(TwitterRateLimit::default()),
+            default_auction_house,
+            listing_tie_break,
+            confirmation_depth,
+            activity_summary_cache: TtlCache::new(
+                Duration::from_secs(aggregate_cache_ttl),
+                Duration::from_secs(aggregate_cache_stale_ttl),
+                aggregate_cache_revalidate,
+            ),
+            marketplace_stats_cache: TtlCache::new(
+                Duration::from_secs(aggregate_cache_ttl),
+                Duration::from_secs(aggregate_cache_stale_ttl),
+                aggregate_cache_revalidate,
+            ),
+            batch_timeout: Some(Duration::from_millis(batch_timeout))
+                .filter(|d| *d != Duration::ZERO),
+            max_nft_full_per_request,
+            nft_activity: nft_activity.clone(),
+        });
+
+        let allowed_origins = Arc::new(
+            allowed_origins
+                .into_iter()
+                .flatten()
+                .map(|o| o.trim().to_owned())
+                .filter(|o| !o.is_empty())
+                .collect::<Vec<_>>(),
+        );
+
+        actix_web::rt::System::new()
+            .block_on(async move {
+                let changes = notify::subscribe_changes(notify_url.clone(), vec![
+                    notify::TABLE_CHANGES_CHANNEL,
+                ]);
+
+                actix_web::rt::spawn({
+                    let shared = shared.clone();
+
+                    changes.for_each(move |change| {
+                        shared.invalidate(&change);
+
+                        future::ready(())
+                    })
+                });
+
+                actix_web::rt::spawn(notify::listen(
+                    notify_url,
+                    schema::NFT_ACTIVITY_CHANNEL,
+                    move |payload| match schema::parse_activity(payload) {
+                        // Sending only fails if there are no subscribers
+                        Ok(activity) => std::mem::drop(nft_activity.send(activity)),
+                        Err(e) => warn!("Ignoring malformed activity notification: {:?}", e),
+                    },
+                ));
+
+                HttpServer::new(move || app(&shared, &api_keys, &allowed_origins, enable_graphiql))
+                    .bind(addr)?
+                    .run()
+                    .await
+            })
+            .context("Actix server failed to run")
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        http::{header, StatusCode},
+        test,
+        web::Bytes,
+    };
+
+    use super::*;
+
+    async fn get(data: SharedData, path: &str) -> (StatusCode, Bytes) {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(data))
+                .service(web::resource("/health").route(web::get().to(health)))
+                .service(web::resource("/readiness").route(web::get().to(readiness))),
+        )
+        .await;
+
+        let res = test::call_service(&app, test::TestRequest::get().uri(path).to_request()).await;
+        let status = res.status();
+
+        (status, test::read_body(res).await)
+    }
+
+    #[test]
+    fn healthy() {
+        let db = match indexer_core::db::testing::test_replicas() {
+            Some(db) => Arc::new(db),
+            None => return,
+        };
+
+        actix_web::rt::System::new().block_on(async {
+            for path in ["/health", "/readiness"] {
+                let (status, body) = get(testing::shared_data(Arc::clone(&db)), path).await;
+
+                assert_eq!(status, StatusCode::OK);
+                assert_eq!(body, "ok");
+            }
+        });
+    }
+
+    #[test]
+    fn routes_and_compression() {
+        actix_web::rt::System::new().block_on(async {
+            let shared = web::Data::new(testing::shared_data(testing::unreachable_replicas()));
+            let app =
+                test::init_service(app(&shared, &Arc::new(None), &Arc::new(vec![]), true)).await;
+
+            let res =
+                test::call_service(&app, test::TestRequest::get().uri("/graphiql").to_request())
+                    .await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert!(res.headers().get(header::CONTENT_ENCODING).is_none());
+            let body = test::read_body(res).await;
+            assert!(String::from_utf8_lossy(&body).contains(VERSION_EXTENSION));
+
+            let res = test::call_service(
+                &app,
+                test::TestRequest::get()
+                    .uri("/graphiql")
+                    .insert_header((header::ACCEPT_ENCODING, "gzip"))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.headers().get(header::CONTENT_ENCODING).unwrap(), "gzip");
+            let gzipped = test::read_body(res).await;
+            assert_eq!(gzipped[..2], [0x1f, 0x8b]);
+            assert!(gzipped.len() < body.len());
+
+            let res = test::call_service(
+                &app,
+                test::TestRequest::get()
+                    .uri("/v0")
+                    .insert_header((header::ACCEPT_ENCODING, "gzip"))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(res.status(), StatusCode::MOVED_PERMANENTLY);
+            assert_eq!(
+                res.headers().get(header::LOCATION).unwrap(),
+                VERSION_EXTENSION
+            );
+        });
+    }
+
+    #[test]
+    fn schema_sdl_lists_types() {
+        let sdl = schema_sdl();
+
+        assert!(sdl.contains("type Nft {"));
+        assert!(sdl.contains("type TwitterProfile {"));
+    }
+
+    #[test]
+    fn database_down() {
+        actix_web::rt::System::new().block_on(async {
+            let data = || testing::shared_data(testing::unreachable_replicas());
+
+            let (status, body) = get(data(), "/health").await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body, "ok");
+
+            let (status, body) = get(data(), "/readiness").await;
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(body, "database unavailable");
+        });
+    }
+}
@@ -0,0 +1,153 @@
+//! Prometheus metrics for the GraphQL server, served at `/metrics`
+
+use actix_web::{web, HttpResponse};
+use indexer_core::{
+    metrics::{
+        self,
+        prometheus::{HistogramOpts, HistogramTimer, HistogramVec, IntCounter, IntGaugeVec, Opts},
+    },
+    prelude::*,
+};
+use lazy_static::lazy_static;
+
+use crate::SharedData;
+
+lazy_static! {
+    /// Twitter API requests rejected for exceeding the rate limit
+    pub(crate) static ref TWITTER_RATE_LIMITED: IntCounter = metrics::register(
+        IntCounter::new(
+            "graphql_twitter_rate_limited_total",
+            "Twitter API requests rejected for exceeding the rate limit",
+        )
+        .unwrap_or_else(|_| unreachable!()),
+    );
+
+    /// Time taken to resolve instrumented fields, in seconds
+    static ref RESOLVER_DURATION: HistogramVec = metrics::register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "graphql_resolver_duration_seconds",
+                "Time taken to resolve instrumented fields",
+            ),
+            &["field"],
+        )
+        .unwrap_or_else(|_| unreachable!()),
+    );
+
+    /// Number of keys requested in each dataloader batch
+    static ref BATCH_SIZE: HistogramVec = metrics::register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "graphql_dataloader_batch_size",
+                "Number of keys requested in each dataloader batch",
+            )
+            .buckets(vec![1.0, 2.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0]),
+            &["loader"],
+        )
+        .unwrap_or_else(|_| unreachable!()),
+    );
+
+    /// Time taken to load each dataloader batch, in seconds
+    static ref BATCH_DURATION: HistogramVec = metrics::register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "graphql_dataloader_batch_duration_seconds",
+                "Time taken to load each dataloader batch",
+            ),
+            &["loader"],
+        )
+        .unwrap_or_else(|_| unreachable!()),
+    );
+
+    /// Whether each database replica is currently receiving reads
+    static ref DB_REPLICA_HEALTHY: IntGaugeVec = metrics::register(
+        IntGaugeVec::new(
+            Opts::new(
+                "graphql_db_replica_healthy",
+                "Whether each database replica is currently receiving reads",
+            ),
+            &["pool", "replica"],
+        )
+        .unwrap_or_else(|_| unreachable!()),
+    );
+}
+
+/// Register all server metrics so they are reported before first use
+pub(crate) fn init() {
+    lazy_static::initialize(&TWITTER_RATE_LIMITED);
+    lazy_static::initialize(&RESOLVER_DURATION);
+    lazy_static::initialize(&BATCH_SIZE);
+    lazy_static::initialize(&BATCH_DURATION);
+    lazy_static::initialize(&DB_REPLICA_HEALTHY);
+}
+
+/// Start timing the resolution of a field, recording it when the returned
+/// timer is dropped
+pub(crate) fn time_resolver(field: &str) -> HistogramTimer {
+    RESOLVER_DURATION.with_label_values(&[field]).start_timer()
+}
+
+/// Record the size of a dataloader batch and start timing its load,
+/// recording it when the returned timer is dropped
+pub(crate) fn time_batch(loader: &str, size: usize) -> HistogramTimer {
+    #[allow(clippy::cast_precision_loss)]
+    let size = size as f64;
+
+    BATCH_SIZE.with_label_values(&[loader]).observe(size);
+
+    BATCH_DURATION.with_label_values(&[loader]).start_timer()
+}
+
+fn record_replica_health(shared: &SharedData) {
+    for (pool, db) in [("default", &shared.db), ("heavy", &shared.heavy_db)] {
+        for replica in db.health() {
+            DB_REPLICA_HEALTHY
+                .with_label_values(&[pool, &replica.name])
+                .set(replica.healthy.into());
+        }
+    }
+}
+
+/// Respond with the current value of all registered metrics
+#[allow(clippy::unused_async)]
+pub(crate) async fn serve(shared: web::Data<SharedData>) -> HttpResponse {
+    record_replica_health(&shared);
+
+    match metrics::encode() {
+        Ok(body) => HttpResponse::Ok()
+            .content_type(metrics::content_type())
+            .body(body),
+        Err(e) => {
+            error!("Failed to scrape metrics: {:?}", e);
+            HttpResponse::InternalServerError().finish()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{context, execute};
+
+    #[test]
+    fn resolving_a_field_is_timed() {
+        let (ctx, _db) = match context() {
+            Some(c) => c,
+            None => return,
+        };
+        let count = || {
+            RESOLVER_DURATION
+                .with_label_values(&["Query.nft"])
+                .get_sample_count()
+        };
+
+        let before = count();
+        let data = execute(&ctx, r#"{ nft(address: "missing") { address } }"#);
+
+        assert_eq!(data, json!({ "nft": null }));
+        // Other tests may resolve the same field concurrently
+        assert!(count() > before);
+    }
+}
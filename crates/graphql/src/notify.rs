@@ -0,0 +1,386 @@
+//! Listener for Postgres `NOTIFY` events
+//!
+//! Besides the NFT activity feed, the indexer's database notifies
+//! [`TABLE_CHANGES_CHANNEL`] whenever a row of a table backing a cache is
+//! written, with the table name and the row's cache key as the payload.
+//! [`subscribe_changes`] exposes these as a stream of [`TableChange`]s for
+//! evicting stale cache entries.
+//!
+//! Listener connections negotiate TLS as requested by the `sslmode` of the
+//! database URL, verifying the server as libpq would for the same URL.
+
+use std::time::Duration;
+
+use futures_util::{future, stream, Stream, StreamExt};
+use indexer_core::prelude::*;
+use native_tls::{Certificate, TlsConnector};
+use postgres_native_tls::MakeTlsConnector;
+use reqwest::Url;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_postgres::AsyncMessage;
+
+/// Delay before reconnecting after the listening connection fails
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Notification channel carrying writes to cache-backing tables
+pub const TABLE_CHANGES_CHANNEL: &str = "table_changes";
+
+/// A write to a row of a cache-backing table
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TableChange {
+    /// Name of the table written to
+    pub table: String,
+    /// Value of the column the table's cached entries are keyed by, if any
+    pub key: Option<String>,
+}
+
+/// Verification of the server requested by the `sslmode` of a database URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verify {
+    /// Check neither the certificate nor the hostname, for `prefer` and
+    /// `require`
+    Nothing,
+    /// Check the certificate chain but not the hostname, for `verify-ca`
+    Ca,
+    /// Check the certificate chain and the hostname, for `verify-full` or if
+    /// no `sslmode` is given
+    Full,
+}
+
+/// TLS options of a database URL which `tokio-postgres` does not understand
+#[derive(Debug, PartialEq, Eq)]
+struct TlsOptions {
+    verify: Verify,
+    root_cert: Option<String>,
+}
+
+/// Remove the `sslmode` and `sslrootcert` options from a database URL,
+/// returning a URL `tokio-postgres` accepts along with the TLS options they
+/// requested.  `verify-ca` and `verify-full` are rewritten as `require`, with
+/// the verification they ask for carried in the returned options.
+fn split_tls_options(url: &str) -> Result<(String, TlsOptions)> {
+    let mut parsed = match Url::parse(url) {
+        Ok(u) => u,
+        // Key-value connection strings are passed through as-is
+        Err(_) => {
+            return Ok((url.to_owned(), TlsOptions {
+                verify: Verify::Full,
+                root_cert: None,
+            }));
+        },
+    };
+
+    let mut mode = None;
+    let mut root_cert = None;
+    let pairs: Vec<_> = parsed
+        .query_pairs()
+        .filter_map(|(key, value)| match &*key {
+            "sslmode" => {
+                mode = Some(value.into_owned());
+                None
+            },
+            "sslrootcert" => {
+                root_cert = Some(value.into_owned());
+                None
+            },
+            _ => Some((key.into_owned(), value.into_owned())),
+        })
+        .collect();
+
+    let (mode, verify) = match mode.as_deref() {
+        None => (None, Verify::Full),
+        // TLS is never negotiated, so there is nothing to verify
+        Some("disable") => (Some("disable"), Verify::Full),
+        Some(m @ ("prefer" | "require")) => (Some(m), Verify::Nothing),
+        Some("verify-ca") => (Some("require"), Verify::Ca),
+        Some("verify-full") => (Some("require"), Verify::Full),
+        Some(m) => bail!("Unsupported sslmode {:?} in database URL", m),
+    };
+
+    parsed.set_query(None);
+
+    if !pairs.is_empty() || mode.is_some() {
+        parsed
+            .query_pairs_mut()
+            .extend_pairs(pairs)
+            .extend_pairs(mode.map(|m| ("sslmode", m)));
+    }
+
+    Ok((parsed.into(), TlsOptions { verify, root_cert }))
+}
+
+/// Construct a TLS connector for listener connections verifying the server
+/// as requested
+fn tls(opts: &TlsOptions) -> Result<MakeTlsConnector> {
+    let mut builder = TlsConnector::builder();
+
+    if let Some(ref path) = opts.root_cert {
+        let pem = std::fs::read(path)
+            .with_context(|| format!("Failed to read sslrootcert {:?}", path))?;
+
+        builder.add_root_certificate(
+            Certificate::from_pem(&pem)
+                .with_context(|| format!("Failed to parse sslrootcert {:?}", path))?,
+        );
+    }
+
+    match opts.verify {
+        Verify::Nothing => {
+            warn!(
+                "Not verifying the notification listener's TLS certificate, as requested by the \
+                 database URL's sslmode; use verify-full to verify it"
+            );
+
+            builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        },
+        Verify::Ca => {
+            builder.danger_accept_invalid_hostnames(true);
+        },
+        Verify::Full => (),
+    }
+
+    let connector = builder
+        .build()
+        .context("Failed to initialize TLS connector")?;
+
+    Ok(MakeTlsConnector::new(connector))
+}
+
+async fn listen_once(
+    url: &str,
+    channels: &[&str],
+    on_notification: &impl Fn(&str, &str),
+) -> Result<()> {
+    let (url, tls_options) = split_tls_options(url)?;
+    let (client, mut conn) = tokio_postgres::connect(&url, tls(&tls_options)?)
+        .await
+        .context("Failed to connect notification listener to Postgres")?;
+
+    let messages = stream::poll_fn(move |cx| conn.poll_message(cx));
+    futures_util::pin_mut!(messages);
+
+    // The connection must be polled for the LISTEN to complete
+    let query = channels
+        .iter()
+        .map(|c| format!("listen {};", c))
+        .collect::<String>();
+    let listen = client.batch_execute(&query);
+    futures_util::pin_mut!(listen);
+
+    loop {
+        match future::select(listen.as_mut(), messages.next()).await {
+            future::Either::Left((res, _)) => {
+                res.with_context(|| format!("Failed to listen on channels {:?}", channels))?;
+                break;
+            },
+            future::Either::Right((Some(msg), _)) => {
+                msg.context("Notification listener connection failed")?;
+            },
+            future::Either::Right((None, _)) => bail!("Notification listener connection closed"),
+        }
+    }
+
+    info!("Listening for notifications on {:?}", channels);
+
+    while let Some(msg) = messages.next().await {
+        match msg.context("Notification listener connection failed")? {
+            AsyncMessage::Notification(n) => on_notification(n.channel(), n.payload()),
+            AsyncMessage::Notice(n) => debug!("Postgres notice: {}", n),
+            _ => (),
+        }
+    }
+
+    bail!("Notification listener connection closed")
+}
+
+async fn listen_all(
+    url: String,
+    channels: Vec<&'static str>,
+    on_notification: impl Fn(&str, &str),
+) {
+    loop {
+        if let Err(e) = listen_once(&url, &channels, &on_notification).await {
+            error!("{:?}", e);
+        }
+
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Listen on a Postgres notification channel, calling `on_payload` with the
+/// payload of each notification received and reconnecting if the connection
+/// fails.  This future never completes.
+pub async fn listen(url: String, channel: &'static str, on_payload: impl Fn(&str)) {
+    listen_all(url, vec![channel], move |_, payload| on_payload(payload)).await;
+}
+
+/// Spawn a listener on the given channels, returning a stream of the
+/// [`TableChange`]s they carry.  Malformed payloads are logged and skipped,
+/// and the listener reconnects if its connection fails, so the stream never
+/// ends.
+pub fn subscribe_changes(
+    url: String,
+    channels: Vec<&'static str>,
+) -> impl Stream<Item = TableChange> {
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    actix_web::rt::spawn(listen_all(url, channels, move |channel, payload| {
+        match serde_json::from_str::<TableChange>(payload) {
+            // Sending only fails once the stream has been dropped
+            Ok(change) => std::mem::drop(tx.send(change)),
+            Err(e) => warn!(
+                "Ignoring malformed change notification on {:?}: {}",
+                channel, e
+            ),
+        }
+    }));
+
+    stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|c| (c, rx)) })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, sync::Arc};
+
+    use indexer_core::db::{models, testing::TEST_DATABASE_URL};
+
+    use super::*;
+    use crate::testing;
+
+    fn summary(listings: i64) -> models::ActivitySummary {
+        models::ActivitySummary {
+            listings,
+            sales: 0,
+            cancellations: 0,
+            bids: 0,
+        }
+    }
+
+    fn market_summary(floor: i64) -> models::MarketplaceSummary {
+        models::MarketplaceSummary {
+            floor: Some(floor),
+            volume: None,
+            listed: 1,
+            holders: 1,
+        }
+    }
+
+    #[test]
+    fn tls_options_follow_sslmode() {
+        let split = |url| split_tls_options(url).unwrap();
+        let options = |verify, root_cert: Option<&str>| TlsOptions {
+            verify,
+            root_cert: root_cert.map(Into::into),
+        };
+
+        assert_eq!(
+            split("postgres://u:p@db:5432/indexer"),
+            (
+                "postgres://u:p@db:5432/indexer".into(),
+                options(Verify::Full, None)
+            )
+        );
+        assert_eq!(
+            split("postgres://db/indexer?sslmode=require&application_name=gql"),
+            (
+                "postgres://db/indexer?application_name=gql&sslmode=require".into(),
+                options(Verify::Nothing, None)
+            )
+        );
+        assert_eq!(
+            split("postgres://db/indexer?sslmode=verify-ca&sslrootcert=/etc/ca.pem"),
+            (
+                "postgres://db/indexer?sslmode=require".into(),
+                options(Verify::Ca, Some("/etc/ca.pem"))
+            )
+        );
+        assert_eq!(
+            split("postgres://db/indexer?sslmode=verify-full"),
+            (
+                "postgres://db/indexer?sslmode=require".into(),
+                options(Verify::Full, None)
+            )
+        );
+        assert_eq!(
+            split("host=db dbname=indexer"),
+            ("host=db dbname=indexer".into(), options(Verify::Full, None))
+        );
+
+        assert!(split_tls_options("postgres://db/indexer?sslmode=bogus").is_err());
+    }
+
+    #[test]
+    fn notification_evicts_cached_summary() {
+        let url = match env::var(TEST_DATABASE_URL) {
+            Ok(u) if !u.trim().is_empty() => u,
+            _ => return,
+        };
+        let shared = Arc::new(testing::shared_data(testing::unreachable_replicas()));
+        // Unique per run, since notifications are not rolled back with the
+        // test database's transactions
+        let house = format!("notify-test-{}", uuid::Uuid::new_v4());
+
+        actix_web::rt::System::new().block_on(async move {
+            let cached = |listings| {
+                shared
+                    .activity_summary_cache
+                    .get_or_try_insert((house.clone(), 7), move || {
+                        Ok::<_, Error>(summary(listings))
+                    })
+            };
+
+            let market = |floor| {
+                shared
+                    .marketplace_stats_cache
+                    .get_or_try_insert(("store".to_owned(), 24), move || {
+                        Ok::<_, Error>(market_summary(floor))
+                    })
+            };
+
+            assert_eq!(cached(1).await.unwrap().listings, 1);
+            assert_eq!(cached(2).await.unwrap().listings, 1);
+            assert_eq!(market(1).await.unwrap().floor, Some(1));
+            assert_eq!(market(2).await.unwrap().floor, Some(1));
+
+            let changes = subscribe_changes(url.clone(), vec![TABLE_CHANGES_CHANNEL])
+                .filter(|c| future::ready(c.key.as_deref() == Some(house.as_str())));
+            futures_util::pin_mut!(changes);
+
+            let (pg_url, tls_options) = split_tls_options(&url).unwrap();
+            let (client, conn) = tokio_postgres::connect(&pg_url, tls(&tls_options).unwrap())
+                .await
+                .unwrap();
+            actix_web::rt::spawn(conn);
+
+            let payload =
+                serde_json::json!({ "table": "listing_receipts", "key": house }).to_string();
+
+            // The listener may not have subscribed yet, so keep notifying
+            // until it receives one
+            let change = loop {
+                client
+                    .execute("select pg_notify($1, $2)", &[
+                        &TABLE_CHANGES_CHANNEL,
+                        &payload,
+                    ])
+                    .await
+                    .unwrap();
+
+                if let Ok(change) =
+                    tokio::time::timeout(Duration::from_millis(500), changes.next()).await
+                {
+                    break change.unwrap();
+                }
+            };
+
+            assert_eq!(change.table, "listing_receipts");
+            shared.invalidate(&change);
+
+            assert_eq!(cached(3).await.unwrap().listings, 3);
+            assert_eq!(market(3).await.unwrap().floor, Some(3));
+        });
+    }
+}
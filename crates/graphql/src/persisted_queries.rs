@@ -0,0 +1,160 @@
+//! Automatic persisted queries, as implemented by Apollo clients
+//!
+//! A client may send the SHA-256 hash of a query in
+//! `extensions.persistedQuery.sha256Hash` in place of its text.  If the hash
+//! is not cached the request fails with `PersistedQueryNotFound`, and the
+//! client retries with both the hash and the full query, which is then cached
+//! so later requests can be served by hash alone.
+
+use std::time::Duration;
+
+use sha2::{Digest, Sha256};
+
+use crate::schema::LruCache;
+
+/// The only persisted query protocol version supported
+const VERSION: u64 = 1;
+
+/// Error returned to clients when a persisted query cannot be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The hash is not cached and no query was sent to register it
+    NotFound,
+    /// The client requested an unsupported protocol version
+    UnsupportedVersion,
+    /// The hash sent does not match the SHA-256 hash of the query sent
+    HashMismatch,
+}
+
+impl Error {
+    /// Render this error as a GraphQL response body
+    #[must_use]
+    pub fn to_response(self) -> serde_json::Value {
+        let (message, code) = match self {
+            Self::NotFound => ("PersistedQueryNotFound", "PERSISTED_QUERY_NOT_FOUND"),
+            Self::UnsupportedVersion => (
+                "Unsupported persisted query version",
+                "PERSISTED_QUERY_NOT_SUPPORTED",
+            ),
+            Self::HashMismatch => ("Provided sha256Hash does not match query", "BAD_USER_INPUT"),
+        };
+
+        serde_json::json!({
+            "errors": [{ "message": message, "extensions": { "code": code } }],
+        })
+    }
+}
+
+/// A bounded cache of registered queries keyed by their SHA-256 hash
+#[derive(Debug)]
+pub struct PersistedQueries(LruCache<String, String>);
+
+impl PersistedQueries {
+    /// Construct an empty cache holding at most `capacity` queries, each of
+    /// which expires `ttl` after it is registered.  A capacity of zero
+    /// disables persisted queries.
+    #[must_use]
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self(LruCache::new(capacity, ttl))
+    }
+
+    /// Fill in the query text of a request body carrying a persisted query
+    /// hash, registering the query if it was sent alongside the hash.  Bodies
+    /// without a persisted query extension are left unchanged.
+    ///
+    /// # Errors
+    /// This function fails if the hash is unknown and no query was sent, if
+    /// the query sent does not match the hash, or if the protocol version is
+    /// unsupported.
+    pub fn resolve(&self, body: &mut serde_json::Value) -> Result<(), Error> {
+        let persisted = match body.pointer("/extensions/persistedQuery") {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+
+        if persisted.get("version").and_then(serde_json::Value::as_u64) != Some(VERSION) {
+            return Err(Error::UnsupportedVersion);
+        }
+
+        let hash = match persisted
+            .get("sha256Hash")
+            .and_then(serde_json::Value::as_str)
+        {
+            Some(h) => h.to_ascii_lowercase(),
+            None => return Err(Error::NotFound),
+        };
+
+        match body.get("query").and_then(serde_json::Value::as_str) {
+            Some(query) => {
+                if hex::encode(Sha256::digest(query.as_bytes())) != hash {
+                    return Err(Error::HashMismatch);
+                }
+
+                self.0.insert(hash, query.to_owned());
+            },
+            None => {
+                let query = self.0.get(&hash).ok_or(Error::NotFound)?;
+
+                body["query"] = query.into();
+            },
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const QUERY: &str = "{ nft(address: \"nft\") { name } }";
+
+    fn persisted(query: Option<&str>, hash: &str) -> serde_json::Value {
+        let mut body = json!({
+            "extensions": { "persistedQuery": { "version": 1, "sha256Hash": hash } },
+        });
+
+        if let Some(query) = query {
+            body["query"] = query.into();
+        }
+
+        body
+    }
+
+    #[test]
+    fn miss_then_hit() {
+        let queries = PersistedQueries::new(10, Duration::from_secs(60));
+        let hash = hex::encode(Sha256::digest(QUERY.as_bytes()));
+
+        let mut body = persisted(None, &hash);
+        assert_eq!(queries.resolve(&mut body), Err(Error::NotFound));
+        assert!(body.get("query").is_none());
+
+        let mut body = persisted(Some(QUERY), &hash);
+        assert_eq!(queries.resolve(&mut body), Ok(()));
+        assert_eq!(body["query"], QUERY);
+
+        let mut body = persisted(None, &hash.to_ascii_uppercase());
+        assert_eq!(queries.resolve(&mut body), Ok(()));
+        assert_eq!(body["query"], QUERY);
+    }
+
+    #[test]
+    fn rejects_bad_requests() {
+        let queries = PersistedQueries::new(10, Duration::from_secs(60));
+        let hash = hex::encode(Sha256::digest(QUERY.as_bytes()));
+
+        let mut body = persisted(Some("{ other }"), &hash);
+        assert_eq!(queries.resolve(&mut body), Err(Error::HashMismatch));
+
+        let mut body = persisted(Some(QUERY), &hash);
+        body["extensions"]["persistedQuery"]["version"] = 2.into();
+        assert_eq!(queries.resolve(&mut body), Err(Error::UnsupportedVersion));
+
+        let mut body = json!({ "query": QUERY });
+        assert_eq!(queries.resolve(&mut body), Ok(()));
+        assert_eq!(body, json!({ "query": QUERY }));
+    }
+}
@@ -0,0 +1,134 @@
+//! Correlation IDs for tracing a request through the server's logs
+//!
+//! Each request is assigned an ID, taken from its `X-Request-Id` header if it
+//! carries a valid one or generated otherwise.  The ID is stored in the
+//! request's extensions, attached as the `request_id` field to every log
+//! record emitted while the request is handled, and returned to the client in
+//! the `X-Request-Id` response header.
+
+use std::future::Future;
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    HttpMessage,
+};
+use indexer_core::logging::WithFieldsExt;
+
+/// Name of the header carrying the request ID
+pub const HEADER: &str = "x-request-id";
+
+/// Longest incoming request ID accepted rather than replaced
+const MAX_LEN: usize = 128;
+
+/// The correlation ID of a request
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Middleware assigning a [`RequestId`] to each request
+pub fn middleware<S, B>(
+    req: ServiceRequest,
+    srv: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, actix_web::Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    let id = req
+        .headers()
+        .get(HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| is_valid(v))
+        .map_or_else(|| uuid::Uuid::new_v4().to_string(), ToOwned::to_owned);
+
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    let res = srv
+        .call(req)
+        .with_log_fields(vec![("request_id", id.clone())]);
+
+    async move {
+        let mut res = res.await?;
+
+        // IDs are validated or generated as header-safe ASCII above
+        if let Ok(value) = HeaderValue::from_str(&id) {
+            res.headers_mut()
+                .insert(HeaderName::from_static(HEADER), value);
+        }
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{dev::Service, test, web, App, HttpRequest, HttpResponse};
+    use indexer_core::logging;
+
+    use super::*;
+
+    async fn echo(req: HttpRequest) -> HttpResponse {
+        let log_id = logging::current_fields()
+            .into_iter()
+            .find_map(|(k, v)| (k == "request_id").then(|| v))
+            .unwrap_or_default();
+        let ext_id = req
+            .extensions()
+            .get::<RequestId>()
+            .map(|RequestId(id)| id.clone())
+            .unwrap_or_default();
+
+        HttpResponse::Ok().body(format!("{} {}", log_id, ext_id))
+    }
+
+    #[test]
+    fn response_header_matches_log_id() {
+        actix_web::rt::System::new().block_on(async {
+            let app = test::init_service(
+                App::new()
+                    .wrap_fn(middleware)
+                    .route("/", web::get().to(echo)),
+            )
+            .await;
+
+            let ids = |req: test::TestRequest| {
+                let res = app.call(req.uri("/").to_request());
+
+                async move {
+                    let res = res.await.unwrap();
+                    let header = res
+                        .headers()
+                        .get(HEADER)
+                        .unwrap()
+                        .to_str()
+                        .unwrap()
+                        .to_owned();
+                    let body = test::read_body(res).await;
+
+                    (header, String::from_utf8(body.to_vec()).unwrap())
+                }
+            };
+
+            let (header, body) = ids(test::TestRequest::get()).await;
+            assert!(is_valid(&header));
+            assert_eq!(body, format!("{} {}", header, header));
+
+            let (header, body) =
+                ids(test::TestRequest::get().insert_header((HEADER, "abc-123"))).await;
+            assert_eq!(header, "abc-123");
+            assert_eq!(body, "abc-123 abc-123");
+
+            let (header, body) =
+                ids(test::TestRequest::get().insert_header((HEADER, "not valid!"))).await;
+            assert_ne!(header, "not valid!");
+            assert_eq!(body, format!("{} {}", header, header));
+        });
+    }
+}
@@ -0,0 +1,461 @@
+//! In-memory caching for expensive aggregate queries and external lookups
+//!
+//! [`TtlCache`] entries are fresh for a fixed TTL, after which they may still
+//! be served for a further stale window while a single recomputation
+//! refreshes them.  Only one computation runs per key at a time; concurrent
+//! requests for a missing key wait for it rather than recomputing the value
+//! themselves.
+//!
+//! [`LruCache`] entries simply expire after a TTL, with the least recently
+//! used entry evicted once the cache reaches its capacity.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    hash::Hash,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use actix_web::{error::BlockingError, rt, web};
+use indexer_core::clap;
+use tokio::sync::watch;
+
+use super::prelude::*;
+
+/// Strategy for refreshing a cache entry served from its stale window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ArgEnum)]
+pub enum Revalidate {
+    /// Recompute on a background task, serving the stale value to every
+    /// request in the meantime
+    Background,
+    /// Recompute in the first request to see the stale value, serving the
+    /// stale value to concurrent requests in the meantime
+    Inline,
+}
+
+#[derive(Debug)]
+struct Slots<K, V> {
+    values: HashMap<K, (Instant, V)>,
+    /// Keys with a computation running, each with a receiver which is closed
+    /// once the computation finishes
+    pending: HashMap<K, watch::Receiver<()>>,
+}
+
+#[derive(Debug)]
+struct Shared<K, V> {
+    slots: Mutex<Slots<K, V>>,
+}
+
+/// Marks a key as pending until dropped, waking any requests waiting on it
+#[derive(Debug)]
+struct Pending<K: Eq + Hash, V> {
+    shared: Arc<Shared<K, V>>,
+    key: K,
+    _done: watch::Sender<()>,
+}
+
+impl<K: Eq + Hash, V> Drop for Pending<K, V> {
+    fn drop(&mut self) {
+        self.shared.lock().pending.remove(&self.key);
+    }
+}
+
+#[derive(Debug)]
+enum Lookup<K: Eq + Hash, V> {
+    Fresh(V),
+    Stale(V, Pending<K, V>),
+    Wait(watch::Receiver<()>),
+    Miss(Pending<K, V>),
+}
+
+impl<K, V> Shared<K, V> {
+    fn lock(&self) -> MutexGuard<Slots<K, V>> {
+        self.slots.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Shared<K, V> {
+    /// Find the cached value for a key, marking the key as pending if it needs
+    /// to be computed by the caller
+    fn lookup(self: &Arc<Self>, key: &K, ttl: Duration, max_age: Duration) -> Lookup<K, V> {
+        let mut slots = self.lock();
+        let cached = slots
+            .values
+            .get(key)
+            .map(|(at, v)| (at.elapsed(), v.clone()));
+        let pending = slots.pending.get(key).cloned();
+
+        let mut mark_pending = || {
+            let (done, rx) = watch::channel(());
+            slots.pending.insert(key.clone(), rx);
+
+            Pending {
+                shared: Arc::clone(self),
+                key: key.clone(),
+                _done: done,
+            }
+        };
+
+        match (cached, pending) {
+            (Some((age, value)), _) if age < ttl => Lookup::Fresh(value),
+            (Some((age, value)), Some(_)) if age < max_age => Lookup::Fresh(value),
+            (Some((age, value)), None) if age < max_age => Lookup::Stale(value, mark_pending()),
+            (_, Some(rx)) => Lookup::Wait(rx),
+            (_, None) => Lookup::Miss(mark_pending()),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone + Send + 'static, V: Clone + Send + 'static> Shared<K, V> {
+    /// Compute the value for a pending key on the blocking thread pool and
+    /// store it on success.  Dropping `pending` afterwards wakes any requests
+    /// waiting on the key, including if this future is cancelled.
+    async fn fill<E: From<BlockingError> + Send + 'static>(
+        pending: Pending<K, V>,
+        max_age: Duration,
+        f: impl FnOnce() -> Result<V, E> + Send + 'static,
+    ) -> Result<V, E> {
+        let res = web::block(f).await.map_err(E::from).and_then(|r| r);
+
+        if let Ok(ref value) = res {
+            let mut slots = pending.shared.lock();
+
+            slots.values.retain(|_, (at, _)| at.elapsed() < max_age);
+            slots
+                .values
+                .insert(pending.key.clone(), (Instant::now(), value.clone()));
+        }
+
+        res
+    }
+}
+
+/// A map of values which expire a fixed duration after they are inserted
+#[derive(Debug)]
+pub struct TtlCache<K, V> {
+    ttl: Duration,
+    stale_for: Duration,
+    revalidate: Revalidate,
+    shared: Arc<Shared<K, V>>,
+}
+
+impl<K: Eq + Hash + Clone + Send + 'static, V: Clone + Send + 'static> TtlCache<K, V> {
+    /// Construct an empty cache whose entries are fresh for `ttl`, and may be
+    /// served for a further `stale_for` while they are recomputed
+    #[must_use]
+    pub fn new(ttl: Duration, stale_for: Duration, revalidate: Revalidate) -> Self {
+        Self {
+            ttl,
+            stale_for,
+            revalidate,
+            shared: Arc::new(Shared {
+                slots: Mutex::new(Slots {
+                    values: HashMap::new(),
+                    pending: HashMap::new(),
+                }),
+            }),
+        }
+    }
+
+    /// Get a value from the cache, computing and inserting it on a miss
+    ///
+    /// `f` runs on the blocking thread pool.  Stale values are returned
+    /// immediately while `f` refreshes them according to the cache's
+    /// [`Revalidate`] strategy.  If a computation for the key is already
+    /// running, this waits for it instead of calling `f`.
+    ///
+    /// # Errors
+    /// This function fails if the value is not cached and `f` fails, or if the
+    /// blocking thread pool is unavailable.
+    pub async fn get_or_try_insert<E: From<BlockingError> + Display + Send + 'static>(
+        &self,
+        key: K,
+        f: impl FnOnce() -> Result<V, E> + Send + 'static,
+    ) -> Result<V, E> {
+        let max_age = self.ttl + self.stale_for;
+
+        loop {
+            match self.shared.lookup(&key, self.ttl, max_age) {
+                Lookup::Fresh(value) => return Ok(value),
+                Lookup::Stale(value, pending) => {
+                    return match self.revalidate {
+                        Revalidate::Background => {
+                            rt::spawn(async move {
+                                if let Err(e) = Shared::fill(pending, max_age, f).await {
+                                    warn!("Failed to revalidate cached value: {}", e);
+                                }
+                            });
+
+                            Ok(value)
+                        },
+                        Revalidate::Inline => Shared::fill(pending, max_age, f).await,
+                    };
+                },
+                Lookup::Wait(mut rx) => {
+                    // The sender is never used, so this resolves once the
+                    // pending computation finishes and drops it
+                    rx.changed().await.ok();
+                },
+                Lookup::Miss(pending) => return Shared::fill(pending, max_age, f).await,
+            }
+        }
+    }
+
+    /// Evict every cached value whose key matches `f`.  A computation already
+    /// running for a matching key is unaffected and still stores its result.
+    pub fn remove_where(&self, f: impl Fn(&K) -> bool) {
+        self.shared.lock().values.retain(|k, _| !f(k));
+    }
+}
+
+#[derive(Debug)]
+struct Lru<K, V> {
+    tick: u64,
+    /// Values with their expiry and the tick they were last used at
+    values: HashMap<K, (Instant, u64, V)>,
+    order: BTreeMap<u64, K>,
+}
+
+/// A bounded map of values which expire a set duration after they are
+/// inserted, evicting the least recently used value when full
+#[derive(Debug)]
+pub struct LruCache<K, V> {
+    capacity: usize,
+    ttl: Duration,
+    lru: Mutex<Lru<K, V>>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> LruCache<K, V> {
+    /// Construct an empty cache holding at most `capacity` entries, each of
+    /// which expires `ttl` after it is inserted unless inserted with
+    /// [`insert_for`](Self::insert_for).  A capacity of zero disables the
+    /// cache.
+    #[must_use]
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            lru: Mutex::new(Lru {
+                tick: 0,
+                values: HashMap::new(),
+                order: BTreeMap::new(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<Lru<K, V>> {
+        self.lru.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Get an unexpired value from the cache, marking it as recently used
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut lru = self.lock();
+        let Lru {
+            tick,
+            values,
+            order,
+        } = &mut *lru;
+
+        match values.get_mut(key) {
+            Some((expires_at, used, value)) if Instant::now() < *expires_at => {
+                *tick += 1;
+                order.remove(&*used);
+                order.insert(*tick, key.clone());
+                *used = *tick;
+
+                Some(value.clone())
+            },
+            Some((_, used, _)) => {
+                let used = *used;
+                order.remove(&used);
+                values.remove(key);
+
+                None
+            },
+            None => None,
+        }
+    }
+
+    /// Insert a value into the cache, evicting the least recently used values
+    /// if it is over capacity
+    pub fn insert(&self, key: K, value: V) {
+        self.insert_for(key, value, self.ttl);
+    }
+
+    /// Insert a value into the cache which expires after `ttl` rather than the
+    /// cache's TTL, evicting the least recently used values if it is over
+    /// capacity
+    pub fn insert_for(&self, key: K, value: V, ttl: Duration) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut lru = self.lock();
+        let Lru {
+            tick,
+            values,
+            order,
+        } = &mut *lru;
+
+        *tick += 1;
+
+        let expires_at = Instant::now() + ttl;
+
+        if let Some((_, used, _)) = values.insert(key.clone(), (expires_at, *tick, value)) {
+            order.remove(&used);
+        }
+
+        order.insert(*tick, key);
+
+        while values.len() > self.capacity {
+            let oldest = match order.keys().next() {
+                Some(t) => *t,
+                None => break,
+            };
+
+            if let Some(key) = order.remove(&oldest) {
+                values.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use actix_web::rt::{time::sleep, System};
+    use futures_util::future;
+
+    use super::*;
+
+    fn counted(calls: &Arc<AtomicUsize>, value: u32) -> impl FnOnce() -> Result<u32> + Send {
+        let calls = Arc::clone(calls);
+
+        move || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(50));
+
+            Ok(value)
+        }
+    }
+
+    #[test]
+    fn concurrent_misses_compute_once() {
+        System::new().block_on(async {
+            let cache = TtlCache::new(Duration::from_secs(60), Duration::ZERO, Revalidate::Inline);
+            let calls = Arc::new(AtomicUsize::new(0));
+
+            let (a, b) = future::join(
+                cache.get_or_try_insert("key", counted(&calls, 1)),
+                cache.get_or_try_insert("key", counted(&calls, 2)),
+            )
+            .await;
+
+            assert_eq!(a.unwrap(), 1);
+            assert_eq!(b.unwrap(), 1);
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+        });
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        System::new().block_on(async {
+            let cache = TtlCache::new(Duration::from_secs(60), Duration::ZERO, Revalidate::Inline);
+
+            let err = cache
+                .get_or_try_insert("key", || -> Result<u32> { Err(anyhow!("nope")) })
+                .await;
+            assert!(err.is_err());
+
+            let calls = Arc::new(AtomicUsize::new(0));
+            assert_eq!(
+                cache
+                    .get_or_try_insert("key", counted(&calls, 1))
+                    .await
+                    .unwrap(),
+                1
+            );
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+        });
+    }
+
+    #[test]
+    fn stale_values_revalidate_in_background() {
+        System::new().block_on(async {
+            let cache = TtlCache::new(
+                Duration::ZERO,
+                Duration::from_secs(60),
+                Revalidate::Background,
+            );
+            let calls = Arc::new(AtomicUsize::new(0));
+
+            assert_eq!(
+                cache
+                    .get_or_try_insert("key", counted(&calls, 1))
+                    .await
+                    .unwrap(),
+                1
+            );
+            assert_eq!(
+                cache
+                    .get_or_try_insert("key", counted(&calls, 2))
+                    .await
+                    .unwrap(),
+                1
+            );
+
+            sleep(Duration::from_millis(200)).await;
+
+            assert_eq!(calls.load(Ordering::SeqCst), 2);
+            assert_eq!(
+                cache
+                    .get_or_try_insert("key", counted(&calls, 3))
+                    .await
+                    .unwrap(),
+                2
+            );
+        });
+    }
+
+    #[test]
+    fn lru_values_expire() {
+        let cache = LruCache::new(4, Duration::from_millis(50));
+
+        cache.insert("key", 1);
+        cache.insert_for("brief", 2, Duration::ZERO);
+        assert_eq!(cache.get(&"key"), Some(1));
+        assert_eq!(cache.get(&"brief"), None);
+
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(cache.get(&"key"), None);
+    }
+
+    #[test]
+    fn least_recently_used_is_evicted() {
+        let cache = LruCache::new(2, Duration::from_secs(60));
+
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        assert_eq!(cache.get(&"a"), Some(1));
+
+        cache.insert("c", 3);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"c"), Some(3));
+
+        // Replacing a value neither grows the cache nor evicts another value
+        cache.insert("a", 4);
+        assert_eq!(cache.get(&"c"), Some(3));
+        assert_eq!(cache.get(&"a"), Some(4));
+    }
+
+    #[test]
+    fn zero_capacity_disables_lru() {
+        let cache = LruCache::new(0, Duration::from_secs(60));
+
+        cache.insert("key", 1);
+        assert_eq!(cache.get(&"key"), None);
+    }
+}
@@ -0,0 +1,372 @@
+//! Static analysis rejecting overly deep or expensive queries before they are
+//! executed
+//!
+//! The cost of a query is the number of fields it selects, with the subtree of
+//! each list-returning field multiplied by the number of items it may return.
+//! This is taken from a `limit` or `first` argument where one is given, and
+//! assumed to be [`DEFAULT_LIST_SIZE`] otherwise.
+//!
+//! Introspection queries, i.e. those selecting `__schema` or `__type`, may
+//! also be rejected to avoid exposing the schema.
+
+use graphql_parser::{
+    query::{Definition, OperationDefinition, Selection, SelectionSet, TypeCondition, Value},
+    schema,
+};
+
+use super::{prelude::*, Schema};
+
+/// Assumed number of items returned by a list field with no `limit` or
+/// `first` argument
+const DEFAULT_LIST_SIZE: u64 = 10;
+
+#[derive(Debug)]
+struct FieldInfo {
+    list: bool,
+    ty: String,
+}
+
+/// Validator for the depth and cost limits of incoming queries
+#[derive(Debug)]
+pub struct QueryAnalyzer {
+    query_type: String,
+    subscription_type: String,
+    types: HashMap<String, HashMap<String, FieldInfo>>,
+    max_depth: Option<usize>,
+    max_cost: Option<u64>,
+    allow_introspection: bool,
+}
+
+fn field_info(ty: &schema::Type<String>) -> FieldInfo {
+    match ty {
+        schema::Type::NamedType(n) => FieldInfo {
+            list: false,
+            ty: n.clone(),
+        },
+        schema::Type::ListType(t) => FieldInfo {
+            list: true,
+            ..field_info(t)
+        },
+        schema::Type::NonNullType(t) => field_info(t),
+    }
+}
+
+fn fields(fields: Vec<schema::Field<String>>) -> HashMap<String, FieldInfo> {
+    fields
+        .into_iter()
+        .map(|f| {
+            let info = field_info(&f.field_type);
+
+            (f.name, info)
+        })
+        .collect()
+}
+
+impl QueryAnalyzer {
+    /// Collect the field types of a schema for analyzing queries against it.
+    /// A limit of `None` disables the corresponding check, and introspection
+    /// queries are rejected unless `allow_introspection` is set.
+    ///
+    /// # Errors
+    /// This function fails if the schema's SDL cannot be parsed.
+    pub fn new(
+        schema: &Schema,
+        max_depth: Option<usize>,
+        max_cost: Option<u64>,
+        allow_introspection: bool,
+    ) -> Result<Self> {
+        let sdl = schema.as_schema_language();
+        let doc = schema::parse_schema::<String>(&sdl).context("Failed to parse schema SDL")?;
+
+        let mut query_type = "Query".to_owned();
+        let mut subscription_type = "Subscription".to_owned();
+        let mut types = HashMap::new();
+
+        for def in doc.definitions {
+            match def {
+                schema::Definition::SchemaDefinition(schema::SchemaDefinition {
+                    query,
+                    subscription,
+                    ..
+                }) => {
+                    query_type = query.unwrap_or(query_type);
+                    subscription_type = subscription.unwrap_or(subscription_type);
+                },
+                schema::Definition::TypeDefinition(schema::TypeDefinition::Object(o)) => {
+                    types.insert(o.name, fields(o.fields));
+                },
+                schema::Definition::TypeDefinition(schema::TypeDefinition::Interface(i)) => {
+                    types.insert(i.name, fields(i.fields));
+                },
+                _ => (),
+            }
+        }
+
+        Ok(Self {
+            query_type,
+            subscription_type,
+            types,
+            max_depth,
+            max_cost,
+            allow_introspection,
+        })
+    }
+
+    /// Check a GraphQL request body against the configured limits, returning
+    /// a message describing the violated limit if any.
+    ///
+    /// Requests which cannot be parsed are accepted, leaving the executor to
+    /// report the error.
+    ///
+    /// # Errors
+    /// This function fails if the requested operation exceeds the maximum
+    /// depth or cost, or if it is an introspection query and introspection is
+    /// disabled.
+    pub fn check(&self, body: &serde_json::Value) -> Result<(), String> {
+        let query = match body.get("query").and_then(serde_json::Value::as_str) {
+            Some(q) => q,
+            None => return Ok(()),
+        };
+        let operation_name = body
+            .get("operationName")
+            .and_then(serde_json::Value::as_str);
+
+        let doc = match graphql_parser::parse_query::<&str>(query) {
+            Ok(d) => d,
+            Err(_) => return Ok(()),
+        };
+
+        let mut walk = Walk {
+            analyzer: self,
+            fragments: HashMap::new(),
+            variables: body.get("variables"),
+            stack: Vec::new(),
+        };
+        let mut operation = None;
+
+        for def in &doc.definitions {
+            match def {
+                Definition::Fragment(f) => {
+                    walk.fragments.insert(f.name, f);
+                },
+                Definition::Operation(o) => {
+                    let (name, root, set) = match o {
+                        OperationDefinition::SelectionSet(s) => (None, Some(&self.query_type), s),
+                        OperationDefinition::Query(q) => {
+                            (q.name, Some(&self.query_type), &q.selection_set)
+                        },
+                        OperationDefinition::Mutation(m) => (m.name, None, &m.selection_set),
+                        OperationDefinition::Subscription(s) => {
+                            (s.name, Some(&self.subscription_type), &s.selection_set)
+                        },
+                    };
+
+                    if operation.is_none() && (operation_name.is_none() || name == operation_name) {
+                        operation = Some((root, set));
+                    }
+                },
+            }
+        }
+
+        let (root, set) = match operation {
+            Some(o) => o,
+            None => return Ok(()),
+        };
+
+        let (depth, cost) = walk.selection_set(root.map(String::as_str), set, 0)?;
+
+        trace!("Query depth {}, cost {}", depth, cost);
+
+        Ok(())
+    }
+}
+
+struct Walk<'a> {
+    analyzer: &'a QueryAnalyzer,
+    fragments: HashMap<&'a str, &'a graphql_parser::query::FragmentDefinition<'a, &'a str>>,
+    variables: Option<&'a serde_json::Value>,
+    stack: Vec<&'a str>,
+}
+
+impl<'a> Walk<'a> {
+    fn list_size(&self, args: &[(&'a str, Value<'a, &'a str>)]) -> u64 {
+        args.iter()
+            .find(|(name, _)| matches!(*name, "limit" | "first"))
+            .and_then(|(_, value)| match value {
+                Value::Int(i) => i.as_i64().and_then(|i| i.try_into().ok()),
+                Value::Variable(v) => self
+                    .variables
+                    .and_then(|vars| vars.get(v))
+                    .and_then(serde_json::Value::as_u64),
+                _ => None,
+            })
+            .unwrap_or(DEFAULT_LIST_SIZE)
+            .max(1)
+    }
+
+    /// Returns the maximum depth and the total cost of a selection set
+    fn selection_set(
+        &mut self,
+        ty: Option<&'a str>,
+        set: &'a SelectionSet<'a, &'a str>,
+        depth: usize,
+    ) -> Result<(usize, u64), String> {
+        let analyzer = self.analyzer;
+        let mut max_depth = depth;
+        let mut total = 0_u64;
+
+        for sel in &set.items {
+            let (d, cost) = match sel {
+                Selection::Field(f) => {
+                    let depth = depth + 1;
+
+                    if !analyzer.allow_introspection && matches!(f.name, "__schema" | "__type") {
+                        return Err("Introspection is disabled on this server".to_owned());
+                    }
+
+                    if let Some(max) = analyzer.max_depth {
+                        if depth > max {
+                            return Err(format!(
+                                "Query exceeds the maximum depth of {} at field {:?}",
+                                max, f.name
+                            ));
+                        }
+                    }
+
+                    let info = ty
+                        .and_then(|t| analyzer.types.get(t))
+                        .and_then(|m| m.get(f.name));
+                    let (d, children) =
+                        self.selection_set(info.map(|i| i.ty.as_str()), &f.selection_set, depth)?;
+                    let cost = children.saturating_add(1);
+                    let cost = if info.map_or(false, |i| i.list) {
+                        cost.saturating_mul(self.list_size(&f.arguments))
+                    } else {
+                        cost
+                    };
+
+                    (d, cost)
+                },
+                Selection::FragmentSpread(s) => {
+                    let frag = match self.fragments.get(s.fragment_name) {
+                        Some(f) if !self.stack.contains(&s.fragment_name) => *f,
+                        _ => continue,
+                    };
+                    let TypeCondition::On(cond) = frag.type_condition;
+
+                    self.stack.push(s.fragment_name);
+                    let res = self.selection_set(Some(cond), &frag.selection_set, depth);
+                    self.stack.pop();
+
+                    res?
+                },
+                Selection::InlineFragment(f) => {
+                    let cond = match f.type_condition {
+                        Some(TypeCondition::On(c)) => Some(c),
+                        None => ty,
+                    };
+
+                    self.selection_set(cond, &f.selection_set, depth)?
+                },
+            };
+
+            max_depth = max_depth.max(d);
+            total = total.saturating_add(cost);
+
+            if let Some(max) = analyzer.max_cost {
+                if total > max {
+                    return Err(format!("Query exceeds the maximum cost of {}", max));
+                }
+            }
+        }
+
+        Ok((max_depth, total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn analyzer(max_depth: Option<usize>, max_cost: Option<u64>) -> QueryAnalyzer {
+        QueryAnalyzer::new(&super::super::create(), max_depth, max_cost, true).unwrap()
+    }
+
+    const QUERY: &str = r#"{ nft(address: "nft") { name creators { address } } }"#;
+
+    #[test]
+    fn within_limits() {
+        assert_eq!(
+            analyzer(Some(3), Some(100)).check(&json!({ "query": QUERY })),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn over_depth() {
+        let err = analyzer(Some(2), None)
+            .check(&json!({ "query": QUERY }))
+            .unwrap_err();
+
+        assert!(err.contains("maximum depth of 2"), "{}", err);
+    }
+
+    #[test]
+    fn over_cost() {
+        // nft, name, and each of the assumed 10 creators with their address
+        let query = json!({ "query": QUERY });
+
+        assert_eq!(analyzer(None, Some(22)).check(&query), Ok(()));
+        assert!(analyzer(None, Some(21)).check(&query).is_err());
+    }
+
+    #[test]
+    fn list_size_from_variables() {
+        let query = json!({
+            "query": "query($n: Int!) { nfts(creators: [\"c\"], limit: $n, offset: 0) { name } }",
+            "variables": { "n": 1000 },
+        });
+
+        assert!(analyzer(None, Some(100)).check(&query).is_err());
+    }
+
+    #[test]
+    fn subscriptions_are_analyzed() {
+        // wallets is a list field of the subscription's NftActivity type
+        let query = json!({
+            "query": "subscription { nftActivity(auctionHouse: \"house\") { address wallets } }",
+        });
+
+        assert_eq!(analyzer(Some(2), Some(12)).check(&query), Ok(()));
+        assert!(analyzer(Some(1), None).check(&query).is_err());
+        assert!(analyzer(None, Some(11)).check(&query).is_err());
+    }
+
+    #[test]
+    fn introspection() {
+        let schema = super::super::create();
+        let blocked = QueryAnalyzer::new(&schema, None, None, false).unwrap();
+        let allowed = QueryAnalyzer::new(&schema, None, None, true).unwrap();
+
+        for query in [
+            "{ __schema { queryType { name } } }",
+            "{ __type(name: \"Nft\") { name } }",
+            "query Named { nft(address: \"nft\") { name } __schema { types { name } } }",
+        ] {
+            let query = json!({ "query": query });
+
+            assert_eq!(
+                blocked.check(&query),
+                Err("Introspection is disabled on this server".to_owned())
+            );
+            assert_eq!(allowed.check(&query), Ok(()));
+        }
+
+        // __typename is not introspection of the schema
+        let typename = json!({ "query": "{ nft(address: \"nft\") { __typename name } }" });
+
+        assert_eq!(blocked.check(&typename), Ok(()));
+    }
+}
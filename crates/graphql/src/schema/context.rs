@@ -1,19 +1,52 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
 use dataloaders::{Batcher, Loader, TwitterBatcher};
+use indexer_core::logging::Fields;
 use objects::{
     auction_house::AuctionHouse,
     bid_receipt::BidReceipt,
+    candy_machine::{
+        CandyMachine, CandyMachineData, CandyMachineEndSettings, CandyMachineGatekeeperConfig,
+        CandyMachineHiddenSettings, CandyMachineWhitelistMintSettings,
+    },
+    edition::{Edition, MasterEdition},
     listing::{Bid, Listing},
     listing_receipt::ListingReceipt,
-    nft::{Nft, NftActivity, NftAttribute, NftCreator, NftOwner},
+    locker::Locker,
+    nft::{
+        Nft, NftActivity, NftAttribute, NftCreator, NftMedia, NftOwner, NftRarity, TokenStandard,
+    },
     profile::TwitterProfile,
+    proposal::{Proposal, ProposalInstruction},
     purchase_receipt::PurchaseReceipt,
+    smart_wallet::{SmartWallet, SmartWalletOwner},
     stats::{MarketStats, MintStats},
     store_creator::StoreCreator,
     storefront::Storefront,
+    vote::Vote,
+    wallet::{Wallet, WalletConnectionCounts},
+};
+use scalars::{
+    markers::{StoreConfig, TokenMint},
+    PublicKey,
 };
-use scalars::{markers::StoreConfig, PublicKey};
 
 use super::prelude::*;
+use crate::{api_keys::Access, request_id::RequestId};
+
+/// Names of root query fields, or of object fields prefixed with their type's
+/// name, which draw connections from the heavy query pool rather than the main
+/// pool
+const HEAVY_OPERATIONS: &[&str] = &[
+    "activitySummary",
+    "collectionFloor",
+    "creatorStats",
+    "dataQualityReport",
+    "listedBelowFloor",
+    "marketplaceStats",
+    "suspiciousTrades",
+    "topHolders",
+];
 
 #[derive(Clone)]
 pub struct AppContext {
@@ -21,50 +54,177 @@ pub struct AppContext {
 
     // Data loaders
     pub auction_house_loader: Loader<PublicKey<AuctionHouse>, Option<AuctionHouse>>,
+    pub candy_machine_loader: Loader<PublicKey<CandyMachine>, Option<CandyMachine>>,
+    pub candy_machine_data_loader: Loader<PublicKey<CandyMachine>, Option<CandyMachineData>>,
+    pub candy_machine_hidden_settings_loader:
+        Loader<PublicKey<CandyMachine>, Option<CandyMachineHiddenSettings>>,
+    pub candy_machine_whitelist_mint_settings_loader:
+        Loader<PublicKey<CandyMachine>, Option<CandyMachineWhitelistMintSettings>>,
+    pub candy_machine_gatekeeper_config_loader:
+        Loader<PublicKey<CandyMachine>, Option<CandyMachineGatekeeperConfig>>,
+    pub candy_machine_end_settings_loader:
+        Loader<PublicKey<CandyMachine>, Option<CandyMachineEndSettings>>,
     pub listing_loader: Loader<PublicKey<Listing>, Option<Listing>>,
     pub listing_bids_loader: Loader<PublicKey<Listing>, Vec<Bid>>,
     pub listing_nfts_loader: Loader<PublicKey<Listing>, Vec<(usize, Nft)>>,
+    pub locker_loader: Loader<PublicKey<Locker>, Option<Locker>>,
+    pub master_edition_loader: Loader<PublicKey<MasterEdition>, Option<MasterEdition>>,
     pub market_stats_loader: Loader<PublicKey<StoreConfig>, Option<MarketStats>>,
     pub mint_stats_loader: Loader<PublicKey<AuctionHouse>, Option<MintStats>>,
+    pub nft_loader: Loader<PublicKey<Nft>, Option<Nft>>,
     pub nft_attributes_loader: Loader<PublicKey<Nft>, Vec<NftAttribute>>,
+    pub nft_collections_loader: Loader<PublicKey<Nft>, Vec<(bool, Nft)>>,
     pub nft_creators_loader: Loader<PublicKey<Nft>, Vec<NftCreator>>,
+    pub nft_media_loader: Loader<PublicKey<Nft>, Option<NftMedia>>,
     pub nft_owner_loader: Loader<PublicKey<Nft>, Option<NftOwner>>,
+    pub nft_rarity_loader: Loader<PublicKey<Nft>, Option<NftRarity>>,
     pub nft_activities_loader: Loader<PublicKey<Nft>, Vec<NftActivity>>,
+    pub nft_edition_loader: Loader<PublicKey<Nft>, Option<Edition>>,
+    pub nft_master_edition_loader: Loader<PublicKey<Nft>, Option<MasterEdition>>,
+    pub nft_token_standard_loader: Loader<PublicKey<Nft>, Option<TokenStandard>>,
     pub storefront_loader: Loader<PublicKey<Storefront>, Option<Storefront>>,
+    pub storefront_by_subdomain_loader: Loader<String, Option<Storefront>>,
     pub listing_receipts_loader: Loader<PublicKey<Nft>, Vec<ListingReceipt>>,
     pub purchase_receipts_loader: Loader<PublicKey<Nft>, Vec<PurchaseReceipt>>,
+    pub last_sale_loader: Loader<PublicKey<Nft>, Option<PurchaseReceipt>>,
+    pub smart_wallet_loader: Loader<PublicKey<SmartWallet>, Option<SmartWallet>>,
+    pub smart_wallet_owners_loader: Loader<PublicKey<SmartWallet>, Vec<SmartWalletOwner>>,
     pub bid_receipts_loader: Loader<PublicKey<Nft>, Vec<BidReceipt>>,
     pub store_creator_loader: Loader<PublicKey<StoreConfig>, Vec<StoreCreator>>,
     pub collection_loader: Loader<PublicKey<StoreCreator>, Vec<Nft>>,
+    pub votes_by_proposal_loader: Loader<PublicKey<Proposal>, Vec<Vote>>,
+    pub proposal_instructions_loader: Loader<PublicKey<Proposal>, Vec<ProposalInstruction>>,
+    pub wallet_connection_counts_loader: Loader<PublicKey<Wallet>, Option<WalletConnectionCounts>>,
+    pub wallet_nfts_loader: Loader<PublicKey<Wallet>, Vec<Nft>>,
+    pub wallet_twitter_handle_loader: Loader<PublicKey<Wallet>, Option<String>>,
+    pub token_mint_decimals_loader: Loader<PublicKey<TokenMint>, Option<i32>>,
     pub twitter_profile_loader: Loader<String, Option<TwitterProfile>, TwitterBatcher>,
+
+    // Per-request budgets
+    nft_full_spent: Arc<AtomicU32>,
+
+    /// Correlation ID of the request being resolved
+    pub(crate) request_id: Option<RequestId>,
+
+    /// Privileges granted to the request being resolved
+    pub(crate) access: Access,
 }
 
 impl juniper::Context for AppContext {}
 
 impl AppContext {
-    pub(crate) fn new(shared: Arc<SharedData>) -> AppContext {
-        let batcher = Batcher::new(shared.db.clone());
-        let twitter_batcher = TwitterBatcher::new(shared.twitter_bearer_token.clone());
+    pub(crate) fn new(
+        shared: Arc<SharedData>,
+        request_id: Option<RequestId>,
+        access: Access,
+    ) -> AppContext {
+        let batcher = Batcher::new(
+            shared.db.clone(),
+            shared.confirmation_depth,
+            shared.batch_timeout,
+        );
+        let twitter_batcher = TwitterBatcher::new(
+            shared.twitter_bearer_token.clone(),
+            shared.twitter_api_endpoint.clone(),
+            Arc::clone(&shared.twitter_profile_cache),
+            shared.twitter_missing_cache_ttl,
+            Arc::clone(&shared.twitter_rate_limit),
+            shared.batch_timeout,
+        );
 
         Self {
             auction_house_loader: Loader::new(batcher.clone()),
+            candy_machine_loader: Loader::new(batcher.clone()),
+            candy_machine_data_loader: Loader::new(batcher.clone()),
+            candy_machine_hidden_settings_loader: Loader::new(batcher.clone()),
+            candy_machine_whitelist_mint_settings_loader: Loader::new(batcher.clone()),
+            candy_machine_gatekeeper_config_loader: Loader::new(batcher.clone()),
+            candy_machine_end_settings_loader: Loader::new(batcher.clone()),
             listing_loader: Loader::new(batcher.clone()),
             listing_bids_loader: Loader::new(batcher.clone()),
             listing_nfts_loader: Loader::new(batcher.clone()),
+            locker_loader: Loader::new(batcher.clone()),
+            master_edition_loader: Loader::new(batcher.clone()),
             market_stats_loader: Loader::new(batcher.clone()),
             mint_stats_loader: Loader::new(batcher.clone()),
+            nft_loader: Loader::new(batcher.clone()),
             nft_attributes_loader: Loader::new(batcher.clone()),
+            nft_collections_loader: Loader::new(batcher.clone()),
             nft_creators_loader: Loader::new(batcher.clone()),
+            nft_media_loader: Loader::new(batcher.clone()),
             nft_owner_loader: Loader::new(batcher.clone()),
+            nft_rarity_loader: Loader::new(batcher.clone()),
             nft_activities_loader: Loader::new(batcher.clone()),
+            nft_edition_loader: Loader::new(batcher.clone()),
+            nft_master_edition_loader: Loader::new(batcher.clone()),
+            nft_token_standard_loader: Loader::new(batcher.clone()),
             storefront_loader: Loader::new(batcher.clone()),
+            storefront_by_subdomain_loader: Loader::new(batcher.clone()),
             listing_receipts_loader: Loader::new(batcher.clone()),
             purchase_receipts_loader: Loader::new(batcher.clone()),
+            last_sale_loader: Loader::new(batcher.clone()),
+            smart_wallet_loader: Loader::new(batcher.clone()),
+            smart_wallet_owners_loader: Loader::new(batcher.clone()),
             bid_receipts_loader: Loader::new(batcher.clone()),
             store_creator_loader: Loader::new(batcher.clone()),
-            collection_loader: Loader::new(batcher),
+            collection_loader: Loader::new(batcher.clone()),
+            votes_by_proposal_loader: Loader::new(batcher.clone()),
+            proposal_instructions_loader: Loader::new(batcher.clone()),
+            wallet_connection_counts_loader: Loader::new(batcher.clone()),
+            wallet_nfts_loader: Loader::new(batcher.clone()),
+            wallet_twitter_handle_loader: Loader::new(batcher.clone()),
+            token_mint_decimals_loader: Loader::new(batcher),
             twitter_profile_loader: Loader::new(twitter_batcher),
+            nft_full_spent: Arc::new(AtomicU32::new(0)),
+            request_id,
+            access,
             shared,
         }
     }
+
+    /// Record a resolution of the heavyweight `nftFull` query, failing if the
+    /// request has exceeded its budget for them
+    pub(crate) fn spend_nft_full(&self) -> FieldResult<()> {
+        let limit = self.shared.max_nft_full_per_request;
+
+        if self.nft_full_spent.fetch_add(1, Ordering::Relaxed) >= limit {
+            return Err(FieldError::new(
+                "Query too complex! Too many nftFull fields requested",
+                graphql_value!({ "Field": "nftFull" }),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Fail unless the request was made with an administrative API key
+    pub(crate) fn require_admin(&self) -> FieldResult<()> {
+        if self.access.admin {
+            Ok(())
+        } else {
+            Err(FieldError::new(
+                "This query requires an administrative API key",
+                graphql_value!({ "Unauthorized": "admin" }),
+            ))
+        }
+    }
+
+    /// Log fields identifying the request being resolved, for attaching to
+    /// work which outlives the request's own handler, such as subscriptions
+    pub(crate) fn log_fields(&self) -> Fields {
+        self.request_id
+            .iter()
+            .map(|RequestId(id)| ("request_id", id.clone()))
+            .collect()
+    }
+
+    /// Get the connection pool to use for the given query field, routing
+    /// heavy aggregate queries to their own pool
+    pub(crate) fn pool(&self, operation: &str) -> &Arc<ReplicaPool> {
+        if HEAVY_OPERATIONS.contains(&operation) {
+            &self.shared.heavy_db
+        } else {
+            &self.shared.db
+        }
+    }
 }
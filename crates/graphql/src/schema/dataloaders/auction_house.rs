@@ -1,6 +1,6 @@
 use objects::auction_house::AuctionHouse;
-use scalars::PublicKey;
-use tables::auction_houses;
+use scalars::{markers::TokenMint, PublicKey};
+use tables::{auction_houses, token_mints};
 
 use super::prelude::*;
 
@@ -23,3 +23,23 @@ impl TryBatchFn<PublicKey<AuctionHouse>, Option<AuctionHouse>> for Batcher {
             .batch(addresses))
     }
 }
+
+#[async_trait]
+impl TryBatchFn<PublicKey<TokenMint>, Option<i32>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<TokenMint>],
+    ) -> TryBatchMap<PublicKey<TokenMint>, Option<i32>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::TokenMint> = token_mints::table
+            .filter(token_mints::address.eq(any(addresses)))
+            .load(&conn)
+            .context("Failed to load token mint decimals")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|m| (m.address, i32::from(m.decimals)))
+            .batch(addresses))
+    }
+}
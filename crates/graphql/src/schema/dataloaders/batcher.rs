@@ -1,11 +1,23 @@
-use std::{collections::HashMap, hash::Hash, sync::Arc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_web::rt::{task, time};
+use tokio::runtime::Handle;
 
 use super::prelude::*;
+use crate::{metrics, schema::TwitterProfileCache};
 
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum Error {
-    #[error("Failed to connect to the database")]
+    #[error("Timed out waiting for a database connection")]
     ConnectionFailed,
+    #[error("Batch took too long to load")]
+    Timeout,
     #[error("Failed to process a model: {0}")]
     ModelConvert(#[from] Arc<indexer_core::error::Error>),
 }
@@ -115,45 +127,184 @@ pub trait TryBatchFn<K, V> {
 }
 
 #[derive(Clone)]
-pub struct Batcher(Arc<Pool>);
+pub struct Batcher {
+    db: Arc<ReplicaPool>,
+    confirmation_depth: u64,
+    timeout: Option<Duration>,
+}
 
 #[derive(Clone)]
 pub struct TwitterBatcher {
     bearer: String,
+    endpoint: String,
+    cache: Arc<TwitterProfileCache>,
+    missing_ttl: Duration,
+    rate_limit: Arc<TwitterRateLimit>,
+    timeout: Option<Duration>,
+}
+
+/// Shared record of when the Twitter API rate limit resets, so requests are
+/// not made while it is known to be in effect
+#[derive(Debug, Default)]
+pub struct TwitterRateLimit {
+    reset_at: Mutex<Option<Instant>>,
+}
+
+impl TwitterRateLimit {
+    /// Get the time remaining until the rate limit resets, if it is in effect
+    pub fn remaining(&self) -> Option<Duration> {
+        let reset_at = (*self.reset_at.lock().unwrap_or_else(|e| e.into_inner()))?;
+
+        reset_at.checked_duration_since(Instant::now())
+    }
+
+    /// Record that the rate limit is in effect for the given duration
+    pub fn limit_for(&self, duration: Duration) {
+        *self.reset_at.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now() + duration);
+    }
 }
 
 impl Batcher {
     #[must_use]
-    pub fn new(pool: Arc<Pool>) -> Self {
-        Self(pool)
+    pub fn new(db: Arc<ReplicaPool>, confirmation_depth: u64, timeout: Option<Duration>) -> Self {
+        Self {
+            db,
+            confirmation_depth,
+            timeout,
+        }
     }
 
+    /// Get a database connection.  Statements run on it are bounded by the
+    /// pool's statement timeout, if one is configured.
     pub fn db(&self) -> Result<indexer_core::db::PooledConnection, Error> {
-        self.0.get().map_err(|_| Error::ConnectionFailed)
+        self.db.get().map_err(|_| Error::ConnectionFailed)
+    }
+
+    /// Get the highest slot rows may be served from given the configured
+    /// confirmation depth
+    pub fn max_slot(&self, conn: &Connection) -> Result<i64, Error> {
+        indexer_core::db::queries::slot::max_confirmed(conn, self.confirmation_depth)
+            .map_err(Into::into)
     }
 }
 
 impl TwitterBatcher {
     #[must_use]
-    pub fn new(bearer: String) -> Self {
-        Self { bearer }
+    pub fn new(
+        bearer: String,
+        endpoint: String,
+        cache: Arc<TwitterProfileCache>,
+        missing_ttl: Duration,
+        rate_limit: Arc<TwitterRateLimit>,
+        timeout: Option<Duration>,
+    ) -> Self {
+        Self {
+            bearer,
+            endpoint,
+            cache,
+            missing_ttl,
+            rate_limit,
+            timeout,
+        }
     }
 
     pub fn bearer(&self) -> &str {
         &self.bearer
     }
+
+    /// Get the base URL of the Twitter API
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Get the cache of profiles shared between requests
+    pub fn cache(&self) -> &TwitterProfileCache {
+        &self.cache
+    }
+
+    /// Get the time to cache the absence of a Twitter user for
+    pub fn missing_ttl(&self) -> Duration {
+        self.missing_ttl
+    }
+
+    /// Get the rate limit state shared between requests
+    pub fn rate_limit(&self) -> &TwitterRateLimit {
+        &self.rate_limit
+    }
+}
+
+/// Name a loader by its key and value types, without their module paths, e.g.
+/// `PublicKey<Nft> -> Vec<NftAttribute>`
+fn loader_name<K, V>() -> String {
+    fn short(name: &str) -> String {
+        let mut out = String::with_capacity(name.len());
+        let mut segment = String::new();
+
+        for c in name.chars() {
+            if c.is_alphanumeric() || c == '_' || c == ':' {
+                segment.push(c);
+            } else {
+                out.push_str(segment.rsplit("::").next().unwrap_or_default());
+                segment.clear();
+                out.push(c);
+            }
+        }
+
+        out.push_str(segment.rsplit("::").next().unwrap_or_default());
+
+        out
+    }
+
+    format!(
+        "{} -> {}",
+        short(std::any::type_name::<K>()),
+        short(std::any::type_name::<V>())
+    )
+}
+
+/// Wait for a batch to load, failing it if it runs past `timeout`, and map
+/// every key to the error if it fails
+async fn finish<K: Clone + Eq + Hash, V>(
+    keys: &[K],
+    timeout: Option<Duration>,
+    load: impl Future<Output = TryBatchMap<K, V>>,
+) -> BatchMap<K, V> {
+    let res = match timeout {
+        Some(timeout) => time::timeout(timeout, load)
+            .await
+            .unwrap_or(Err(Error::Timeout)),
+        None => load.await,
+    };
+
+    match res {
+        Ok(m) => m,
+        Err(e) => keys.iter().cloned().map(|k| (k, Err(e.clone()))).collect(),
+    }
 }
 
 #[async_trait]
-impl<K: Clone + Eq + Hash + Sync, V> BatchFn<K, BatchResult<V>> for Batcher
+impl<K: Clone + Eq + Hash + Send + Sync + 'static, V: Send + 'static> BatchFn<K, BatchResult<V>>
+    for Batcher
 where
     Batcher: TryBatchFn<K, V>,
 {
     async fn load(&mut self, keys: &[K]) -> BatchMap<K, V> {
-        match TryBatchFn::load(self, keys).await {
-            Ok(m) => m,
-            Err(e) => keys.iter().cloned().map(|k| (k, Err(e.clone()))).collect(),
-        }
+        let _timer = metrics::time_batch(&loader_name::<K, V>(), keys.len());
+
+        // Database batches block, so they are run on the blocking thread pool
+        // to let the timeout fire.  A timed-out batch is left to finish in
+        // the background, bounded by the statement timeout.
+        let mut batcher = self.clone();
+        let owned_keys = keys.to_vec();
+        let handle = Handle::current();
+        let load = task::spawn_blocking(move || {
+            handle.block_on(TryBatchFn::load(&mut batcher, &owned_keys))
+        });
+
+        finish(keys, self.timeout, async move {
+            load.await.unwrap_or_else(|e| Err(Error::model_convert(e)))
+        })
+        .await
     }
 }
 
@@ -163,9 +314,51 @@ where
     TwitterBatcher: TryBatchFn<K, V>,
 {
     async fn load(&mut self, keys: &[K]) -> BatchMap<K, V> {
-        match TryBatchFn::load(self, keys).await {
-            Ok(m) => m,
-            Err(e) => keys.iter().cloned().map(|k| (k, Err(e.clone()))).collect(),
+        let _timer = metrics::time_batch(&loader_name::<K, V>(), keys.len());
+
+        finish(keys, self.timeout, TryBatchFn::load(self, keys)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::rt::System;
+
+    use super::*;
+    use crate::testing;
+
+    /// Blocks for as many milliseconds as the largest key, the way a slow
+    /// query would
+    #[async_trait]
+    impl TryBatchFn<u64, u64> for Batcher {
+        async fn load(&mut self, keys: &[u64]) -> TryBatchMap<u64, u64> {
+            let ms = keys.iter().copied().max().unwrap_or_default();
+            std::thread::sleep(Duration::from_millis(ms));
+
+            Ok(keys.iter().map(|&k| (k, Ok(k))).collect())
         }
     }
+
+    #[test]
+    fn slow_batches_time_out() {
+        let mut batcher = Batcher::new(
+            testing::unreachable_replicas(),
+            0,
+            Some(Duration::from_millis(200)),
+        );
+
+        System::new().block_on(async {
+            let fast: BatchMap<u64, u64> = BatchFn::load(&mut batcher, &[1, 2]).await;
+
+            assert_eq!(fast[&1].as_ref().ok(), Some(&1));
+            assert_eq!(fast[&2].as_ref().ok(), Some(&2));
+
+            let start = Instant::now();
+            let slow: BatchMap<u64, u64> = BatchFn::load(&mut batcher, &[3, 1_000]).await;
+
+            assert!(start.elapsed() < Duration::from_millis(800));
+            assert!(matches!(slow[&3], Err(Error::Timeout)));
+            assert!(matches!(slow[&1_000], Err(Error::Timeout)));
+        });
+    }
 }
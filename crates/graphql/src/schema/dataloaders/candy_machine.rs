@@ -0,0 +1,215 @@
+use objects::candy_machine::{
+    CandyMachine, CandyMachineData, CandyMachineEndSettings, CandyMachineGatekeeperConfig,
+    CandyMachineHiddenSettings, CandyMachineWhitelistMintSettings,
+};
+use scalars::PublicKey;
+use tables::{
+    candy_machine_datas, candy_machine_end_settings, candy_machine_gate_keeper_configs,
+    candy_machine_hidden_settings, candy_machine_whitelist_mint_settings, candy_machines,
+};
+
+use super::prelude::*;
+
+#[async_trait]
+impl TryBatchFn<PublicKey<CandyMachine>, Option<CandyMachine>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<CandyMachine>],
+    ) -> TryBatchMap<PublicKey<CandyMachine>, Option<CandyMachine>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::CandyMachine> = candy_machines::table
+            .filter(candy_machines::address.eq(any(addresses)))
+            .load(&conn)
+            .context("Failed to load candy machines")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|c| (c.address.clone(), CandyMachine::from(c)))
+            .batch(addresses))
+    }
+}
+
+#[async_trait]
+impl TryBatchFn<PublicKey<CandyMachine>, Option<CandyMachineData>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<CandyMachine>],
+    ) -> TryBatchMap<PublicKey<CandyMachine>, Option<CandyMachineData>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::CandyMachineData> = candy_machine_datas::table
+            .filter(candy_machine_datas::candy_machine_address.eq(any(addresses)))
+            .load(&conn)
+            .context("Failed to load candy machine data")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|d| (d.candy_machine_address.clone(), d.try_into()))
+            .batch(addresses))
+    }
+}
+
+#[async_trait]
+impl TryBatchFn<PublicKey<CandyMachine>, Option<CandyMachineHiddenSettings>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<CandyMachine>],
+    ) -> TryBatchMap<PublicKey<CandyMachine>, Option<CandyMachineHiddenSettings>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::CMHiddenSetting> = candy_machine_hidden_settings::table
+            .filter(candy_machine_hidden_settings::candy_machine_address.eq(any(addresses)))
+            .load(&conn)
+            .context("Failed to load candy machine hidden settings")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|s| {
+                (
+                    s.candy_machine_address.clone(),
+                    CandyMachineHiddenSettings::from(s),
+                )
+            })
+            .batch(addresses))
+    }
+}
+
+#[async_trait]
+impl TryBatchFn<PublicKey<CandyMachine>, Option<CandyMachineWhitelistMintSettings>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<CandyMachine>],
+    ) -> TryBatchMap<PublicKey<CandyMachine>, Option<CandyMachineWhitelistMintSettings>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::CMWhitelistMintSetting> =
+            candy_machine_whitelist_mint_settings::table
+                .filter(
+                    candy_machine_whitelist_mint_settings::candy_machine_address.eq(any(addresses)),
+                )
+                .load(&conn)
+                .context("Failed to load candy machine whitelist mint settings")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|s| (s.candy_machine_address.clone(), s.try_into()))
+            .batch(addresses))
+    }
+}
+
+#[async_trait]
+impl TryBatchFn<PublicKey<CandyMachine>, Option<CandyMachineGatekeeperConfig>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<CandyMachine>],
+    ) -> TryBatchMap<PublicKey<CandyMachine>, Option<CandyMachineGatekeeperConfig>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::CMGateKeeperConfig> = candy_machine_gate_keeper_configs::table
+            .filter(candy_machine_gate_keeper_configs::candy_machine_address.eq(any(addresses)))
+            .load(&conn)
+            .context("Failed to load candy machine gatekeeper configs")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|c| {
+                (
+                    c.candy_machine_address.clone(),
+                    CandyMachineGatekeeperConfig::from(c),
+                )
+            })
+            .batch(addresses))
+    }
+}
+
+#[async_trait]
+impl TryBatchFn<PublicKey<CandyMachine>, Option<CandyMachineEndSettings>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<CandyMachine>],
+    ) -> TryBatchMap<PublicKey<CandyMachine>, Option<CandyMachineEndSettings>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::CMEndSetting> = candy_machine_end_settings::table
+            .filter(candy_machine_end_settings::candy_machine_address.eq(any(addresses)))
+            .load(&conn)
+            .context("Failed to load candy machine end settings")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|s| (s.candy_machine_address.clone(), s.try_into()))
+            .batch(addresses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexer_core::db::insert_into;
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{context, execute};
+
+    #[test]
+    fn missing_settings_are_null() {
+        let (ctx, db) = match context() {
+            Some(c) => c,
+            None => return,
+        };
+        let conn = db.get().unwrap();
+
+        insert_into(candy_machines::table)
+            .values(&models::CandyMachine {
+                address: Borrowed("cm"),
+                authority: Borrowed("authority"),
+                wallet: Borrowed("wallet"),
+                token_mint: None,
+                items_redeemed: 40,
+            })
+            .execute(&conn)
+            .unwrap();
+
+        insert_into(candy_machine_datas::table)
+            .values(&models::CandyMachineData {
+                candy_machine_address: Borrowed("cm"),
+                uuid: Borrowed("uuid"),
+                price: 1_000_000_000,
+                symbol: Borrowed("CM"),
+                seller_fee_basis_points: 500,
+                max_supply: 0,
+                is_mutable: true,
+                retain_authority: true,
+                go_live_date: None,
+                items_available: 100,
+            })
+            .execute(&conn)
+            .unwrap();
+
+        assert_eq!(
+            execute(
+                &ctx,
+                r#"{
+                    candyMachine(address: "cm") {
+                        authority
+                        data { uuid symbol }
+                        hiddenSettings { name }
+                        whitelistMintSettings { mint }
+                        gatekeeperConfig { gatekeeperNetwork }
+                        endSettings { endSettingType }
+                    }
+                }"#,
+            ),
+            json!({
+                "candyMachine": {
+                    "authority": "authority",
+                    "data": { "uuid": "uuid", "symbol": "CM" },
+                    "hiddenSettings": null,
+                    "whitelistMintSettings": null,
+                    "gatekeeperConfig": null,
+                    "endSettings": null,
+                },
+            })
+        );
+    }
+}
@@ -4,6 +4,7 @@ use indexer_core::db::{
 };
 use objects::{nft::Nft, store_creator::StoreCreator};
 use scalars::PublicKey;
+use tables::{metadata_collection_keys, metadata_jsons, metadatas};
 
 use super::prelude::*;
 
@@ -63,3 +64,117 @@ impl TryBatchFn<PublicKey<StoreCreator>, Vec<Nft>> for Batcher {
             .batch(addresses))
     }
 }
+
+#[async_trait]
+impl TryBatchFn<PublicKey<Nft>, Vec<(bool, Nft)>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Nft>],
+    ) -> TryBatchMap<PublicKey<Nft>, Vec<(bool, Nft)>> {
+        let conn = self.db()?;
+
+        let rows: Vec<(String, bool, models::Nft)> = metadata_collection_keys::table
+            .inner_join(
+                metadatas::table
+                    .on(metadata_collection_keys::collection_address.eq(metadatas::mint_address)),
+            )
+            .inner_join(
+                metadata_jsons::table.on(metadatas::address.eq(metadata_jsons::metadata_address)),
+            )
+            .filter(metadata_collection_keys::metadata_address.eq(any(addresses)))
+            .select((
+                metadata_collection_keys::metadata_address,
+                metadata_collection_keys::verified,
+                (
+                    metadatas::address,
+                    metadatas::name,
+                    metadatas::seller_fee_basis_points,
+                    metadatas::mint_address,
+                    metadatas::primary_sale_happened,
+                    metadata_jsons::description,
+                    metadata_jsons::image,
+                ),
+            ))
+            .load(&conn)
+            .context("Failed to load NFT collections")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(address, verified, nft)| (address, (verified, nft.into())))
+            .batch(addresses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::rt::System;
+    use indexer_core::db::{insert_into, testing};
+
+    use super::*;
+
+    fn collection_key(
+        metadata: &'static str,
+        collection: &'static str,
+        verified: bool,
+    ) -> models::MetadataCollectionKey<'static> {
+        models::MetadataCollectionKey {
+            metadata_address: Borrowed(metadata),
+            collection_address: Borrowed(collection),
+            verified,
+        }
+    }
+
+    #[test]
+    fn verified_and_unverified_collections() {
+        let db = match testing::test_replicas() {
+            Some(db) => Arc::new(db),
+            None => return,
+        };
+        let conn = db.get().unwrap();
+
+        insert_into(metadatas::table)
+            .values(&[
+                testing::metadata("member", "member_mint", false),
+                testing::metadata("verified", "verified_mint", false),
+                testing::metadata("unverified", "unverified_mint", false),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(metadata_jsons::table)
+            .values(&[
+                testing::metadata_json("member", "member.png"),
+                testing::metadata_json("verified", "verified.png"),
+                testing::metadata_json("unverified", "unverified.png"),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(metadata_collection_keys::table)
+            .values(&[
+                collection_key("member", "verified_mint", true),
+                collection_key("member", "unverified_mint", false),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        let nfts: Vec<PublicKey<Nft>> = vec![
+            String::from("member").into(),
+            String::from("verified").into(),
+        ];
+        let mut batcher = Batcher::new(db, 0, None);
+
+        let collections: BatchMap<_, Vec<(bool, Nft)>> = System::new()
+            .block_on(TryBatchFn::load(&mut batcher, &nfts))
+            .unwrap();
+
+        let mut member = collections[&nfts[0]]
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|(verified, nft)| (nft.address.as_str(), *verified))
+            .collect::<Vec<_>>();
+        member.sort_unstable();
+
+        assert_eq!(member, vec![("unverified", false), ("verified", true)]);
+        assert!(collections[&nfts[1]].as_ref().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+use objects::{
+    edition::{Edition, MasterEdition},
+    nft::Nft,
+};
+use scalars::PublicKey;
+use tables::{editions, master_editions, metadatas};
+
+use super::prelude::*;
+
+#[async_trait]
+impl TryBatchFn<PublicKey<MasterEdition>, Option<MasterEdition>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<MasterEdition>],
+    ) -> TryBatchMap<PublicKey<MasterEdition>, Option<MasterEdition>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::MasterEdition> = master_editions::table
+            .filter(master_editions::address.eq(any(addresses)))
+            .load(&conn)
+            .context("Failed to load master editions")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|e| (e.address.clone(), e.try_into()))
+            .batch(addresses))
+    }
+}
+
+#[async_trait]
+impl TryBatchFn<PublicKey<Nft>, Option<MasterEdition>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Nft>],
+    ) -> TryBatchMap<PublicKey<Nft>, Option<MasterEdition>> {
+        let conn = self.db()?;
+
+        let rows: Vec<(String, models::MasterEdition)> = metadatas::table
+            .inner_join(
+                master_editions::table.on(master_editions::address.eq(metadatas::edition_pda)),
+            )
+            .filter(metadatas::address.eq(any(addresses)))
+            .select((metadatas::address, master_editions::all_columns))
+            .load(&conn)
+            .context("Failed to load NFT master editions")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(address, e)| (address, e.try_into()))
+            .batch(addresses))
+    }
+}
+
+#[async_trait]
+impl TryBatchFn<PublicKey<Nft>, Option<Edition>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Nft>],
+    ) -> TryBatchMap<PublicKey<Nft>, Option<Edition>> {
+        let conn = self.db()?;
+
+        let rows: Vec<(String, models::Edition)> = metadatas::table
+            .inner_join(editions::table.on(editions::address.eq(metadatas::edition_pda)))
+            .filter(metadatas::address.eq(any(addresses)))
+            .select((metadatas::address, editions::all_columns))
+            .load(&conn)
+            .context("Failed to load NFT editions")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(address, e)| (address, e.try_into()))
+            .batch(addresses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::rt::System;
+    use indexer_core::db::{insert_into, testing};
+
+    use super::*;
+
+    #[test]
+    fn prints_link_to_their_master() {
+        let db = match testing::test_replicas() {
+            Some(db) => Arc::new(db),
+            None => return,
+        };
+        let conn = db.get().unwrap();
+
+        insert_into(metadatas::table)
+            .values(&[
+                testing::metadata("master", "master_mint", true),
+                testing::metadata("print", "print_mint", false),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(master_editions::table)
+            .values(&models::MasterEdition {
+                address: Borrowed("master-edition"),
+                supply: 7,
+                max_supply: Some(10),
+            })
+            .execute(&conn)
+            .unwrap();
+        insert_into(editions::table)
+            .values(&models::Edition {
+                address: Borrowed("print-edition"),
+                parent_address: Borrowed("master-edition"),
+                edition: 7,
+            })
+            .execute(&conn)
+            .unwrap();
+
+        let nfts: Vec<PublicKey<Nft>> =
+            vec![String::from("master").into(), String::from("print").into()];
+        let mut batcher = Batcher::new(db, 0, None);
+
+        let prints: BatchMap<_, Option<Edition>> = System::new()
+            .block_on(TryBatchFn::load(&mut batcher, &nfts))
+            .unwrap();
+        let masters: BatchMap<_, Option<MasterEdition>> = System::new()
+            .block_on(TryBatchFn::load(&mut batcher, &nfts))
+            .unwrap();
+
+        assert!(prints[&nfts[0]].as_ref().unwrap().is_none());
+        assert!(masters[&nfts[1]].as_ref().unwrap().is_none());
+
+        let print = prints[&nfts[1]].as_ref().unwrap().as_ref().unwrap();
+        assert_eq!(print.address.to_string(), "print-edition");
+        assert_eq!(print.edition, 7_u64.into());
+
+        let master = masters[&nfts[0]].as_ref().unwrap().as_ref().unwrap();
+        assert_eq!(master.address, print.parent);
+        assert_eq!(master.supply, 7_u64.into());
+        assert_eq!(master.max_supply, Some(10_u64.into()));
+
+        // The print's parent resolves to the same master edition
+        let parents: BatchMap<_, Option<MasterEdition>> = System::new()
+            .block_on(TryBatchFn::load(&mut batcher, &[print.parent.clone()]))
+            .unwrap();
+        let parent = parents[&print.parent].as_ref().unwrap().as_ref().unwrap();
+
+        assert_eq!(parent.address, master.address);
+    }
+}
@@ -0,0 +1,27 @@
+use objects::locker::Locker;
+use scalars::PublicKey;
+use tables::{locker_params, lockers};
+
+use super::prelude::*;
+
+#[async_trait]
+impl TryBatchFn<PublicKey<Locker>, Option<Locker>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Locker>],
+    ) -> TryBatchMap<PublicKey<Locker>, Option<Locker>> {
+        let conn = self.db()?;
+
+        let rows: Vec<(models::Locker, Option<models::LockerParam>)> = lockers::table
+            .left_join(locker_params::table.on(locker_params::locker_address.eq(lockers::address)))
+            .filter(lockers::address.eq(any(addresses)))
+            .select((lockers::all_columns, locker_params::all_columns.nullable()))
+            .load(&conn)
+            .context("Failed to load lockers")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(l, p)| (l.address.clone(), (l, p).try_into()))
+            .batch(addresses))
+    }
+}
@@ -1,11 +1,16 @@
 pub mod auction_house;
 pub mod bid_receipt;
+pub mod candy_machine;
 pub mod collection;
+pub mod edition;
 pub mod listing;
+pub mod locker;
 pub mod nft;
+pub mod smart_wallet;
 pub mod stats;
 pub mod store_creator;
 pub mod storefront;
+pub mod vote;
 pub mod wallet;
 
 pub(self) mod batcher;
@@ -25,4 +30,4 @@ pub(self) mod prelude {
     };
 }
 
-pub use batcher::{BatchResult, Batcher, Error, Loader, TwitterBatcher};
+pub use batcher::{BatchResult, Batcher, Error, Loader, TwitterBatcher, TwitterRateLimit};
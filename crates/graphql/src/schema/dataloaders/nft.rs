@@ -1,17 +1,51 @@
-use indexer_core::db::queries;
+use indexer_core::db::{custom_types::TokenStandardEnum, queries};
 use objects::{
     listing_receipt::ListingReceipt,
-    nft::{Nft, NftActivity, NftAttribute, NftCreator, NftOwner},
+    nft::{
+        Nft, NftActivity, NftAttribute, NftCreator, NftMedia, NftOwner, NftRarity, TokenStandard,
+    },
     purchase_receipt::PurchaseReceipt,
 };
 use scalars::PublicKey;
 use tables::{
-    attributes, listing_receipts, metadata_creators, metadatas, purchase_receipts, token_accounts,
-    twitter_handle_name_services,
+    attributes, listing_receipts, metadata_creators, metadata_jsons, metadatas, purchase_receipts,
+    rarity_scores, token_accounts, twitter_handle_name_services,
 };
 
 use super::prelude::*;
 
+#[async_trait]
+impl TryBatchFn<PublicKey<Nft>, Option<Nft>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Nft>],
+    ) -> TryBatchMap<PublicKey<Nft>, Option<Nft>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::Nft> = metadatas::table
+            .inner_join(
+                metadata_jsons::table.on(metadatas::address.eq(metadata_jsons::metadata_address)),
+            )
+            .filter(metadatas::address.eq(any(addresses)))
+            .select((
+                metadatas::address,
+                metadatas::name,
+                metadatas::seller_fee_basis_points,
+                metadatas::mint_address,
+                metadatas::primary_sale_happened,
+                metadata_jsons::description,
+                metadata_jsons::image,
+            ))
+            .load(&conn)
+            .context("Failed to load NFTs")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|n| (n.address.clone(), Nft::from(n)))
+            .batch(addresses))
+    }
+}
+
 #[async_trait]
 impl TryBatchFn<PublicKey<Nft>, Vec<NftAttribute>> for Batcher {
     async fn load(
@@ -20,8 +54,12 @@ impl TryBatchFn<PublicKey<Nft>, Vec<NftAttribute>> for Batcher {
     ) -> TryBatchMap<PublicKey<Nft>, Vec<NftAttribute>> {
         let conn = self.db()?;
 
+        // Attributes missing a trait type or value are skipped rather than
+        // failing the whole list
         let rows: Vec<models::MetadataAttribute> = attributes::table
             .filter(attributes::metadata_address.eq(any(addresses)))
+            .filter(attributes::value.is_not_null())
+            .filter(attributes::trait_type.is_not_null())
             .load(&conn)
             .context("Failed to load NFT attributes")?;
 
@@ -39,11 +77,16 @@ impl TryBatchFn<PublicKey<Nft>, Vec<NftCreator>> for Batcher {
         addresses: &[PublicKey<Nft>],
     ) -> TryBatchMap<PublicKey<Nft>, Vec<NftCreator>> {
         let conn = self.db()?;
+        let max_slot = self.max_slot(&conn)?;
 
         let rows: Vec<(Option<String>, models::MetadataCreator)> = metadata_creators::table
-            .left_join(twitter_handle_name_services::table.on(
-                twitter_handle_name_services::wallet_address.eq(metadata_creators::creator_address),
-            ))
+            .left_join(
+                twitter_handle_name_services::table.on(
+                    twitter_handle_name_services::wallet_address
+                        .eq(metadata_creators::creator_address)
+                        .and(twitter_handle_name_services::slot.le(max_slot)),
+                ),
+            )
             .filter(metadata_creators::metadata_address.eq(any(addresses)))
             .order(metadata_creators::position.asc())
             .select((
@@ -60,6 +103,36 @@ impl TryBatchFn<PublicKey<Nft>, Vec<NftCreator>> for Batcher {
     }
 }
 
+#[async_trait]
+impl TryBatchFn<PublicKey<Nft>, Option<NftMedia>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Nft>],
+    ) -> TryBatchMap<PublicKey<Nft>, Option<NftMedia>> {
+        let conn = self.db()?;
+
+        let rows: Vec<(String, Option<String>, Option<String>)> = metadata_jsons::table
+            .filter(metadata_jsons::metadata_address.eq(any(addresses)))
+            .select((
+                metadata_jsons::metadata_address,
+                metadata_jsons::category,
+                metadata_jsons::animation_url,
+            ))
+            .load(&conn)
+            .context("Failed to load NFT media")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(address, category, animation_url)| {
+                (address, NftMedia {
+                    category,
+                    animation_url,
+                })
+            })
+            .batch(addresses))
+    }
+}
+
 #[async_trait]
 impl TryBatchFn<PublicKey<Nft>, Option<NftOwner>> for Batcher {
     async fn load(
@@ -67,26 +140,31 @@ impl TryBatchFn<PublicKey<Nft>, Option<NftOwner>> for Batcher {
         mint_addresses: &[PublicKey<Nft>],
     ) -> TryBatchMap<PublicKey<Nft>, Option<NftOwner>> {
         let conn = self.db()?;
+        let max_slot = self.max_slot(&conn)?;
 
-        let rows: Vec<(Option<String>, models::TokenAccount)> =
-            token_accounts::table
-                .left_join(twitter_handle_name_services::table.on(
-                    twitter_handle_name_services::wallet_address.eq(token_accounts::owner_address),
-                ))
-                .filter(token_accounts::mint_address.eq(any(mint_addresses)))
-                .filter(token_accounts::amount.eq(1))
-                .select((
-                    twitter_handle_name_services::twitter_handle.nullable(),
-                    (
-                        token_accounts::address,
-                        token_accounts::mint_address,
-                        token_accounts::owner_address,
-                        token_accounts::amount,
-                        token_accounts::slot,
-                    ),
-                ))
-                .load(&conn)
-                .context("Failed to load NFT owners")?;
+        let rows: Vec<(Option<String>, models::TokenAccount)> = token_accounts::table
+            .left_join(
+                twitter_handle_name_services::table.on(
+                    twitter_handle_name_services::wallet_address
+                        .eq(token_accounts::owner_address)
+                        .and(twitter_handle_name_services::slot.le(max_slot)),
+                ),
+            )
+            .filter(token_accounts::mint_address.eq(any(mint_addresses)))
+            .filter(token_accounts::amount.eq(1))
+            .filter(queries::slot::coalesce_slot(token_accounts::slot, 0).le(max_slot))
+            .select((
+                twitter_handle_name_services::twitter_handle.nullable(),
+                (
+                    token_accounts::address,
+                    token_accounts::mint_address,
+                    token_accounts::owner_address,
+                    token_accounts::amount,
+                    token_accounts::slot,
+                ),
+            ))
+            .load(&conn)
+            .context("Failed to load NFT owners")?;
 
         Ok(rows
             .into_iter()
@@ -101,6 +179,31 @@ impl TryBatchFn<PublicKey<Nft>, Option<NftOwner>> for Batcher {
     }
 }
 
+#[async_trait]
+impl TryBatchFn<PublicKey<Nft>, Option<NftRarity>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Nft>],
+    ) -> TryBatchMap<PublicKey<Nft>, Option<NftRarity>> {
+        let conn = self.db()?;
+
+        let rows: Vec<(String, f64, i64)> = rarity_scores::table
+            .filter(rarity_scores::metadata_address.eq(any(addresses)))
+            .select((
+                rarity_scores::metadata_address,
+                rarity_scores::score,
+                rarity_scores::rank,
+            ))
+            .load(&conn)
+            .context("Failed to load NFT rarity scores")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(address, score, rank)| (address, NftRarity { score, rank }))
+            .batch(addresses))
+    }
+}
+
 #[async_trait]
 impl TryBatchFn<PublicKey<Nft>, Vec<PurchaseReceipt>> for Batcher {
     async fn load(
@@ -124,6 +227,32 @@ impl TryBatchFn<PublicKey<Nft>, Vec<PurchaseReceipt>> for Batcher {
     }
 }
 
+#[async_trait]
+impl TryBatchFn<PublicKey<Nft>, Option<PurchaseReceipt>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Nft>],
+    ) -> TryBatchMap<PublicKey<Nft>, Option<PurchaseReceipt>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::PurchaseReceipt> = purchase_receipts::table
+            .filter(purchase_receipts::metadata.eq(any(addresses)))
+            .distinct_on(purchase_receipts::metadata)
+            .order((
+                purchase_receipts::metadata,
+                purchase_receipts::created_at.desc(),
+                purchase_receipts::address.desc(),
+            ))
+            .load(&conn)
+            .context("Failed to load last sales")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|purchase| (purchase.metadata.clone(), purchase.try_into()))
+            .batch(addresses))
+    }
+}
+
 #[async_trait]
 impl TryBatchFn<PublicKey<Nft>, Vec<ListingReceipt>> for Batcher {
     async fn load(
@@ -168,3 +297,117 @@ impl TryBatchFn<PublicKey<Nft>, Vec<NftActivity>> for Batcher {
             .batch(addresses))
     }
 }
+
+#[async_trait]
+impl TryBatchFn<PublicKey<Nft>, Option<TokenStandard>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Nft>],
+    ) -> TryBatchMap<PublicKey<Nft>, Option<TokenStandard>> {
+        let conn = self.db()?;
+
+        let rows: Vec<(String, Option<TokenStandardEnum>)> = metadatas::table
+            .filter(metadatas::address.eq(any(addresses)))
+            .filter(metadatas::token_standard.is_not_null())
+            .select((metadatas::address, metadatas::token_standard))
+            .load(&conn)
+            .context("Failed to load NFT token standards")?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(address, standard)| standard.map(|s| (address, TokenStandard::from(s))))
+            .batch(addresses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::rt::System;
+    use indexer_core::db::{insert_into, models::MetadataAttributeWrite, testing};
+
+    use super::*;
+
+    fn attribute(
+        metadata_address: &'static str,
+        trait_type: Option<&'static str>,
+        value: Option<&'static str>,
+    ) -> MetadataAttributeWrite<'static> {
+        MetadataAttributeWrite {
+            metadata_address: Borrowed(metadata_address),
+            value: value.map(Borrowed),
+            trait_type: trait_type.map(Borrowed),
+            first_verified_creator: None,
+            trait_type_normalized: None,
+        }
+    }
+
+    #[test]
+    fn incomplete_attributes_are_skipped() {
+        let db = match testing::test_replicas() {
+            Some(db) => Arc::new(db),
+            None => return,
+        };
+
+        insert_into(attributes::table)
+            .values(&[
+                attribute("nft", Some("Background"), Some("Gold")),
+                attribute("nft", None, Some("Blue")),
+                attribute("nft", Some("Eyes"), None),
+                attribute("other", Some("Hat"), Some("Crown")),
+            ])
+            .execute(&db.get().unwrap())
+            .unwrap();
+
+        let keys: Vec<PublicKey<Nft>> = vec![String::from("nft").into()];
+
+        let mut batcher = Batcher::new(db, 0, None);
+        let attrs: BatchMap<_, Vec<NftAttribute>> = System::new()
+            .block_on(TryBatchFn::load(&mut batcher, &keys))
+            .unwrap();
+        let attrs = attrs[&keys[0]].as_ref().unwrap();
+
+        assert_eq!(attrs.len(), 1);
+        assert_eq!(attrs[0].trait_type, "Background");
+        assert_eq!(attrs[0].value, "Gold");
+    }
+
+    #[test]
+    fn last_sale_is_the_newest() {
+        let db = match testing::test_replicas() {
+            Some(db) => Arc::new(db),
+            None => return,
+        };
+        let now = Local::now().naive_utc();
+
+        insert_into(purchase_receipts::table)
+            .values(&[
+                testing::purchase_receipt("second", "house", "nft", ("bob", "carol"), 200, now),
+                testing::purchase_receipt(
+                    "first",
+                    "house",
+                    "nft",
+                    ("alice", "bob"),
+                    100,
+                    now - chrono::Duration::days(1),
+                ),
+            ])
+            .execute(&db.get().unwrap())
+            .unwrap();
+
+        let keys: Vec<PublicKey<Nft>> =
+            vec![String::from("nft").into(), String::from("unsold").into()];
+
+        let mut batcher = Batcher::new(db, 0, None);
+        let sales: BatchMap<_, Option<PurchaseReceipt>> = System::new()
+            .block_on(TryBatchFn::load(&mut batcher, &keys))
+            .unwrap();
+
+        let last = sales[&keys[0]].as_ref().unwrap().as_ref().unwrap();
+        assert_eq!(last.address, "second");
+        assert_eq!(last.seller, "bob");
+        assert_eq!(last.buyer, "carol");
+        assert_eq!(last.price, 200_u64.into());
+
+        assert!(sales[&keys[1]].as_ref().unwrap().is_none());
+    }
+}
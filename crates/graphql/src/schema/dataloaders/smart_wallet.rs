@@ -0,0 +1,49 @@
+use objects::smart_wallet::{SmartWallet, SmartWalletOwner};
+use scalars::PublicKey;
+use tables::{smart_wallet_owners, smart_wallets};
+
+use super::prelude::*;
+
+#[async_trait]
+impl TryBatchFn<PublicKey<SmartWallet>, Option<SmartWallet>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<SmartWallet>],
+    ) -> TryBatchMap<PublicKey<SmartWallet>, Option<SmartWallet>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::SmartWallet> = smart_wallets::table
+            .filter(smart_wallets::address.eq(any(addresses)))
+            .load(&conn)
+            .context("Failed to load smart wallets")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|w| (w.address.clone(), w.try_into()))
+            .batch(addresses))
+    }
+}
+
+#[async_trait]
+impl TryBatchFn<PublicKey<SmartWallet>, Vec<SmartWalletOwner>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<SmartWallet>],
+    ) -> TryBatchMap<PublicKey<SmartWallet>, Vec<SmartWalletOwner>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::SmartWalletOwner> = smart_wallet_owners::table
+            .filter(smart_wallet_owners::smart_wallet_address.eq(any(addresses)))
+            .order((
+                smart_wallet_owners::smart_wallet_address.asc(),
+                smart_wallet_owners::index.asc(),
+            ))
+            .load(&conn)
+            .context("Failed to load smart wallet owners")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|o| (o.smart_wallet_address.clone(), o.try_into()))
+            .batch(addresses))
+    }
+}
@@ -24,3 +24,101 @@ impl TryBatchFn<PublicKey<Storefront>, Option<Storefront>> for Batcher {
             .batch(keys))
     }
 }
+
+#[async_trait]
+impl TryBatchFn<String, Option<Storefront>> for Batcher {
+    async fn load(&mut self, subdomains: &[String]) -> TryBatchMap<String, Option<Storefront>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::Storefront> = storefronts::table
+            .select(StorefrontColumns::default())
+            .filter(storefronts::subdomain.eq(any(subdomains)))
+            .load(&conn)
+            .context("Failed to load storefronts")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|s| (s.subdomain.clone(), s.try_into()))
+            .batch(subdomains))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::rt::System;
+    use indexer_core::db::{insert_into, testing};
+
+    use super::*;
+
+    fn storefront(address: &'static str, subdomain: &'static str) -> models::Storefront<'static> {
+        models::Storefront {
+            owner_address: Owned(format!("{}-owner", address)),
+            subdomain: Borrowed(subdomain),
+            title: Borrowed(subdomain),
+            description: Borrowed(""),
+            favicon_url: Borrowed(""),
+            logo_url: Borrowed(""),
+            updated_at: None,
+            banner_url: None,
+            address: Borrowed(address),
+        }
+    }
+
+    #[test]
+    fn two_storefronts() {
+        let db = match testing::test_replicas() {
+            Some(db) => Arc::new(db),
+            None => return,
+        };
+
+        insert_into(storefronts::table)
+            .values(&[storefront("store1", "one"), storefront("store2", "two")])
+            .execute(&db.get().unwrap())
+            .unwrap();
+
+        let mut batcher = Batcher::new(db, 0, None);
+
+        let addresses: Vec<PublicKey<Storefront>> = ["store1", "store2", "store3"]
+            .into_iter()
+            .map(|a| String::from(a).into())
+            .collect();
+        let by_address: BatchMap<_, Option<Storefront>> = System::new()
+            .block_on(TryBatchFn::load(&mut batcher, &addresses))
+            .unwrap();
+
+        let subdomains: Vec<String> = vec!["two".into(), "one".into(), "three".into()];
+        let by_subdomain: BatchMap<_, Option<Storefront>> = System::new()
+            .block_on(TryBatchFn::load(&mut batcher, &subdomains))
+            .unwrap();
+
+        let subdomain = |s: &BatchResult<Option<Storefront>>| {
+            s.as_ref().unwrap().as_ref().map(|s| s.subdomain.clone())
+        };
+        let owner = |s: &BatchResult<Option<Storefront>>| {
+            s.as_ref()
+                .unwrap()
+                .as_ref()
+                .map(|s| s.owner_address.clone())
+        };
+
+        assert_eq!(
+            subdomain(&by_address[&addresses[0]]).as_deref(),
+            Some("one")
+        );
+        assert_eq!(
+            subdomain(&by_address[&addresses[1]]).as_deref(),
+            Some("two")
+        );
+        assert_eq!(subdomain(&by_address[&addresses[2]]), None);
+
+        assert_eq!(
+            owner(&by_subdomain[&subdomains[0]]).as_deref(),
+            Some("store2-owner")
+        );
+        assert_eq!(
+            owner(&by_subdomain[&subdomains[1]]).as_deref(),
+            Some("store1-owner")
+        );
+        assert_eq!(owner(&by_subdomain[&subdomains[2]]), None);
+    }
+}
@@ -0,0 +1,140 @@
+use objects::{
+    proposal::{Proposal, ProposalAccount, ProposalInstruction},
+    vote::Vote,
+};
+use scalars::PublicKey;
+use tables::{proposal_account_metas, proposal_instructions, votes};
+
+use super::prelude::*;
+
+#[async_trait]
+impl TryBatchFn<PublicKey<Proposal>, Vec<Vote>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Proposal>],
+    ) -> TryBatchMap<PublicKey<Proposal>, Vec<Vote>> {
+        let conn = self.db()?;
+
+        let rows: Vec<models::Vote> = votes::table
+            .filter(votes::proposal.eq(any(addresses)))
+            .order(votes::weight.desc())
+            .load(&conn)
+            .context("Failed to load votes")?;
+
+        Ok(rows
+            .into_iter()
+            .map(|v| (v.proposal.clone(), v.try_into()))
+            .batch(addresses))
+    }
+}
+
+#[async_trait]
+impl TryBatchFn<PublicKey<Proposal>, Vec<ProposalInstruction>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Proposal>],
+    ) -> TryBatchMap<PublicKey<Proposal>, Vec<ProposalInstruction>> {
+        let conn = self.db()?;
+
+        let instructions: Vec<models::ProposalInstruction> = proposal_instructions::table
+            .filter(proposal_instructions::proposal_address.eq(any(addresses)))
+            .order(proposal_instructions::program_id.asc())
+            .load(&conn)
+            .context("Failed to load proposal instructions")?;
+
+        let metas: Vec<models::ProposalAccountMeta> = proposal_account_metas::table
+            .filter(proposal_account_metas::proposal_address.eq(any(addresses)))
+            .load(&conn)
+            .context("Failed to load proposal account metas")?;
+
+        let mut accounts: HashMap<(String, String), Vec<ProposalAccount>> = HashMap::new();
+
+        for meta in metas {
+            accounts
+                .entry((
+                    meta.proposal_address.to_string(),
+                    meta.program_id.to_string(),
+                ))
+                .or_default()
+                .push(meta.into());
+        }
+
+        Ok(instructions
+            .into_iter()
+            .map(
+                |models::ProposalInstruction {
+                     proposal_address,
+                     program_id,
+                     data,
+                 }| {
+                    let accounts = accounts
+                        .remove(&(proposal_address.to_string(), program_id.to_string()))
+                        .unwrap_or_default();
+
+                    (proposal_address, ProposalInstruction {
+                        program_id: program_id.into_owned(),
+                        data,
+                        accounts,
+                    })
+                },
+            )
+            .batch(addresses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::rt::System;
+    use indexer_core::db::{sql_query, testing};
+    use objects::vote::VoteSide;
+
+    use super::*;
+
+    #[test]
+    fn votes_are_grouped_by_proposal() {
+        let db = match testing::test_replicas() {
+            Some(db) => Arc::new(db),
+            None => return,
+        };
+
+        // Inserted as raw values so that an unrecognized side can be stored
+        sql_query(
+            "insert into votes (address, proposal, voter, bump, side, weight) values
+                ('a1', 'a', 'alice', 0, 2, 10),
+                ('a2', 'a', 'bob', 0, 1, 30),
+                ('b1', 'b', 'alice', 0, 9, 5),
+                ('x1', 'other', 'carol', 0, 3, 1)",
+        )
+        .execute(&db.get().unwrap())
+        .unwrap();
+
+        let keys: Vec<PublicKey<Proposal>> = ["a", "b", "c"]
+            .into_iter()
+            .map(|k| String::from(k).into())
+            .collect();
+
+        let mut batcher = Batcher::new(db, 0, None);
+        let votes: BatchMap<_, Vec<Vote>> = System::new()
+            .block_on(TryBatchFn::load(&mut batcher, &keys))
+            .unwrap();
+
+        let summary = |key: &PublicKey<Proposal>| {
+            votes[key]
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|v| (v.voter.clone(), v.side))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(summary(&keys[0]), vec![
+            ("bob".to_owned(), VoteSide::Against),
+            ("alice".to_owned(), VoteSide::For),
+        ]);
+        assert_eq!(summary(&keys[1]), vec![(
+            "alice".to_owned(),
+            VoteSide::Pending
+        )]);
+        assert!(summary(&keys[2]).is_empty());
+    }
+}
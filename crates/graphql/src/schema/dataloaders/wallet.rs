@@ -1,56 +1,152 @@
-use futures_util::future::join_all;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use indexer_core::db::queries;
 use itertools::Either;
-use objects::profile::{TwitterProfile, TwitterUserProfileResponse};
+use objects::{
+    nft::Nft,
+    profile::{TwitterProfile, TwitterUserProfileResponse, TwitterUsersResponse},
+    wallet::{Wallet, WalletConnectionCounts, WALLET_PROFILE_NFT_SAMPLE},
+};
+use reqwest::StatusCode;
+use scalars::PublicKey;
+use tables::twitter_handle_name_services;
 
 use super::prelude::*;
+use crate::metrics;
 
 const TWITTER_SCREEN_NAME_CHUNKS: usize = 100;
 
+/// Longest time a batch will wait for the Twitter rate limit to reset before
+/// falling back to profiles with only a handle
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(2);
+
+/// Time to back off for when Twitter does not say when its rate limit resets
+const DEFAULT_RATE_LIMIT_RESET: Duration = Duration::from_secs(60);
+
+/// Read the time until the rate limit resets from a Twitter response
+fn rate_limit_reset(res: &reqwest::Response) -> Option<Duration> {
+    let reset = res
+        .headers()
+        .get("x-rate-limit-reset")?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+
+    Some(Duration::from_secs(reset).saturating_sub(now))
+}
+
+/// Look up a chunk of users by screen name, returning `None` without making
+/// a request if the rate limit will not reset soon
+async fn lookup(
+    http_client: &reqwest::Client,
+    batcher: &TwitterBatcher,
+    screen_names: &[String],
+) -> Result<Option<Vec<TwitterUserProfileResponse>>, Error> {
+    let rate_limit = batcher.rate_limit();
+
+    if let Some(wait) = rate_limit.remaining() {
+        if wait > MAX_RATE_LIMIT_WAIT {
+            return Ok(None);
+        }
+
+        actix_web::rt::time::sleep(wait).await;
+    }
+
+    let res = http_client
+        .get(format!(
+            "{}/2/users/by",
+            batcher.endpoint().trim_end_matches('/')
+        ))
+        .header("Accept", "application/json")
+        .query(&[
+            ("usernames", screen_names.join(",").as_str()),
+            ("user.fields", "description,profile_image_url"),
+        ])
+        .bearer_auth(batcher.bearer())
+        .send()
+        .await
+        .map_err(Error::model_convert)?;
+
+    if res.status() == StatusCode::TOO_MANY_REQUESTS {
+        let reset = rate_limit_reset(&res).unwrap_or(DEFAULT_RATE_LIMIT_RESET);
+
+        warn!("Twitter rate limit hit, backing off for {:?}", reset);
+        metrics::TWITTER_RATE_LIMITED.inc();
+        rate_limit.limit_for(reset);
+
+        return Ok(None);
+    }
+
+    res.json()
+        .await
+        .map(|r: TwitterUsersResponse| Some(r.data))
+        .map_err(Error::model_convert)
+}
+
 #[async_trait]
 impl TryBatchFn<String, Option<TwitterProfile>> for TwitterBatcher {
     async fn load(
         &mut self,
         screen_names: &[String],
     ) -> TryBatchMap<String, Option<TwitterProfile>> {
-        let http_client = reqwest::Client::new();
         let twitter_bearer_token = self.bearer();
 
-        let chunked_screen_names = screen_names.chunks(TWITTER_SCREEN_NAME_CHUNKS);
+        if twitter_bearer_token.is_empty() {
+            return Ok(screen_names
+                .iter()
+                .map(|s| (s.clone(), TwitterProfile::handle_only(s.clone())))
+                .batch(screen_names));
+        }
 
-        let twitter_users = chunked_screen_names
-            .clone()
-            .into_iter()
-            .map(|screen_names| {
-                let http_client = &http_client;
-
-                async move {
-                    http_client
-                        .post("https://api.twitter.com/1.1/users/lookup.json")
-                        .header("Accept", "application/json")
-                        .form(&[("screen_name", &screen_names.join(", "))])
-                        .bearer_auth(twitter_bearer_token)
-                        .send()
-                        .await
-                        .map_err(Error::model_convert)?
-                        .json::<Vec<TwitterUserProfileResponse>>()
-                        .await
-                        .map_err(Error::model_convert)
-                }
-            })
-            .collect::<Vec<_>>();
+        // Profiles are shared between requests to spare the API rate limit,
+        // so only look up handles which have not been seen recently
+        let cache = self.cache();
+        let (cached, screen_names): (Vec<_>, Vec<_>) = screen_names
+            .iter()
+            .map(|s| (s, cache.get(&s.to_lowercase())))
+            .partition(|(_, p)| p.is_some());
+
+        let screen_names: Vec<String> = screen_names.into_iter().map(|(s, _)| s.clone()).collect();
+
+        let http_client = reqwest::Client::new();
 
-        let twitter_users: Vec<_> = join_all(twitter_users).await;
+        // Chunks are looked up one at a time so that once the rate limit is
+        // hit the rest of the batch can fall back without further requests
+        let mut twitter_users = Vec::new();
+        let mut rate_limited = Vec::new();
 
-        Ok(twitter_users
+        for keys in screen_names.chunks(TWITTER_SCREEN_NAME_CHUNKS) {
+            match lookup(&http_client, self, keys).await {
+                Ok(Some(users)) => twitter_users.push((Ok(users), keys)),
+                Ok(None) => rate_limited.extend(keys),
+                Err(e) => twitter_users.push((Err(e), keys)),
+            }
+        }
+
+        let looked_up: Vec<String> = twitter_users
+            .iter()
+            .flat_map(|(_, keys)| keys.iter().cloned())
+            .collect();
+
+        let mut profiles: BatchMap<String, Option<TwitterProfile>> = twitter_users
             .into_iter()
-            .zip(chunked_screen_names)
             .flat_map(|(result, keys)| match result {
-                Ok(users) => Either::Left(
-                    users
+                Ok(users) => {
+                    // Twitter omits unknown users and does not preserve the
+                    // order of the request, so match users to keys by name
+                    let mut users: HashMap<_, _> = users
                         .into_iter()
-                        .zip(keys)
-                        .map(|(user, key)| (key, Ok(user))),
-                ),
+                        .map(|u| (u.username.to_lowercase(), u))
+                        .collect();
+
+                    Either::Left(keys.iter().filter_map(move |key| {
+                        users
+                            .remove(&key.to_lowercase())
+                            .map(|user| (key, Ok(user)))
+                    }))
+                },
                 Err(e) => Either::Right(keys.iter().map(move |key| (key, Err(e.clone())))),
             })
             .map(|(k, user)| {
@@ -60,6 +156,282 @@ impl TryBatchFn<String, Option<TwitterProfile>> for TwitterBatcher {
                         .and_then(|u| u.try_into().context("failed to convert to twitter profile")),
                 )
             })
-            .batch(screen_names))
+            .batch(&looked_up);
+
+        for (key, profile) in &profiles {
+            match profile {
+                Ok(Some(profile)) => cache.insert(key.to_lowercase(), Some(profile.clone())),
+                // The handle may be registered later, so its absence is only
+                // cached briefly
+                Ok(None) => cache.insert_for(key.to_lowercase(), None, self.missing_ttl()),
+                Err(_) => (),
+            }
+        }
+
+        // Rate-limited handles are served without their profile details, and
+        // not cached so they are looked up again once the limit resets
+        profiles.extend(rate_limited.into_iter().map(|key| {
+            (
+                key.clone(),
+                Ok(Some(TwitterProfile::handle_only(key.clone()))),
+            )
+        }));
+        profiles.extend(
+            cached
+                .into_iter()
+                .filter_map(|(key, profile)| profile.map(|p| (key.clone(), Ok(p)))),
+        );
+
+        Ok(profiles)
+    }
+}
+
+#[async_trait]
+impl TryBatchFn<PublicKey<Wallet>, Option<WalletConnectionCounts>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Wallet>],
+    ) -> TryBatchMap<PublicKey<Wallet>, Option<WalletConnectionCounts>> {
+        let conn = self.db()?;
+
+        let rows = queries::graph_connection::counts(&conn, addresses)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|c| (c.wallet.clone(), c.try_into()))
+            .batch(addresses))
+    }
+}
+
+#[async_trait]
+impl TryBatchFn<PublicKey<Wallet>, Option<String>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Wallet>],
+    ) -> TryBatchMap<PublicKey<Wallet>, Option<String>> {
+        let conn = self.db()?;
+        let max_slot = self.max_slot(&conn)?;
+
+        let rows: Vec<(String, String)> = twitter_handle_name_services::table
+            .filter(twitter_handle_name_services::wallet_address.eq(any(addresses)))
+            .filter(twitter_handle_name_services::slot.le(max_slot))
+            .select((
+                twitter_handle_name_services::wallet_address,
+                twitter_handle_name_services::twitter_handle,
+            ))
+            .load(&conn)
+            .context("Failed to load wallet Twitter handles")?;
+
+        Ok(rows.into_iter().batch(addresses))
+    }
+}
+
+#[async_trait]
+impl TryBatchFn<PublicKey<Wallet>, Vec<Nft>> for Batcher {
+    async fn load(
+        &mut self,
+        addresses: &[PublicKey<Wallet>],
+    ) -> TryBatchMap<PublicKey<Wallet>, Vec<Nft>> {
+        let conn = self.db()?;
+        let max_slot = self.max_slot(&conn)?;
+
+        let rows = queries::metadatas::sample_owned_by(
+            &conn,
+            addresses,
+            max_slot,
+            WALLET_PROFILE_NFT_SAMPLE.into(),
+        )?;
+
+        Ok(rows
+            .into_iter()
+            .map(|o| (o.owner, Nft::from(o.nft)))
+            .batch(addresses))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::rt::System;
+    use indexer_core::db::{insert_into, testing};
+
+    use super::*;
+    use crate::{
+        schema::{LruCache, TwitterProfileCache, TwitterRateLimit},
+        testing::{respond, MockServer},
+    };
+
+    const ALICE: &str = r#"{
+        "data": [{
+            "username": "Alice",
+            "description": "gm",
+            "profile_image_url": "https://example.com/alice.png"
+        }]
+    }"#;
+
+    fn twitter(server: &MockServer, cache: &Arc<TwitterProfileCache>) -> TwitterBatcher {
+        TwitterBatcher::new(
+            "token".into(),
+            server.url(),
+            Arc::clone(cache),
+            Duration::from_millis(100),
+            Arc::new(TwitterRateLimit::default()),
+            None,
+        )
+    }
+
+    fn profiles(
+        batcher: &mut TwitterBatcher,
+        handles: &[&str],
+    ) -> BatchMap<String, Option<TwitterProfile>> {
+        let keys: Vec<String> = handles.iter().map(|h| (*h).to_owned()).collect();
+
+        System::new()
+            .block_on(TryBatchFn::load(batcher, &keys))
+            .unwrap()
+    }
+
+    #[test]
+    fn cached_profiles_are_not_fetched_again() {
+        let server =
+            MockServer::start(|_, _| respond(200, &[("Content-Type", "application/json")], ALICE));
+        let cache = Arc::new(LruCache::new(16, Duration::from_secs(60)));
+
+        let first = profiles(&mut twitter(&server, &cache), &["alice"]);
+        assert_eq!(server.requests(), 1);
+
+        // A later request shares the cache, but not the batcher
+        let second = profiles(&mut twitter(&server, &cache), &["ALICE"]);
+        assert_eq!(server.requests(), 1);
+
+        for (profiles, key) in [(first, "alice"), (second, "ALICE")] {
+            let profile = profiles[key].as_ref().unwrap().as_ref().unwrap();
+
+            assert_eq!(profile.handle, "Alice");
+            assert_eq!(profile.description, "gm");
+            assert_eq!(profile.profile_image_url, "https://example.com/alice.png");
+        }
+    }
+
+    fn handle(
+        wallet: &'static str,
+        twitter_handle: &'static str,
+    ) -> models::TwitterHandle<'static> {
+        models::TwitterHandle {
+            address: Owned(format!("{}-name", wallet)),
+            wallet_address: Borrowed(wallet),
+            twitter_handle: Borrowed(twitter_handle),
+            slot: 1,
+        }
+    }
+
+    #[test]
+    fn missing_users_are_cached_briefly() {
+        let server = MockServer::start(|_, _| {
+            respond(
+                200,
+                &[("Content-Type", "application/json")],
+                r#"{ "errors": [{ "value": "nobody", "title": "Not Found Error" }] }"#,
+            )
+        });
+        let cache = Arc::new(LruCache::new(16, Duration::from_secs(60)));
+
+        for _ in 0..2 {
+            let profiles = profiles(&mut twitter(&server, &cache), &["nobody"]);
+
+            assert!(profiles["nobody"].as_ref().unwrap().is_none());
+            assert_eq!(server.requests(), 1);
+        }
+
+        std::thread::sleep(Duration::from_millis(200));
+
+        profiles(&mut twitter(&server, &cache), &["nobody"]);
+        assert_eq!(server.requests(), 2);
+    }
+
+    #[test]
+    fn rate_limited_lookups_back_off() {
+        let reset =
+            SystemTime::now().duration_since(UNIX_EPOCH).unwrap() + Duration::from_secs(600);
+        let reset = reset.as_secs().to_string();
+        let server = MockServer::start(move |i, _| {
+            if i == 0 {
+                respond(429, &[("x-rate-limit-reset", reset.as_str())], "")
+            } else {
+                respond(200, &[("Content-Type", "application/json")], ALICE)
+            }
+        });
+        let cache = Arc::new(LruCache::new(16, Duration::from_secs(60)));
+        let mut batcher = twitter(&server, &cache);
+        let rate_limited = metrics::TWITTER_RATE_LIMITED.get();
+
+        // The limited handle falls back to a bare profile, and later batches
+        // wait out the reset rather than retrying
+        for _ in 0..2 {
+            let profiles = profiles(&mut batcher, &["alice"]);
+            let profile = profiles["alice"].as_ref().unwrap().as_ref().unwrap();
+
+            assert_eq!(profile.handle, "alice");
+            assert!(profile.description.is_empty());
+            assert_eq!(server.requests(), 1);
+        }
+
+        let wait = batcher.rate_limit().remaining().unwrap();
+        assert!(wait > Duration::from_secs(590), "{:?}", wait);
+        assert!(metrics::TWITTER_RATE_LIMITED.get() > rate_limited);
+
+        // Bare profiles are not cached, so the handle is looked up again once
+        // the limit resets
+        batcher.rate_limit().limit_for(Duration::ZERO);
+
+        let profiles = profiles(&mut batcher, &["alice"]);
+        let profile = profiles["alice"].as_ref().unwrap().as_ref().unwrap();
+
+        assert_eq!(profile.description, "gm");
+        assert_eq!(server.requests(), 2);
+    }
+
+    #[test]
+    fn twitter_handles_by_wallet() {
+        let db = match testing::test_replicas() {
+            Some(db) => Arc::new(db),
+            None => return,
+        };
+
+        insert_into(twitter_handle_name_services::table)
+            .values(&[
+                handle("alice", "alice_nft"),
+                handle("bob", "bobby"),
+                handle("carol", "carol_sol"),
+                handle("erin", "not_requested"),
+            ])
+            .execute(&db.get().unwrap())
+            .unwrap();
+
+        let keys: Vec<PublicKey<Wallet>> = vec![
+            String::from("alice").into(),
+            String::from("bob").into(),
+            String::from("carol").into(),
+            String::from("dave").into(),
+        ];
+
+        let mut batcher = Batcher::new(db, 0, None);
+        let handles: BatchMap<_, Option<String>> = System::new()
+            .block_on(TryBatchFn::load(&mut batcher, &keys))
+            .unwrap();
+
+        assert_eq!(
+            handles[&keys[0]].as_ref().unwrap().as_deref(),
+            Some("alice_nft")
+        );
+        assert_eq!(
+            handles[&keys[1]].as_ref().unwrap().as_deref(),
+            Some("bobby")
+        );
+        assert_eq!(
+            handles[&keys[2]].as_ref().unwrap().as_deref(),
+            Some("carol_sol")
+        );
+        assert_eq!(handles[&keys[3]].as_ref().unwrap().as_deref(), None);
+        assert_eq!(handles.len(), keys.len());
     }
 }
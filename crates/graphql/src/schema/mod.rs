@@ -1,41 +1,55 @@
 #![allow(clippy::module_name_repetitions)]
 
-use juniper::{EmptyMutation, EmptySubscription, RootNode};
+use juniper::{EmptyMutation, RootNode};
 
+mod cache;
+mod complexity;
 mod context;
 pub(self) mod dataloaders;
 pub(self) mod objects;
 mod query_root;
 pub(self) mod scalars;
+mod subscription_root;
 
 pub(self) mod prelude {
     pub use std::{collections::HashMap, sync::Arc};
 
     pub use indexer_core::{
-        db::{models, tables, Pool},
+        db::{models, tables, ReplicaPool},
         prelude::*,
     };
     pub use juniper::{
-        graphql_object, graphql_value, FieldError, FieldResult, GraphQLInputObject, GraphQLObject,
+        graphql_object, graphql_value, FieldError, FieldResult, GraphQLEnum, GraphQLInputObject,
+        GraphQLObject,
     };
 
     pub(super) use super::{context::AppContext, dataloaders, objects, scalars};
-    pub(crate) use crate::SharedData;
+    pub(crate) use crate::{asset_proxy, ListingTieBreak, SharedData};
 }
 
+pub(crate) use cache::{LruCache, Revalidate, TtlCache};
+pub use complexity::QueryAnalyzer;
 pub use context::AppContext;
+pub(crate) use dataloaders::TwitterRateLimit;
+pub use subscription_root::{
+    nft_activity_channel, parse_activity, NftActivitySender, NFT_ACTIVITY_CHANNEL,
+};
+
+/// Cache of Twitter profiles shared between requests, keyed on lowercased
+/// handle.  Handles with no Twitter user are cached as `None`.
+pub(crate) type TwitterProfileCache = LruCache<String, Option<objects::profile::TwitterProfile>>;
 
 pub type Schema = RootNode<
     'static,
     query_root::QueryRoot,
     EmptyMutation<AppContext>,
-    EmptySubscription<AppContext>,
+    subscription_root::SubscriptionRoot,
 >;
 
 pub fn create() -> Schema {
     Schema::new(
         query_root::QueryRoot,
         EmptyMutation::new(),
-        EmptySubscription::new(),
+        subscription_root::SubscriptionRoot,
     )
 }
@@ -1,7 +1,59 @@
 use objects::stats::MintStats;
+use scalars::{BasisPoints, Lamports};
 
 use super::prelude::*;
 
+/// Mint address of wrapped SOL, used by auction houses trading in native SOL
+pub const NATIVE_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// Decimals of native SOL, i.e. the number of lamports in one SOL as a power
+/// of ten
+pub const NATIVE_MINT_DECIMALS: i32 = 9;
+
+/// Resolve the decimals of a token mint, or `None` if the mint is not indexed
+///
+/// # Errors
+/// This function fails if the token mint decimals cannot be loaded.
+pub async fn mint_decimals(ctx: &AppContext, mint: &str) -> FieldResult<Option<i32>> {
+    if mint == NATIVE_MINT {
+        return Ok(Some(NATIVE_MINT_DECIMALS));
+    }
+
+    ctx.token_mint_decimals_loader
+        .load(mint.to_owned().into())
+        .await
+        .map_err(Into::into)
+}
+
+/// Convert a price in base units of an auction house's treasury token to a
+/// UI amount, or `None` if the auction house or the decimals of its treasury
+/// mint are not indexed
+///
+/// # Errors
+/// This function fails if the auction house or its treasury mint decimals
+/// cannot be loaded.
+pub async fn ui_price(
+    ctx: &AppContext,
+    auction_house: &str,
+    price: Lamports,
+) -> FieldResult<Option<f64>> {
+    let house = match ctx
+        .auction_house_loader
+        .load(auction_house.to_owned().into())
+        .await?
+    {
+        Some(h) => h,
+        None => return Ok(None),
+    };
+
+    let decimals = mint_decimals(ctx, &house.treasury_mint).await?;
+
+    #[allow(clippy::cast_precision_loss)]
+    let price = decimals.map(|d| u64::from(price) as f64 / 10_f64.powi(d));
+
+    Ok(price)
+}
+
 #[derive(Debug, Clone)]
 /// A Metaplex auction house
 pub struct AuctionHouse {
@@ -63,12 +115,21 @@ impl<'a> From<models::AuctionHouse<'a>> for AuctionHouse {
 
 #[graphql_object(Context = AppContext)]
 impl AuctionHouse {
-    pub async fn stats(&self, context: &AppContext) -> FieldResult<Option<MintStats>> {
-        context
+    #[graphql(arguments(secondary_only(
+        description = "Exclude listings whose primary sale has not happened from the floor",
+        default = true,
+    )))]
+    pub async fn stats(
+        &self,
+        context: &AppContext,
+        secondary_only: bool,
+    ) -> FieldResult<Option<MintStats>> {
+        let stats = context
             .mint_stats_loader
             .load(self.address.clone().into())
-            .await
-            .map_err(Into::into)
+            .await?;
+
+        Ok(stats.map(|s| s.secondary_only(secondary_only)))
     }
 
     pub fn address(&self) -> &str {
@@ -79,6 +140,11 @@ impl AuctionHouse {
         &self.treasury_mint
     }
 
+    #[graphql(description = "Decimals of the treasury mint, or null if it is not indexed")]
+    pub async fn treasury_mint_decimals(&self, ctx: &AppContext) -> FieldResult<Option<i32>> {
+        mint_decimals(ctx, &self.treasury_mint).await
+    }
+
     pub fn auction_house_treasury(&self) -> &str {
         &self.auction_house_treasury
     }
@@ -111,8 +177,9 @@ impl AuctionHouse {
         self.fee_payer_bump
     }
 
-    pub fn seller_fee_basis_points(&self) -> i32 {
-        self.seller_fee_basis_points
+    #[graphql(description = "Fee charged on sales, in basis points (1/100th of a percent)")]
+    pub fn seller_fee_basis_points(&self) -> FieldResult<BasisPoints> {
+        self.seller_fee_basis_points.try_into().map_err(Into::into)
     }
 
     pub fn requires_sign_off(&self) -> bool {
@@ -127,3 +194,54 @@ impl AuctionHouse {
         &self.auction_house_fee_account
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::rt::System;
+    use indexer_core::db::{
+        insert_into,
+        tables::{auction_houses, token_mints},
+        testing,
+    };
+
+    use super::*;
+    use crate::testing::context;
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn ui_price_uses_treasury_decimals() {
+        let (ctx, db) = match context() {
+            Some(c) => c,
+            None => return,
+        };
+        let conn = db.get().unwrap();
+
+        insert_into(auction_houses::table)
+            .values(&[
+                testing::auction_house("sol_house", NATIVE_MINT),
+                testing::auction_house("usdc_house", "usdc"),
+                testing::auction_house("unknown_house", "unknown"),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(token_mints::table)
+            .values(&models::TokenMint {
+                address: Borrowed("usdc"),
+                decimals: 6,
+            })
+            .execute(&conn)
+            .unwrap();
+
+        let price = |house: &str, price: u64| {
+            System::new()
+                .block_on(ui_price(&ctx, house, price.into()))
+                .unwrap()
+        };
+
+        assert_eq!(price("sol_house", 1_500_000_000), Some(1.5));
+        assert_eq!(price("usdc_house", 1_500_000), Some(1.5));
+        assert_eq!(price("usdc_house", 1), Some(0.000_001));
+        assert_eq!(price("unknown_house", 1_500_000), None);
+        assert_eq!(price("missing_house", 1_500_000), None);
+    }
+}
@@ -11,8 +11,8 @@ pub struct BidReceipt {
     pub price: scalars::Lamports,
     pub trade_state_bump: i32,
     pub token_account: Option<String>,
-    pub created_at: DateTime<Utc>,
-    pub canceled_at: Option<DateTime<Utc>>,
+    pub created_at: scalars::DateTime,
+    pub canceled_at: Option<scalars::DateTime>,
 }
 
 impl<'a> TryFrom<models::BidReceipt<'a>> for BidReceipt {
@@ -45,8 +45,8 @@ impl<'a> TryFrom<models::BidReceipt<'a>> for BidReceipt {
             token_account: token_account.map(Cow::into_owned),
             auction_house: auction_house.into_owned(),
             trade_state_bump: trade_state_bump.into(),
-            created_at: DateTime::from_utc(created_at, Utc),
-            canceled_at: canceled_at.map(|c| DateTime::from_utc(c, Utc)),
+            created_at: created_at.into(),
+            canceled_at: canceled_at.map(Into::into),
         })
     }
 }
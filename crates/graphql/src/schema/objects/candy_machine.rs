@@ -0,0 +1,367 @@
+use indexer_core::db::custom_types::{EndSettingType, WhitelistMintMode};
+use scalars::{BasisPoints, Lamports, Volume};
+
+use super::prelude::*;
+
+#[derive(Debug, Clone)]
+/// A Metaplex candy machine
+pub struct CandyMachine {
+    pub address: String,
+    pub authority: String,
+    pub wallet: String,
+    pub token_mint: Option<String>,
+    pub items_redeemed: i64,
+}
+
+impl<'a> From<models::CandyMachine<'a>> for CandyMachine {
+    fn from(
+        models::CandyMachine {
+            address,
+            authority,
+            wallet,
+            token_mint,
+            items_redeemed,
+        }: models::CandyMachine,
+    ) -> Self {
+        Self {
+            address: address.into_owned(),
+            authority: authority.into_owned(),
+            wallet: wallet.into_owned(),
+            token_mint: token_mint.map(Cow::into_owned),
+            items_redeemed,
+        }
+    }
+}
+
+impl CandyMachine {
+    fn redeemed(&self) -> u64 {
+        self.items_redeemed.try_into().unwrap_or(0)
+    }
+
+    /// Number of items not yet minted out of the given number available
+    #[must_use]
+    pub fn items_remaining_of(&self, items_available: u64) -> u64 {
+        items_available.saturating_sub(self.redeemed())
+    }
+
+    /// Percentage of the given number of available items already minted, or
+    /// `None` if no items are available
+    #[must_use]
+    pub fn percent_minted_of(&self, items_available: u64) -> Option<f64> {
+        if items_available == 0 {
+            return None;
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let percent = self.redeemed().min(items_available) as f64 * 100.0 / items_available as f64;
+
+        Some(percent)
+    }
+
+    async fn items_available(&self, ctx: &AppContext) -> FieldResult<Option<u64>> {
+        let data = ctx
+            .candy_machine_data_loader
+            .load(self.address.clone().into())
+            .await?;
+
+        Ok(data.map(|d| d.items_available.into()))
+    }
+}
+
+#[graphql_object(Context = AppContext)]
+impl CandyMachine {
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn authority(&self) -> &str {
+        &self.authority
+    }
+
+    pub fn wallet(&self) -> &str {
+        &self.wallet
+    }
+
+    #[graphql(description = "Mint of the SPL token accepted as payment, or null for SOL")]
+    pub fn token_mint(&self) -> Option<&str> {
+        self.token_mint.as_deref()
+    }
+
+    pub fn items_redeemed(&self) -> FieldResult<Volume> {
+        self.items_redeemed.try_into().map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Number of items left to mint, or null if the candy machine's \
+                             data is not indexed"
+    )]
+    pub async fn items_remaining(&self, ctx: &AppContext) -> FieldResult<Option<Volume>> {
+        Ok(self
+            .items_available(ctx)
+            .await?
+            .map(|a| self.items_remaining_of(a).into()))
+    }
+
+    #[graphql(
+        description = "Percentage of available items already minted, from 0 to 100, or \
+                             null if no items are available"
+    )]
+    pub async fn percent_minted(&self, ctx: &AppContext) -> FieldResult<Option<f64>> {
+        Ok(self
+            .items_available(ctx)
+            .await?
+            .and_then(|a| self.percent_minted_of(a)))
+    }
+
+    pub async fn data(&self, ctx: &AppContext) -> FieldResult<Option<CandyMachineData>> {
+        ctx.candy_machine_data_loader
+            .load(self.address.clone().into())
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn hidden_settings(
+        &self,
+        ctx: &AppContext,
+    ) -> FieldResult<Option<CandyMachineHiddenSettings>> {
+        ctx.candy_machine_hidden_settings_loader
+            .load(self.address.clone().into())
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn whitelist_mint_settings(
+        &self,
+        ctx: &AppContext,
+    ) -> FieldResult<Option<CandyMachineWhitelistMintSettings>> {
+        ctx.candy_machine_whitelist_mint_settings_loader
+            .load(self.address.clone().into())
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn gatekeeper_config(
+        &self,
+        ctx: &AppContext,
+    ) -> FieldResult<Option<CandyMachineGatekeeperConfig>> {
+        ctx.candy_machine_gatekeeper_config_loader
+            .load(self.address.clone().into())
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn end_settings(
+        &self,
+        ctx: &AppContext,
+    ) -> FieldResult<Option<CandyMachineEndSettings>> {
+        ctx.candy_machine_end_settings_loader
+            .load(self.address.clone().into())
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "Configuration of a candy machine")]
+pub struct CandyMachineData {
+    pub uuid: String,
+    #[graphql(description = "The price of a mint, in the candy machine's payment token")]
+    pub price: Lamports,
+    pub symbol: String,
+    pub seller_fee_basis_points: BasisPoints,
+    pub max_supply: Volume,
+    pub is_mutable: bool,
+    pub retain_authority: bool,
+    #[graphql(description = "When minting opens, or null if the go-live date is unset")]
+    pub go_live_date: Option<scalars::DateTime>,
+    pub items_available: Volume,
+}
+
+impl<'a> TryFrom<models::CandyMachineData<'a>> for CandyMachineData {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::CandyMachineData {
+            uuid,
+            price,
+            symbol,
+            seller_fee_basis_points,
+            max_supply,
+            is_mutable,
+            retain_authority,
+            go_live_date,
+            items_available,
+            ..
+        }: models::CandyMachineData,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            uuid: uuid.into_owned(),
+            price: price.try_into()?,
+            symbol: symbol.into_owned(),
+            seller_fee_basis_points: seller_fee_basis_points.try_into()?,
+            max_supply: max_supply.try_into()?,
+            is_mutable,
+            retain_authority,
+            go_live_date: go_live_date.and_then(scalars::DateTime::from_timestamp),
+            items_available: items_available.try_into()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "Settings for a candy machine minting a single hidden URI")]
+pub struct CandyMachineHiddenSettings {
+    pub name: String,
+    pub uri: String,
+    pub hash: String,
+}
+
+impl<'a> From<models::CMHiddenSetting<'a>> for CandyMachineHiddenSettings {
+    fn from(
+        models::CMHiddenSetting {
+            name, uri, hash, ..
+        }: models::CMHiddenSetting,
+    ) -> Self {
+        Self {
+            name: name.into_owned(),
+            uri: uri.into_owned(),
+            hash: String::from_utf8_lossy(&hash).into_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum CandyMachineWhitelistMintMode {
+    BurnEveryTime,
+    NeverBurn,
+}
+
+impl From<WhitelistMintMode> for CandyMachineWhitelistMintMode {
+    fn from(mode: WhitelistMintMode) -> Self {
+        match mode {
+            WhitelistMintMode::BurnEveryTime => Self::BurnEveryTime,
+            WhitelistMintMode::NeverBurn => Self::NeverBurn,
+        }
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "Settings for a candy machine's whitelist token")]
+pub struct CandyMachineWhitelistMintSettings {
+    pub mode: CandyMachineWhitelistMintMode,
+    pub mint: String,
+    pub presale: bool,
+    pub discount_price: Option<Lamports>,
+}
+
+impl<'a> TryFrom<models::CMWhitelistMintSetting<'a>> for CandyMachineWhitelistMintSettings {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::CMWhitelistMintSetting {
+            mode,
+            mint,
+            presale,
+            discount_price,
+            ..
+        }: models::CMWhitelistMintSetting,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            mode: mode.into(),
+            mint: mint.into_owned(),
+            presale,
+            discount_price: discount_price.map(TryInto::try_into).transpose()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "Settings for a candy machine's Civic gatekeeper")]
+pub struct CandyMachineGatekeeperConfig {
+    pub gatekeeper_network: String,
+    pub expire_on_use: bool,
+}
+
+impl<'a> From<models::CMGateKeeperConfig<'a>> for CandyMachineGatekeeperConfig {
+    fn from(
+        models::CMGateKeeperConfig {
+            gatekeeper_network,
+            expire_on_use,
+            ..
+        }: models::CMGateKeeperConfig,
+    ) -> Self {
+        Self {
+            gatekeeper_network: gatekeeper_network.into_owned(),
+            expire_on_use,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+pub enum CandyMachineEndSettingType {
+    Date,
+    Amount,
+}
+
+impl From<EndSettingType> for CandyMachineEndSettingType {
+    fn from(ty: EndSettingType) -> Self {
+        match ty {
+            EndSettingType::Date => Self::Date,
+            EndSettingType::Amount => Self::Amount,
+        }
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "Settings for when a candy machine stops minting")]
+pub struct CandyMachineEndSettings {
+    pub end_setting_type: CandyMachineEndSettingType,
+    #[graphql(description = "A unix timestamp or an item count, depending on the setting type")]
+    pub number: Volume,
+}
+
+impl<'a> TryFrom<models::CMEndSetting<'a>> for CandyMachineEndSettings {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::CMEndSetting {
+            end_setting_type,
+            number,
+            ..
+        }: models::CMEndSetting,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            end_setting_type: end_setting_type.into(),
+            number: number.try_into()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candy_machine(items_redeemed: i64) -> CandyMachine {
+        CandyMachine {
+            address: "candy_machine".into(),
+            authority: "authority".into(),
+            wallet: "wallet".into(),
+            token_mint: None,
+            items_redeemed,
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn percent_minted() {
+        let cm = candy_machine(40);
+
+        assert_eq!(cm.items_remaining_of(100), 60);
+        assert_eq!(cm.percent_minted_of(100), Some(40.0));
+        assert_eq!(cm.percent_minted_of(0), None);
+
+        assert_eq!(candy_machine(120).items_remaining_of(100), 0);
+        assert_eq!(candy_machine(120).percent_minted_of(100), Some(100.0));
+        assert_eq!(candy_machine(-1).percent_minted_of(100), Some(0.0));
+    }
+}
@@ -0,0 +1,156 @@
+//! Relay-style cursor pagination
+//!
+//! Cursors are opaque to clients, and encode the `(created_at, key)` keyset
+//! position of a row, where the key is unique among rows created at the same
+//! time.  Since rows are ordered by that tuple, a cursor continues to name the
+//! same position as rows are inserted, so paging never skips or repeats a
+//! row.
+
+use super::prelude::*;
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "Information about the current page of a connection")]
+pub struct PageInfo {
+    #[graphql(description = "Whether more rows follow this page")]
+    pub has_next_page: bool,
+    #[graphql(description = "The cursor of the last row in this page, if any")]
+    pub end_cursor: Option<String>,
+}
+
+/// Encode a keyset position as an opaque cursor
+#[must_use]
+pub fn encode_cursor(created_at: NaiveDateTime, key: &str) -> String {
+    base64::encode(format!(
+        "{}.{:09}:{}",
+        created_at.timestamp(),
+        created_at.timestamp_subsec_nanos(),
+        key
+    ))
+}
+
+/// Decode a cursor produced by [`encode_cursor`]
+#[must_use]
+pub fn parse_cursor(cursor: &str) -> Option<(NaiveDateTime, String)> {
+    let decoded = String::from_utf8(base64::decode(cursor).ok()?).ok()?;
+    let (created_at, key) = decoded.split_once(':')?;
+    let (secs, nanos) = created_at.split_once('.')?;
+
+    Some((
+        NaiveDateTime::from_timestamp_opt(secs.parse().ok()?, nanos.parse().ok()?)?,
+        key.to_owned(),
+    ))
+}
+
+/// Validate the `first` and `after` arguments of a connection field
+///
+/// On success, returns the number of rows to load and the decoded cursor.
+/// One more row than requested is loaded so [`paginate`] can tell whether
+/// another page follows.
+///
+/// # Errors
+/// This function fails if `first` is negative or `after` is not a valid
+/// cursor.
+pub fn parse_args(
+    first: Option<i32>,
+    default: i32,
+    after: Option<String>,
+) -> FieldResult<(i32, Option<(NaiveDateTime, String)>)> {
+    let first = first.unwrap_or(default);
+
+    if first < 0 {
+        return Err(FieldError::new(
+            "Invalid argument! first must not be negative",
+            graphql_value!({ "Arguments": "first: Int" }),
+        ));
+    }
+
+    let after = after
+        .map(|c| {
+            parse_cursor(&c).ok_or_else(|| {
+                FieldError::new(
+                    "Invalid argument! after is not a valid cursor",
+                    graphql_value!({ "Arguments": "after: String" }),
+                )
+            })
+        })
+        .transpose()?;
+
+    Ok((first.saturating_add(1), after))
+}
+
+/// Split rows loaded with the limit returned by [`parse_args`] into a page
+/// of rows paired with their cursors, and the page info describing it
+pub fn paginate<T>(
+    mut rows: Vec<T>,
+    limit: i32,
+    cursor: impl Fn(&T) -> String,
+) -> (Vec<(String, T)>, PageInfo) {
+    let first = usize::try_from(limit.saturating_sub(1)).unwrap_or(0);
+    let has_next_page = rows.len() > first;
+
+    rows.truncate(first);
+
+    let edges: Vec<_> = rows.into_iter().map(|r| (cursor(&r), r)).collect();
+    let end_cursor = edges.last().map(|(c, _)| c.clone());
+
+    (edges, PageInfo {
+        has_next_page,
+        end_cursor,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_round_trip() {
+        let created_at = NaiveDate::from_ymd(2022, 4, 1).and_hms_nano(12, 30, 0, 5_000);
+
+        for key in ["address", "address:bid", "address:bid_cancelled"] {
+            assert_eq!(
+                parse_cursor(&encode_cursor(created_at, key)),
+                Some((created_at, key.to_owned()))
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_cursors() {
+        assert_eq!(parse_cursor("not base64!"), None);
+        assert_eq!(parse_cursor(&base64::encode("1648816200:address")), None);
+        assert_eq!(
+            parse_cursor(&base64::encode("soon.000000000:address")),
+            None
+        );
+        assert_eq!(parse_cursor(&base64::encode("1648816200.000000000")), None);
+    }
+
+    #[test]
+    fn args() {
+        assert_eq!(parse_args(None, 25, None).unwrap(), (26, None));
+        assert_eq!(parse_args(Some(0), 25, None).unwrap(), (1, None));
+        assert!(parse_args(Some(-1), 25, None).is_err());
+        assert!(parse_args(Some(10), 25, Some("garbage".into())).is_err());
+    }
+
+    #[test]
+    fn pages() {
+        let (limit, _) = parse_args(Some(10), 25, None).unwrap();
+
+        let (edges, info) = paginate((0..11).collect(), limit, ToString::to_string);
+        assert_eq!(edges.len(), 10);
+        assert!(info.has_next_page);
+        assert_eq!(info.end_cursor.as_deref(), Some("9"));
+
+        let (edges, info) = paginate((0..5).collect(), limit, ToString::to_string);
+        assert_eq!(edges.len(), 5);
+        assert!(!info.has_next_page);
+        assert_eq!(info.end_cursor.as_deref(), Some("4"));
+
+        let (edges, info) = paginate(Vec::<i32>::new(), limit, ToString::to_string);
+        assert!(edges.is_empty());
+        assert!(!info.has_next_page);
+        assert_eq!(info.end_cursor, None);
+    }
+}
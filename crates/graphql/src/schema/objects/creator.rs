@@ -63,17 +63,24 @@ impl Creator {
         CreatorCounts::new(self.clone())
     }
 
-    #[graphql(arguments(auction_houses(description = "Auction house public keys")))]
+    #[graphql(arguments(
+        auction_houses(description = "Auction house public keys"),
+        secondary_only(
+            description = "Exclude listings whose primary sale has not happened from the floor",
+            default = true,
+        ),
+    ))]
     pub async fn stats(
         &self,
         auction_houses: Vec<PublicKey<AuctionHouse>>,
+        secondary_only: bool,
         ctx: &AppContext,
     ) -> FieldResult<Vec<MintStats>> {
-        let conn = ctx.shared.db.get()?;
+        let conn = ctx.pool("creatorStats").get()?;
         let rows = stats::collection(&conn, auction_houses, &self.address)?;
 
         rows.into_iter()
-            .map(TryInto::try_into)
+            .map(|r| MintStats::try_from(r).map(|s| s.secondary_only(secondary_only)))
             .collect::<Result<_, _>>()
             .map_err(Into::into)
     }
@@ -88,6 +95,7 @@ impl Creator {
             )
             .filter(metadata_creators::creator_address.eq(&self.address))
             .filter(metadata_creators::verified.eq(true))
+            .filter(attributes::trait_type.is_not_null())
             .select(attributes::all_columns)
             .load(&conn)
             .context("Failed to load metadata attributes")?;
@@ -98,22 +106,28 @@ impl Creator {
                 HashMap::new(),
                 |mut groups,
                  models::MetadataAttribute {
-                     trait_type, value, ..
+                     trait_type,
+                     value,
+                     trait_type_normalized,
+                     ..
                  }| {
+                    let trait_type = trait_type
+                        .ok_or_else(|| anyhow!("Missing trait type from attribute"))?
+                        .into_owned();
+                    let key =
+                        trait_type_normalized.map_or_else(|| trait_type.clone(), Cow::into_owned);
+
                     *groups
-                        .entry(
-                            trait_type
-                                .ok_or_else(|| anyhow!("Missing trait type from attribute"))?
-                                .into_owned(),
-                        )
-                        .or_insert_with(HashMap::new)
+                        .entry(key)
+                        .or_insert_with(|| (trait_type, HashMap::new()))
+                        .1
                         .entry(value)
                         .or_insert(0) += 1;
 
                     Result::<_>::Ok(groups)
                 },
             )?
-            .into_iter()
+            .into_values()
             .map(|(name, vars)| AttributeGroup {
                 name,
                 variants: vars
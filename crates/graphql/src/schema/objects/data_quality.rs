@@ -0,0 +1,57 @@
+use indexer_core::db::queries::data_quality;
+
+use super::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+#[graphql(description = "A kind of indexed data covered by consistency checks")]
+pub enum DataQualityScope {
+    CandyMachines,
+    Editions,
+    Creators,
+}
+
+impl From<DataQualityScope> for data_quality::Scope {
+    fn from(scope: DataQualityScope) -> Self {
+        match scope {
+            DataQualityScope::CandyMachines => Self::CandyMachines,
+            DataQualityScope::Editions => Self::Editions,
+            DataQualityScope::Creators => Self::Creators,
+        }
+    }
+}
+
+impl From<data_quality::Scope> for DataQualityScope {
+    fn from(scope: data_quality::Scope) -> Self {
+        match scope {
+            data_quality::Scope::CandyMachines => Self::CandyMachines,
+            data_quality::Scope::Editions => Self::Editions,
+            data_quality::Scope::Creators => Self::Creators,
+        }
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "An indexed account which failed a consistency check")]
+pub struct DataQualityAnomaly {
+    pub scope: DataQualityScope,
+    #[graphql(description = "The name of the invariant which failed")]
+    pub check: String,
+    pub address: String,
+    pub detail: String,
+}
+
+impl From<(&data_quality::Check, models::DataQualityAnomaly)> for DataQualityAnomaly {
+    fn from(
+        (check, models::DataQualityAnomaly { address, detail }): (
+            &data_quality::Check,
+            models::DataQualityAnomaly,
+        ),
+    ) -> Self {
+        Self {
+            scope: check.scope.into(),
+            check: check.name.into(),
+            address,
+            detail,
+        }
+    }
+}
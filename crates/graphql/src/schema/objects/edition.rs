@@ -0,0 +1,86 @@
+use scalars::{PublicKey, U64};
+
+use super::prelude::*;
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "A master edition, from which limited editions of an NFT may be printed")]
+pub struct MasterEdition {
+    pub address: PublicKey<MasterEdition>,
+    #[graphql(description = "Number of editions printed so far")]
+    pub supply: U64,
+    #[graphql(
+        description = "Maximum number of editions that may be printed, or null if unlimited"
+    )]
+    pub max_supply: Option<U64>,
+}
+
+impl<'a> TryFrom<models::MasterEdition<'a>> for MasterEdition {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::MasterEdition {
+            address,
+            supply,
+            max_supply,
+        }: models::MasterEdition,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            address: address.into_owned().into(),
+            supply: supply.try_into()?,
+            max_supply: max_supply.map(TryInto::try_into).transpose()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Edition {
+    pub address: PublicKey<Edition>,
+    pub parent: PublicKey<MasterEdition>,
+    pub edition: U64,
+}
+
+impl<'a> TryFrom<models::Edition<'a>> for Edition {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::Edition {
+            address,
+            parent_address,
+            edition,
+        }: models::Edition,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            address: address.into_owned().into(),
+            parent: parent_address.into_owned().into(),
+            edition: edition.try_into()?,
+        })
+    }
+}
+
+#[graphql_object(
+    Context = AppContext,
+    description = "A limited edition printed from a master edition"
+)]
+impl Edition {
+    fn address(&self) -> &PublicKey<Edition> {
+        &self.address
+    }
+
+    #[graphql(description = "Ordinal of this edition among those printed from its master edition")]
+    fn edition(&self) -> U64 {
+        self.edition
+    }
+
+    #[graphql(description = "Address of the master edition this edition was printed from")]
+    fn parent(&self) -> &PublicKey<MasterEdition> {
+        &self.parent
+    }
+
+    #[graphql(description = "The master edition this edition was printed from")]
+    async fn master_edition(&self, ctx: &AppContext) -> FieldResult<Option<MasterEdition>> {
+        ctx.master_edition_loader
+            .load(self.parent.clone())
+            .await
+            .map_err(Into::into)
+    }
+}
@@ -0,0 +1,192 @@
+use objects::{
+    locker::{Locker, LockerParams},
+    wallet::Wallet,
+};
+use scalars::{DateTime, U64};
+
+use super::prelude::*;
+
+#[derive(Debug, Clone)]
+pub struct Escrow {
+    pub address: String,
+    pub locker: String,
+    pub owner: String,
+    pub amount: u64,
+    pub escrow_started_at: i64,
+    pub escrow_ends_at: i64,
+    pub vote_delegate: String,
+}
+
+impl Escrow {
+    /// Compute the voting power of this escrow at the given Unix time, as in
+    /// the Tribeca locked-voter program: the staked amount scaled by the
+    /// locker's maximum vote multiplier, in proportion to how much of the
+    /// maximum stake duration remains before the escrow unlocks.  Escrows
+    /// which have not started or have already unlocked have no power.
+    #[must_use]
+    pub fn voting_power_at(&self, params: &LockerParams, now: i64) -> u64 {
+        let max_duration = u64::from(params.max_stake_duration);
+
+        if now < self.escrow_started_at || self.escrow_ends_at <= now || max_duration == 0 {
+            return 0;
+        }
+
+        let remaining = u64::try_from(self.escrow_ends_at - now)
+            .unwrap_or(0)
+            .min(max_duration);
+        let multiplier = u64::try_from(params.max_stake_vote_multiplier).unwrap_or(0);
+
+        (u128::from(self.amount) * u128::from(multiplier) * u128::from(remaining)
+            / u128::from(max_duration))
+        .try_into()
+        .unwrap_or(u64::MAX)
+    }
+}
+
+#[graphql_object(Context = AppContext, description = "Tokens staked in a Tribeca locker")]
+impl Escrow {
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn locker_address(&self) -> &str {
+        &self.locker
+    }
+
+    pub async fn locker(&self, ctx: &AppContext) -> FieldResult<Option<Locker>> {
+        ctx.locker_loader
+            .load(self.locker.clone().into())
+            .await
+            .map_err(Into::into)
+    }
+
+    pub fn owner(&self) -> Wallet {
+        Wallet::new(self.owner.clone().into(), None)
+    }
+
+    #[graphql(description = "Number of tokens staked")]
+    pub fn amount(&self) -> U64 {
+        self.amount.into()
+    }
+
+    pub fn escrow_started_at(&self) -> Option<DateTime> {
+        DateTime::from_timestamp(self.escrow_started_at)
+    }
+
+    #[graphql(description = "When the staked tokens unlock")]
+    pub fn escrow_ends_at(&self) -> Option<DateTime> {
+        DateTime::from_timestamp(self.escrow_ends_at)
+    }
+
+    pub fn vote_delegate(&self) -> &str {
+        &self.vote_delegate
+    }
+
+    #[graphql(
+        description = "Current vote weight of this escrow, or null if its locker is not \
+                             indexed"
+    )]
+    pub async fn voting_power(&self, ctx: &AppContext) -> FieldResult<Option<U64>> {
+        let locker = ctx.locker_loader.load(self.locker.clone().into()).await?;
+
+        Ok(locker
+            .and_then(|l| l.params)
+            .map(|p| self.voting_power_at(&p, Utc::now().timestamp()).into()))
+    }
+}
+
+impl<'a> TryFrom<models::Escrow<'a>> for Escrow {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::Escrow {
+            address,
+            locker,
+            owner,
+            amount,
+            escrow_started_at,
+            escrow_ends_at,
+            vote_delegate,
+            ..
+        }: models::Escrow,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            address: address.into_owned(),
+            locker: locker.into_owned(),
+            owner: owner.into_owned(),
+            amount: amount.into(),
+            escrow_started_at,
+            escrow_ends_at,
+            vote_delegate: vote_delegate.into_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN_DURATION: u64 = 100;
+    const MAX_DURATION: u64 = 1000;
+    const NOW: i64 = 1_650_000_000;
+
+    fn params(max_stake_duration: u64) -> LockerParams {
+        LockerParams {
+            whitelist_enabled: false,
+            max_stake_vote_multiplier: 10,
+            min_stake_duration: MIN_DURATION.into(),
+            max_stake_duration: max_stake_duration.into(),
+            proposal_activation_min_votes: 0.into(),
+        }
+    }
+
+    fn escrow(amount: u64, locked_for: i64) -> Escrow {
+        Escrow {
+            address: "escrow".into(),
+            locker: "locker".into(),
+            owner: "owner".into(),
+            amount,
+            escrow_started_at: NOW - 10,
+            escrow_ends_at: NOW + locked_for,
+            vote_delegate: "owner".into(),
+        }
+    }
+
+    fn power(amount: u64, locked_for: i64) -> u64 {
+        escrow(amount, locked_for).voting_power_at(&params(MAX_DURATION), NOW)
+    }
+
+    #[test]
+    fn multiplier_scales_with_remaining_duration() {
+        let max = i64::try_from(MAX_DURATION).unwrap();
+        let min = i64::try_from(MIN_DURATION).unwrap();
+
+        assert_eq!(power(1000, max), 10_000);
+        assert_eq!(power(1000, max * 4), 10_000);
+        assert_eq!(power(1000, max / 2), 5000);
+        assert_eq!(power(1000, min), 1000);
+        assert_eq!(power(1000, 1), 10);
+    }
+
+    #[test]
+    fn inactive_escrows_have_no_power() {
+        assert_eq!(power(1000, 0), 0);
+        assert_eq!(power(1000, -1), 0);
+
+        let not_started = Escrow {
+            escrow_started_at: NOW + 1,
+            ..escrow(1000, 500)
+        };
+        assert_eq!(not_started.voting_power_at(&params(MAX_DURATION), NOW), 0);
+
+        assert_eq!(escrow(1000, 500).voting_power_at(&params(0), NOW), 0);
+    }
+
+    #[test]
+    fn power_saturates() {
+        assert_eq!(
+            power(u64::MAX, i64::try_from(MAX_DURATION).unwrap()),
+            u64::MAX
+        );
+    }
+}
@@ -3,6 +3,15 @@ use objects::wallet::Wallet;
 
 use super::prelude::*;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+#[graphql(description = "Which side of a wallet's graph connections to list")]
+pub enum ConnectionDirection {
+    /// Connections from the wallet to wallets it follows
+    Following,
+    /// Connections to the wallet from wallets following it
+    Followers,
+}
+
 #[derive(Debug, Clone)]
 pub struct GraphConnection {
     pub address: String,
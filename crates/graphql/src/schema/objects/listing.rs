@@ -97,7 +97,7 @@ pub struct Listing {
     pub cache_address: String,
     pub store_address: String,
     pub token_mint: Option<String>,
-    pub ends_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<scalars::DateTime>,
     pub ended: bool,
 }
 
@@ -132,7 +132,7 @@ impl Listing {
             cache_address,
             store_address,
             token_mint,
-            ends_at: ends_at.map(|t| DateTime::from_utc(t, Utc)),
+            ends_at: ends_at.map(Into::into),
             ended,
         })
     }
@@ -156,7 +156,7 @@ impl Listing {
         &self.store_address
     }
 
-    pub fn ends_at(&self) -> Option<DateTime<Utc>> {
+    pub fn ends_at(&self) -> Option<scalars::DateTime> {
         self.ends_at
     }
 
@@ -1,23 +1,111 @@
+use indexer_core::db::queries;
+use objects::auction_house;
+use scalars::{DateTime, Lamports};
+
 use super::prelude::*;
 
-#[derive(Debug, Clone, GraphQLObject)]
-#[graphql(description = "An NFT listing receipt")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+#[graphql(description = "Order in which to return listings")]
+pub enum ListingSort {
+    /// Cheapest listings first
+    PriceAsc,
+    /// Most expensive listings first
+    PriceDesc,
+    /// Most recently created listings first
+    Newest,
+}
+
+impl From<ListingSort> for queries::listing_receipts::ListingSort {
+    fn from(s: ListingSort) -> Self {
+        match s {
+            ListingSort::PriceAsc => Self::PriceAsc,
+            ListingSort::PriceDesc => Self::PriceDesc,
+            ListingSort::Newest => Self::Newest,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// An NFT listing receipt
 pub struct ListingReceipt {
     pub address: String,
     pub trade_state: String,
     pub seller: String,
     pub metadata: String,
     pub auction_house: String,
-    pub price: scalars::Lamports,
+    pub price: Lamports,
     pub trade_state_bump: i32,
-    pub created_at: DateTime<Utc>,
-    pub canceled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime,
+    pub canceled_at: Option<DateTime>,
     pub bookkeeper: String,
     pub purchase_receipt: Option<String>,
     pub token_size: i32,
     pub bump: i32,
 }
 
+#[graphql_object(Context = AppContext, description = "An NFT listing receipt")]
+impl ListingReceipt {
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn trade_state(&self) -> &str {
+        &self.trade_state
+    }
+
+    pub fn seller(&self) -> &str {
+        &self.seller
+    }
+
+    pub fn metadata(&self) -> &str {
+        &self.metadata
+    }
+
+    pub fn auction_house(&self) -> &str {
+        &self.auction_house
+    }
+
+    pub fn price(&self) -> Lamports {
+        self.price
+    }
+
+    #[graphql(
+        description = "Price in whole units of the auction house's treasury token, or \
+                             null if the decimals of the treasury mint are not indexed"
+    )]
+    pub async fn price_ui(&self, ctx: &AppContext) -> FieldResult<Option<f64>> {
+        auction_house::ui_price(ctx, &self.auction_house, self.price).await
+    }
+
+    pub fn trade_state_bump(&self) -> i32 {
+        self.trade_state_bump
+    }
+
+    pub fn created_at(&self) -> DateTime {
+        self.created_at
+    }
+
+    pub fn canceled_at(&self) -> Option<DateTime> {
+        self.canceled_at
+    }
+
+    pub fn bookkeeper(&self) -> &str {
+        &self.bookkeeper
+    }
+
+    pub fn purchase_receipt(&self) -> Option<&str> {
+        self.purchase_receipt.as_deref()
+    }
+
+    pub fn token_size(&self) -> i32 {
+        self.token_size
+    }
+
+    pub fn bump(&self) -> i32 {
+        self.bump
+    }
+}
+
 impl<'a> TryFrom<models::ListingReceipt<'a>> for ListingReceipt {
     type Error = std::num::TryFromIntError;
 
@@ -46,8 +134,8 @@ impl<'a> TryFrom<models::ListingReceipt<'a>> for ListingReceipt {
             auction_house: auction_house.into_owned(),
             price: price.try_into()?,
             trade_state_bump: trade_state_bump.into(),
-            created_at: DateTime::from_utc(created_at, Utc),
-            canceled_at: canceled_at.map(|c| DateTime::from_utc(c, Utc)),
+            created_at: created_at.into(),
+            canceled_at: canceled_at.map(Into::into),
             bookkeeper: bookkeeper.into_owned(),
             purchase_receipt: purchase_receipt.map(Cow::into_owned),
             token_size: token_size.try_into()?,
@@ -0,0 +1,78 @@
+use scalars::U64;
+
+use super::prelude::*;
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "Staking parameters of a Tribeca locker")]
+pub struct LockerParams {
+    pub whitelist_enabled: bool,
+    #[graphql(description = "Multiplier applied to the vote weight of a maximum-length stake")]
+    pub max_stake_vote_multiplier: i32,
+    #[graphql(description = "Minimum stake duration, in seconds")]
+    pub min_stake_duration: U64,
+    #[graphql(description = "Maximum stake duration, in seconds")]
+    pub max_stake_duration: U64,
+    pub proposal_activation_min_votes: U64,
+}
+
+impl<'a> TryFrom<models::LockerParam<'a>> for LockerParams {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::LockerParam {
+            whitelist_enabled,
+            max_stake_vote_multiplier,
+            min_stake_duration,
+            max_stake_duration,
+            proposal_activation_min_votes,
+            ..
+        }: models::LockerParam,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            whitelist_enabled,
+            max_stake_vote_multiplier: max_stake_vote_multiplier.into(),
+            min_stake_duration: min_stake_duration.try_into()?,
+            max_stake_duration: max_stake_duration.try_into()?,
+            proposal_activation_min_votes: proposal_activation_min_votes.try_into()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "A Tribeca locker, holding the tokens staked for a governor")]
+pub struct Locker {
+    pub address: String,
+    pub base: String,
+    pub token_mint: String,
+    #[graphql(description = "Total number of tokens locked in this locker's escrows")]
+    pub locked_supply: U64,
+    pub governor: String,
+    pub params: Option<LockerParams>,
+}
+
+impl<'a> TryFrom<(models::Locker<'a>, Option<models::LockerParam<'a>>)> for Locker {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        (
+            models::Locker {
+                address,
+                base,
+                token_mint,
+                locked_supply,
+                governor,
+                ..
+            },
+            params,
+        ): (models::Locker, Option<models::LockerParam>),
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            address: address.into_owned(),
+            base: base.into_owned(),
+            token_mint: token_mint.into_owned(),
+            locked_supply: locked_supply.into(),
+            governor: governor.into_owned(),
+            params: params.map(TryInto::try_into).transpose()?,
+        })
+    }
+}
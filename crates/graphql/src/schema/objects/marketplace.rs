@@ -1,6 +1,7 @@
 use objects::{auction_house::AuctionHouse, stats::MarketStats, store_creator::StoreCreator};
 
 use super::prelude::*;
+use crate::asset_proxy::{self, ImageTransform};
 
 #[derive(Debug, Clone)]
 /// An Holaplex marketplace
@@ -14,21 +15,25 @@ pub struct Marketplace {
     pub owner_address: String,
     pub auction_house_address: String,
     pub store_address: Option<String>,
+    pub settings_uri: Option<String>,
 }
 
-impl<'a> From<models::StoreConfigJson<'a>> for Marketplace {
+impl<'a> From<(models::StoreConfigJson<'a>, Option<String>)> for Marketplace {
     fn from(
-        models::StoreConfigJson {
-            config_address,
-            name,
-            description,
-            logo_url,
-            banner_url,
-            subdomain,
-            owner_address,
-            auction_house_address,
-            store_address,
-        }: models::StoreConfigJson,
+        (
+            models::StoreConfigJson {
+                config_address,
+                name,
+                description,
+                logo_url,
+                banner_url,
+                subdomain,
+                owner_address,
+                auction_house_address,
+                store_address,
+            },
+            settings_uri,
+        ): (models::StoreConfigJson, Option<String>),
     ) -> Self {
         Self {
             config_address: config_address.into_owned(),
@@ -40,11 +45,12 @@ impl<'a> From<models::StoreConfigJson<'a>> for Marketplace {
             owner_address: owner_address.into_owned(),
             auction_house_address: auction_house_address.into_owned(),
             store_address: store_address.map(Cow::into_owned),
+            settings_uri,
         }
     }
 }
 
-#[graphql_object(Context = AppContext)]
+#[graphql_object(Context = AppContext, description = "A Holaplex marketplace")]
 impl Marketplace {
     pub fn config_address(&self) -> &str {
         &self.config_address
@@ -62,12 +68,14 @@ impl Marketplace {
         &self.description
     }
 
-    pub fn logo_url(&self) -> &str {
-        &self.logo_url
+    pub fn logo_url(&self, ctx: &AppContext) -> FieldResult<String> {
+        asset_proxy::proxy_image(&ctx.shared, &self.logo_url, &ImageTransform::default())
+            .map_err(Into::into)
     }
 
-    pub fn banner_url(&self) -> &str {
-        &self.banner_url
+    pub fn banner_url(&self, ctx: &AppContext) -> FieldResult<String> {
+        asset_proxy::proxy_image(&ctx.shared, &self.banner_url, &ImageTransform::default())
+            .map_err(Into::into)
     }
 
     pub fn owner_address(&self) -> &str {
@@ -82,6 +90,11 @@ impl Marketplace {
         self.store_address.as_deref()
     }
 
+    #[graphql(description = "URI of the marketplace settings, from its store config account")]
+    pub fn settings_uri(&self) -> Option<&str> {
+        self.settings_uri.as_deref()
+    }
+
     pub async fn auction_house(&self, context: &AppContext) -> FieldResult<Option<AuctionHouse>> {
         context
             .auction_house_loader
@@ -90,6 +103,7 @@ impl Marketplace {
             .map_err(Into::into)
     }
 
+    #[graphql(description = "Creators whitelisted to sell in this marketplace")]
     pub async fn creators(&self, context: &AppContext) -> FieldResult<Vec<StoreCreator>> {
         context
             .store_creator_loader
@@ -105,3 +119,109 @@ impl Marketplace {
             .map_err(Into::into)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use indexer_core::db::{
+        insert_into,
+        tables::{auction_houses, store_config_jsons, store_creators},
+        testing,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{context, execute};
+
+    fn creator(config: &'static str, creator: &'static str) -> models::StoreCreator<'static> {
+        models::StoreCreator {
+            store_config_address: Borrowed(config),
+            creator_address: Borrowed(creator),
+        }
+    }
+
+    #[test]
+    fn marketplace_with_three_creators() {
+        let (ctx, db) = match context() {
+            Some(c) => c,
+            None => return,
+        };
+        let conn = db.get().unwrap();
+
+        insert_into(store_config_jsons::table)
+            .values(&models::StoreConfigJson {
+                config_address: Borrowed("config"),
+                name: Borrowed("Market"),
+                description: Borrowed("A marketplace"),
+                logo_url: Borrowed(""),
+                banner_url: Borrowed(""),
+                subdomain: Borrowed("market"),
+                owner_address: Borrowed("owner"),
+                auction_house_address: Borrowed("house"),
+                store_address: None,
+            })
+            .execute(&conn)
+            .unwrap();
+
+        insert_into(auction_houses::table)
+            .values(&testing::auction_house("house", "sol"))
+            .execute(&conn)
+            .unwrap();
+
+        insert_into(store_creators::table)
+            .values(&[
+                creator("config", "carol"),
+                creator("config", "alice"),
+                creator("config", "bob"),
+                creator("other", "dave"),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        let mut data = execute(
+            &ctx,
+            r#"{
+                marketplace(subdomain: "market") {
+                    configAddress
+                    name
+                    settingsUri
+                    auctionHouse { address treasuryMint }
+                    creators { storeConfigAddress creatorAddress }
+                }
+            }"#,
+        );
+
+        let creators = data["marketplace"]
+            .as_object_mut()
+            .unwrap()
+            .remove("creators")
+            .unwrap();
+        let mut creators: Vec<_> = creators
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| {
+                assert_eq!(c["storeConfigAddress"], "config");
+                c["creatorAddress"].as_str().unwrap().to_owned()
+            })
+            .collect();
+        creators.sort();
+
+        assert_eq!(creators, vec!["alice", "bob", "carol"]);
+        assert_eq!(
+            data,
+            json!({
+                "marketplace": {
+                    "configAddress": "config",
+                    "name": "Market",
+                    "settingsUri": null,
+                    "auctionHouse": { "address": "house", "treasuryMint": "sol" },
+                },
+            })
+        );
+
+        assert_eq!(
+            execute(&ctx, r#"{ marketplace(subdomain: "missing") { name } }"#),
+            json!({ "marketplace": null })
+        );
+    }
+}
@@ -1,17 +1,29 @@
 pub mod auction_house;
 pub mod bid_receipt;
+pub mod candy_machine;
+pub mod connection;
 pub mod creator;
+pub mod data_quality;
 pub mod denylist;
+pub mod edition;
+pub mod escrow;
 pub mod graph_connection;
 pub mod listing;
 pub mod listing_receipt;
+pub mod locker;
 pub mod marketplace;
 pub mod nft;
+pub mod ownership;
 pub mod profile;
+pub mod proposal;
 pub mod purchase_receipt;
+pub mod search;
+pub mod smart_wallet;
 pub mod stats;
 pub mod store_creator;
 pub mod storefront;
+pub mod token_holder;
+pub mod vote;
 pub mod wallet;
 
 pub(self) mod prelude {
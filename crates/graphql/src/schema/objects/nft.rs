@@ -1,16 +1,57 @@
-use base64::display::Base64Display;
 use indexer_core::{
-    assets::{AssetHint, AssetIdentifier, ImageSize},
-    db::queries,
+    assets::ImageSize,
+    db::{custom_types::TokenStandardEnum, queries},
 };
 use objects::{
-    auction_house::AuctionHouse, bid_receipt::BidReceipt, listing_receipt::ListingReceipt,
-    profile::TwitterProfile, purchase_receipt::PurchaseReceipt,
+    auction_house::AuctionHouse,
+    bid_receipt::BidReceipt,
+    connection::PageInfo,
+    edition::{Edition, MasterEdition},
+    listing_receipt::ListingReceipt,
+    profile::TwitterProfile,
+    purchase_receipt::PurchaseReceipt,
 };
 use reqwest::Url;
-use scalars::PublicKey;
+use scalars::{BasisPoints, PublicKey};
 
 use super::prelude::*;
+use crate::asset_proxy::{ImageFormat, ImageTransform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+#[graphql(description = "The Metaplex token standard of an NFT")]
+pub enum TokenStandard {
+    /// A master edition
+    NonFungible,
+    /// A token with metadata that may also have attributes, sometimes called
+    /// semi-fungible
+    FungibleAsset,
+    /// A token with simple metadata
+    Fungible,
+    /// A limited edition printed from a master edition
+    NonFungibleEdition,
+}
+
+impl From<TokenStandardEnum> for TokenStandard {
+    fn from(standard: TokenStandardEnum) -> Self {
+        match standard {
+            TokenStandardEnum::NonFungible => Self::NonFungible,
+            TokenStandardEnum::FungibleAsset => Self::FungibleAsset,
+            TokenStandardEnum::Fungible => Self::Fungible,
+            TokenStandardEnum::NonFungibleEdition => Self::NonFungibleEdition,
+        }
+    }
+}
+
+impl From<TokenStandard> for TokenStandardEnum {
+    fn from(standard: TokenStandard) -> Self {
+        match standard {
+            TokenStandard::NonFungible => Self::NonFungible,
+            TokenStandard::FungibleAsset => Self::FungibleAsset,
+            TokenStandard::Fungible => Self::Fungible,
+            TokenStandard::NonFungibleEdition => Self::NonFungibleEdition,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct NftAttribute {
@@ -82,6 +123,7 @@ impl NftCreator {
         self.share
     }
 
+    #[graphql(description = "Whether this creator has signed the NFT's metadata")]
     pub fn verified(&self) -> bool {
         self.verified
     }
@@ -131,6 +173,20 @@ impl<'a> From<(Option<String>, models::MetadataCreator<'a>)> for NftCreator {
     }
 }
 
+/// Rich media details of an NFT, read from its metadata JSON
+#[derive(Debug, Clone)]
+pub struct NftMedia {
+    pub category: Option<String>,
+    pub animation_url: Option<String>,
+}
+
+/// Trait rarity of an NFT within its collection
+#[derive(Debug, Clone, Copy)]
+pub struct NftRarity {
+    pub score: f64,
+    pub rank: i64,
+}
+
 #[derive(Debug, Clone)]
 pub struct NftOwner {
     pub address: String,
@@ -171,7 +227,7 @@ pub struct NftActivity {
     pub metadata: String,
     pub auction_house: String,
     pub price: scalars::Lamports,
-    pub created_at: DateTime<Utc>,
+    pub created_at: scalars::DateTime,
     pub wallets: Vec<String>,
     pub activity_type: String,
 }
@@ -195,13 +251,46 @@ impl TryFrom<models::NftActivity> for NftActivity {
             metadata,
             auction_house,
             price: price.try_into()?,
-            created_at: DateTime::from_utc(created_at, Utc),
+            created_at: created_at.into(),
             wallets,
             activity_type,
         })
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+#[graphql(description = "A kind of NFT activity")]
+pub enum ActivityType {
+    Listing,
+    Purchase,
+    Bid,
+    BidCancelled,
+}
+
+impl From<ActivityType> for queries::metadatas::ActivityType {
+    fn from(t: ActivityType) -> Self {
+        match t {
+            ActivityType::Listing => Self::Listing,
+            ActivityType::Purchase => Self::Purchase,
+            ActivityType::Bid => Self::Bid,
+            ActivityType::BidCancelled => Self::BidCancelled,
+        }
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+pub struct NftActivityEdge {
+    pub cursor: String,
+    pub node: NftActivity,
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "A page of NFT activity")]
+pub struct NftActivityConnection {
+    pub edges: Vec<NftActivityEdge>,
+    pub page_info: PageInfo,
+}
+
 #[derive(Debug, Clone)]
 /// An NFT
 pub struct Nft {
@@ -238,6 +327,19 @@ impl From<models::Nft> for Nft {
     }
 }
 
+impl Nft {
+    async fn collection_nft(&self, ctx: &AppContext, verified: bool) -> FieldResult<Option<Nft>> {
+        let collections = ctx
+            .nft_collections_loader
+            .load(self.address.clone().into())
+            .await?;
+
+        Ok(collections
+            .into_iter()
+            .find_map(|(v, nft)| if v == verified { Some(nft) } else { None }))
+    }
+}
+
 #[graphql_object(Context = AppContext)]
 impl Nft {
     pub fn address(&self) -> &str {
@@ -248,8 +350,15 @@ impl Nft {
         &self.name
     }
 
-    pub fn seller_fee_basis_points(&self) -> i32 {
-        self.seller_fee_basis_points
+    pub fn seller_fee_basis_points(&self) -> FieldResult<BasisPoints> {
+        self.seller_fee_basis_points.try_into().map_err(Into::into)
+    }
+
+    #[graphql(description = "The seller fee as a percentage, from 0.0 to 100.0")]
+    pub fn seller_fee_percentage(&self) -> FieldResult<f64> {
+        BasisPoints::try_from(self.seller_fee_basis_points)
+            .map(BasisPoints::percentage)
+            .map_err(Into::into)
     }
 
     pub fn mint_address(&self) -> &str {
@@ -264,7 +373,8 @@ impl Nft {
         &self.description
     }
 
-    #[graphql(arguments(width(description = r"Image width possible values are:
+    #[graphql(arguments(
+        width(description = r"Image width possible values are:
 - 0 (Original size)
 - 100 (Tiny)
 - 400 (XSmall)
@@ -274,79 +384,110 @@ impl Nft {
 
 Any other value will return the original image size.
 
-If no value is provided, it will return XSmall")))]
-    pub fn image(&self, width: Option<i32>, ctx: &AppContext) -> FieldResult<String> {
-        fn format_cdn_url<'a>(
-            shared: &SharedData,
-            id: &AssetIdentifier,
-            hint: AssetHint,
-            path: impl IntoIterator<Item = &'a str>,
-            query: impl IntoIterator<Item = (&'a str, &'a str)>,
-        ) -> Url {
-            let rem = md5::compute(
-                id.fingerprint(Some(hint))
-                    .unwrap_or_else(|| unreachable!())
-                    .as_ref(),
-            )[0]
-            .rem_euclid(shared.asset_proxy_count);
-            let assets_cdn = &shared.asset_proxy_endpoint;
-
-            let mut url = Url::parse(&assets_cdn.replace(
-                "[n]",
-                &if rem == 0 {
-                    String::new()
-                } else {
-                    rem.to_string()
-                },
-            ))
-            .unwrap_or_else(|_| unreachable!());
-
-            url.path_segments_mut()
-                .unwrap_or_else(|_| unreachable!())
-                .extend(path);
-            url.query_pairs_mut().extend_pairs(query);
-
-            url
-        }
-
+If no value is provided, it will return XSmall"),
+        height(
+            description = "Image height, at most 2048.  If no value is provided, the \
+                              aspect ratio of the original image is preserved"
+        ),
+        format(
+            description = "Image encoding.  If no value is provided, the original \
+                              encoding is preserved"
+        )
+    ))]
+    pub fn image(
+        &self,
+        width: Option<i32>,
+        height: Option<i32>,
+        format: Option<ImageFormat>,
+        ctx: &AppContext,
+    ) -> FieldResult<String> {
         let width = ImageSize::from(width.unwrap_or(ImageSize::XSmall as i32));
-        let width_str = (width as i32).to_string();
-        let id =
-            AssetIdentifier::new(&Url::parse(&self.image).context("Couldn't parse asset URL")?);
-
-        Ok(match (id.arweave, &id.ipfs) {
-            (Some(_), Some(_)) | (None, None) => self.image.clone(),
-            (Some(txid), None) => {
-                let txid = Base64Display::with_config(&txid.0, base64::URL_SAFE_NO_PAD).to_string();
-
-                format_cdn_url(
-                    &ctx.shared,
-                    &id,
-                    AssetHint::Arweave,
-                    ["arweave", &txid],
-                    Some(("width", &*width_str)),
-                )
-                .to_string()
-            },
-            (None, Some((cid, path))) => {
-                let cid = cid.to_string();
-
-                format_cdn_url(
-                    &ctx.shared,
-                    &id,
-                    AssetHint::Ipfs,
-                    ["ipfs", &cid],
-                    Some(("width", &*width_str))
-                        .into_iter()
-                        .chain(if path.is_empty() {
-                            None
-                        } else {
-                            Some(("path", &**path))
-                        }),
-                )
-                .to_string()
-            },
-        })
+        let height = height
+            .map(|h| {
+                u32::try_from(h).map_err(|_| {
+                    FieldError::new(
+                        "Invalid argument! height must not be negative",
+                        graphql_value!({ "Arguments": "height: Int" }),
+                    )
+                })
+            })
+            .transpose()?;
+        let transform = ImageTransform {
+            width: Some((width as i32).try_into().unwrap_or_else(|_| unreachable!())),
+            height,
+            format,
+        };
+        let url = Url::parse(&self.image).context("Couldn't parse asset URL")?;
+
+        Ok(asset_proxy::proxy_asset(
+            &ctx.shared.asset_proxy_endpoint,
+            ctx.shared.asset_proxy_count,
+            &url,
+            &transform,
+        )?
+        .map_or_else(|| self.image.clone(), |u| u.to_string()))
+    }
+
+    #[graphql(
+        description = "The category of the NFT's media, e.g. `image`, `video`, `audio`, \
+                             `vr`, or `html`, as given in its metadata JSON or inferred from \
+                             its files"
+    )]
+    pub async fn category(&self, ctx: &AppContext) -> FieldResult<Option<String>> {
+        let media = ctx
+            .nft_media_loader
+            .load(self.address.clone().into())
+            .await?;
+
+        Ok(media.and_then(|m| m.category))
+    }
+
+    #[graphql(
+        description = "URL of the NFT's animation or other rich media, served through \
+                             the asset proxy if it is hosted on Arweave or IPFS"
+    )]
+    pub async fn animation_url(&self, ctx: &AppContext) -> FieldResult<Option<String>> {
+        let media = ctx
+            .nft_media_loader
+            .load(self.address.clone().into())
+            .await?;
+
+        media
+            .and_then(|m| m.animation_url)
+            .map(|u| asset_proxy::proxy_image(&ctx.shared, &u, &ImageTransform::default()))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "The NFT's trait rarity score within the collection identified by its \
+                             first verified creator.  Each attribute scores the collection size \
+                             divided by the number of NFTs sharing it, and higher scores are \
+                             rarer."
+    )]
+    pub async fn rarity_score(&self, ctx: &AppContext) -> FieldResult<Option<f64>> {
+        let rarity = ctx
+            .nft_rarity_loader
+            .load(self.address.clone().into())
+            .await?;
+
+        Ok(rarity.map(|r| r.score))
+    }
+
+    #[graphql(
+        description = "The one-based rank of the NFT's rarity score within its collection, \
+                             rarest first.  NFTs with equal scores share a rank."
+    )]
+    pub async fn rarity_rank(&self, ctx: &AppContext) -> FieldResult<Option<i32>> {
+        let rarity = ctx
+            .nft_rarity_loader
+            .load(self.address.clone().into())
+            .await?;
+
+        rarity
+            .map(|r| r.rank.try_into())
+            .transpose()
+            .map_err(Into::into)
     }
 
     pub async fn creators(&self, ctx: &AppContext) -> FieldResult<Vec<NftCreator>> {
@@ -356,6 +497,22 @@ If no value is provided, it will return XSmall")))]
             .map_err(Into::into)
     }
 
+    #[graphql(description = "The verified creator with the lowest position")]
+    pub async fn first_verified_creator(
+        &self,
+        ctx: &AppContext,
+    ) -> FieldResult<Option<NftCreator>> {
+        Ok(ctx
+            .nft_creators_loader
+            .load(self.address.clone().into())
+            .await?
+            .into_iter()
+            .find(|c| c.verified))
+    }
+
+    #[graphql(
+        description = "Off-chain attributes, as matched by the `attributes` filter of `nfts`"
+    )]
     pub async fn attributes(&self, ctx: &AppContext) -> FieldResult<Vec<NftAttribute>> {
         ctx.nft_attributes_loader
             .load(self.address.clone().into())
@@ -363,6 +520,46 @@ If no value is provided, it will return XSmall")))]
             .map_err(Into::into)
     }
 
+    #[graphql(description = "The master edition of the NFT, if it is one")]
+    pub async fn master_edition(&self, ctx: &AppContext) -> FieldResult<Option<MasterEdition>> {
+        ctx.nft_master_edition_loader
+            .load(self.address.clone().into())
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "The collection NFT of the collection this NFT is a verified \
+                             member of"
+    )]
+    pub async fn collection(&self, ctx: &AppContext) -> FieldResult<Option<Nft>> {
+        self.collection_nft(ctx, true).await
+    }
+
+    #[graphql(
+        description = "The collection NFT named by this NFT's metadata, if its \
+                             membership has not been verified by the collection's authority"
+    )]
+    pub async fn unverified_collection(&self, ctx: &AppContext) -> FieldResult<Option<Nft>> {
+        self.collection_nft(ctx, false).await
+    }
+
+    #[graphql(description = "The edition of the NFT, if it was printed from a master edition")]
+    pub async fn edition(&self, ctx: &AppContext) -> FieldResult<Option<Edition>> {
+        ctx.nft_edition_loader
+            .load(self.address.clone().into())
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "The token standard of the NFT, if it is known")]
+    pub async fn token_standard(&self, ctx: &AppContext) -> FieldResult<Option<TokenStandard>> {
+        ctx.nft_token_standard_loader
+            .load(self.address.clone().into())
+            .await
+            .map_err(Into::into)
+    }
+
     pub async fn owner(&self, ctx: &AppContext) -> FieldResult<Option<NftOwner>> {
         ctx.nft_owner_loader
             .load(self.mint_address.clone().into())
@@ -384,6 +581,41 @@ If no value is provided, it will return XSmall")))]
             .map_err(Into::into)
     }
 
+    #[graphql(description = "The current active listing of this NFT, if any")]
+    pub async fn listing(&self, ctx: &AppContext) -> FieldResult<Option<ListingReceipt>> {
+        let listings = ctx
+            .listing_receipts_loader
+            .load(self.address.clone().into())
+            .await?;
+
+        let default_house = ctx.shared.default_auction_house.as_deref();
+        let is_default = |l: &ListingReceipt| default_house.map_or(false, |h| l.auction_house == h);
+
+        Ok(listings.into_iter().min_by(|a, b| {
+            let by_house = is_default(b).cmp(&is_default(a));
+            let by_price = a.price.cmp(&b.price);
+
+            match ctx.shared.listing_tie_break {
+                ListingTieBreak::DefaultHouse => by_house.then(by_price),
+                ListingTieBreak::LowestPrice => by_price.then(by_house),
+            }
+        }))
+    }
+
+    #[graphql(
+        description = "The most recent auction house sale of this NFT, or null if it \
+                             has never been sold on an auction house.  Primary sales made \
+                             elsewhere, such as candy machine mints, are not recorded, so \
+                             this may be null even if `primarySaleHappened` is true."
+    )]
+    pub async fn last_sale(&self, ctx: &AppContext) -> FieldResult<Option<PurchaseReceipt>> {
+        ctx.last_sale_loader
+            .load(self.address.clone().into())
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Sales of this NFT, newest first")]
     pub async fn purchases(&self, ctx: &AppContext) -> FieldResult<Vec<PurchaseReceipt>> {
         ctx.purchase_receipts_loader
             .load(self.address.clone().into())
@@ -391,6 +623,7 @@ If no value is provided, it will return XSmall")))]
             .map_err(Into::into)
     }
 
+    #[graphql(description = "Active offers on this NFT, i.e. those neither purchased nor canceled")]
     pub async fn offers(&self, ctx: &AppContext) -> FieldResult<Vec<BidReceipt>> {
         ctx.bid_receipts_loader
             .load(self.address.clone().into())
@@ -434,3 +667,212 @@ impl NftCount {
         Ok(count.try_into()?)
     }
 }
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "A file listed in an NFT's off-chain metadata")]
+pub struct NftFile {
+    pub uri: String,
+    pub file_type: String,
+}
+
+impl<'a> From<models::File<'a>> for NftFile {
+    fn from(models::File { uri, file_type, .. }: models::File) -> Self {
+        Self {
+            uri: uri.into_owned(),
+            file_type: file_type.into_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "The collection named in an NFT's off-chain metadata")]
+pub struct NftCollection {
+    pub name: Option<String>,
+    pub family: Option<String>,
+}
+
+impl<'a> From<models::MetadataCollection<'a>> for NftCollection {
+    fn from(models::MetadataCollection { name, family, .. }: models::MetadataCollection) -> Self {
+        Self {
+            name: name.map(Cow::into_owned),
+            family: family.map(Cow::into_owned),
+        }
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(
+    context = AppContext,
+    description = "An NFT with all of its on- and off-chain relationships"
+)]
+pub struct NftFull {
+    pub nft: Nft,
+    pub creators: Vec<NftCreator>,
+    pub attributes: Vec<NftAttribute>,
+    pub files: Vec<NftFile>,
+    pub collection: Option<NftCollection>,
+    pub owner: Option<NftOwner>,
+    #[graphql(description = "The most recently created active listing of this NFT")]
+    pub latest_listing: Option<ListingReceipt>,
+    #[graphql(description = "The most recent sale of this NFT")]
+    pub latest_sale: Option<PurchaseReceipt>,
+}
+
+#[cfg(test)]
+mod tests {
+    use indexer_core::db::testing;
+
+    use super::*;
+
+    fn attribute(
+        value: Option<&'static str>,
+        trait_type: Option<&'static str>,
+    ) -> models::MetadataAttribute<'static> {
+        models::MetadataAttribute {
+            metadata_address: Borrowed("metadata"),
+            value: value.map(Borrowed),
+            trait_type: trait_type.map(Borrowed),
+            id: Owned(uuid::Uuid::nil()),
+            first_verified_creator: None,
+            trait_type_normalized: trait_type.map(|t| Owned(t.to_lowercase())),
+        }
+    }
+
+    #[test]
+    fn complete_attributes_convert() {
+        let attr = NftAttribute::try_from(attribute(Some("Gold"), Some("Background"))).unwrap();
+
+        assert_eq!(attr.metadata_address, "metadata");
+        assert_eq!(attr.value, "Gold");
+        assert_eq!(attr.trait_type, "Background");
+    }
+
+    #[test]
+    fn incomplete_attributes_are_rejected() {
+        assert!(NftAttribute::try_from(attribute(None, Some("Background"))).is_err());
+        assert!(NftAttribute::try_from(attribute(Some("Gold"), None)).is_err());
+        assert!(NftAttribute::try_from(attribute(None, None)).is_err());
+    }
+
+    #[test]
+    fn creators_and_first_verified_creator() {
+        let (ctx, db) = match crate::testing::context() {
+            Some(c) => c,
+            None => return,
+        };
+
+        let creator = |address, share, verified, position| models::MetadataCreator {
+            metadata_address: Borrowed("nft"),
+            creator_address: Borrowed(address),
+            share,
+            verified,
+            position: Some(position),
+        };
+
+        indexer_core::db::insert_into(tables::metadata_creators::table)
+            .values(&[
+                creator("second", 70, true, 1),
+                creator("first", 30, false, 0),
+            ])
+            .execute(&db.get().unwrap())
+            .unwrap();
+
+        let nft = Nft {
+            address: "nft".to_owned(),
+            name: "NFT".to_owned(),
+            seller_fee_basis_points: 500,
+            mint_address: "mint".to_owned(),
+            primary_sale_happened: false,
+            description: String::new(),
+            image: String::new(),
+        };
+
+        let (creators, first_verified) = actix_web::rt::System::new().block_on(async {
+            (
+                nft.creators(&ctx).await.unwrap(),
+                nft.first_verified_creator(&ctx).await.unwrap(),
+            )
+        });
+
+        let creators: Vec<_> = creators
+            .iter()
+            .map(|c| (c.address.as_str(), c.share, c.verified))
+            .collect();
+
+        assert_eq!(creators, vec![("first", 30, false), ("second", 70, true)]);
+        assert_eq!(creators.iter().map(|(_, s, _)| s).sum::<i32>(), 100);
+        assert_eq!(first_verified.unwrap().address, "second");
+    }
+
+    #[test]
+    fn token_standards() {
+        const CREATOR: &str = "11111111111111111111111111111111";
+
+        let (ctx, db) = match crate::testing::context() {
+            Some(c) => c,
+            None => return,
+        };
+        let conn = db.get().unwrap();
+
+        let nft = |address, token_standard| models::Metadata {
+            token_standard,
+            ..testing::metadata(address, address, false)
+        };
+
+        indexer_core::db::insert_into(tables::metadatas::table)
+            .values(&[
+                nft("edition", Some(TokenStandardEnum::NonFungibleEdition)),
+                nft("master", Some(TokenStandardEnum::NonFungible)),
+                nft("unknown", None),
+            ])
+            .execute(&conn)
+            .unwrap();
+        indexer_core::db::insert_into(tables::metadata_jsons::table)
+            .values(&[
+                testing::metadata_json("edition", ""),
+                testing::metadata_json("master", ""),
+                testing::metadata_json("unknown", ""),
+            ])
+            .execute(&conn)
+            .unwrap();
+        indexer_core::db::insert_into(tables::metadata_creators::table)
+            .values(&[
+                testing::metadata_creator("edition", CREATOR, true),
+                testing::metadata_creator("master", CREATOR, true),
+                testing::metadata_creator("unknown", CREATOR, true),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        let nfts = |filter: &str| {
+            crate::testing::execute(
+                &ctx,
+                &format!(
+                    r#"{{
+                        nfts(creators: ["{}"], {} limit: 10, offset: 0) {{
+                            address
+                            tokenStandard
+                        }}
+                    }}"#,
+                    CREATOR, filter
+                ),
+            )["nfts"]
+                .clone()
+        };
+
+        assert_eq!(
+            nfts(""),
+            serde_json::json!([
+                { "address": "edition", "tokenStandard": "NON_FUNGIBLE_EDITION" },
+                { "address": "master", "tokenStandard": "NON_FUNGIBLE" },
+                { "address": "unknown", "tokenStandard": null },
+            ])
+        );
+        assert_eq!(
+            nfts("tokenStandard: NON_FUNGIBLE_EDITION,"),
+            serde_json::json!([
+                { "address": "edition", "tokenStandard": "NON_FUNGIBLE_EDITION" },
+            ])
+        );
+    }
+}
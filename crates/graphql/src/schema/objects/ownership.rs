@@ -0,0 +1,59 @@
+use indexer_core::db::queries::token_accounts::{OwnershipEvent as DbEvent, OwnershipEventKind};
+use objects::wallet::Wallet;
+use scalars::{DateTime, PublicKey};
+
+use super::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+#[graphql(description = "The way an NFT came into the custody of a wallet")]
+pub enum OwnershipKind {
+    /// The wallet is the earliest known holder of the NFT
+    Mint,
+    /// The wallet bought the NFT from the previous holder on an auction house
+    Sale,
+    /// The NFT was transferred from the previous holder outside of a sale
+    Transfer,
+}
+
+impl From<OwnershipEventKind> for OwnershipKind {
+    fn from(kind: OwnershipEventKind) -> Self {
+        match kind {
+            OwnershipEventKind::Mint => Self::Mint,
+            OwnershipEventKind::Sale => Self::Sale,
+            OwnershipEventKind::Transfer => Self::Transfer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "A change in the custody of an NFT")]
+pub struct OwnershipEvent {
+    #[graphql(description = "The wallet receiving the NFT")]
+    pub owner: PublicKey<Wallet>,
+    #[graphql(description = "The wallet the NFT was received from, if any")]
+    pub from: Option<PublicKey<Wallet>>,
+    #[graphql(
+        description = "The time of the sale, or for transfers the approximate time the \
+                             previous holder's token account was emptied.  Unknown for mints."
+    )]
+    pub timestamp: Option<DateTime>,
+    pub kind: OwnershipKind,
+}
+
+impl From<DbEvent> for OwnershipEvent {
+    fn from(
+        DbEvent {
+            owner,
+            from,
+            timestamp,
+            kind,
+        }: DbEvent,
+    ) -> Self {
+        Self {
+            owner: owner.into(),
+            from: from.map(Into::into),
+            timestamp: timestamp.map(Into::into),
+            kind: kind.into(),
+        }
+    }
+}
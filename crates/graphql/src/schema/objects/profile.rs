@@ -1,9 +1,11 @@
+use indexer_core::db::queries;
 use serde::Deserialize;
 use tables::twitter_handle_name_services;
 
 use super::prelude::*;
 
 #[derive(Debug, Clone, GraphQLObject)]
+#[graphql(context = AppContext)]
 pub struct TwitterProfile {
     pub handle: String,
     pub profile_image_url: String,
@@ -19,19 +21,32 @@ pub struct Profile {
     pub banner_image_url: String,
 }
 
+impl TwitterProfile {
+    /// Construct a profile with no information beyond its handle, for use when
+    /// the Twitter API is unavailable
+    #[must_use]
+    pub fn handle_only(handle: String) -> Self {
+        Self {
+            handle,
+            profile_image_url: String::new(),
+            banner_image_url: String::new(),
+            description: String::new(),
+        }
+    }
+}
+
 impl From<TwitterUserProfileResponse> for TwitterProfile {
     fn from(
         TwitterUserProfileResponse {
-            screen_name,
+            username,
             description,
-            profile_image_url_https,
-            profile_banner_url,
+            profile_image_url,
         }: TwitterUserProfileResponse,
     ) -> Self {
         Self {
-            handle: screen_name,
-            profile_image_url: profile_image_url_https,
-            banner_image_url: profile_banner_url,
+            handle: username,
+            profile_image_url,
+            banner_image_url: String::new(),
             description,
         }
     }
@@ -54,22 +69,37 @@ pub struct TwitterShowResponse {
     pub profile_banner_url: String,
 }
 
+/// Response of the Twitter v2 `users/by` lookup.  Unknown handles are
+/// reported in a separate `errors` list, and `data` is omitted entirely if no
+/// handles were found.
+#[derive(Debug, Deserialize)]
+pub struct TwitterUsersResponse {
+    #[serde(default)]
+    pub data: Vec<TwitterUserProfileResponse>,
+}
+
+/// A user returned by the Twitter v2 API.  The v2 API does not expose profile
+/// banners.
 #[derive(Debug, Deserialize)]
 pub struct TwitterUserProfileResponse {
-    pub screen_name: String,
+    pub username: String,
+    #[serde(default)]
     pub description: String,
-    pub profile_image_url_https: String,
-    pub profile_banner_url: String,
+    #[serde(default)]
+    pub profile_image_url: String,
 }
 
 #[graphql_object(Context = AppContext)]
 impl Profile {
     fn wallet_address(&self, ctx: &AppContext) -> FieldResult<Option<String>> {
         let db_conn = ctx.shared.db.get()?;
+        let max_slot = queries::slot::max_confirmed(&db_conn, ctx.shared.confirmation_depth)?;
         let result: Vec<models::TwitterHandle> = twitter_handle_name_services::table
             .select(twitter_handle_name_services::all_columns)
-            .limit(1)
             .filter(twitter_handle_name_services::twitter_handle.eq(&self.handle))
+            .filter(twitter_handle_name_services::slot.le(max_slot))
+            .order_by(twitter_handle_name_services::slot.desc())
+            .limit(1)
             .load(&db_conn)
             .context("Failed to load wallet address")?;
         if result.is_empty() {
@@ -111,3 +141,48 @@ impl From<(TwitterProfilePictureResponse, TwitterShowResponse)> for Profile {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_users_by_response() {
+        let res: TwitterUsersResponse = serde_json::from_str(
+            r#"{
+                "data": [{
+                    "id": "2244994945",
+                    "name": "Twitter Dev",
+                    "username": "TwitterDev",
+                    "description": "The voice of the #TwitterDev team",
+                    "profile_image_url": "https://pbs.twimg.com/profile_images/1/a_normal.jpg"
+                }],
+                "errors": [{
+                    "value": "nobody_here",
+                    "detail": "Could not find user with usernames: [nobody_here].",
+                    "title": "Not Found Error"
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let profiles: Vec<TwitterProfile> = res.data.into_iter().map(Into::into).collect();
+
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].handle, "TwitterDev");
+        assert_eq!(profiles[0].description, "The voice of the #TwitterDev team");
+        assert_eq!(
+            profiles[0].profile_image_url,
+            "https://pbs.twimg.com/profile_images/1/a_normal.jpg"
+        );
+        assert_eq!(profiles[0].banner_image_url, "");
+    }
+
+    #[test]
+    fn parse_users_by_response_without_data() {
+        let res: TwitterUsersResponse =
+            serde_json::from_str(r#"{ "errors": [{ "value": "nobody_here" }] }"#).unwrap();
+
+        assert!(res.data.is_empty());
+    }
+}
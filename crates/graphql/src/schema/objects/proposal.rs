@@ -0,0 +1,629 @@
+use indexer_core::{db::queries, instructions};
+use objects::{vote::Vote, wallet::Wallet};
+use scalars::Volume;
+
+use super::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+#[graphql(description = "The lifecycle state of a governance proposal")]
+pub enum ProposalState {
+    Draft,
+    Active,
+    Canceled,
+    Defeated,
+    Succeeded,
+    Queued,
+    Executed,
+}
+
+impl From<ProposalState> for queries::proposals::ProposalState {
+    fn from(s: ProposalState) -> Self {
+        match s {
+            ProposalState::Draft => Self::Draft,
+            ProposalState::Active => Self::Active,
+            ProposalState::Canceled => Self::Canceled,
+            ProposalState::Defeated => Self::Defeated,
+            ProposalState::Succeeded => Self::Succeeded,
+            ProposalState::Queued => Self::Queued,
+            ProposalState::Executed => Self::Executed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+#[graphql(description = "The result of a proposal's vote, once voting has ended")]
+pub enum ProposalOutcome {
+    Succeeded,
+    Defeated,
+}
+
+#[derive(Debug, Clone)]
+pub struct Proposal {
+    pub address: String,
+    pub governor: String,
+    pub index: i64,
+    pub proposer: Wallet,
+    pub quorum_votes: i64,
+    pub for_votes: i64,
+    pub against_votes: i64,
+    pub abstain_votes: i64,
+    pub canceled_at: i64,
+    pub created_at: i64,
+    pub activated_at: i64,
+    pub voting_ends_at: i64,
+    pub queued_at: i64,
+    pub executed_at: Option<i64>,
+    pub title: Option<String>,
+    pub description_link: Option<String>,
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "An account passed to a proposal instruction")]
+pub struct ProposalAccount {
+    pub pubkey: String,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl<'a> From<models::ProposalAccountMeta<'a>> for ProposalAccount {
+    fn from(
+        models::ProposalAccountMeta {
+            pubkey,
+            is_signer,
+            is_writable,
+            ..
+        }: models::ProposalAccountMeta,
+    ) -> Self {
+        Self {
+            pubkey: pubkey.into_owned(),
+            is_signer,
+            is_writable,
+        }
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "A named argument of a decoded instruction")]
+pub struct InstructionArg {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "A human-readable form of an instruction")]
+pub struct DecodedInstruction {
+    #[graphql(description = "Name of the program, or its address if it is not recognized")]
+    pub program: String,
+    #[graphql(description = "Name of the instruction, or null if it could not be decoded")]
+    pub name: Option<String>,
+    #[graphql(description = "Addresses of the accounts passed to the instruction")]
+    pub accounts: Vec<String>,
+    #[graphql(description = "Arguments of the instruction, empty if it could not be decoded")]
+    pub args: Vec<InstructionArg>,
+    #[graphql(description = "The raw instruction data, hex-encoded")]
+    pub data: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProposalInstruction {
+    pub program_id: String,
+    pub data: Vec<u8>,
+    pub accounts: Vec<ProposalAccount>,
+}
+
+#[graphql_object(Context = AppContext, description = "An instruction executed by a proposal")]
+impl ProposalInstruction {
+    fn program_id(&self) -> &str {
+        &self.program_id
+    }
+
+    #[graphql(description = "The instruction data, hex-encoded")]
+    fn data(&self) -> String {
+        hex::encode(&self.data)
+    }
+
+    fn accounts(&self) -> Vec<ProposalAccount> {
+        self.accounts.clone()
+    }
+
+    #[graphql(
+        description = "The instruction decoded for known programs, falling back to the \
+                             program address and raw data for others"
+    )]
+    fn decoded(&self) -> DecodedInstruction {
+        let accounts = self.accounts.iter().map(|a| a.pubkey.clone()).collect();
+        let data = hex::encode(&self.data);
+
+        match instructions::decode(&self.program_id, &self.data) {
+            Some(instructions::DecodedInstruction {
+                program,
+                name,
+                args,
+            }) => DecodedInstruction {
+                program: program.into(),
+                name: Some(name.into()),
+                accounts,
+                args: args
+                    .into_iter()
+                    .map(|(name, value)| InstructionArg {
+                        name: name.into(),
+                        value,
+                    })
+                    .collect(),
+                data,
+            },
+            None => DecodedInstruction {
+                program: self.program_id.clone(),
+                name: None,
+                accounts,
+                args: vec![],
+                data,
+            },
+        }
+    }
+}
+
+/// Convert a unix timestamp in seconds to a `DateTime`, treating zero and
+/// out-of-range values as unset
+fn timestamp(secs: i64) -> Option<scalars::DateTime> {
+    if secs > 0 {
+        scalars::DateTime::from_timestamp(secs)
+    } else {
+        None
+    }
+}
+
+impl Proposal {
+    /// Compute the state of this proposal at the given unix timestamp, following
+    /// the Tribeca `Proposal::get_state` logic.  A queued proposal is considered
+    /// executed once its Goki smart wallet transaction has been executed.
+    #[must_use]
+    pub fn state_at(&self, now: i64) -> ProposalState {
+        if self.canceled_at > 0 {
+            ProposalState::Canceled
+        } else if self.activated_at == 0 {
+            ProposalState::Draft
+        } else if now < self.voting_ends_at {
+            ProposalState::Active
+        } else if self.for_votes < self.quorum_votes || self.for_votes <= self.against_votes {
+            ProposalState::Defeated
+        } else if self.executed_at.map_or(false, |e| e > 0) {
+            ProposalState::Executed
+        } else if self.queued_at > 0 {
+            ProposalState::Queued
+        } else {
+            ProposalState::Succeeded
+        }
+    }
+
+    /// Encode the keyset pagination position of this proposal as a cursor
+    #[must_use]
+    pub fn encode_cursor(&self) -> String {
+        base64::encode(format!("{}:{}", self.created_at, self.address))
+    }
+
+    /// Decode a cursor produced by [`Proposal::encode_cursor`]
+    #[must_use]
+    pub fn parse_cursor(cursor: &str) -> Option<(i64, String)> {
+        let decoded = String::from_utf8(base64::decode(cursor).ok()?).ok()?;
+        let (created_at, address) = decoded.split_once(':')?;
+
+        Some((created_at.parse().ok()?, address.to_owned()))
+    }
+}
+
+#[graphql_object(Context = AppContext)]
+impl Proposal {
+    fn address(&self) -> &str {
+        &self.address
+    }
+
+    fn governor(&self) -> &str {
+        &self.governor
+    }
+
+    fn index(&self) -> FieldResult<Volume> {
+        self.index.try_into().map_err(Into::into)
+    }
+
+    fn proposer(&self) -> Wallet {
+        self.proposer.clone()
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    fn description_link(&self) -> Option<&str> {
+        self.description_link.as_deref()
+    }
+
+    fn quorum_votes(&self) -> FieldResult<Volume> {
+        self.quorum_votes.try_into().map_err(Into::into)
+    }
+
+    fn for_votes(&self) -> FieldResult<Volume> {
+        self.for_votes.try_into().map_err(Into::into)
+    }
+
+    fn against_votes(&self) -> FieldResult<Volume> {
+        self.against_votes.try_into().map_err(Into::into)
+    }
+
+    fn abstain_votes(&self) -> FieldResult<Volume> {
+        self.abstain_votes.try_into().map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Votes counting toward quorum (for and abstain) as a fraction of \
+                             the quorum, or null if the quorum is zero"
+    )]
+    fn quorum_progress(&self) -> Option<f64> {
+        if self.quorum_votes <= 0 {
+            return None;
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        let progress = (self.for_votes + self.abstain_votes) as f64 / self.quorum_votes as f64;
+
+        Some(progress)
+    }
+
+    fn created_at(&self) -> Option<scalars::DateTime> {
+        timestamp(self.created_at)
+    }
+
+    fn activated_at(&self) -> Option<scalars::DateTime> {
+        timestamp(self.activated_at)
+    }
+
+    fn voting_ends_at(&self) -> Option<scalars::DateTime> {
+        timestamp(self.voting_ends_at)
+    }
+
+    fn canceled_at(&self) -> Option<scalars::DateTime> {
+        timestamp(self.canceled_at)
+    }
+
+    fn queued_at(&self) -> Option<scalars::DateTime> {
+        timestamp(self.queued_at)
+    }
+
+    fn executed_at(&self) -> Option<scalars::DateTime> {
+        self.executed_at.and_then(timestamp)
+    }
+
+    #[graphql(description = "The current lifecycle state of the proposal")]
+    fn state(&self) -> ProposalState {
+        self.state_at(Utc::now().timestamp())
+    }
+
+    #[graphql(description = "Whether the proposal passed, or null if voting has not ended")]
+    fn outcome(&self) -> Option<ProposalOutcome> {
+        match self.state_at(Utc::now().timestamp()) {
+            ProposalState::Draft | ProposalState::Active | ProposalState::Canceled => None,
+            ProposalState::Defeated => Some(ProposalOutcome::Defeated),
+            ProposalState::Succeeded | ProposalState::Queued | ProposalState::Executed => {
+                Some(ProposalOutcome::Succeeded)
+            },
+        }
+    }
+
+    #[graphql(description = "Instructions the proposal executes once it passes")]
+    async fn instructions(&self, ctx: &AppContext) -> FieldResult<Vec<ProposalInstruction>> {
+        ctx.proposal_instructions_loader
+            .load(self.address.clone().into())
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Votes cast on the proposal, heaviest first")]
+    async fn votes(&self, ctx: &AppContext) -> FieldResult<Vec<Vote>> {
+        ctx.votes_by_proposal_loader
+            .load(self.address.clone().into())
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Cursor for paginating past this proposal")]
+    fn cursor(&self) -> String {
+        self.encode_cursor()
+    }
+}
+
+impl<'a> From<models::ProposalWithMeta<'a>> for Proposal {
+    fn from(
+        models::ProposalWithMeta {
+            proposal:
+                models::Proposal {
+                    address,
+                    governor,
+                    index,
+                    proposer,
+                    quorum_votes,
+                    for_votes,
+                    against_votes,
+                    abstain_votes,
+                    canceled_at,
+                    created_at,
+                    activated_at,
+                    voting_ends_at,
+                    queued_at,
+                    ..
+                },
+            title,
+            description_link,
+            executed_at,
+            twitter_handle,
+        }: models::ProposalWithMeta,
+    ) -> Self {
+        Self {
+            address: address.into_owned(),
+            governor: governor.into_owned(),
+            index,
+            proposer: Wallet::new(proposer.into_owned().into(), twitter_handle),
+            quorum_votes,
+            for_votes,
+            against_votes,
+            abstain_votes,
+            canceled_at,
+            created_at,
+            activated_at,
+            voting_ends_at,
+            queued_at,
+            executed_at,
+            title,
+            description_link,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexer_core::db::{
+        insert_into,
+        tables::{proposals, transactions},
+    };
+
+    use super::*;
+
+    fn proposal() -> Proposal {
+        Proposal {
+            address: "proposal".into(),
+            governor: "governor".into(),
+            index: 0,
+            proposer: Wallet::new(String::from("proposer").into(), None),
+            quorum_votes: 0,
+            for_votes: 0,
+            against_votes: 0,
+            abstain_votes: 0,
+            canceled_at: 0,
+            created_at: 1_650_000_000,
+            activated_at: 0,
+            voting_ends_at: 0,
+            queued_at: 0,
+            executed_at: None,
+            title: None,
+            description_link: None,
+        }
+    }
+
+    #[test]
+    fn cursor_round_trip() {
+        let cursor = proposal().encode_cursor();
+
+        assert_eq!(
+            Proposal::parse_cursor(&cursor),
+            Some((1_650_000_000, "proposal".into()))
+        );
+    }
+
+    #[test]
+    fn malformed_cursors() {
+        assert_eq!(Proposal::parse_cursor("not base64!"), None);
+        assert_eq!(
+            Proposal::parse_cursor(&base64::encode("no-separator")),
+            None
+        );
+        assert_eq!(
+            Proposal::parse_cursor(&base64::encode("soon:proposal")),
+            None
+        );
+    }
+
+    fn voted(for_votes: i64, against_votes: i64) -> Proposal {
+        Proposal {
+            quorum_votes: 100,
+            for_votes,
+            against_votes,
+            activated_at: 1_650_000_100,
+            voting_ends_at: 1_650_000_200,
+            ..proposal()
+        }
+    }
+
+    #[test]
+    fn pending_states() {
+        assert_eq!(proposal().state_at(1_650_000_300), ProposalState::Draft);
+        assert_eq!(voted(0, 0).state_at(1_650_000_150), ProposalState::Active);
+
+        let canceled = Proposal {
+            canceled_at: 1_650_000_150,
+            ..voted(150, 0)
+        };
+        assert_eq!(canceled.state_at(1_650_000_300), ProposalState::Canceled);
+    }
+
+    #[test]
+    fn defeated_states() {
+        assert_eq!(
+            voted(99, 0).state_at(1_650_000_300),
+            ProposalState::Defeated
+        );
+        assert_eq!(
+            voted(150, 150).state_at(1_650_000_300),
+            ProposalState::Defeated
+        );
+    }
+
+    #[test]
+    fn passed_states() {
+        assert_eq!(
+            voted(100, 50).state_at(1_650_000_200),
+            ProposalState::Succeeded
+        );
+
+        let queued = Proposal {
+            queued_at: 1_650_000_250,
+            ..voted(100, 50)
+        };
+        assert_eq!(queued.state_at(1_650_000_300), ProposalState::Queued);
+
+        let executed = Proposal {
+            executed_at: Some(1_650_000_280),
+            ..queued
+        };
+        assert_eq!(executed.state_at(1_650_000_300), ProposalState::Executed);
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(timestamp(0), None);
+        assert_eq!(timestamp(-1), None);
+        assert_eq!(timestamp(i64::MAX), None);
+        assert_eq!(
+            timestamp(1_650_000_000),
+            scalars::DateTime::from_timestamp(1_650_000_000)
+        );
+    }
+
+    fn row(address: &'static str, index: i64) -> models::Proposal<'static> {
+        models::Proposal {
+            address: Borrowed(address),
+            governor: Borrowed("governor"),
+            index,
+            bump: 0,
+            proposer: Borrowed("proposer"),
+            quorum_votes: 100,
+            for_votes: 100,
+            against_votes: 50,
+            abstain_votes: 0,
+            canceled_at: 0,
+            created_at: 1_650_000_000,
+            activated_at: 1_650_000_100,
+            voting_ends_at: 1_650_000_200,
+            queued_at: 0,
+            queued_transaction: Borrowed(""),
+        }
+    }
+
+    #[test]
+    fn governor_state_filter_agrees_with_state_at() {
+        let (_ctx, db) = match crate::testing::context() {
+            Some(c) => c,
+            None => return,
+        };
+        let conn = db.get().unwrap();
+        let now = 1_650_000_300;
+
+        insert_into(proposals::table)
+            .values(&[
+                models::Proposal {
+                    activated_at: 0,
+                    ..row("draft", 0)
+                },
+                models::Proposal {
+                    voting_ends_at: 1_650_000_400,
+                    ..row("active", 1)
+                },
+                models::Proposal {
+                    canceled_at: 1_650_000_150,
+                    ..row("canceled", 2)
+                },
+                models::Proposal {
+                    against_votes: 100,
+                    ..row("defeated", 3)
+                },
+                row("succeeded", 4),
+                models::Proposal {
+                    queued_at: 1_650_000_250,
+                    queued_transaction: Borrowed("queued_tx"),
+                    ..row("queued", 5)
+                },
+                models::Proposal {
+                    queued_at: 1_650_000_250,
+                    queued_transaction: Borrowed("executed_tx"),
+                    ..row("executed", 6)
+                },
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(transactions::table)
+            .values(
+                &[("queued_tx", 0), ("executed_tx", 1_650_000_280)]
+                    .into_iter()
+                    .map(|(address, executed_at)| models::Transaction {
+                        address: Borrowed(address),
+                        smart_wallet: Borrowed("smart_wallet"),
+                        index: 0,
+                        bump: 0,
+                        proposer: Borrowed("proposer"),
+                        signers: vec![true],
+                        owner_set_seqno: 0,
+                        eta: 0,
+                        executor: Borrowed("executor"),
+                        executed_at,
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .execute(&conn)
+            .unwrap();
+
+        let load = |states: Option<&[queries::proposals::ProposalState]>| {
+            queries::proposals::by_governor(&conn, "governor", states, now, 10, 0, 0)
+                .unwrap()
+                .into_iter()
+                .map(Proposal::from)
+                .collect::<Vec<_>>()
+        };
+
+        let all = load(None);
+        assert_eq!(
+            all.iter().map(|p| p.address.as_str()).collect::<Vec<_>>(),
+            vec![
+                "executed",
+                "queued",
+                "succeeded",
+                "defeated",
+                "canceled",
+                "active",
+                "draft"
+            ]
+        );
+
+        for state in [
+            ProposalState::Draft,
+            ProposalState::Active,
+            ProposalState::Canceled,
+            ProposalState::Defeated,
+            ProposalState::Succeeded,
+            ProposalState::Queued,
+            ProposalState::Executed,
+        ] {
+            let filtered = load(Some(&[state.into()]));
+
+            assert_eq!(filtered.len(), 1, "{:?}", state);
+            assert_eq!(filtered[0].state_at(now), state);
+            assert_eq!(
+                all.iter()
+                    .filter(|p| p.state_at(now) == state)
+                    .map(|p| &p.address)
+                    .collect::<Vec<_>>(),
+                vec![&filtered[0].address]
+            );
+        }
+
+        let active = load(Some(&[ProposalState::Active.into()]));
+        assert_eq!(active[0].address, "active");
+    }
+}
@@ -1,14 +1,94 @@
+use indexer_core::db::queries::wash_trading;
+use objects::{auction_house, nft::Nft, profile::TwitterProfile, wallet::Wallet};
+use scalars::{DateTime, Lamports, PublicKey};
+
 use super::prelude::*;
 
-#[derive(Debug, Clone, GraphQLObject)]
-#[graphql(description = "auction house bid receipt")]
+#[derive(Debug, Clone)]
+/// An auction house purchase receipt
 pub struct PurchaseReceipt {
     pub address: String,
     pub buyer: String,
     pub seller: String,
     pub auction_house: String,
-    pub price: scalars::Lamports,
-    pub created_at: DateTime<Utc>,
+    pub metadata: PublicKey<Nft>,
+    pub price: Lamports,
+    pub created_at: DateTime,
+}
+
+impl PurchaseReceipt {
+    async fn wallet_twitter(ctx: &AppContext, wallet: &str) -> FieldResult<Option<TwitterProfile>> {
+        let handle = match ctx
+            .wallet_twitter_handle_loader
+            .load(PublicKey::<Wallet>::from(wallet.to_owned()))
+            .await?
+        {
+            Some(h) => h,
+            None => return Ok(None),
+        };
+
+        ctx.twitter_profile_loader
+            .load(handle)
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[graphql_object(Context = AppContext, description = "An auction house purchase receipt")]
+impl PurchaseReceipt {
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn buyer(&self) -> &str {
+        &self.buyer
+    }
+
+    pub fn seller(&self) -> &str {
+        &self.seller
+    }
+
+    #[graphql(description = "Twitter profile of the buyer, if they have registered a handle")]
+    pub async fn buyer_twitter(&self, ctx: &AppContext) -> FieldResult<Option<TwitterProfile>> {
+        Self::wallet_twitter(ctx, &self.buyer).await
+    }
+
+    #[graphql(description = "Twitter profile of the seller, if they have registered a handle")]
+    pub async fn seller_twitter(&self, ctx: &AppContext) -> FieldResult<Option<TwitterProfile>> {
+        Self::wallet_twitter(ctx, &self.seller).await
+    }
+
+    pub fn metadata(&self) -> &PublicKey<Nft> {
+        &self.metadata
+    }
+
+    #[graphql(description = "The NFT sold")]
+    pub async fn nft(&self, ctx: &AppContext) -> FieldResult<Option<Nft>> {
+        ctx.nft_loader
+            .load(self.metadata.clone())
+            .await
+            .map_err(Into::into)
+    }
+
+    pub fn auction_house(&self) -> &str {
+        &self.auction_house
+    }
+
+    pub fn price(&self) -> Lamports {
+        self.price
+    }
+
+    #[graphql(
+        description = "Price in whole units of the auction house's treasury token, or \
+                             null if the decimals of the treasury mint are not indexed"
+    )]
+    pub async fn price_ui(&self, ctx: &AppContext) -> FieldResult<Option<f64>> {
+        auction_house::ui_price(ctx, &self.auction_house, self.price).await
+    }
+
+    pub fn created_at(&self) -> DateTime {
+        self.created_at
+    }
 }
 
 impl<'a> TryFrom<models::PurchaseReceipt<'a>> for PurchaseReceipt {
@@ -21,6 +101,7 @@ impl<'a> TryFrom<models::PurchaseReceipt<'a>> for PurchaseReceipt {
             price,
             created_at,
             auction_house,
+            metadata,
             ..
         }: models::PurchaseReceipt,
     ) -> Result<Self, Self::Error> {
@@ -29,8 +110,30 @@ impl<'a> TryFrom<models::PurchaseReceipt<'a>> for PurchaseReceipt {
             auction_house: auction_house.into_owned(),
             buyer: buyer.into_owned(),
             seller: seller.into_owned(),
+            metadata: metadata.into(),
             price: price.try_into()?,
-            created_at: DateTime::from_utc(created_at, Utc),
+            created_at: created_at.into(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(context = AppContext, description = "A sale flagged as a possible wash trade")]
+pub struct SuspiciousTrade {
+    pub purchase: PurchaseReceipt,
+    #[graphql(description = "Why the sale was flagged")]
+    pub reason: String,
+}
+
+impl TryFrom<wash_trading::SuspiciousTrade> for SuspiciousTrade {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        wash_trading::SuspiciousTrade { purchase, reason }: wash_trading::SuspiciousTrade,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            purchase: purchase.try_into()?,
+            reason,
         })
     }
 }
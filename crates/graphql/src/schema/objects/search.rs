@@ -0,0 +1,139 @@
+use objects::{marketplace::Marketplace, nft::Nft, profile::TwitterProfile};
+
+use super::prelude::*;
+
+#[derive(Debug, Clone, juniper::GraphQLUnion)]
+#[graphql(context = AppContext, description = "An NFT, marketplace, or profile matching a search")]
+pub enum SearchResult {
+    Nft(Nft),
+    Marketplace(Marketplace),
+    Profile(TwitterProfile),
+}
+
+impl SearchResult {
+    /// Rank this result against the search term, higher being more relevant.
+    ///
+    /// Exact (case-insensitive) matches rank above prefix matches, which rank
+    /// above any other match.  Within a rank, profiles rank above
+    /// marketplaces, which rank above NFTs, since there are far fewer of them
+    /// and a match is more likely to be what the user is looking for.
+    #[must_use]
+    pub fn relevance(&self, term: &str) -> (u8, u8) {
+        let term = term.to_lowercase();
+        let rank = |name: &str| {
+            let name = name.to_lowercase();
+
+            if name == term {
+                2
+            } else if name.starts_with(&term) {
+                1
+            } else {
+                0
+            }
+        };
+
+        match self {
+            Self::Profile(p) => (rank(&p.handle), 2),
+            Self::Marketplace(m) => (rank(&m.name).max(rank(&m.subdomain)), 1),
+            Self::Nft(n) => (rank(&n.name), 0),
+        }
+    }
+}
+
+/// Escape the wildcard characters of a `LIKE` pattern
+#[must_use]
+pub fn escape_like(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            out.push('\\');
+        }
+
+        out.push(c);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use indexer_core::db::testing;
+
+    use super::*;
+
+    fn nft(name: &str) -> SearchResult {
+        SearchResult::Nft(
+            models::Nft {
+                address: "nft".into(),
+                name: name.into(),
+                seller_fee_basis_points: 0,
+                mint_address: "mint".into(),
+                primary_sale_happened: false,
+                description: None,
+                image: None,
+            }
+            .into(),
+        )
+    }
+
+    fn marketplace(name: &'static str, subdomain: &'static str) -> SearchResult {
+        SearchResult::Marketplace(
+            (
+                models::StoreConfigJson {
+                    name: Borrowed(name),
+                    ..testing::store_config_json("config", subdomain, "house")
+                },
+                None,
+            )
+                .into(),
+        )
+    }
+
+    fn profile(handle: &str) -> SearchResult {
+        SearchResult::Profile(TwitterProfile::handle_only(handle.into()))
+    }
+
+    #[test]
+    fn relevance() {
+        assert_eq!(nft("DeGods").relevance("degods"), (2, 0));
+        assert_eq!(nft("DeGods #1").relevance("degods"), (1, 0));
+        assert_eq!(nft("y00ts DeGods").relevance("degods"), (0, 0));
+
+        assert_eq!(marketplace("DeGods", "dao").relevance("DEGODS"), (2, 1));
+        assert_eq!(
+            marketplace("The DeGods Market", "degods").relevance("degods"),
+            (2, 1)
+        );
+        assert_eq!(
+            marketplace("The DeGods Market", "dgd").relevance("degods"),
+            (0, 1)
+        );
+
+        assert_eq!(profile("degods").relevance("DeGods"), (2, 2));
+        assert_eq!(profile("degodsnft").relevance("degods"), (1, 2));
+
+        let mut results = vec![
+            nft("DeGods"),
+            marketplace("DeGods Market", "market"),
+            profile("degodsnft"),
+            nft("y00ts DeGods"),
+        ];
+        results.sort_by_key(|r| std::cmp::Reverse(r.relevance("degods")));
+
+        assert_eq!(
+            results
+                .iter()
+                .map(|r| r.relevance("degods"))
+                .collect::<Vec<_>>(),
+            vec![(2, 0), (1, 2), (1, 1), (0, 0)]
+        );
+    }
+
+    #[test]
+    fn escape_like() {
+        assert_eq!(super::escape_like("degods"), "degods");
+        assert_eq!(super::escape_like("100%_real"), r"100\%\_real");
+        assert_eq!(super::escape_like(r"back\slash"), r"back\\slash");
+    }
+}
@@ -0,0 +1,348 @@
+use objects::wallet::Wallet;
+use scalars::{DateTime, PublicKey, U64};
+use tables::transactions;
+
+use super::prelude::*;
+
+/// Value of `executed_at` for transactions which have not been executed
+const NOT_EXECUTED: i64 = -1;
+
+#[derive(Debug, Clone)]
+pub struct SmartWallet {
+    pub address: String,
+    pub base: String,
+    pub threshold: U64,
+    pub minimum_delay: U64,
+    pub grace_period: U64,
+    pub num_transactions: U64,
+}
+
+impl<'a> TryFrom<models::SmartWallet<'a>> for SmartWallet {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::SmartWallet {
+            address,
+            base,
+            threshold,
+            minimum_delay,
+            grace_period,
+            num_transactions,
+            ..
+        }: models::SmartWallet,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            address: address.into_owned(),
+            base: base.into_owned(),
+            threshold: threshold.try_into()?,
+            minimum_delay: minimum_delay.try_into()?,
+            grace_period: grace_period.try_into()?,
+            num_transactions: num_transactions.try_into()?,
+        })
+    }
+}
+
+#[graphql_object(Context = AppContext, description = "A Goki multisig smart wallet")]
+impl SmartWallet {
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    #[graphql(description = "Number of owner approvals needed to execute a transaction")]
+    pub fn threshold(&self) -> U64 {
+        self.threshold
+    }
+
+    #[graphql(description = "Minimum delay between approval and execution, in seconds")]
+    pub fn minimum_delay(&self) -> U64 {
+        self.minimum_delay
+    }
+
+    #[graphql(description = "Time after its ETA until a transaction expires, in seconds")]
+    pub fn grace_period(&self) -> U64 {
+        self.grace_period
+    }
+
+    pub fn num_transactions(&self) -> U64 {
+        self.num_transactions
+    }
+
+    #[graphql(description = "Owners of the wallet, in the order of their signatures")]
+    pub async fn owners(&self, ctx: &AppContext) -> FieldResult<Vec<SmartWalletOwner>> {
+        ctx.smart_wallet_owners_loader
+            .load(self.address.clone().into())
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Transactions which have not been executed, oldest first")]
+    pub fn pending_transactions(&self, ctx: &AppContext) -> FieldResult<Vec<Transaction>> {
+        let conn = ctx.shared.db.get()?;
+
+        let rows: Vec<models::Transaction> = transactions::table
+            .filter(transactions::smart_wallet.eq(&self.address))
+            .filter(transactions::executed_at.eq(NOT_EXECUTED))
+            .order(transactions::index.asc())
+            .load(&conn)
+            .context("Failed to load pending transactions")?;
+
+        rows.into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SmartWalletOwner {
+    pub smart_wallet: String,
+    pub owner: String,
+    pub index: i64,
+}
+
+impl<'a> From<models::SmartWalletOwner<'a>> for SmartWalletOwner {
+    fn from(
+        models::SmartWalletOwner {
+            smart_wallet_address,
+            owner_address,
+            index,
+        }: models::SmartWalletOwner,
+    ) -> Self {
+        Self {
+            smart_wallet: smart_wallet_address.into_owned(),
+            owner: owner_address.into_owned(),
+            index,
+        }
+    }
+}
+
+#[graphql_object(Context = AppContext, description = "An owner of a Goki smart wallet")]
+impl SmartWalletOwner {
+    pub fn smart_wallet_address(&self) -> &str {
+        &self.smart_wallet
+    }
+
+    pub fn owner(&self) -> Wallet {
+        Wallet::new(PublicKey::from(self.owner.clone()), None)
+    }
+
+    #[graphql(description = "Position of the owner, matching its entry in transaction signers")]
+    pub fn index(&self) -> FieldResult<i32> {
+        self.index.try_into().map_err(Into::into)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    pub address: String,
+    pub smart_wallet: String,
+    pub index: U64,
+    pub proposer: String,
+    pub signers: Vec<bool>,
+    pub eta: i64,
+    pub executor: String,
+    pub executed_at: i64,
+}
+
+impl<'a> TryFrom<models::Transaction<'a>> for Transaction {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::Transaction {
+            address,
+            smart_wallet,
+            index,
+            proposer,
+            signers,
+            eta,
+            executor,
+            executed_at,
+            ..
+        }: models::Transaction,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            address: address.into_owned(),
+            smart_wallet: smart_wallet.into_owned(),
+            index: index.try_into()?,
+            proposer: proposer.into_owned(),
+            signers,
+            eta,
+            executor: executor.into_owned(),
+            executed_at,
+        })
+    }
+}
+
+#[graphql_object(Context = AppContext, description = "A Goki smart wallet transaction")]
+impl Transaction {
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn smart_wallet_address(&self) -> &str {
+        &self.smart_wallet
+    }
+
+    pub fn index(&self) -> U64 {
+        self.index
+    }
+
+    pub fn proposer(&self) -> &str {
+        &self.proposer
+    }
+
+    #[graphql(description = "Whether each owner, by index, has signed the transaction")]
+    pub fn signers(&self) -> Vec<bool> {
+        self.signers.clone()
+    }
+
+    #[graphql(description = "Earliest time the transaction may be executed, if it is timelocked")]
+    pub fn eta(&self) -> Option<DateTime> {
+        (self.eta >= 0)
+            .then(|| DateTime::from_timestamp(self.eta))
+            .flatten()
+    }
+
+    pub fn executed(&self) -> bool {
+        self.executed_at != NOT_EXECUTED
+    }
+
+    pub fn executed_at(&self) -> Option<DateTime> {
+        (self.executed_at != NOT_EXECUTED)
+            .then(|| DateTime::from_timestamp(self.executed_at))
+            .flatten()
+    }
+
+    pub fn executor(&self) -> Option<&str> {
+        (self.executed_at != NOT_EXECUTED).then(|| &*self.executor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexer_core::db::{
+        insert_into,
+        tables::{smart_wallet_owners, smart_wallets},
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{context, execute};
+
+    const WALLET: &str = "11111111111111111111111111111111";
+
+    fn owner(owner: &'static str, index: i64) -> models::SmartWalletOwner<'static> {
+        models::SmartWalletOwner {
+            smart_wallet_address: Borrowed(WALLET),
+            owner_address: Borrowed(owner),
+            index,
+        }
+    }
+
+    fn transaction(
+        address: &'static str,
+        index: i64,
+        signers: Vec<bool>,
+        executed_at: i64,
+    ) -> models::Transaction<'static> {
+        models::Transaction {
+            address: Borrowed(address),
+            smart_wallet: Borrowed(WALLET),
+            index,
+            bump: 255,
+            proposer: Borrowed("alice"),
+            signers,
+            owner_set_seqno: 0,
+            eta: -1,
+            executor: Borrowed(if executed_at == NOT_EXECUTED {
+                ""
+            } else {
+                "bob"
+            }),
+            executed_at,
+        }
+    }
+
+    #[test]
+    fn two_of_three_with_one_pending_transaction() {
+        let (ctx, db) = match context() {
+            Some(c) => c,
+            None => return,
+        };
+        let conn = db.get().unwrap();
+
+        insert_into(smart_wallets::table)
+            .values(&models::SmartWallet {
+                address: Borrowed(WALLET),
+                base: Borrowed("base"),
+                bump: 255,
+                threshold: 2,
+                minimum_delay: 0,
+                grace_period: 86_400,
+                owner_set_seqno: 0,
+                num_transactions: 2,
+            })
+            .execute(&conn)
+            .unwrap();
+        insert_into(smart_wallet_owners::table)
+            .values(&[owner("carol", 2), owner("alice", 0), owner("bob", 1)])
+            .execute(&conn)
+            .unwrap();
+        insert_into(transactions::table)
+            .values(&[
+                transaction("tx0", 0, vec![true, true, false], 1_650_000_000),
+                transaction("tx1", 1, vec![true, false, false], NOT_EXECUTED),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        assert_eq!(
+            execute(
+                &ctx,
+                &format!(
+                    r#"{{
+                        smartWallet(address: "{}") {{
+                            threshold
+                            numTransactions
+                            owners {{ index owner {{ address }} }}
+                            pendingTransactions {{
+                                address
+                                index
+                                signers
+                                eta
+                                executed
+                                executedAt
+                                executor
+                            }}
+                        }}
+                    }}"#,
+                    WALLET
+                ),
+            ),
+            json!({
+                "smartWallet": {
+                    "threshold": "2",
+                    "numTransactions": "2",
+                    "owners": [
+                        { "index": 0, "owner": { "address": "alice" } },
+                        { "index": 1, "owner": { "address": "bob" } },
+                        { "index": 2, "owner": { "address": "carol" } },
+                    ],
+                    "pendingTransactions": [{
+                        "address": "tx1",
+                        "index": "1",
+                        "signers": [true, false, false],
+                        "eta": null,
+                        "executed": false,
+                        "executedAt": null,
+                        "executor": null,
+                    }],
+                },
+            })
+        );
+    }
+}
@@ -1,14 +1,40 @@
-use scalars::Volume;
+use indexer_core::db::queries::stats;
+use scalars::{DateTime, Lamports, Volume, U64};
 
 use super::prelude::*;
 
 #[derive(Debug, Clone, GraphQLObject)]
+#[graphql(
+    description = "Listing and sale statistics for an auction house, in units of its \
+                         treasury mint"
+)]
 pub struct MintStats {
     pub auction_house: String,
     pub mint: String,
-    pub floor: Option<Volume>,
-    pub average: Option<Volume>,
-    pub volume_24hr: Option<Volume>,
+    #[graphql(description = "Lowest price of an active listing")]
+    pub floor: Option<U64>,
+    #[graphql(description = "Average sale price")]
+    pub average: Option<U64>,
+    #[graphql(description = "Total price of sales within the last 24 hours")]
+    pub volume_24hr: Option<U64>,
+    #[graphql(skip)]
+    pub secondary_floor: Option<U64>,
+}
+
+impl MintStats {
+    /// Report the floor of listings past their primary sale if
+    /// `secondary_only` is set, so mint listings do not distort the floor
+    #[must_use]
+    pub fn secondary_only(self, secondary_only: bool) -> Self {
+        if secondary_only {
+            Self {
+                floor: self.secondary_floor,
+                ..self
+            }
+        } else {
+            self
+        }
+    }
 }
 
 impl<'a> TryFrom<models::MintStats<'a>> for MintStats {
@@ -19,6 +45,7 @@ impl<'a> TryFrom<models::MintStats<'a>> for MintStats {
             auction_house,
             mint,
             floor,
+            secondary_floor,
             average,
             volume_24hr,
         }: models::MintStats,
@@ -27,6 +54,7 @@ impl<'a> TryFrom<models::MintStats<'a>> for MintStats {
             auction_house: auction_house.into_owned(),
             mint: mint.into_owned(),
             floor: floor.map(TryInto::try_into).transpose()?,
+            secondary_floor: secondary_floor.map(TryInto::try_into).transpose()?,
             average: average.map(TryInto::try_into).transpose()?,
             volume_24hr: volume_24hr.map(TryInto::try_into).transpose()?,
         })
@@ -52,3 +80,99 @@ impl<'a> TryFrom<models::MarketStats<'a>> for MarketStats {
         })
     }
 }
+
+#[derive(Debug, Clone, Copy, GraphQLObject)]
+#[graphql(description = "Trading statistics for a marketplace")]
+pub struct MarketplaceStats {
+    #[graphql(description = "Lowest price of an active listing")]
+    pub floor: Option<Lamports>,
+    #[graphql(description = "Total price of sales within the requested window")]
+    pub volume: Option<Lamports>,
+    #[graphql(description = "Number of active listings")]
+    pub listed: U64,
+    #[graphql(description = "Number of wallets holding an NFT in the marketplace")]
+    pub holders: U64,
+}
+
+impl TryFrom<models::MarketplaceSummary> for MarketplaceStats {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::MarketplaceSummary {
+            floor,
+            volume,
+            listed,
+            holders,
+        }: models::MarketplaceSummary,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            floor: floor.map(Into::into),
+            volume: volume.map(Into::into),
+            listed: listed.try_into()?,
+            holders: holders.try_into()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, GraphQLObject)]
+#[graphql(description = "Counts of auction house activity within a window of time")]
+pub struct ActivitySummary {
+    pub listings: i32,
+    pub sales: i32,
+    pub cancellations: i32,
+    pub bids: i32,
+}
+
+impl TryFrom<models::ActivitySummary> for ActivitySummary {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::ActivitySummary {
+            listings,
+            sales,
+            cancellations,
+            bids,
+        }: models::ActivitySummary,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            listings: listings.try_into()?,
+            sales: sales.try_into()?,
+            cancellations: cancellations.try_into()?,
+            bids: bids.try_into()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+#[graphql(description = "Width of the buckets in a time series")]
+pub enum Interval {
+    Hour,
+    Day,
+}
+
+impl From<Interval> for stats::Interval {
+    fn from(interval: Interval) -> Self {
+        match interval {
+            Interval::Hour => Self::Hour,
+            Interval::Day => Self::Day,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, GraphQLObject)]
+#[graphql(description = "The floor price of a collection over a single bucket of time")]
+pub struct FloorPoint {
+    #[graphql(description = "Start of the bucket")]
+    pub timestamp: DateTime,
+    #[graphql(description = "Lowest price of a listing active during the bucket, if any")]
+    pub floor: Option<Lamports>,
+}
+
+impl From<models::FloorPoint> for FloorPoint {
+    fn from(models::FloorPoint { timestamp, floor }: models::FloorPoint) -> Self {
+        Self {
+            timestamp: timestamp.into(),
+            floor: floor.map(Into::into),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use tables::storefronts;
 
 use super::prelude::*;
+use crate::asset_proxy::{self, ImageTransform};
 
 pub type StorefrontColumns = (
     storefronts::owner_address,
@@ -14,8 +15,7 @@ pub type StorefrontColumns = (
     storefronts::address,
 );
 
-#[derive(Debug, Clone, GraphQLObject)]
-#[graphql(description = "A Metaplex storefront")]
+#[derive(Debug, Clone)]
 pub struct Storefront {
     pub address: String,
     pub owner_address: String,
@@ -27,6 +27,44 @@ pub struct Storefront {
     pub banner_url: String,
 }
 
+#[graphql_object(Context = AppContext, description = "A Metaplex storefront")]
+impl Storefront {
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn owner_address(&self) -> &str {
+        &self.owner_address
+    }
+
+    pub fn subdomain(&self) -> &str {
+        &self.subdomain
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn favicon_url(&self, ctx: &AppContext) -> FieldResult<String> {
+        asset_proxy::proxy_image(&ctx.shared, &self.favicon_url, &ImageTransform::default())
+            .map_err(Into::into)
+    }
+
+    pub fn logo_url(&self, ctx: &AppContext) -> FieldResult<String> {
+        asset_proxy::proxy_image(&ctx.shared, &self.logo_url, &ImageTransform::default())
+            .map_err(Into::into)
+    }
+
+    pub fn banner_url(&self, ctx: &AppContext) -> FieldResult<String> {
+        asset_proxy::proxy_image(&ctx.shared, &self.banner_url, &ImageTransform::default())
+            .map_err(Into::into)
+    }
+}
+
 impl<'a> From<models::Storefront<'a>> for Storefront {
     fn from(
         models::Storefront {
@@ -0,0 +1,57 @@
+use objects::wallet::Wallet;
+use scalars::Volume;
+
+use super::prelude::*;
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(context = AppContext, description = "A wallet holding a balance of a token")]
+pub struct TokenHolder {
+    pub wallet: Wallet,
+    pub amount: Volume,
+    #[graphql(description = "Percentage of the total held balance owned by this wallet")]
+    pub percentage: f64,
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(context = AppContext, description = "The largest holders of a token")]
+pub struct TopHolders {
+    #[graphql(description = "Total balance held across all wallets")]
+    pub total_supply: Volume,
+    pub holders: Vec<TokenHolder>,
+}
+
+impl TryFrom<Vec<models::TokenHolder>> for TopHolders {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(rows: Vec<models::TokenHolder>) -> Result<Self, Self::Error> {
+        let total = rows.first().map_or(0, |r| r.total);
+
+        #[allow(clippy::cast_precision_loss)]
+        let holders = rows
+            .into_iter()
+            .map(
+                |models::TokenHolder {
+                     owner_address,
+                     amount,
+                     total,
+                     twitter_handle,
+                 }| {
+                    Ok(TokenHolder {
+                        wallet: Wallet::new(owner_address.into(), twitter_handle),
+                        amount: amount.try_into()?,
+                        percentage: if total == 0 {
+                            0.0
+                        } else {
+                            amount as f64 * 100.0 / total as f64
+                        },
+                    })
+                },
+            )
+            .collect::<Result<_, Self::Error>>()?;
+
+        Ok(Self {
+            total_supply: total.try_into()?,
+            holders,
+        })
+    }
+}
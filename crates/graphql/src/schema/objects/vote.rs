@@ -0,0 +1,85 @@
+use indexer_core::db::custom_types;
+use scalars::Volume;
+
+use super::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GraphQLEnum)]
+#[graphql(description = "The side of a governance proposal a vote was cast for")]
+pub enum VoteSide {
+    Pending,
+    Against,
+    For,
+    Abstain,
+}
+
+impl From<custom_types::VoteSide> for VoteSide {
+    fn from(side: custom_types::VoteSide) -> Self {
+        match side {
+            custom_types::VoteSide::Pending => Self::Pending,
+            custom_types::VoteSide::Against => Self::Against,
+            custom_types::VoteSide::For => Self::For,
+            custom_types::VoteSide::Abstain => Self::Abstain,
+        }
+    }
+}
+
+#[derive(Debug, Clone, GraphQLObject)]
+#[graphql(description = "A vote cast on a governance proposal")]
+pub struct Vote {
+    pub address: String,
+    pub proposal: String,
+    pub voter: String,
+    pub side: VoteSide,
+    pub weight: Volume,
+}
+
+impl<'a> TryFrom<models::Vote<'a>> for Vote {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::Vote {
+            address,
+            proposal,
+            voter,
+            side,
+            weight,
+            ..
+        }: models::Vote,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            address: address.into_owned(),
+            proposal: proposal.into_owned(),
+            voter: voter.into_owned(),
+            side: side.into(),
+            weight: weight.try_into()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_sides_are_pending() {
+        let vote = |side: i16| {
+            Vote::try_from(models::Vote {
+                address: Borrowed("vote"),
+                proposal: Borrowed("proposal"),
+                voter: Borrowed("voter"),
+                bump: 255,
+                side: side.into(),
+                weight: 10,
+            })
+            .unwrap()
+            .side
+        };
+
+        assert_eq!(vote(0), VoteSide::Pending);
+        assert_eq!(vote(1), VoteSide::Against);
+        assert_eq!(vote(2), VoteSide::For);
+        assert_eq!(vote(3), VoteSide::Abstain);
+        assert_eq!(vote(4), VoteSide::Pending);
+        assert_eq!(vote(-1), VoteSide::Pending);
+    }
+}
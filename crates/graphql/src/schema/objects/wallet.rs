@@ -1,6 +1,10 @@
 use indexer_core::db::queries;
 use objects::{
-    auction_house::AuctionHouse, listing::Bid, nft::NftCreator, profile::TwitterProfile,
+    auction_house::AuctionHouse,
+    graph_connection::{ConnectionDirection, GraphConnection},
+    listing::Bid,
+    nft::{Nft, NftCreator},
+    profile::TwitterProfile,
 };
 use scalars::PublicKey;
 use tables::{bids, graph_connections};
@@ -116,6 +120,43 @@ impl Wallet {
             .map_err(Into::into)
     }
 
+    #[graphql(
+        description = "Wallets following or followed by this wallet, with their Twitter handles",
+        arguments(
+            direction(description = "Whether to list followed wallets or followers"),
+            limit(description = "Maximum number of connections to return (default 25)"),
+            offset(description = "Number of connections to skip (default 0)"),
+        )
+    )]
+    pub fn connections(
+        &self,
+        ctx: &AppContext,
+        direction: ConnectionDirection,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> FieldResult<Vec<GraphConnection>> {
+        let limit = limit.unwrap_or(25);
+        let offset = offset.unwrap_or(0);
+
+        if limit < 0 || offset < 0 {
+            return Err(FieldError::new(
+                "Invalid argument! limit and offset must not be negative",
+                graphql_value!({ "Arguments": "limit: Int, offset: Int" }),
+            ));
+        }
+
+        let db_conn = ctx.shared.db.get()?;
+        let wallet = vec![String::from(self.address.clone())];
+        let (from, to) = match direction {
+            ConnectionDirection::Following => (wallet, vec![]),
+            ConnectionDirection::Followers => (vec![], wallet),
+        };
+
+        let rows = queries::graph_connection::list(&db_conn, from, to, limit, offset)?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
     pub fn connection_counts(&self) -> FieldResult<ConnectionCounts> {
         Ok(ConnectionCounts {
             address: self.address.clone(),
@@ -162,3 +203,303 @@ impl ConnectionCounts {
         Ok(count.try_into()?)
     }
 }
+
+/// Most NFTs a `WalletProfile` will list
+pub const WALLET_PROFILE_NFT_SAMPLE: i32 = 25;
+
+/// Follower and followed wallet counts loaded for a `WalletProfile`
+#[derive(Debug, Clone, Copy)]
+pub struct WalletConnectionCounts {
+    pub followers: i32,
+    pub following: i32,
+}
+
+impl TryFrom<models::ConnectionCounts> for WalletConnectionCounts {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(
+        models::ConnectionCounts {
+            wallet: _,
+            followers,
+            following,
+        }: models::ConnectionCounts,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self {
+            followers: followers.try_into()?,
+            following: following.try_into()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WalletProfile {
+    pub address: PublicKey<Wallet>,
+    pub twitter_handle: Option<String>,
+}
+
+#[graphql_object(
+    Context = AppContext,
+    description = "A summary of a wallet's Twitter profile, connections, and NFTs"
+)]
+impl WalletProfile {
+    pub fn address(&self) -> &PublicKey<Wallet> {
+        &self.address
+    }
+
+    pub async fn twitter(&self, ctx: &AppContext) -> FieldResult<Option<TwitterProfile>> {
+        let twitter_handle = match self.twitter_handle {
+            Some(ref t) => t.clone(),
+            None => return Ok(None),
+        };
+
+        ctx.twitter_profile_loader
+            .load(twitter_handle)
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Number of wallets following this wallet")]
+    pub async fn followers(&self, ctx: &AppContext) -> FieldResult<i32> {
+        let counts = ctx
+            .wallet_connection_counts_loader
+            .load(self.address.clone())
+            .await?;
+
+        Ok(counts.map_or(0, |c| c.followers))
+    }
+
+    #[graphql(description = "Number of wallets this wallet follows")]
+    pub async fn following(&self, ctx: &AppContext) -> FieldResult<i32> {
+        let counts = ctx
+            .wallet_connection_counts_loader
+            .load(self.address.clone())
+            .await?;
+
+        Ok(counts.map_or(0, |c| c.following))
+    }
+
+    #[graphql(
+        description = "A sample of NFTs held by this wallet, excluding fungible tokens",
+        arguments(limit(
+            description = "Maximum number of NFTs to return (default 5, at most 25)"
+        ))
+    )]
+    pub async fn nfts(&self, ctx: &AppContext, limit: Option<i32>) -> FieldResult<Vec<Nft>> {
+        let limit = limit.unwrap_or(5);
+
+        if !(0..=WALLET_PROFILE_NFT_SAMPLE).contains(&limit) {
+            return Err(FieldError::new(
+                "Invalid argument! limit must be between 0 and 25",
+                graphql_value!({ "Arguments": "limit: Int" }),
+            ));
+        }
+
+        let mut nfts = ctx.wallet_nfts_loader.load(self.address.clone()).await?;
+        nfts.truncate(limit.try_into()?);
+
+        Ok(nfts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexer_core::db::{
+        insert_into,
+        tables::{
+            graph_connections, metadata_jsons, metadatas, token_accounts,
+            twitter_handle_name_services,
+        },
+        testing,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{context, execute};
+
+    fn follow(
+        address: &'static str,
+        from: &'static str,
+        to: &'static str,
+    ) -> models::GraphConnection<'static> {
+        models::GraphConnection {
+            address: Borrowed(address),
+            from_account: Borrowed(from),
+            to_account: Borrowed(to),
+        }
+    }
+
+    #[test]
+    fn follow_graph() {
+        let (ctx, db) = match context() {
+            Some(c) => c,
+            None => return,
+        };
+        let conn = db.get().unwrap();
+
+        insert_into(graph_connections::table)
+            .values(&[
+                follow("c1", "alice", "bob"),
+                follow("c2", "alice", "carol"),
+                follow("c3", "bob", "alice"),
+                follow("c4", "dave", "bob"),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(twitter_handle_name_services::table)
+            .values(&models::TwitterHandle {
+                address: Borrowed("bob-name"),
+                wallet_address: Borrowed("bob"),
+                twitter_handle: Borrowed("bobby"),
+                slot: 1,
+            })
+            .execute(&conn)
+            .unwrap();
+
+        let connections = |wallet: &str, direction: &str, page: &str| {
+            execute(
+                &ctx,
+                &format!(
+                    r#"{{
+                        wallet(address: "{}") {{
+                            connections(direction: {}{}) {{
+                                address
+                                from {{ address }}
+                                to {{ address profile {{ handle }} }}
+                            }}
+                        }}
+                    }}"#,
+                    wallet, direction, page
+                ),
+            )["wallet"]["connections"]
+                .clone()
+        };
+        let edge = |address, from, to, handle: Option<&str>| {
+            json!({
+                "address": address,
+                "from": { "address": from },
+                "to": { "address": to, "profile": handle.map(|h| json!({ "handle": h })) },
+            })
+        };
+
+        assert_eq!(
+            connections("alice", "FOLLOWING", ""),
+            json!([
+                edge("c1", "alice", "bob", Some("bobby")),
+                edge("c2", "alice", "carol", None),
+            ])
+        );
+        assert_eq!(
+            connections("bob", "FOLLOWERS", ""),
+            json!([
+                edge("c1", "alice", "bob", Some("bobby")),
+                edge("c4", "dave", "bob", Some("bobby")),
+            ])
+        );
+        assert_eq!(
+            connections("bob", "FOLLOWERS", ", limit: 1, offset: 1"),
+            json!([edge("c4", "dave", "bob", Some("bobby"))])
+        );
+        assert_eq!(connections("carol", "FOLLOWING", ""), json!([]));
+
+        assert_eq!(
+            execute(
+                &ctx,
+                r#"{
+                    wallet(address: "bob") {
+                        connectionCounts { fromCount toCount }
+                    }
+                }"#,
+            ),
+            json!({ "wallet": { "connectionCounts": { "fromCount": 1, "toCount": 2 } } })
+        );
+    }
+
+    #[test]
+    fn wallet_profile() {
+        const WALLET: &str = "11111111111111111111111111111111";
+
+        let (ctx, db) = match context() {
+            Some(c) => c,
+            None => return,
+        };
+        let conn = db.get().unwrap();
+
+        insert_into(graph_connections::table)
+            .values(&[
+                follow("c1", "alice", WALLET),
+                follow("c2", "bob", WALLET),
+                follow("c3", WALLET, "carol"),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(twitter_handle_name_services::table)
+            .values(&models::TwitterHandle {
+                address: Borrowed("wallet-name"),
+                wallet_address: Borrowed(WALLET),
+                twitter_handle: Borrowed("degods"),
+                slot: 1,
+            })
+            .execute(&conn)
+            .unwrap();
+        insert_into(metadatas::table)
+            .values(&[
+                testing::metadata("nft_c", "mint_c", false),
+                testing::metadata("nft_a", "mint_a", false),
+                testing::metadata("nft_b", "mint_b", false),
+                testing::metadata("token", "mint_t", false),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(metadata_jsons::table)
+            .values(&[
+                testing::metadata_json("nft_a", ""),
+                testing::metadata_json("nft_b", ""),
+                testing::metadata_json("nft_c", ""),
+                testing::metadata_json("token", ""),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(token_accounts::table)
+            .values(&[
+                testing::token_account("ta", "mint_a", WALLET, 1),
+                testing::token_account("tb", "mint_b", WALLET, 1),
+                testing::token_account("tc", "mint_c", WALLET, 1),
+                testing::token_account("tt", "mint_t", WALLET, 500),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        assert_eq!(
+            execute(
+                &ctx,
+                &format!(
+                    r#"{{
+                        walletProfile(address: "{}") {{
+                            address
+                            twitter {{ handle }}
+                            followers
+                            following
+                            nfts {{ address }}
+                            sample: nfts(limit: 2) {{ address }}
+                        }}
+                    }}"#,
+                    WALLET
+                ),
+            ),
+            json!({
+                "walletProfile": {
+                    "address": WALLET,
+                    "twitter": { "handle": "degods" },
+                    "followers": 2,
+                    "following": 1,
+                    "nfts": [
+                        { "address": "nft_a" },
+                        { "address": "nft_b" },
+                        { "address": "nft_c" },
+                    ],
+                    "sample": [{ "address": "nft_a" }, { "address": "nft_b" }],
+                },
+            })
+        );
+    }
+}
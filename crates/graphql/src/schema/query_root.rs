@@ -1,25 +1,61 @@
 use indexer_core::db::queries;
 use objects::{
     auction_house::AuctionHouse,
+    bid_receipt::BidReceipt,
+    candy_machine::CandyMachine,
+    connection,
     creator::Creator,
+    data_quality::{DataQualityAnomaly, DataQualityScope},
     denylist::Denylist,
+    escrow::Escrow,
     graph_connection::GraphConnection,
     listing::{Listing, ListingColumns, ListingRow},
+    listing_receipt::{ListingReceipt, ListingSort},
     marketplace::Marketplace,
-    nft::{Nft, NftCount, NftCreator},
-    profile::{Profile, TwitterProfilePictureResponse, TwitterShowResponse},
+    nft::{
+        ActivityType, Nft, NftActivityConnection, NftActivityEdge, NftCount, NftCreator, NftFull,
+        TokenStandard,
+    },
+    ownership::OwnershipEvent,
+    profile::{Profile, TwitterProfile, TwitterProfilePictureResponse, TwitterShowResponse},
+    proposal::{Proposal, ProposalState},
+    purchase_receipt::{PurchaseReceipt, SuspiciousTrade},
+    search::{escape_like, SearchResult},
+    smart_wallet::SmartWallet,
+    stats::{ActivitySummary, FloorPoint, Interval, MarketplaceStats},
     storefront::{Storefront, StorefrontColumns},
-    wallet::Wallet,
+    token_holder::TopHolders,
+    wallet::{Wallet, WalletProfile},
+};
+use scalars::{
+    markers::{Governor, StoreConfig, TokenMint},
+    DateTime, Lamports, PublicKey,
 };
-use scalars::PublicKey;
 use tables::{
-    auction_caches, auction_datas, auction_datas_ext, metadata_jsons, metadatas,
-    store_config_jsons, storefronts,
+    auction_caches, auction_datas, auction_datas_ext, candy_machines, escrows, files,
+    metadata_collections, metadata_jsons, metadatas, store_config_jsons, store_configs,
+    storefronts, twitter_handle_name_services,
 };
 
 use super::prelude::*;
+use crate::metrics;
 pub struct QueryRoot;
 
+/// Maximum number of results of each type returned by `search`
+const SEARCH_LIMIT_PER_TYPE: i32 = 5;
+
+/// Maximum number of results returned by `search`
+const SEARCH_LIMIT: usize = 10;
+
+/// Maximum number of NFTs returned by `searchNfts`
+const MAX_SEARCH_NFTS_LIMIT: i32 = 100;
+
+/// Longest window, in hours, over which `marketplaceStats` will sum sales
+const MAX_MARKETPLACE_STATS_WINDOW_HOURS: i32 = 24 * 30;
+
+/// Maximum number of buckets returned by `collectionFloor`
+const MAX_FLOOR_BUCKETS: i64 = 24 * 31;
+
 #[derive(GraphQLInputObject, Clone, Debug)]
 #[graphql(description = "Filter on NFT attributes")]
 struct AttributeFilter {
@@ -45,6 +81,8 @@ impl QueryRoot {
         ctx: &AppContext,
         #[graphql(description = "Twitter handle")] handle: String,
     ) -> Option<Profile> {
+        let _timer = metrics::time_resolver("Query.profile");
+
         let twitter_bearer_token = &ctx.shared.twitter_bearer_token;
         let http_client = reqwest::Client::new();
 
@@ -126,7 +164,8 @@ impl QueryRoot {
     ) -> FieldResult<Creator> {
         let conn = context.shared.db.get().context("failed to connect to db")?;
 
-        let twitter_handle = queries::twitter_handle_name_service::get(&conn, &address)?;
+        let max_slot = queries::slot::max_confirmed(&conn, context.shared.confirmation_depth)?;
+        let twitter_handle = queries::twitter_handle_name_service::get(&conn, &address, max_slot)?;
 
         Ok(Creator {
             address,
@@ -146,9 +185,12 @@ impl QueryRoot {
         >,
         #[graphql(description = "Filter on attributes")] attributes: Option<Vec<AttributeFilter>>,
         #[graphql(description = "Filter on listed")] listed: Option<Vec<PublicKey<AuctionHouse>>>,
+        #[graphql(description = "Filter on token standard")] token_standard: Option<TokenStandard>,
         #[graphql(description = "Limit for query")] limit: i32,
         #[graphql(description = "Offset for query")] offset: i32,
     ) -> FieldResult<Vec<Nft>> {
+        let _timer = metrics::time_resolver("Query.nfts");
+
         if owners.is_none() && creators.is_none() && listed.is_none() && offerers.is_none() {
             return Err(FieldError::new(
                 "No filter provided! Please provide at least one of the filters",
@@ -164,6 +206,8 @@ impl QueryRoot {
             offerers: offerers.map(|a| a.into_iter().map(Into::into).collect()),
             attributes: attributes.map(|a| a.into_iter().map(Into::into).collect()),
             listed: listed.map(|a| a.into_iter().map(Into::into).collect()),
+            token_standard: token_standard.map(Into::into),
+            after: None,
             limit: limit.into(),
             offset: offset.into(),
         };
@@ -172,6 +216,332 @@ impl QueryRoot {
         Ok(nfts.into_iter().map(Into::into).collect())
     }
 
+    #[graphql(description = "NFTs whose name fuzzily matches a search term, best matches first")]
+    fn search_nfts(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Search term")] query: String,
+        #[graphql(description = "Maximum number of NFTs to return (default 25, at most 100)")]
+        limit: Option<i32>,
+    ) -> FieldResult<Vec<Nft>> {
+        let _timer = metrics::time_resolver("Query.searchNfts");
+
+        let limit = limit.unwrap_or(25);
+
+        if !(0..=MAX_SEARCH_NFTS_LIMIT).contains(&limit) {
+            return Err(FieldError::new(
+                "Invalid argument! limit must be between 0 and 100",
+                graphql_value!({ "Arguments": "limit: Int" }),
+            ));
+        }
+
+        let query = query.trim();
+
+        if query.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let conn = context.shared.db.get()?;
+        let nfts = queries::metadatas::search(&conn, query, limit)?;
+
+        Ok(nfts.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(
+        description = "NFTs, marketplaces, and profiles matching a search term, most \
+                             relevant first"
+    )]
+    async fn search(&self, context: &AppContext, query: String) -> FieldResult<Vec<SearchResult>> {
+        let _timer = metrics::time_resolver("Query.search");
+
+        let query = query.trim();
+
+        if query.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let (nfts, marketplaces, handles) = {
+            let conn = context.shared.db.get()?;
+            let prefix = format!("{}%", escape_like(query));
+            let contains = format!("%{}%", escape_like(query));
+
+            let nfts = queries::metadatas::search(&conn, query, SEARCH_LIMIT_PER_TYPE)?;
+
+            let marketplaces: Vec<(models::StoreConfigJson, Option<String>)> =
+                store_config_jsons::table
+                    .left_join(
+                        store_configs::table
+                            .on(store_configs::address.eq(store_config_jsons::config_address)),
+                    )
+                    .filter(
+                        store_config_jsons::name
+                            .ilike(&contains)
+                            .or(store_config_jsons::subdomain.ilike(&prefix)),
+                    )
+                    .select((
+                        store_config_jsons::all_columns,
+                        store_configs::settings_uri.nullable(),
+                    ))
+                    .order(store_config_jsons::name.asc())
+                    .limit(SEARCH_LIMIT_PER_TYPE.into())
+                    .load(&conn)
+                    .context("Failed to search marketplaces")?;
+
+            let max_slot = queries::slot::max_confirmed(&conn, context.shared.confirmation_depth)?;
+            let handles: Vec<String> = twitter_handle_name_services::table
+                .filter(twitter_handle_name_services::twitter_handle.ilike(&prefix))
+                .filter(twitter_handle_name_services::slot.le(max_slot))
+                .select(twitter_handle_name_services::twitter_handle)
+                .distinct()
+                .order(twitter_handle_name_services::twitter_handle.asc())
+                .limit(SEARCH_LIMIT_PER_TYPE.into())
+                .load(&conn)
+                .context("Failed to search Twitter handles")?;
+
+            (nfts, marketplaces, handles)
+        };
+
+        let mut profiles = context
+            .twitter_profile_loader
+            .load_many(handles.clone())
+            .await;
+
+        let mut results: Vec<_> = handles
+            .into_iter()
+            .map(|h| {
+                // Fall back to the bare handle rather than failing the search if
+                // the Twitter API is unavailable
+                let profile = profiles.remove(&h).and_then(Result::ok).flatten();
+
+                SearchResult::Profile(profile.unwrap_or_else(|| TwitterProfile::handle_only(h)))
+            })
+            .chain(
+                marketplaces
+                    .into_iter()
+                    .map(|m| SearchResult::Marketplace(m.into())),
+            )
+            .chain(nfts.into_iter().map(|n| SearchResult::Nft(n.into())))
+            .collect();
+
+        // Stable, so the order of results from each subquery is kept
+        results.sort_by_key(|r| std::cmp::Reverse(r.relevance(query)));
+        results.truncate(SEARCH_LIMIT);
+
+        Ok(results)
+    }
+
+    #[graphql(description = "NFTs held by a wallet, excluding fungible tokens")]
+    fn nfts_by_owner(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the wallet")] owner: PublicKey<Wallet>,
+        #[graphql(description = "Maximum number of NFTs to return (default 25)")] limit: Option<
+            i32,
+        >,
+        #[graphql(description = "Number of NFTs to skip (default 0)")] offset: Option<i32>,
+    ) -> FieldResult<Vec<Nft>> {
+        let _timer = metrics::time_resolver("Query.nftsByOwner");
+
+        let limit = limit.unwrap_or(25);
+        let offset = offset.unwrap_or(0);
+
+        if limit < 0 || offset < 0 {
+            return Err(FieldError::new(
+                "Invalid argument! limit and offset must not be negative",
+                graphql_value!({ "Arguments": "limit: Int, offset: Int" }),
+            ));
+        }
+
+        let conn = context.shared.db.get()?;
+
+        let max_slot = queries::slot::max_confirmed(&conn, context.shared.confirmation_depth)?;
+        let nfts =
+            queries::metadatas::owned_by(&conn, &owner, max_slot, limit.into(), offset.into())?;
+
+        Ok(nfts.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(description = "Verified members of a collection")]
+    fn collection_nfts(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Mint address of the collection NFT")] collection: PublicKey<
+            TokenMint,
+        >,
+        #[graphql(description = "Maximum number of NFTs to return (default 25)")] limit: Option<
+            i32,
+        >,
+        #[graphql(description = "Number of NFTs to skip (default 0)")] offset: Option<i32>,
+    ) -> FieldResult<Vec<Nft>> {
+        let limit = limit.unwrap_or(25);
+        let offset = offset.unwrap_or(0);
+
+        if limit < 0 || offset < 0 {
+            return Err(FieldError::new(
+                "Invalid argument! limit and offset must not be negative",
+                graphql_value!({ "Arguments": "limit: Int, offset: Int" }),
+            ));
+        }
+
+        let conn = context.shared.db.get()?;
+
+        let nfts = queries::metadatas::collection_members(
+            &conn,
+            &collection,
+            limit.into(),
+            offset.into(),
+        )?;
+
+        Ok(nfts.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(description = "Active offers made by a wallet, newest first")]
+    fn offers_made(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the buyer's wallet")] buyer: PublicKey<Wallet>,
+        #[graphql(description = "Maximum number of offers to return (default 25)")] limit: Option<
+            i32,
+        >,
+        #[graphql(description = "Number of offers to skip (default 0)")] offset: Option<i32>,
+    ) -> FieldResult<Vec<BidReceipt>> {
+        let _timer = metrics::time_resolver("Query.offersMade");
+
+        let limit = limit.unwrap_or(25);
+        let offset = offset.unwrap_or(0);
+
+        if limit < 0 || offset < 0 {
+            return Err(FieldError::new(
+                "Invalid argument! limit and offset must not be negative",
+                graphql_value!({ "Arguments": "limit: Int, offset: Int" }),
+            ));
+        }
+
+        let conn = context.shared.db.get()?;
+        let rows =
+            queries::bid_receipts::made_by(&conn, buyer.as_ref(), limit.into(), offset.into())?;
+
+        rows.into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Active offers on NFTs held by a wallet, newest first")]
+    fn offers_received(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the seller's wallet")] seller: PublicKey<Wallet>,
+        #[graphql(description = "Maximum number of offers to return (default 25)")] limit: Option<
+            i32,
+        >,
+        #[graphql(description = "Number of offers to skip (default 0)")] offset: Option<i32>,
+    ) -> FieldResult<Vec<BidReceipt>> {
+        let _timer = metrics::time_resolver("Query.offersReceived");
+
+        let limit = limit.unwrap_or(25);
+        let offset = offset.unwrap_or(0);
+
+        if limit < 0 || offset < 0 {
+            return Err(FieldError::new(
+                "Invalid argument! limit and offset must not be negative",
+                graphql_value!({ "Arguments": "limit: Int, offset: Int" }),
+            ));
+        }
+
+        let conn = context.shared.db.get()?;
+
+        let max_slot = queries::slot::max_confirmed(&conn, context.shared.confirmation_depth)?;
+        let rows = queries::bid_receipts::received_by(
+            &conn,
+            seller.as_ref(),
+            max_slot,
+            limit.into(),
+            offset.into(),
+        )?;
+
+        rows.into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Every recorded sale of an NFT, oldest first")]
+    fn sales_history(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Metadata address of the NFT")] metadata: PublicKey<Nft>,
+    ) -> FieldResult<Vec<PurchaseReceipt>> {
+        let _timer = metrics::time_resolver("Query.salesHistory");
+
+        let conn = context.shared.db.get()?;
+        let rows = queries::purchase_receipts::sales_history(&conn, metadata.as_ref())?;
+
+        rows.into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "The chain of custody of an NFT, oldest first, reconstructed from its \
+                             token accounts and auction house sales"
+    )]
+    fn ownership_history(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Mint address of the NFT")] mint: PublicKey<TokenMint>,
+    ) -> FieldResult<Vec<OwnershipEvent>> {
+        let _timer = metrics::time_resolver("Query.ownershipHistory");
+
+        let conn = context.shared.db.get()?;
+        let max_slot = queries::slot::max_confirmed(&conn, context.shared.confirmation_depth)?;
+        let events = queries::token_accounts::ownership_history(&conn, mint.as_ref(), max_slot)?;
+
+        Ok(events.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(description = "NFTs held by a Goki smart wallet treasury, excluding fungible tokens")]
+    fn treasury_nfts(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the smart wallet")] smart_wallet: PublicKey<
+            SmartWallet,
+        >,
+        #[graphql(description = "Maximum number of NFTs to return (default 25)")] first: Option<
+            i32,
+        >,
+        #[graphql(description = "Return NFTs after this NFT address")] after: Option<
+            PublicKey<Nft>,
+        >,
+    ) -> FieldResult<Vec<Nft>> {
+        let first = first.unwrap_or(25);
+
+        if first < 0 {
+            return Err(FieldError::new(
+                "Invalid argument! first must not be negative",
+                graphql_value!({ "Arguments": "first: Int" }),
+            ));
+        }
+
+        let conn = context.shared.db.get()?;
+
+        let query_options = queries::metadatas::ListQueryOptions {
+            owners: Some(vec![smart_wallet.into()]),
+            creators: None,
+            offerers: None,
+            attributes: None,
+            listed: None,
+            token_standard: None,
+            after: after.map(Into::into),
+            limit: first.into(),
+            offset: 0,
+        };
+        let nfts = queries::metadatas::list(&conn, query_options)?;
+
+        Ok(nfts.into_iter().map(Into::into).collect())
+    }
+
     fn wallet(
         &self,
         context: &AppContext,
@@ -179,12 +549,48 @@ impl QueryRoot {
     ) -> FieldResult<Wallet> {
         let conn = context.shared.db.get()?;
 
-        let twitter_handle = queries::twitter_handle_name_service::get(&conn, &address)?;
+        let max_slot = queries::slot::max_confirmed(&conn, context.shared.confirmation_depth)?;
+        let twitter_handle = queries::twitter_handle_name_service::get(&conn, &address, max_slot)?;
 
         Ok(Wallet::new(address, twitter_handle))
     }
 
+    #[graphql(description = "A wallet's Twitter profile, connection counts, and sample of NFTs")]
+    fn wallet_profile(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the wallet")] address: PublicKey<Wallet>,
+    ) -> FieldResult<WalletProfile> {
+        let _timer = metrics::time_resolver("Query.walletProfile");
+
+        let conn = context.shared.db.get()?;
+
+        let max_slot = queries::slot::max_confirmed(&conn, context.shared.confirmation_depth)?;
+        let twitter_handle = queries::twitter_handle_name_service::get(&conn, &address, max_slot)?;
+
+        Ok(WalletProfile {
+            address,
+            twitter_handle,
+        })
+    }
+
+    #[graphql(description = "Look up the wallet linked to a Twitter handle, if any")]
+    fn wallet_by_handle(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Twitter handle")] handle: String,
+    ) -> FieldResult<Option<Wallet>> {
+        let conn = context.shared.db.get()?;
+
+        let max_slot = queries::slot::max_confirmed(&conn, context.shared.confirmation_depth)?;
+        let address = queries::twitter_handle_name_service::get_wallet(&conn, &handle, max_slot)?;
+
+        Ok(address.map(|a| Wallet::new(a.into(), Some(handle))))
+    }
+
     fn listings(&self, context: &AppContext) -> FieldResult<Vec<Listing>> {
+        let _timer = metrics::time_resolver("Query.listings");
+
         let now = Local::now().naive_utc();
         let conn = context.shared.db.get()?;
 
@@ -214,11 +620,492 @@ impl QueryRoot {
             .map_err(Into::into)
     }
 
+    #[graphql(
+        description = "Active listings of a collection priced at or near its floor, cheapest first"
+    )]
+    fn listed_below_floor(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Verified creator address of the collection")]
+        collection: PublicKey<Creator>,
+        #[graphql(description = "Auction house the listings were made on")]
+        auction_house: PublicKey<AuctionHouse>,
+        #[graphql(
+            description = "Include listings priced up to this percentage above the floor (default 10)"
+        )]
+        within_percent: Option<i32>,
+        #[graphql(description = "Maximum number of listings to return (default 25)")] first: Option<
+            i32,
+        >,
+        #[graphql(
+            description = "Ignore listings whose primary sale has not happened (default true)"
+        )]
+        secondary_only: Option<bool>,
+    ) -> FieldResult<Vec<ListingReceipt>> {
+        let _timer = metrics::time_resolver("Query.listedBelowFloor");
+
+        let within_percent = within_percent.unwrap_or(10);
+        let first = first.unwrap_or(25);
+        let secondary_only = secondary_only.unwrap_or(true);
+
+        if within_percent < 0 || first < 0 {
+            return Err(FieldError::new(
+                "Invalid argument! withinPercent and first must not be negative",
+                graphql_value!({ "Arguments": "withinPercent: Int, first: Int" }),
+            ));
+        }
+
+        let conn = context.pool("listedBelowFloor").get()?;
+        let rows = queries::listing_receipts::below_floor(
+            &conn,
+            collection,
+            auction_house,
+            within_percent,
+            first,
+            secondary_only,
+        )?;
+
+        rows.into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Active (neither purchased nor canceled) listings, optionally \
+                             filtered by auction house and price"
+    )]
+    fn active_listings(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Only return listings made on this auction house")]
+        auction_house: Option<PublicKey<AuctionHouse>>,
+        #[graphql(description = "Only return listings priced at or above this amount")]
+        min_price: Option<Lamports>,
+        #[graphql(description = "Only return listings priced at or below this amount")]
+        max_price: Option<Lamports>,
+        #[graphql(description = "Order to return listings in (default PRICE_ASC)")] sort: Option<
+            ListingSort,
+        >,
+        #[graphql(description = "Maximum number of listings to return (default 25)")] limit: Option<
+            i32,
+        >,
+        #[graphql(description = "Number of listings to skip (default 0)")] offset: Option<i32>,
+    ) -> FieldResult<Vec<ListingReceipt>> {
+        let _timer = metrics::time_resolver("Query.activeListings");
+
+        let limit = limit.unwrap_or(25);
+        let offset = offset.unwrap_or(0);
+
+        if limit < 0 || offset < 0 {
+            return Err(FieldError::new(
+                "Invalid argument! limit and offset must not be negative",
+                graphql_value!({ "Arguments": "limit: Int, offset: Int" }),
+            ));
+        }
+
+        let price = |p: Lamports| i64::try_from(u64::from(p)).unwrap_or(i64::MAX);
+
+        let conn = context.shared.db.get()?;
+        let rows =
+            queries::listing_receipts::active(&conn, queries::listing_receipts::ActiveOptions {
+                auction_house: auction_house.map(Into::into),
+                min_price: min_price.map(price),
+                max_price: max_price.map(price),
+                sort: sort.unwrap_or(ListingSort::PriceAsc).into(),
+                limit: limit.into(),
+                offset: offset.into(),
+            })?;
+
+        rows.into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Counts of auction house listings, sales, cancellations, and bids")]
+    async fn activity_summary(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the auction house")] auction_house: PublicKey<
+            AuctionHouse,
+        >,
+        #[graphql(description = "Hours of activity to count (default 24)")] window_hours: Option<
+            i32,
+        >,
+    ) -> FieldResult<ActivitySummary> {
+        let _timer = metrics::time_resolver("Query.activitySummary");
+
+        let window_hours = window_hours.unwrap_or(24);
+
+        if window_hours < 0 {
+            return Err(FieldError::new(
+                "Invalid argument! windowHours must not be negative",
+                graphql_value!({ "Arguments": "windowHours: Int" }),
+            ));
+        }
+
+        let auction_house = auction_house.to_string();
+        let pool = Arc::clone(context.pool("activitySummary"));
+
+        let summary = context
+            .shared
+            .activity_summary_cache
+            .get_or_try_insert(
+                (auction_house.clone(), window_hours),
+                move || -> Result<_> {
+                    let conn = pool.get()?;
+                    let since =
+                        Local::now().naive_utc() - chrono::Duration::hours(window_hours.into());
+
+                    queries::stats::activity(&conn, &auction_house, since)
+                },
+            )
+            .await?;
+
+        summary.try_into().map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Floor, average, and 24-hour volume of an auction house's listings",
+        arguments(
+            auction_house(description = "Address of the auction house"),
+            mints(description = "Only return stats for these treasury mints"),
+            secondary_only(
+                description = "Exclude listings whose primary sale has not happened from the \
+                               floor",
+                default = true,
+            ),
+        )
+    )]
+    async fn mint_stats(
+        &self,
+        context: &AppContext,
+        auction_house: PublicKey<AuctionHouse>,
+        mints: Option<Vec<PublicKey<TokenMint>>>,
+        secondary_only: bool,
+    ) -> FieldResult<Vec<MintStats>> {
+        let stats = context.mint_stats_loader.load(auction_house).await?;
+
+        Ok(stats
+            .into_iter()
+            .filter(|s| {
+                mints
+                    .as_ref()
+                    .map_or(true, |m| m.iter().any(|k| k.as_ref() == s.mint))
+            })
+            .map(|s| s.secondary_only(secondary_only))
+            .collect())
+    }
+
+    #[graphql(
+        description = "Floor price, sale volume, listing count, and holder count for a \
+                       marketplace.  Results are cached and may be up to a few minutes old."
+    )]
+    async fn marketplace_stats(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the marketplace's store config")]
+        store_config: PublicKey<StoreConfig>,
+        #[graphql(description = "Hours of sales to sum for the volume (default 24, at most 720)")]
+        window_hours: Option<i32>,
+    ) -> FieldResult<MarketplaceStats> {
+        let _timer = metrics::time_resolver("Query.marketplaceStats");
+
+        let window_hours = window_hours.unwrap_or(24);
+
+        if !(0..=MAX_MARKETPLACE_STATS_WINDOW_HOURS).contains(&window_hours) {
+            return Err(FieldError::new(
+                "Invalid argument! windowHours must be between 0 and 720",
+                graphql_value!({ "Arguments": "windowHours: Int" }),
+            ));
+        }
+
+        let store_config = store_config.to_string();
+        let pool = Arc::clone(context.pool("marketplaceStats"));
+
+        // The summary scans every NFT and receipt in the marketplace, so it
+        // is shared between requests for the aggregate cache TTL, and the
+        // window is capped to bound the size of the sales scan
+        let summary = context
+            .shared
+            .marketplace_stats_cache
+            .get_or_try_insert(
+                (store_config.clone(), window_hours),
+                move || -> Result<_> {
+                    let conn = pool.get()?;
+                    let since =
+                        Local::now().naive_utc() - chrono::Duration::hours(window_hours.into());
+
+                    queries::stats::marketplace_summary(&conn, &store_config, since)
+                },
+            )
+            .await?;
+
+        summary.try_into().map_err(Into::into)
+    }
+
+    #[graphql(
+        description = "Floor price of a collection over time, as the lowest price of a listing \
+                       active during each hour or day between from and to"
+    )]
+    fn collection_floor(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "First verified creator address of the collection")]
+        creator: PublicKey<Creator>,
+        #[graphql(description = "Start of the first bucket, rounded down to the interval")]
+        from: DateTime,
+        #[graphql(description = "End of the time range, exclusive")] to: DateTime,
+        #[graphql(description = "Width of each bucket")] interval: Interval,
+    ) -> FieldResult<Vec<FloorPoint>> {
+        let _timer = metrics::time_resolver("Query.collectionFloor");
+
+        let from: NaiveDateTime = from.into();
+        let to: NaiveDateTime = to.into();
+        let interval: queries::stats::Interval = interval.into();
+
+        if from >= to {
+            return Err(FieldError::new(
+                "Invalid argument! from must be before to",
+                graphql_value!({ "Arguments": "from: DateTime, to: DateTime" }),
+            ));
+        }
+
+        if (to - from).num_seconds() / interval.duration().num_seconds() >= MAX_FLOOR_BUCKETS {
+            return Err(FieldError::new(
+                "Invalid argument! Too many buckets between from and to for the interval",
+                graphql_value!({ "Arguments": "from: DateTime, to: DateTime, interval: Interval" }),
+            ));
+        }
+
+        let conn = context.pool("collectionFloor").get()?;
+        let rows = queries::stats::floor_history(&conn, creator, from, to, interval)?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(
+        description = "Run consistency checks over indexed data and report anomalies.  Requires an \
+                       administrative API key."
+    )]
+    fn data_quality_report(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Kinds of data to check (default all)")] scope: Option<
+            Vec<DataQualityScope>,
+        >,
+        #[graphql(description = "Maximum anomalies to return per check (default 100)")]
+        first: Option<i32>,
+    ) -> FieldResult<Vec<DataQualityAnomaly>> {
+        context.require_admin()?;
+
+        let first = first.unwrap_or(100);
+
+        if first < 0 {
+            return Err(FieldError::new(
+                "Invalid argument! first must not be negative",
+                graphql_value!({ "Arguments": "first: Int" }),
+            ));
+        }
+
+        let scope: Option<Vec<queries::data_quality::Scope>> =
+            scope.map(|s| s.into_iter().map(Into::into).collect());
+
+        let conn = context.pool("dataQualityReport").get()?;
+        let rows = queries::data_quality::report(&conn, scope.as_deref(), first)?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(description = "A governance proposal by its address")]
+    fn proposal(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the proposal")] address: PublicKey<Proposal>,
+    ) -> FieldResult<Option<Proposal>> {
+        let conn = context.shared.db.get()?;
+        let max_slot = queries::slot::max_confirmed(&conn, context.shared.confirmation_depth)?;
+
+        Ok(queries::proposals::get(&conn, address, max_slot)?.map(Into::into))
+    }
+
+    #[graphql(description = "Governance proposals of a governor, highest index first")]
+    fn proposals(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the governor")] governor: PublicKey<Governor>,
+        #[graphql(description = "Only return proposals currently in one of these states")]
+        states: Option<Vec<ProposalState>>,
+        #[graphql(description = "Maximum number of proposals to return (default 25)")]
+        limit: Option<i32>,
+        #[graphql(description = "Number of proposals to skip (default 0)")] offset: Option<i32>,
+    ) -> FieldResult<Vec<Proposal>> {
+        let limit = limit.unwrap_or(25);
+        let offset = offset.unwrap_or(0);
+
+        if limit < 0 || offset < 0 {
+            return Err(FieldError::new(
+                "Invalid argument! limit and offset must not be negative",
+                graphql_value!({ "Arguments": "limit: Int, offset: Int" }),
+            ));
+        }
+
+        let states: Option<Vec<_>> = states.map(|s| s.into_iter().map(Into::into).collect());
+
+        let conn = context.shared.db.get()?;
+        let max_slot = queries::slot::max_confirmed(&conn, context.shared.confirmation_depth)?;
+        let rows = queries::proposals::by_governor(
+            &conn,
+            governor,
+            states.as_deref(),
+            Utc::now().timestamp(),
+            limit,
+            offset,
+            max_slot,
+        )?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(description = "Governance proposals created by a wallet, newest first")]
+    fn proposals_by_proposer(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the proposer")] proposer: PublicKey<Wallet>,
+        #[graphql(description = "Maximum number of proposals to return (default 25)")]
+        first: Option<i32>,
+        #[graphql(description = "Return proposals after this cursor")] after: Option<String>,
+    ) -> FieldResult<Vec<Proposal>> {
+        let first = first.unwrap_or(25);
+
+        if first < 0 {
+            return Err(FieldError::new(
+                "Invalid argument! first must not be negative",
+                graphql_value!({ "Arguments": "first: Int" }),
+            ));
+        }
+
+        let after = after
+            .map(|c| {
+                Proposal::parse_cursor(&c).ok_or_else(|| {
+                    FieldError::new(
+                        "Invalid argument! after is not a valid cursor",
+                        graphql_value!({ "Arguments": "after: String" }),
+                    )
+                })
+            })
+            .transpose()?;
+
+        let conn = context.shared.db.get()?;
+        let max_slot = queries::slot::max_confirmed(&conn, context.shared.confirmation_depth)?;
+        let rows = queries::proposals::by_proposer(&conn, proposer, after, first, max_slot)?;
+
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(description = "Listing, sale, and bid activity for an NFT, newest first")]
+    fn nft_activities(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of NFT")] address: PublicKey<Nft>,
+        #[graphql(description = "Only return activities of these types")] types: Option<
+            Vec<ActivityType>,
+        >,
+        #[graphql(description = "Maximum number of activities to return (default 25)")]
+        first: Option<i32>,
+        #[graphql(description = "Return activities after this cursor")] after: Option<String>,
+    ) -> FieldResult<NftActivityConnection> {
+        let _timer = metrics::time_resolver("Query.nftActivities");
+
+        let (limit, after) = connection::parse_args(first, 25, after)?;
+
+        let conn = context.shared.db.get()?;
+        let types: Option<Vec<_>> = types.map(|t| t.into_iter().map(Into::into).collect());
+        let rows =
+            queries::metadatas::activity_page(&conn, address, after, limit, types.as_deref())?;
+
+        let (edges, page_info) = connection::paginate(rows, limit, |a| {
+            connection::encode_cursor(a.created_at, &queries::metadatas::activity_key(a))
+        });
+
+        Ok(NftActivityConnection {
+            edges: edges
+                .into_iter()
+                .map(|(cursor, a)| {
+                    Ok(NftActivityEdge {
+                        cursor,
+                        node: a.try_into()?,
+                    })
+                })
+                .collect::<FieldResult<_>>()?,
+            page_info,
+        })
+    }
+
+    #[graphql(description = "Sales of an NFT which look like part of a wash-trading cycle")]
+    fn suspicious_trades(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of NFT")] metadata: PublicKey<Nft>,
+        #[graphql(description = "Hours of preceding sales to compare each sale to (default 168)")]
+        window_hours: Option<i32>,
+        #[graphql(description = "Maximum distinct wallets in a flagged cycle (default 3)")]
+        max_wallets: Option<i32>,
+        #[graphql(description = "Minimum sales in a flagged cycle (default 3)")] min_sales: Option<
+            i32,
+        >,
+    ) -> FieldResult<Vec<SuspiciousTrade>> {
+        let defaults = queries::wash_trading::Thresholds::default();
+        let thresholds = queries::wash_trading::Thresholds {
+            window: window_hours.map_or(defaults.window, |h| chrono::Duration::hours(h.into())),
+            max_wallets: max_wallets.map_or(Ok(defaults.max_wallets), TryInto::try_into)?,
+            min_sales: min_sales.map_or(Ok(defaults.min_sales), TryInto::try_into)?,
+        };
+
+        let conn = context.pool("suspiciousTrades").get()?;
+        let rows = queries::wash_trading::suspicious_trades(&conn, metadata.as_ref(), thresholds)?;
+
+        rows.into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "The wallets holding the largest balances of a token")]
+    fn top_holders(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Mint address of the token")] mint: PublicKey<TokenMint>,
+        #[graphql(description = "Maximum number of holders to return (default 20)")] first: Option<
+            i32,
+        >,
+    ) -> FieldResult<TopHolders> {
+        let _timer = metrics::time_resolver("Query.topHolders");
+
+        let first = first.unwrap_or(20);
+
+        if first < 0 {
+            return Err(FieldError::new(
+                "Invalid argument! first must not be negative",
+                graphql_value!({ "Arguments": "first: Int" }),
+            ));
+        }
+
+        let conn = context.pool("topHolders").get()?;
+        let max_slot = queries::slot::max_confirmed(&conn, context.shared.confirmation_depth)?;
+        let rows = queries::token_accounts::top_holders(&conn, mint, first, max_slot)?;
+
+        rows.try_into().map_err(Into::into)
+    }
+
     fn nft(
         &self,
         context: &AppContext,
         #[graphql(description = "Address of NFT")] address: String,
     ) -> FieldResult<Option<Nft>> {
+        let _timer = metrics::time_resolver("Query.nft");
+
         let conn = context.shared.db.get()?;
         let mut rows: Vec<models::Nft> = metadatas::table
             .inner_join(
@@ -241,6 +1128,87 @@ impl QueryRoot {
         Ok(rows.pop().map(Into::into))
     }
 
+    #[graphql(description = "An NFT with all of its relationships, loaded in a single call")]
+    async fn nft_full(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Mint address of the NFT")] mint: PublicKey<TokenMint>,
+    ) -> FieldResult<Option<NftFull>> {
+        let _timer = metrics::time_resolver("Query.nftFull");
+
+        context.spend_nft_full()?;
+
+        let (nft, files, collection) = {
+            let conn = context.shared.db.get()?;
+
+            let mut rows: Vec<models::Nft> = metadatas::table
+                .inner_join(
+                    metadata_jsons::table
+                        .on(metadatas::address.eq(metadata_jsons::metadata_address)),
+                )
+                .filter(metadatas::mint_address.eq(&mint))
+                .select((
+                    metadatas::address,
+                    metadatas::name,
+                    metadatas::seller_fee_basis_points,
+                    metadatas::mint_address,
+                    metadatas::primary_sale_happened,
+                    metadata_jsons::description,
+                    metadata_jsons::image,
+                ))
+                .limit(1)
+                .load(&conn)
+                .context("Failed to load metadata")?;
+
+            let nft: Nft = match rows.pop() {
+                Some(n) => n.into(),
+                None => return Ok(None),
+            };
+
+            let files: Vec<models::File> = files::table
+                .filter(files::metadata_address.eq(&nft.address))
+                .select((files::metadata_address, files::uri, files::file_type))
+                .load(&conn)
+                .context("Failed to load metadata files")?;
+
+            let mut collections: Vec<models::MetadataCollection> = metadata_collections::table
+                .filter(metadata_collections::metadata_address.eq(&nft.address))
+                .select((
+                    metadata_collections::metadata_address,
+                    metadata_collections::name,
+                    metadata_collections::family,
+                ))
+                .limit(1)
+                .load(&conn)
+                .context("Failed to load metadata collection")?;
+
+            (nft, files, collections.pop())
+        };
+
+        let address: PublicKey<Nft> = nft.address.clone().into();
+
+        let (creators, attributes, owner, listings, purchases) = futures_util::try_join!(
+            context.nft_creators_loader.load(address.clone()),
+            context.nft_attributes_loader.load(address.clone()),
+            context
+                .nft_owner_loader
+                .load(nft.mint_address.clone().into()),
+            context.listing_receipts_loader.load(address.clone()),
+            context.purchase_receipts_loader.load(address),
+        )?;
+
+        Ok(Some(NftFull {
+            nft,
+            creators,
+            attributes,
+            files: files.into_iter().map(Into::into).collect(),
+            collection: collection.map(Into::into),
+            owner,
+            latest_listing: listings.into_iter().max_by_key(|l| l.created_at),
+            latest_sale: purchases.into_iter().max_by_key(|p| p.created_at),
+        }))
+    }
+
     fn storefronts(&self, context: &AppContext) -> FieldResult<Vec<Storefront>> {
         let conn = context.shared.db.get()?;
         let rows: Vec<models::Storefront> = storefronts::table
@@ -254,21 +1222,106 @@ impl QueryRoot {
         Ok(rows.into_iter().map(Into::into).collect())
     }
 
-    #[graphql(description = "A storefront")]
-    fn storefront(
+    #[graphql(description = "A Goki smart wallet by its address")]
+    async fn smart_wallet(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the smart wallet")] address: PublicKey<SmartWallet>,
+    ) -> FieldResult<Option<SmartWallet>> {
+        context
+            .smart_wallet_loader
+            .load(address)
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Tribeca escrows staked by a wallet")]
+    fn escrows_by_owner(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the escrow owner")] owner: PublicKey<Wallet>,
+    ) -> FieldResult<Vec<Escrow>> {
+        let conn = context.shared.db.get()?;
+
+        let rows: Vec<models::Escrow> = escrows::table
+            .filter(escrows::owner.eq(owner))
+            .order(escrows::address.asc())
+            .load(&conn)
+            .context("Failed to load escrows")?;
+
+        rows.into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "A storefront by its subdomain")]
+    async fn storefront(
         &self,
         context: &AppContext,
         subdomain: String,
     ) -> FieldResult<Option<Storefront>> {
+        context
+            .storefront_by_subdomain_loader
+            .load(subdomain)
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "A storefront by its address")]
+    async fn storefront_by_address(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the storefront")] address: PublicKey<Storefront>,
+    ) -> FieldResult<Option<Storefront>> {
+        context
+            .storefront_loader
+            .load(address)
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "A candy machine by its address")]
+    async fn candy_machine(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the candy machine")] address: PublicKey<CandyMachine>,
+    ) -> FieldResult<Option<CandyMachine>> {
+        context
+            .candy_machine_loader
+            .load(address)
+            .await
+            .map_err(Into::into)
+    }
+
+    #[graphql(description = "Candy machines controlled by an authority")]
+    fn candy_machines_by_authority(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the authority")] authority: PublicKey<Wallet>,
+    ) -> FieldResult<Vec<CandyMachine>> {
         let conn = context.shared.db.get()?;
-        let mut rows: Vec<models::Storefront> = storefronts::table
-            .filter(storefronts::subdomain.eq(subdomain))
-            .select(StorefrontColumns::default())
-            .limit(1)
+
+        let rows: Vec<models::CandyMachine> = candy_machines::table
+            .filter(candy_machines::authority.eq(authority))
+            .order(candy_machines::address.asc())
             .load(&conn)
-            .context("Failed to load storefront")?;
+            .context("Failed to load candy machines")?;
 
-        Ok(rows.pop().map(Into::into))
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    #[graphql(description = "An auction house by its address")]
+    async fn auction_house(
+        &self,
+        context: &AppContext,
+        #[graphql(description = "Address of the auction house")] address: PublicKey<AuctionHouse>,
+    ) -> FieldResult<Option<AuctionHouse>> {
+        context
+            .auction_house_loader
+            .load(address)
+            .await
+            .map_err(Into::into)
     }
 
     #[graphql(description = "A marketplace")]
@@ -278,9 +1331,16 @@ impl QueryRoot {
         subdomain: String,
     ) -> FieldResult<Option<Marketplace>> {
         let conn = context.shared.db.get()?;
-        let mut rows: Vec<models::StoreConfigJson> = store_config_jsons::table
+        let mut rows: Vec<(models::StoreConfigJson, Option<String>)> = store_config_jsons::table
+            .left_join(
+                store_configs::table
+                    .on(store_configs::address.eq(store_config_jsons::config_address)),
+            )
             .filter(store_config_jsons::subdomain.eq(subdomain))
-            .select(store_config_jsons::all_columns)
+            .select((
+                store_config_jsons::all_columns,
+                store_configs::settings_uri.nullable(),
+            ))
             .limit(1)
             .load(&conn)
             .context("Failed to load store config JSON")?;
@@ -292,3 +1352,216 @@ impl QueryRoot {
         Denylist
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use indexer_core::db::{
+        insert_into,
+        models::{ListingReceipt, PurchaseReceipt},
+        tables::{auction_houses, listing_receipts, metadata_jsons, metadatas, purchase_receipts},
+        testing,
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::{context, execute};
+
+    #[test]
+    fn search_nfts_ranks_whole_words_first() {
+        let (ctx, db) = match context() {
+            Some(c) => c,
+            None => return,
+        };
+        let conn = db.get().unwrap();
+
+        let named = |address, name| models::Metadata {
+            name: Borrowed(name),
+            ..testing::metadata(address, address, false)
+        };
+
+        insert_into(metadatas::table)
+            .values(&[
+                named("squad", "DeGodsSquad"),
+                named("edition", "y00ts DeGods Edition"),
+                named("god", "DeGods #1"),
+                named("monkey", "Solana Monkey #1"),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(metadata_jsons::table)
+            .values(&[
+                testing::metadata_json("squad", ""),
+                testing::metadata_json("edition", ""),
+                testing::metadata_json("god", ""),
+                testing::metadata_json("monkey", ""),
+            ])
+            .execute(&conn)
+            .unwrap();
+
+        let search = |limit: i32| {
+            execute(
+                &ctx,
+                &format!(
+                    r#"{{ searchNfts(query: " degods ", limit: {}) {{ name }} }}"#,
+                    limit
+                ),
+            )["searchNfts"]
+                .clone()
+        };
+
+        assert_eq!(
+            search(10),
+            json!([
+                { "name": "DeGods #1" },
+                { "name": "y00ts DeGods Edition" },
+                { "name": "DeGodsSquad" },
+            ])
+        );
+        assert_eq!(search(1), json!([{ "name": "DeGods #1" }]));
+
+        assert!(
+            QueryRoot
+                .search_nfts(&ctx, "degods".into(), Some(MAX_SEARCH_NFTS_LIMIT + 1))
+                .is_err()
+        );
+        assert!(
+            QueryRoot
+                .search_nfts(&ctx, "degods".into(), Some(-1))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn auction_house_round_trips() {
+        let (ctx, db) = match context() {
+            Some(c) => c,
+            None => return,
+        };
+
+        insert_into(auction_houses::table)
+            .values(&testing::auction_house("house", "sol"))
+            .execute(&db.get().unwrap())
+            .unwrap();
+
+        assert_eq!(
+            execute(
+                &ctx,
+                r#"{ auctionHouse(address: "house") { address treasuryMint } }"#
+            ),
+            json!({ "auctionHouse": { "address": "house", "treasuryMint": "sol" } })
+        );
+        assert_eq!(
+            execute(&ctx, r#"{ auctionHouse(address: "missing") { address } }"#),
+            json!({ "auctionHouse": null })
+        );
+    }
+
+    /// Seed a sale through a listing, so it is counted by the mint stats
+    fn sale(
+        address: &'static str,
+        auction_house: &'static str,
+        price: i64,
+        created_at: NaiveDateTime,
+    ) -> (ListingReceipt<'static>, PurchaseReceipt<'static>) {
+        let purchase =
+            testing::purchase_receipt(address, auction_house, "nft", ("x", "y"), price, created_at);
+        let listing = ListingReceipt {
+            purchase_receipt: Some(purchase.address.clone()),
+            ..testing::listing_receipt(address, auction_house, "nft", "x", price, created_at)
+        };
+
+        (listing, purchase)
+    }
+
+    #[test]
+    fn mint_stats_window() {
+        let (ctx, db) = match context() {
+            Some(c) => c,
+            None => return,
+        };
+        let conn = db.get().unwrap();
+        let now = Local::now().naive_utc();
+        let hours_ago = |h| now - chrono::Duration::hours(h);
+
+        insert_into(auction_houses::table)
+            .values(&[
+                testing::auction_house("sol_house", "sol"),
+                testing::auction_house("usdc_house", "usdc"),
+            ])
+            .execute(&conn)
+            .unwrap();
+        insert_into(metadatas::table)
+            .values(&testing::metadata("nft", "mint", true))
+            .execute(&conn)
+            .unwrap();
+
+        let (listings, purchases): (Vec<_>, Vec<_>) = [
+            sale("s1", "sol_house", 100, hours_ago(23)),
+            sale("s2", "sol_house", 300, hours_ago(25)),
+            sale("u1", "usdc_house", 40, hours_ago(25)),
+        ]
+        .into_iter()
+        .unzip();
+
+        insert_into(listing_receipts::table)
+            .values(&listings)
+            .execute(&conn)
+            .unwrap();
+        insert_into(purchase_receipts::table)
+            .values(&purchases)
+            .execute(&conn)
+            .unwrap();
+        insert_into(listing_receipts::table)
+            .values(&testing::listing_receipt(
+                "open",
+                "sol_house",
+                "nft",
+                "x",
+                250,
+                now,
+            ))
+            .execute(&conn)
+            .unwrap();
+
+        let stats = |house: &str, mints: &str| {
+            execute(
+                &ctx,
+                &format!(
+                    r#"{{
+                        mintStats(auctionHouse: "{}", mints: {}) {{
+                            mint floor average volume24hr
+                        }}
+                    }}"#,
+                    house, mints
+                ),
+            )
+        };
+
+        assert_eq!(
+            stats("sol_house", "null"),
+            json!({
+                "mintStats": [{
+                    "mint": "sol",
+                    "floor": "250",
+                    "average": "200",
+                    "volume24hr": "100",
+                }],
+            })
+        );
+        assert_eq!(
+            stats("usdc_house", r#"["usdc"]"#),
+            json!({
+                "mintStats": [{
+                    "mint": "usdc",
+                    "floor": null,
+                    "average": "40",
+                    "volume24hr": null,
+                }],
+            })
+        );
+        assert_eq!(
+            stats("sol_house", r#"["usdc"]"#),
+            json!({ "mintStats": [] })
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use super::prelude::*;
+
+/// The maximum valid number of basis points, equal to 100%
+const MAX_BASIS_POINTS: u16 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct BasisPoints(u16);
+
+#[graphql_scalar(
+    description = "Basis points, in hundredths of a percent (0 to 10000 for 0% to 100%)"
+)]
+impl<S> GraphQLScalar for BasisPoints
+where
+    S: ScalarValue,
+{
+    fn resolve(&self) -> Value {
+        Value::scalar(i32::from(self.0))
+    }
+
+    fn from_input_value(v: &InputValue) -> Option<BasisPoints> {
+        v.as_int_value()
+            .and_then(|i| u16::try_from(i).ok())
+            .filter(|b| *b <= MAX_BASIS_POINTS)
+            .map(Self)
+    }
+
+    fn from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        <i32 as ParseScalarValue<S>>::from_str(value)
+    }
+}
+
+impl BasisPoints {
+    /// Convert this value to a percentage, such that 10000 basis points is
+    /// 100.0
+    #[must_use]
+    pub fn percentage(self) -> f64 {
+        f64::from(self.0) / 100.0
+    }
+}
+
+impl From<u16> for BasisPoints {
+    fn from(value: u16) -> Self {
+        Self(value)
+    }
+}
+
+impl TryFrom<i16> for BasisPoints {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        value.try_into().map(Self)
+    }
+}
+
+impl TryFrom<i32> for BasisPoints {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        value.try_into().map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use juniper::{DefaultScalarValue, FromInputValue, InputValue, ToInputValue};
+
+    use super::*;
+
+    fn parse(v: InputValue<DefaultScalarValue>) -> Option<BasisPoints> {
+        BasisPoints::from_input_value(&v)
+    }
+
+    #[test]
+    fn input_bounds() {
+        assert_eq!(parse(InputValue::scalar(0)), Some(BasisPoints(0)));
+        assert_eq!(parse(InputValue::scalar(10_000)), Some(BasisPoints(10_000)));
+        assert_eq!(parse(InputValue::scalar(10_001)), None);
+        assert_eq!(parse(InputValue::scalar(-1)), None);
+        assert_eq!(parse(InputValue::scalar("500")), None);
+    }
+
+    #[test]
+    fn output_is_an_int() {
+        let value: InputValue<DefaultScalarValue> = BasisPoints(250).to_input_value();
+
+        assert_eq!(value, InputValue::scalar(250));
+    }
+
+    #[test]
+    fn conversions() {
+        assert!((BasisPoints(250).percentage() - 2.5).abs() < f64::EPSILON);
+        assert!((BasisPoints(10_000).percentage() - 100.0).abs() < f64::EPSILON);
+        assert_eq!(BasisPoints::try_from(500_i16), Ok(BasisPoints(500)));
+        assert!(BasisPoints::try_from(-1_i16).is_err());
+        assert!(BasisPoints::try_from(i32::from(u16::MAX) + 1).is_err());
+    }
+}
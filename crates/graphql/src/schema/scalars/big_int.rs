@@ -0,0 +1,87 @@
+use indexer_core::db::custom_types::NumericU64;
+
+use super::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct U64(u64);
+
+#[graphql_scalar(description = "An unsigned 64-bit integer, serialized as a string")]
+impl<S> GraphQLScalar for U64
+where
+    S: ScalarValue,
+{
+    fn resolve(&self) -> Value {
+        Value::scalar(self.0.to_string())
+    }
+
+    fn from_input_value(v: &InputValue) -> Option<U64> {
+        v.as_string_value().and_then(|s| s.parse().ok()).map(Self)
+    }
+
+    fn from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        <String as ParseScalarValue<S>>::from_str(value)
+    }
+}
+
+impl From<u64> for U64 {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<U64> for u64 {
+    fn from(U64(value): U64) -> Self {
+        value
+    }
+}
+
+impl TryFrom<i64> for U64 {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        value.try_into().map(Self)
+    }
+}
+
+impl From<NumericU64> for U64 {
+    fn from(value: NumericU64) -> Self {
+        Self(value.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use juniper::{DefaultScalarValue, FromInputValue, InputValue, ToInputValue};
+
+    use super::*;
+
+    fn parse(s: &str) -> Option<U64> {
+        U64::from_input_value(&InputValue::<DefaultScalarValue>::scalar(s))
+    }
+
+    #[test]
+    fn input_bounds() {
+        assert_eq!(parse("0"), Some(U64(0)));
+        assert_eq!(parse("18446744073709551615"), Some(U64(u64::MAX)));
+        assert_eq!(parse("18446744073709551616"), None);
+        assert_eq!(parse("-1"), None);
+        assert_eq!(
+            U64::from_input_value(&InputValue::<DefaultScalarValue>::scalar(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn output_is_a_string() {
+        let value: InputValue<DefaultScalarValue> = U64(u64::MAX).to_input_value();
+
+        assert_eq!(value, InputValue::scalar("18446744073709551615"));
+    }
+
+    #[test]
+    fn signed_conversions() {
+        assert_eq!(U64::try_from(i64::MAX), Ok(U64(9_223_372_036_854_775_807)));
+        assert!(U64::try_from(-1_i64).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use chrono::SecondsFormat;
+
+use super::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct DateTime(chrono::DateTime<Utc>);
+
+#[graphql_scalar(description = "An RFC 3339 timestamp in UTC, e.g. 2023-01-02T03:04:05Z")]
+impl<S> GraphQLScalar for DateTime
+where
+    S: ScalarValue,
+{
+    fn resolve(&self) -> Value {
+        Value::scalar(self.0.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+
+    fn from_input_value(v: &InputValue) -> Option<DateTime> {
+        v.as_string_value()
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+            .map(|d| Self(d.with_timezone(&Utc)))
+    }
+
+    fn from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+        <String as ParseScalarValue<S>>::from_str(value)
+    }
+}
+
+impl DateTime {
+    /// Convert a Unix timestamp in seconds, returning `None` if it is out of
+    /// range
+    #[must_use]
+    pub fn from_timestamp(secs: i64) -> Option<Self> {
+        NaiveDateTime::from_timestamp_opt(secs, 0).map(Into::into)
+    }
+}
+
+impl From<chrono::DateTime<Utc>> for DateTime {
+    fn from(value: chrono::DateTime<Utc>) -> Self {
+        Self(value)
+    }
+}
+
+impl From<NaiveDateTime> for DateTime {
+    /// Interpret a naive database timestamp as UTC
+    fn from(value: NaiveDateTime) -> Self {
+        Self(chrono::DateTime::from_utc(value, Utc))
+    }
+}
+
+impl From<DateTime> for NaiveDateTime {
+    /// Convert to a naive UTC timestamp for querying the database
+    fn from(DateTime(value): DateTime) -> Self {
+        value.naive_utc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use juniper::{DefaultScalarValue, FromInputValue, InputValue, ToInputValue};
+
+    use super::*;
+
+    fn parse(s: &str) -> Option<DateTime> {
+        DateTime::from_input_value(&InputValue::<DefaultScalarValue>::scalar(s))
+    }
+
+    #[test]
+    fn input_is_converted_to_utc() {
+        assert_eq!(
+            parse("2023-01-02T03:04:05+02:00"),
+            Some(Utc.ymd(2023, 1, 2).and_hms(1, 4, 5).into())
+        );
+        assert_eq!(parse("2023-01-02 03:04:05"), None);
+        assert_eq!(parse("yesterday"), None);
+    }
+
+    #[test]
+    fn output_is_rfc3339_utc() {
+        let date: DateTime = Utc.ymd(2023, 1, 2).and_hms_milli(3, 4, 5, 600).into();
+        let value: InputValue<DefaultScalarValue> = date.to_input_value();
+
+        assert_eq!(value, InputValue::scalar("2023-01-02T03:04:05.600Z"));
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(
+            DateTime::from_timestamp(1_672_628_645),
+            Some(Utc.ymd(2023, 1, 2).and_hms(3, 4, 5).into())
+        );
+        assert_eq!(DateTime::from_timestamp(i64::MAX), None);
+    }
+}
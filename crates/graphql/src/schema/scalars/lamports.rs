@@ -1,6 +1,12 @@
 use super::prelude::*;
 
-#[derive(Debug, Clone, Copy)]
+/// An amount of lamports
+///
+/// Amounts are stored as signed integers in the database.  Negative values
+/// are never valid amounts, and usually indicate a sentinel or a miscomputed
+/// value, so they are clamped to zero and logged rather than failing the
+/// whole query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[repr(transparent)]
 pub struct Lamports(u64);
 
@@ -28,10 +34,48 @@ impl From<u64> for Lamports {
     }
 }
 
-impl TryFrom<i64> for Lamports {
-    type Error = std::num::TryFromIntError;
+impl From<Lamports> for u64 {
+    fn from(Lamports(value): Lamports) -> Self {
+        value
+    }
+}
+
+impl From<i64> for Lamports {
+    fn from(value: i64) -> Self {
+        Self(value.try_into().unwrap_or_else(|_| {
+            warn!("Clamping negative lamport amount {} to zero", value);
+
+            0
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use juniper::{DefaultScalarValue, FromInputValue, InputValue, ToInputValue};
+
+    use super::*;
+
+    #[test]
+    fn negative_amounts_are_clamped() {
+        assert_eq!(Lamports::from(-1_i64), Lamports(0));
+        assert_eq!(Lamports::from(i64::MIN), Lamports(0));
+        assert_eq!(Lamports::from(0_i64), Lamports(0));
+        assert_eq!(
+            Lamports::from(i64::MAX),
+            Lamports(9_223_372_036_854_775_807)
+        );
+    }
+
+    #[test]
+    fn string_round_trip() {
+        let value: InputValue<DefaultScalarValue> = Lamports(u64::MAX).to_input_value();
 
-    fn try_from(value: i64) -> Result<Self, Self::Error> {
-        value.try_into().map(Self)
+        assert_eq!(value, InputValue::scalar("18446744073709551615"));
+        assert_eq!(Lamports::from_input_value(&value), Some(Lamports(u64::MAX)));
+        assert_eq!(
+            Lamports::from_input_value(&InputValue::<DefaultScalarValue>::scalar("-1")),
+            None
+        );
     }
 }
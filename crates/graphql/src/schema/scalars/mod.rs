@@ -1,3 +1,6 @@
+mod basis_points;
+mod big_int;
+mod datetime;
 mod lamports;
 mod public_key;
 mod volume;
@@ -9,9 +12,14 @@ pub(self) mod prelude {
 }
 
 pub mod markers {
+    pub struct Governor;
     pub struct StoreConfig;
+    pub struct TokenMint;
 }
 
+pub use basis_points::BasisPoints;
+pub use big_int::U64;
+pub use datetime::DateTime;
 pub use lamports::Lamports;
 pub use public_key::PublicKey;
 pub use volume::Volume;
@@ -11,6 +11,25 @@ use super::prelude::*;
 #[repr(transparent)]
 pub struct PublicKey<T: 'static>(String, PhantomData<&'static T>);
 
+const PUBKEY_BYTES: usize = 32;
+
+/// Normalize user input to the base58 form of a 32-byte Solana public key
+///
+/// Base58 is the canonical form, but the base64 encoding of the raw key bytes
+/// is also accepted and converted to base58.  Anything else is rejected.
+fn parse_pubkey(s: &str) -> Option<String> {
+    let mut buf = [0_u8; PUBKEY_BYTES];
+
+    if matches!(bs58::decode(s).into(&mut buf), Ok(PUBKEY_BYTES)) {
+        return Some(s.to_owned());
+    }
+
+    base64::decode(s)
+        .ok()
+        .filter(|b| b.len() == PUBKEY_BYTES)
+        .map(|b| bs58::encode(b).into_string())
+}
+
 // Safety: PublicKey only contains a string
 unsafe impl<T> Send for PublicKey<T> {}
 unsafe impl<T> Sync for PublicKey<T> {}
@@ -152,7 +171,7 @@ where
 //     }
 
 //     fn from_input_value(v: &InputValue) -> Option<PublicKey<T>> {
-//         v.as_string_value().and_then(|s| s.parse().ok()).map(Self)
+//         v.as_string_value().and_then(parse_pubkey).map(Self)
 //     }
 
 //     fn from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
@@ -181,7 +200,7 @@ where
     {
         registry
             .build_scalar_type::<Self>(info)
-            .description("PublicKey")
+            .description("A base58-encoded Solana public key (base64 is also accepted as input)")
             .into_meta()
     }
 }
@@ -240,7 +259,7 @@ where
 {
     fn from_input_value(v: &::juniper::InputValue<S>) -> Option<PublicKey<T>> {
         v.as_string_value()
-            .and_then(|s| s.parse().ok())
+            .and_then(parse_pubkey)
             .map(|s| Self(s, PhantomData::default()))
     }
 }
@@ -253,3 +272,54 @@ where
         <String as ParseScalarValue<S>>::from_str(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use juniper::{DefaultScalarValue, FromInputValue, InputValue};
+
+    use super::*;
+
+    const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+    #[test]
+    fn base58_keys_are_kept() {
+        assert_eq!(parse_pubkey(TOKEN_PROGRAM).as_deref(), Some(TOKEN_PROGRAM));
+        assert_eq!(
+            parse_pubkey("11111111111111111111111111111111").as_deref(),
+            Some("11111111111111111111111111111111")
+        );
+    }
+
+    #[test]
+    fn base64_keys_are_converted() {
+        assert_eq!(
+            parse_pubkey("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=").as_deref(),
+            Some("11111111111111111111111111111111")
+        );
+    }
+
+    #[test]
+    fn invalid_keys_are_rejected() {
+        assert_eq!(parse_pubkey(""), None);
+        assert_eq!(parse_pubkey("Tokenkeg"), None);
+        assert_eq!(parse_pubkey(&format!("{}1", TOKEN_PROGRAM)), None);
+        assert_eq!(parse_pubkey("not a public key"), None);
+        assert_eq!(parse_pubkey("AAAAAAAAAAAAAAAAAAAAAA=="), None);
+    }
+
+    #[test]
+    fn input_values() {
+        struct Marker;
+
+        let parse = |v: InputValue<DefaultScalarValue>| {
+            PublicKey::<Marker>::from_input_value(&v).map(String::from)
+        };
+
+        assert_eq!(
+            parse(InputValue::scalar(TOKEN_PROGRAM)).as_deref(),
+            Some(TOKEN_PROGRAM)
+        );
+        assert_eq!(parse(InputValue::scalar("Tokenkeg")), None);
+        assert_eq!(parse(InputValue::scalar(1)), None);
+    }
+}
@@ -24,6 +24,12 @@ impl From<u64> for Volume {
     }
 }
 
+impl From<Volume> for u64 {
+    fn from(Volume(value): Volume) -> Self {
+        value
+    }
+}
+
 impl TryFrom<i64> for Volume {
     type Error = std::num::TryFromIntError;
 
@@ -0,0 +1,163 @@
+use std::pin::Pin;
+
+use futures_util::{stream, Stream, StreamExt};
+use indexer_core::logging::WithFieldsExt;
+use juniper::graphql_subscription;
+use objects::{auction_house::AuctionHouse, nft::NftActivity};
+use scalars::PublicKey;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use super::prelude::*;
+
+/// Name of the Postgres notification channel new activity is published on
+pub const NFT_ACTIVITY_CHANNEL: &str = "nft_activity";
+
+/// Capacity of the channel fanning out activity to subscribers.  Subscribers
+/// which fall this far behind skip the events they missed.
+pub const NFT_ACTIVITY_CAPACITY: usize = 1024;
+
+#[derive(serde::Deserialize)]
+struct ActivityPayload {
+    address: String,
+    metadata: String,
+    auction_house: String,
+    price: i64,
+    created_at: String,
+    wallets: Vec<String>,
+    activity_type: String,
+}
+
+/// Parse the payload of a notification on [`NFT_ACTIVITY_CHANNEL`]
+///
+/// # Errors
+/// This function fails if the payload is not a valid activity object.
+pub fn parse_activity(payload: &str) -> Result<NftActivity> {
+    let ActivityPayload {
+        address,
+        metadata,
+        auction_house,
+        price,
+        created_at,
+        wallets,
+        activity_type,
+    } = serde_json::from_str(payload).context("Failed to parse activity notification")?;
+
+    let created_at = NaiveDateTime::parse_from_str(&created_at, "%Y-%m-%dT%H:%M:%S%.f")
+        .context("Failed to parse activity timestamp")?;
+
+    models::NftActivity {
+        address,
+        metadata,
+        auction_house,
+        price,
+        created_at,
+        wallets,
+        activity_type,
+    }
+    .try_into()
+    .context("Failed to convert activity notification")
+}
+
+type NftActivityStream = Pin<Box<dyn Stream<Item = NftActivity> + Send>>;
+
+pub struct SubscriptionRoot;
+
+#[graphql_subscription(Context = AppContext)]
+impl SubscriptionRoot {
+    #[graphql(description = "Stream new listings, purchases, and bids on an auction house")]
+    async fn nft_activity(
+        &self,
+        ctx: &AppContext,
+        #[graphql(description = "Address of the auction house")] auction_house: PublicKey<
+            AuctionHouse,
+        >,
+    ) -> NftActivityStream {
+        let rx = ctx.shared.nft_activity.subscribe();
+        let fields = ctx.log_fields();
+
+        let events = stream::unfold(rx, move |mut rx| {
+            async move {
+                loop {
+                    match rx.recv().await {
+                        Ok(a) => break Some((a, rx)),
+                        Err(RecvError::Lagged(n)) => {
+                            warn!("Activity subscriber lagged, skipping {} events", n);
+                        },
+                        Err(RecvError::Closed) => break None,
+                    }
+                }
+            }
+            .with_log_fields(fields.clone())
+        });
+
+        Box::pin(events.filter(move |a: &NftActivity| {
+            futures_util::future::ready(a.auction_house == auction_house.as_ref())
+        }))
+    }
+}
+
+/// Sending half of the channel fanning out new activity to subscribers
+pub type NftActivitySender = broadcast::Sender<NftActivity>;
+
+/// Construct the sending half of the activity fan-out channel
+#[must_use]
+pub fn nft_activity_channel() -> NftActivitySender {
+    broadcast::channel(NFT_ACTIVITY_CAPACITY).0
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{api_keys::Access, testing};
+
+    fn payload(address: &str, auction_house: &str) -> String {
+        json!({
+            "address": address,
+            "metadata": "nft",
+            "auction_house": auction_house,
+            "price": 1_000_000_000_i64,
+            "created_at": "2022-03-01T12:30:00.5",
+            "wallets": ["seller", "buyer"],
+            "activity_type": "purchase",
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn parses_activity() {
+        let activity = parse_activity(&payload("sale", "house")).unwrap();
+
+        assert_eq!(activity.address, "sale");
+        assert_eq!(activity.auction_house, "house");
+        assert_eq!(activity.wallets, vec!["seller", "buyer"]);
+        assert_eq!(activity.activity_type, "purchase");
+
+        assert!(parse_activity("{}").is_err());
+        assert!(parse_activity(&payload("sale", "house").replace("12:30", "noon")).is_err());
+    }
+
+    #[test]
+    fn published_activity_is_received() {
+        let data = Arc::new(testing::shared_data(testing::unreachable_replicas()));
+        let ctx = AppContext::new(Arc::clone(&data), None, Access::default());
+
+        actix_web::rt::System::new().block_on(async {
+            let mut events = SubscriptionRoot
+                .nft_activity(&ctx, String::from("house").into())
+                .await;
+
+            for (address, auction_house) in [("elsewhere", "other"), ("sale", "house")] {
+                data.nft_activity
+                    .send(parse_activity(&payload(address, auction_house)).unwrap())
+                    .unwrap();
+            }
+
+            let activity = events.next().await.unwrap();
+
+            assert_eq!(activity.address, "sale");
+            assert_eq!(activity.auction_house, "house");
+        });
+    }
+}
@@ -0,0 +1,377 @@
+//! Static analysis of operations started over the subscriptions WebSocket
+//!
+//! Operations sent over the WebSocket are executed by the connection handler
+//! rather than the GraphQL request handler, so they would otherwise bypass the
+//! depth, cost, and introspection checks made there.  Instead, the frames sent
+//! by the client are inspected as they arrive: the payload of each `start` (or
+//! `subscribe`) message is checked like a request body, and if it is rejected
+//! the connection is closed with a policy violation in its place.
+
+use std::pin::Pin;
+
+use actix_web::{
+    dev,
+    error::PayloadError,
+    web::{self, Bytes},
+    FromRequest, HttpRequest,
+};
+use futures_util::{future, Stream, StreamExt};
+use indexer_core::prelude::*;
+use serde_json::Value;
+
+/// Largest message buffered for inspection.  This matches the default frame
+/// size limit of the WebSocket codec, which rejects larger frames anyway.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// WebSocket close code for a policy violation
+const CLOSE_POLICY_VIOLATION: u16 = 1008;
+
+/// Longest close reason that fits in a control frame
+const MAX_CLOSE_REASON: usize = 123;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+
+/// A frame parsed from the start of a buffer
+#[derive(Debug, PartialEq, Eq)]
+struct Frame {
+    /// Number of bytes the frame occupied
+    len: usize,
+    fin: bool,
+    opcode: u8,
+    /// The frame's payload, unmasked
+    payload: Vec<u8>,
+}
+
+/// Parse a frame from the start of `buf`, returning `None` if the buffer does
+/// not yet hold a whole frame
+fn parse_frame(buf: &[u8]) -> Result<Option<Frame>, String> {
+    let (b0, b1) = match buf {
+        [b0, b1, ..] => (*b0, *b1),
+        _ => return Ok(None),
+    };
+
+    let mut header = 2;
+    let len = match b1 & 0x7f {
+        126 => {
+            let bytes = match buf.get(2..4) {
+                Some(b) => b,
+                None => return Ok(None),
+            };
+            header += 2;
+
+            u64::from(u16::from_be_bytes([bytes[0], bytes[1]]))
+        },
+        127 => {
+            let bytes = match buf.get(2..10) {
+                Some(b) => b,
+                None => return Ok(None),
+            };
+            header += 8;
+
+            let mut be = [0_u8; 8];
+            be.copy_from_slice(bytes);
+            u64::from_be_bytes(be)
+        },
+        n => u64::from(n),
+    };
+
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|l| *l <= MAX_MESSAGE_SIZE)
+        .ok_or_else(|| "WebSocket frame is too large".to_owned())?;
+
+    let mask = if b1 & 0x80 == 0 {
+        None
+    } else {
+        let mask = match buf.get(header..header + 4) {
+            Some(m) => [m[0], m[1], m[2], m[3]],
+            None => return Ok(None),
+        };
+        header += 4;
+
+        Some(mask)
+    };
+
+    let mut payload = match buf.get(header..header + len) {
+        Some(p) => p.to_vec(),
+        None => return Ok(None),
+    };
+
+    if let Some(mask) = mask {
+        for (i, b) in payload.iter_mut().enumerate() {
+            *b ^= mask[i % 4];
+        }
+    }
+
+    Ok(Some(Frame {
+        len: header + len,
+        fin: b0 & 0x80 != 0,
+        opcode: b0 & 0x0f,
+        payload,
+    }))
+}
+
+/// Incremental reader of the frames sent by a WebSocket client, collecting
+/// complete data messages
+#[derive(Debug, Default)]
+struct FrameReader {
+    buf: Vec<u8>,
+    message: Option<Vec<u8>>,
+}
+
+impl FrameReader {
+    /// Feed bytes received from the client, returning the messages they
+    /// complete
+    fn feed(&mut self, bytes: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+        self.buf.extend_from_slice(bytes);
+
+        let mut messages = vec![];
+
+        while let Some(Frame {
+            len,
+            fin,
+            opcode,
+            payload,
+        }) = parse_frame(&self.buf)?
+        {
+            self.buf.drain(..len);
+
+            let message = match opcode {
+                OP_TEXT | OP_BINARY => payload,
+                OP_CONTINUATION => match self.message.take() {
+                    Some(mut message) => {
+                        message.extend_from_slice(&payload);
+                        message
+                    },
+                    None => continue,
+                },
+                // Control frames may be interleaved with fragments
+                _ => continue,
+            };
+
+            if message.len() > MAX_MESSAGE_SIZE {
+                return Err("WebSocket message is too large".to_owned());
+            }
+
+            if fin {
+                messages.push(message);
+            } else {
+                self.message = Some(message);
+            }
+        }
+
+        Ok(messages)
+    }
+}
+
+/// Check a message sent by a subscription client, failing if it starts an
+/// operation rejected by `check`.  Messages which are not operations are
+/// accepted.
+fn check_message(
+    message: &[u8],
+    check: impl Fn(&Value) -> Result<(), String>,
+) -> Result<(), String> {
+    let message: Value = match serde_json::from_slice(message) {
+        Ok(m) => m,
+        Err(_) => return Ok(()),
+    };
+
+    match (
+        message.get("type").and_then(Value::as_str),
+        message.get("payload"),
+    ) {
+        (Some("start" | "subscribe"), Some(payload)) => check(payload),
+        _ => Ok(()),
+    }
+}
+
+/// Encode a close frame as sent by a client, i.e. masked, with the given code
+/// and reason
+fn close_frame(code: u16, reason: &str) -> Bytes {
+    let end = (0..=reason.len().min(MAX_CLOSE_REASON))
+        .rev()
+        .find(|i| reason.is_char_boundary(*i))
+        .unwrap_or(0);
+
+    let mut payload = code.to_be_bytes().to_vec();
+    payload.extend_from_slice(reason[..end].as_bytes());
+
+    // An all-zero mask leaves the payload unchanged
+    let mut frame = vec![
+        0x80 | OP_CLOSE,
+        0x80 | u8::try_from(payload.len()).unwrap_or_else(|_| unreachable!()),
+        0,
+        0,
+        0,
+        0,
+    ];
+    frame.extend(payload);
+
+    frame.into()
+}
+
+/// Wrap the payload of a subscriptions WebSocket request, closing the
+/// connection in place of the first operation rejected by `check`
+///
+/// # Errors
+/// This function fails if the request payload cannot be extracted.
+pub async fn guard(
+    req: &HttpRequest,
+    payload: web::Payload,
+    check: impl Fn(&Value) -> Result<(), String> + 'static,
+) -> Result<web::Payload, actix_web::Error> {
+    let stream = payload.scan(
+        (FrameReader::default(), false),
+        move |(reader, closed), chunk| {
+            if *closed {
+                return future::ready(None);
+            }
+
+            future::ready(Some(chunk.map(|bytes| {
+                match reader
+                    .feed(&bytes)
+                    .and_then(|messages| messages.iter().try_for_each(|m| check_message(m, &check)))
+                {
+                    Ok(()) => bytes,
+                    Err(reason) => {
+                        debug!("Closing subscription connection: {}", reason);
+                        *closed = true;
+
+                        close_frame(CLOSE_POLICY_VIOLATION, &reason)
+                    },
+                }
+            })))
+        },
+    );
+
+    let stream: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> = Box::pin(stream);
+
+    web::Payload::from_request(req, &mut dev::Payload::from(stream)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Encode a masked frame as sent by a client
+    fn frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x12, 0x34, 0x56, 0x78];
+        let mut out = vec![if fin { 0x80 } else { 0 } | opcode];
+
+        match payload.len() {
+            l if l < 126 => out.push(0x80 | u8::try_from(l).unwrap()),
+            l => {
+                out.push(0x80 | 126);
+                out.extend_from_slice(&u16::try_from(l).unwrap().to_be_bytes());
+            },
+        }
+
+        out.extend_from_slice(&mask);
+        out.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+
+        out
+    }
+
+    #[test]
+    fn reads_split_and_fragmented_messages() {
+        let long = "x".repeat(300);
+        let mut bytes = frame(true, OP_TEXT, b"hello");
+        bytes.extend(frame(false, OP_TEXT, b"frag"));
+        bytes.extend(frame(true, 0x9, b"ping"));
+        bytes.extend(frame(true, OP_CONTINUATION, b"mented"));
+        bytes.extend(frame(true, OP_TEXT, long.as_bytes()));
+
+        let mut reader = FrameReader::default();
+        let mut messages = vec![];
+
+        for chunk in bytes.chunks(7) {
+            messages.extend(reader.feed(chunk).unwrap());
+        }
+
+        assert_eq!(messages, vec![
+            b"hello".to_vec(),
+            b"fragmented".to_vec(),
+            long.into_bytes()
+        ]);
+    }
+
+    #[test]
+    fn rejects_oversized_frames() {
+        let mut reader = FrameReader::default();
+        let header = [0x81, 0x80 | 127, 0, 0, 0, 0, 0, 0x10, 0, 0];
+
+        assert!(reader.feed(&header).is_err());
+    }
+
+    #[test]
+    fn checks_started_operations() {
+        let reject = |body: &Value| match body.get("query") {
+            Some(q) if q == "deep" => Err("too deep".to_owned()),
+            _ => Ok(()),
+        };
+        let message = |v: Value| serde_json::to_vec(&v).unwrap();
+
+        for ty in ["start", "subscribe"] {
+            assert_eq!(
+                check_message(
+                    &message(json!({ "id": "1", "type": ty, "payload": { "query": "deep" } })),
+                    reject,
+                ),
+                Err("too deep".to_owned())
+            );
+            assert_eq!(
+                check_message(
+                    &message(json!({ "id": "1", "type": ty, "payload": { "query": "ok" } })),
+                    reject,
+                ),
+                Ok(())
+            );
+        }
+
+        assert_eq!(
+            check_message(&message(json!({ "type": "connection_init" })), reject),
+            Ok(())
+        );
+        assert_eq!(check_message(b"not json", reject), Ok(()));
+    }
+
+    #[test]
+    fn close_frames_parse() {
+        let reason = "é".repeat(100);
+        let close = close_frame(CLOSE_POLICY_VIOLATION, &reason);
+        let frame = parse_frame(&close).unwrap().unwrap();
+
+        assert_eq!(frame.len, close.len());
+        assert!(frame.fin);
+        assert_eq!(frame.opcode, OP_CLOSE);
+        assert_eq!(frame.payload[..2], 1008_u16.to_be_bytes());
+        assert!(frame.payload.len() <= MAX_CLOSE_REASON + 2);
+        assert!(std::str::from_utf8(&frame.payload[2..]).is_ok());
+    }
+
+    #[test]
+    fn introspection_over_websocket() {
+        let schema = crate::schema::create();
+        let blocked = crate::schema::QueryAnalyzer::new(&schema, None, None, false).unwrap();
+        let allowed = crate::schema::QueryAnalyzer::new(&schema, None, None, true).unwrap();
+
+        let start = serde_json::to_vec(&json!({
+            "id": "1",
+            "type": "start",
+            "payload": { "query": "subscription { __schema { queryType { name } } }" },
+        }))
+        .unwrap();
+
+        let mut reader = FrameReader::default();
+        let messages = reader.feed(&frame(true, OP_TEXT, &start)).unwrap();
+
+        assert_eq!(messages.len(), 1);
+        assert!(check_message(&messages[0], |b| blocked.check(b)).is_err());
+        assert_eq!(check_message(&messages[0], |b| allowed.check(b)), Ok(()));
+    }
+}
@@ -0,0 +1,215 @@
+//! Helpers for resolving GraphQL queries against the scratch database
+//!
+//! [`context`] builds an [`AppContext`] reading from the rollback replica set
+//! returned by [`test_replicas`], and [`execute`] resolves a query with it,
+//! returning its data as JSON.  As with the database tests in the core crate,
+//! [`context`] returns `None` if no scratch database is configured.
+//!
+//! [`MockServer`] stands in for external HTTP APIs, answering requests from a
+//! background thread.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use indexer_core::db::{self, testing::test_replicas, ReplicaPool};
+use juniper::Variables;
+
+use crate::{
+    api_keys::Access,
+    persisted_queries::PersistedQueries,
+    schema::{self, AppContext, LruCache, QueryAnalyzer, Revalidate, TtlCache, TwitterRateLimit},
+    ListingTieBreak, SharedData,
+};
+
+/// Construct server state with default settings around the given database
+#[must_use]
+pub fn shared_data(db: Arc<ReplicaPool>) -> SharedData {
+    let schema = schema::create();
+    let query_analyzer = QueryAnalyzer::new(&schema, None, None, true)
+        .unwrap_or_else(|e| panic!("Failed to initialize query analyzer: {:?}", e));
+
+    SharedData {
+        schema: Arc::new(schema),
+        query_analyzer,
+        persisted_queries: PersistedQueries::new(16, Duration::from_secs(60)),
+        heavy_db: Arc::clone(&db),
+        db,
+        asset_proxy_endpoint: "https://assets[n].example.com/".to_owned(),
+        asset_proxy_count: 5,
+        twitter_bearer_token: String::new(),
+        twitter_api_endpoint: "https://api.twitter.com".to_owned(),
+        twitter_profile_cache: Arc::new(LruCache::new(16, Duration::from_secs(60))),
+        twitter_missing_cache_ttl: Duration::from_secs(60),
+        twitter_rate_limit: Arc::new(TwitterRateLimit::default()),
+        default_auction_house: None,
+        listing_tie_break: ListingTieBreak::DefaultHouse,
+        confirmation_depth: 0,
+        activity_summary_cache: TtlCache::new(
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+            Revalidate::Inline,
+        ),
+        marketplace_stats_cache: TtlCache::new(
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+            Revalidate::Inline,
+        ),
+        batch_timeout: None,
+        max_nft_full_per_request: 5,
+        nft_activity: schema::nft_activity_channel(),
+    }
+}
+
+/// Construct a request context reading from the scratch database, along with
+/// the database for seeding rows, or return `None` if none is configured
+#[must_use]
+pub fn context() -> Option<(AppContext, Arc<ReplicaPool>)> {
+    let db = Arc::new(test_replicas()?);
+    let ctx = AppContext::new(
+        Arc::new(shared_data(Arc::clone(&db))),
+        None,
+        Access::default(),
+    );
+
+    Some((ctx, db))
+}
+
+/// Construct a replica set whose only member never connects, for exercising
+/// handling of an unavailable database
+///
+/// # Panics
+/// This function panics if the replica set cannot be constructed.
+#[must_use]
+pub fn unreachable_replicas() -> Arc<ReplicaPool> {
+    let pool = db::Pool::builder()
+        .max_size(1)
+        .connection_timeout(Duration::from_millis(100))
+        .build_unchecked(db::ConnectionManager::new("postgres://localhost:1/none"));
+
+    Arc::new(
+        ReplicaPool::new([("unreachable".to_owned(), pool)])
+            .expect("Failed to construct replica set"),
+    )
+}
+
+/// Resolve a query, returning its data as JSON
+///
+/// # Panics
+/// This function panics if the query is invalid or any field fails to
+/// resolve.
+#[must_use]
+pub fn execute(ctx: &AppContext, query: &str) -> serde_json::Value {
+    let schema = Arc::clone(&ctx.shared.schema);
+
+    let (data, errors) = actix_web::rt::System::new()
+        .block_on(juniper::execute(
+            query,
+            None,
+            &schema,
+            &Variables::new(),
+            ctx,
+        ))
+        .unwrap_or_else(|e| panic!("Invalid query {:?}: {:?}", query, e));
+
+    assert!(errors.is_empty(), "Query {:?} failed: {:?}", query, errors);
+
+    serde_json::to_value(&data).unwrap_or_else(|e| panic!("Failed to serialize data: {}", e))
+}
+
+/// A local HTTP server whose responses are computed by a closure
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    requests: Arc<AtomicUsize>,
+}
+
+impl MockServer {
+    /// Start serving on a random local port.  The handler is called with the
+    /// zero-based index of each request and its request line, e.g.
+    /// `GET /path?query HTTP/1.1`, and returns a response built by
+    /// [`respond`].
+    ///
+    /// # Panics
+    /// This function panics if the server cannot be bound.
+    #[must_use]
+    pub fn start(handler: impl Fn(usize, &str) -> String + Send + 'static) -> Self {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("Failed to bind mock server");
+        let addr = listener
+            .local_addr()
+            .expect("Failed to get mock server address");
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        thread::spawn({
+            let requests = Arc::clone(&requests);
+
+            move || {
+                for stream in listener.incoming() {
+                    let mut stream = match stream {
+                        Ok(s) => s,
+                        Err(_) => continue,
+                    };
+                    let request_line = {
+                        let mut lines = BufReader::new(&stream).lines();
+                        let request_line = match lines.next() {
+                            Some(Ok(l)) => l,
+                            _ => continue,
+                        };
+
+                        // Requests made by tests have no body, so the rest of
+                        // the request ends at the first blank line
+                        lines
+                            .take_while(|l| l.as_ref().map_or(false, |l| !l.is_empty()))
+                            .for_each(drop);
+
+                        request_line
+                    };
+
+                    let res = handler(requests.fetch_add(1, Ordering::SeqCst), &request_line);
+                    stream.write_all(res.as_bytes()).ok();
+                }
+            }
+        });
+
+        Self { addr, requests }
+    }
+
+    /// Get the base URL of this server
+    #[must_use]
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Get the number of requests received so far
+    #[must_use]
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+/// Format an HTTP response with the given status, headers, and body, closing
+/// the connection afterwards
+#[must_use]
+pub fn respond(status: u16, headers: &[(&str, &str)], body: &str) -> String {
+    let mut res = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+
+    for (name, value) in headers {
+        res.push_str(&format!("{}: {}\r\n", name, value));
+    }
+
+    res.push_str("\r\n");
+    res.push_str(body);
+
+    res
+}
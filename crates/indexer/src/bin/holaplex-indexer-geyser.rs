@@ -25,6 +25,10 @@ struct Args {
     #[clap(long, env, use_value_delimiter(true))]
     ignore_on_startup: Option<Vec<IgnoreType>>,
 
+    /// The codec to compress messages sent to the HTTP indexers with
+    #[clap(long, env, default_value_t = indexer_rabbitmq::Compression::None)]
+    amqp_compression: indexer_rabbitmq::Compression,
+
     /// An optional suffix for the AMQP queue ID
     ///
     /// For debug builds a value must be provided here to avoid interfering with
@@ -39,6 +43,7 @@ fn main() {
              network,
              startup,
              ignore_on_startup,
+             amqp_compression,
              queue_suffix,
          },
          params,
@@ -55,14 +60,12 @@ fn main() {
                 &conn,
                 http_indexer::QueueType::new(&sender, queue_suffix.as_deref()),
                 http_indexer::QueueType::new(&sender, queue_suffix.as_deref()),
+                amqp_compression,
             )
             .await
             .context("Failed to construct Client")?;
 
             let queue_type = geyser::QueueType::new(network, startup, queue_suffix.as_deref());
-            let consumer = geyser::Consumer::new(&conn, queue_type.clone(), "geyser-consumer")
-                .await
-                .context("Failed to create queue consumer")?;
 
             let ignore_on_startup = Arc::new(
                 ignore_on_startup
@@ -71,7 +74,7 @@ fn main() {
                     .collect::<HashSet<_>>(),
             );
 
-            holaplex_indexer::amqp_consume(&params, conn, consumer, queue_type, move |m| {
+            holaplex_indexer::amqp_consume(&params, conn, queue_type, "geyser-consumer", move |m| {
                 let client = client.clone();
                 let ignore_on_startup = ignore_on_startup.clone();
 
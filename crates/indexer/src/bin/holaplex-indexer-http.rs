@@ -28,7 +28,7 @@ struct Args {
 
 fn main() {
     holaplex_indexer::run(|args: Args, params, db| async move {
-        use http_indexer::{EntityId, MetadataJson, StoreConfig};
+        use http_indexer::{CollectionJson, EntityId, MetadataJson, StoreConfig};
 
         // Note: each match arm will increase the compiled size of this
         //       binary, it may be advantageous to split this into separate
@@ -36,6 +36,7 @@ fn main() {
         match args.entity {
             EntityId::MetadataJson => run::<MetadataJson>(args, params, db).await,
             EntityId::StoreConfig => run::<StoreConfig>(args, params, db).await,
+            EntityId::CollectionJson => run::<CollectionJson>(args, params, db).await,
         }
     });
 }
@@ -61,13 +62,21 @@ async fn run<E: Send + holaplex_indexer::http::Process + 'static>(
     let client = Client::new_rc(db, client).context("Failed to construct Client")?;
 
     let queue_type = http_indexer::QueueType::<E>::new(&sender, queue_suffix.as_deref());
-    let consumer = http_indexer::Consumer::new(&conn, queue_type.clone(), "http-consumer")
-        .await
-        .context("Failed to create queue consumer")?;
 
-    holaplex_indexer::amqp_consume(&params, conn, consumer, queue_type, move |m| {
+    let rarity = tokio::spawn(client.clone().run_rarity_recompute());
+
+    let res = holaplex_indexer::amqp_consume(&params, conn, queue_type, "http-consumer", {
         let client = client.clone();
-        async move { m.process(&client).await }
+        move |m| {
+            let client = client.clone();
+            async move { m.process(&client).await }
+        }
     })
-    .await
+    .await;
+
+    // Rescore any collections left stale by the last messages processed
+    rarity.abort();
+    client.recompute_rarity().await;
+
+    res
 }
@@ -0,0 +1,26 @@
+use indexer_core::clap;
+
+/// Discard indexed rows written at slots rolled back by a fork
+///
+/// Rows above the given slot in `token_accounts` and
+/// `twitter_handle_name_services` are DELETED, not reverted to their previous
+/// state, because earlier versions of these rows are not kept.  The affected
+/// accounts must be re-indexed afterwards to restore them.
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// Remove rows written at any slot above this one
+    #[clap(long, env)]
+    slot: u64,
+
+    /// Confirm that the affected rows should be deleted
+    #[clap(long)]
+    yes: bool,
+}
+
+fn main() {
+    holaplex_indexer::run(|args: Args, _params, db| async move {
+        let Args { slot, yes } = args;
+
+        holaplex_indexer::rollback::run(&db, slot, yes).await
+    })
+}
@@ -0,0 +1,40 @@
+use holaplex_indexer::seed::Counts;
+use indexer_core::clap;
+
+#[derive(Debug, clap::Parser)]
+struct Args {
+    /// Number of auction houses to create
+    #[clap(long, env, default_value_t = 2)]
+    auction_houses: usize,
+
+    /// Number of NFTs to create
+    #[clap(long, env, default_value_t = 100)]
+    nfts: usize,
+
+    /// Number of seeded NFTs to list for sale
+    #[clap(long, env, default_value_t = 30)]
+    listings: usize,
+
+    /// Number of sales to create
+    #[clap(long, env, default_value_t = 20)]
+    purchases: usize,
+}
+
+fn main() {
+    holaplex_indexer::run(|args: Args, _params, db| async move {
+        let Args {
+            auction_houses,
+            nfts,
+            listings,
+            purchases,
+        } = args;
+
+        holaplex_indexer::seed::run(&db, Counts {
+            auction_houses,
+            nfts,
+            listings,
+            purchases,
+        })
+        .await
+    })
+}
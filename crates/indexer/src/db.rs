@@ -23,6 +23,12 @@ impl Pool {
         self.1
     }
 
+    /// Get a handle to the underlying connection pool, for reporting its
+    /// state
+    pub(crate) fn inner(&self) -> db::Pool {
+        self.0.clone()
+    }
+
     /// Spawn a blocking thread to perform operations on the database.
     ///
     /// # Errors
@@ -84,7 +84,7 @@ pub(crate) async fn process_vote(client: &Client, key: Pubkey, v: Vote) -> Resul
         proposal: Owned(v.proposal.to_string()),
         voter: Owned(v.voter.to_string()),
         bump: v.bump.try_into()?,
-        side: v.side.try_into()?,
+        side: i16::from(v.side).into(),
         weight: v.weight.try_into()?,
     };
 
@@ -21,7 +21,7 @@ pub(crate) async fn process_locker(client: &Client, key: Pubkey, l: Locker) -> R
         base: Owned(l.base.to_string()),
         bump: l.bump.try_into()?,
         token_mint: Owned(l.token_mint.to_string()),
-        locked_supply: l.locked_supply.try_into()?,
+        locked_supply: l.locked_supply.into(),
         governor: Owned(l.governor.to_string()),
     };
 
@@ -74,7 +74,7 @@ pub(crate) async fn process_escrow(client: &Client, key: Pubkey, es: Escrow) ->
         owner: Owned(es.owner.to_string()),
         bump: es.bump.try_into()?,
         tokens: Owned(es.tokens.to_string()),
-        amount: es.amount.try_into()?,
+        amount: es.amount.into(),
         escrow_started_at: es.escrow_started_at,
         escrow_ends_at: es.escrow_ends_at,
         vote_delegate: Owned(es.vote_delegate.to_string()),
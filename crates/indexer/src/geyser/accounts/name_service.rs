@@ -1,6 +1,8 @@
 use borsh::BorshDeserialize;
 use indexer_core::{
-    db::{insert_into, models::TwitterHandle, tables::twitter_handle_name_services, update},
+    db::{
+        insert_into, models::TwitterHandle, tables::twitter_handle_name_services, upsert_if_newer,
+    },
     prelude::*,
 };
 
@@ -23,59 +25,28 @@ pub(crate) async fn process(
     let th = TwitterHandleAndRegistry::deserialize(&mut data.as_slice())
         .context("failed to deserialize registry key and handle!")?;
 
-    let incoming_slot: i64 = slot.try_into()?;
-
-    let rows = client
-        .db()
-        .run(move |db| {
-            twitter_handle_name_services::table
-                .select(twitter_handle_name_services::all_columns)
-                .filter(twitter_handle_name_services::address.eq(key.to_string()))
-                .load::<TwitterHandle>(db)
-        })
-        .await
-        .context("failed to load token accounts!")?;
-
-    let pubkey: String = key.to_string();
-
     let values = TwitterHandle {
-        address: Owned(pubkey.clone()),
+        address: Owned(key.to_string()),
         wallet_address: Owned(wallet.to_string()),
         twitter_handle: Owned(th.handle),
         slot: slot.try_into()?,
     };
 
-    match rows.get(0) {
-        Some(indexed) if incoming_slot > indexed.slot => {
-            client
-                .db()
-                .run(move |db| {
-                    update(
-                        twitter_handle_name_services::table
-                            .filter(twitter_handle_name_services::address.eq(pubkey)),
-                    )
-                    .set(&values)
-                    .execute(db)
-                })
-                .await
-                .context("failed to update twitter handle")?;
-        },
-        Some(_) => (),
-        None => {
-            client
-                .db()
-                .run(move |db| {
-                    insert_into(twitter_handle_name_services::table)
-                        .values(&values)
-                        .on_conflict(twitter_handle_name_services::address)
-                        .do_update()
-                        .set(&values)
-                        .execute(db)
-                })
-                .await
-                .context("failed to insert twitter handle")?;
-        },
-    }
+    client
+        .db()
+        .run(move |db| {
+            upsert_if_newer(
+                twitter_handle_name_services::table,
+                insert_into(twitter_handle_name_services::table)
+                    .values(&values)
+                    .on_conflict(twitter_handle_name_services::address)
+                    .do_update()
+                    .set(&values),
+            )
+            .execute(db)
+        })
+        .await
+        .context("failed to insert twitter handle")?;
 
     Ok(())
 }
@@ -1,8 +1,13 @@
 use indexer_core::{
-    db::{insert_into, models::TokenAccount as TokenAccountModel, tables::token_accounts, update},
+    db::{
+        insert_into,
+        models::{TokenAccount as TokenAccountModel, TokenMint as TokenMintModel},
+        tables::{token_accounts, token_mints},
+        update, upsert_if_newer,
+    },
     prelude::*,
 };
-use spl_token::state::Account as TokenAccount;
+use spl_token::state::{Account as TokenAccount, Mint};
 
 use super::Client;
 use crate::prelude::*;
@@ -84,13 +89,16 @@ pub async fn process(
                     db.build_transaction()
                         .read_write()
                         .run(|| {
-                            insert_into(token_accounts::table)
-                                .values(&values)
-                                .on_conflict(token_accounts::address)
-                                .do_update()
-                                .set(&values)
-                                .execute(db)
-                                .map(|_| ())
+                            upsert_if_newer(
+                                token_accounts::table,
+                                insert_into(token_accounts::table)
+                                    .values(&values)
+                                    .on_conflict(token_accounts::address)
+                                    .do_update()
+                                    .set(&values),
+                            )
+                            .execute(db)
+                            .map(|_| ())
                         })
                         .context("transaction failed! unable to insert token account")?;
 
@@ -102,3 +110,35 @@ pub async fn process(
         .context("failed to insert token account!")?;
     Ok(())
 }
+
+/// Index the decimals of a token mint
+///
+/// Minted NFTs (zero decimals and a supply of exactly one) are skipped, since
+/// their decimals are never resolved and they vastly outnumber fungible token
+/// mints.  Mints with no supply yet are still indexed, as a zero-decimal
+/// fungible token may later be minted from them.
+pub async fn process_mint(client: &Client, key: Pubkey, mint: Mint) -> Result<()> {
+    if mint.decimals == 0 && mint.supply == 1 {
+        return Ok(());
+    }
+
+    let row = TokenMintModel {
+        address: Owned(key.to_string()),
+        decimals: mint.decimals.into(),
+    };
+
+    client
+        .db()
+        .run(move |db| {
+            insert_into(token_mints::table)
+                .values(&row)
+                .on_conflict(token_mints::address)
+                .do_update()
+                .set(&row)
+                .execute(db)
+        })
+        .await
+        .context("failed to insert token mint")?;
+
+    Ok(())
+}
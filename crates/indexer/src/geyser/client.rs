@@ -1,7 +1,7 @@
 use std::{panic::AssertUnwindSafe, sync::Arc};
 
 use indexer_core::prelude::*;
-use indexer_rabbitmq::http_indexer;
+use indexer_rabbitmq::{http_indexer, Compression};
 use solana_sdk::pubkey::Pubkey;
 
 use crate::db::Pool;
@@ -33,16 +33,19 @@ impl Client {
         conn: &indexer_rabbitmq::lapin::Connection,
         meta_queue: http_indexer::QueueType<http_indexer::MetadataJson>,
         store_cfg_queue: http_indexer::QueueType<http_indexer::StoreConfig>,
+        compression: Compression,
     ) -> Result<Arc<Self>> {
         Ok(Arc::new(Self {
             db: AssertUnwindSafe(db),
             http: HttpProducers {
                 metadata_json: http_indexer::Producer::new(conn, meta_queue)
                     .await
-                    .context("Couldn't create AMQP metadata JSON producer")?,
+                    .context("Couldn't create AMQP metadata JSON producer")?
+                    .with_compression(compression),
                 store_config: http_indexer::Producer::new(conn, store_cfg_queue)
                     .await
-                    .context("Couldn't create AMQP store config producer")?,
+                    .context("Couldn't create AMQP store config producer")?
+                    .with_compression(compression),
             },
         }))
     }
@@ -1,5 +1,5 @@
 use solana_program::program_pack::Pack;
-use spl_token::state::Account as TokenAccount;
+use spl_token::state::{Account as TokenAccount, Mint};
 
 use super::{accounts::token, AccountUpdate, Client};
 use crate::prelude::*;
@@ -10,9 +10,21 @@ async fn process_token(client: &Client, update: AccountUpdate) -> Result<()> {
     token::process(client, update.key, token_account, update.slot).await
 }
 
-pub(crate) async fn process(client: &Client, update: AccountUpdate) -> Result<()> {
-    if update.data.len() != TokenAccount::LEN {
+async fn process_mint(client: &Client, update: AccountUpdate) -> Result<()> {
+    let mint =
+        Mint::unpack_unchecked(&update.data).context("Failed to deserialize token mint data")?;
+
+    if !mint.is_initialized {
         return Ok(());
     }
-    process_token(client, update).await
+
+    token::process_mint(client, update.key, mint).await
+}
+
+pub(crate) async fn process(client: &Client, update: AccountUpdate) -> Result<()> {
+    match update.data.len() {
+        TokenAccount::LEN => process_token(client, update).await,
+        Mint::LEN => process_mint(client, update).await,
+        _ => Ok(()),
+    }
 }
@@ -1,9 +1,9 @@
 use std::{sync::Arc, time::Duration};
 
 use cid::Cid;
-use indexer_core::{assets::ArTxid, clap};
+use indexer_core::{assets::ArTxid, clap, db::queries, hash::DashSet};
 use reqwest::Url;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, Semaphore};
 
 use crate::{db::Pool, prelude::*};
 
@@ -15,6 +15,11 @@ pub struct Args {
     #[clap(long, env)]
     pub ipfs_cdn: String,
 
+    /// Comma-separated list of additional IPFS gateway base URLs to try, in
+    /// order, when fetching an IPFS link from the CDN fails
+    #[clap(long, env, use_value_delimiter(true))]
+    pub ipfs_gateways: Option<Vec<String>>,
+
     /// A valid base URL to use when fetching Arweave links
     #[clap(long, env)]
     pub arweave_cdn: String,
@@ -22,6 +27,66 @@ pub struct Args {
     /// HTTP request timeout, in seconds
     #[clap(long, env = "HTTP_INDEXER_TIMEOUT")]
     pub timeout: f64,
+
+    /// Maximum size, in bytes, of the raw JSON content to store for a single
+    /// document.  Larger documents are reduced to their standard fields.
+    #[clap(
+        long,
+        env = "HTTP_INDEXER_MAX_RAW_CONTENT_SIZE",
+        default_value_t = 4 * 1024 * 1024
+    )]
+    pub max_raw_content_size: usize,
+
+    /// Maximum size, in bytes, of a JSON document to download.  Responses
+    /// advertising or streaming a larger body are rejected.
+    #[clap(long, env, default_value_t = 32 * 1024 * 1024)]
+    pub max_json_bytes: usize,
+
+    /// Maximum size, in bytes, of a non-JSON file, such as an image or video,
+    /// as advertised in response to a `HEAD` request.  Files listed in
+    /// metadata JSON advertising a larger size are not stored.  Such files are
+    /// never buffered, so this may be far larger than the JSON limit.
+    #[clap(long, env, default_value_t = 512 * 1024 * 1024)]
+    pub max_file_bytes: u64,
+
+    /// Store a trimmed, lowercased copy of attribute trait types for
+    /// filtering and grouping alongside the raw trait type
+    #[clap(long, env, parse(try_from_str), default_value = "true")]
+    pub normalize_trait_types: bool,
+
+    /// Maximum age, in seconds, of indexed metadata JSON to trust when its
+    /// URI's fingerprint is unchanged.  Older documents are downloaded again.
+    /// If unset, an unchanged fingerprint never triggers a download.
+    #[clap(long, env)]
+    pub metadata_json_max_age: Option<u64>,
+
+    /// Download and reprocess all metadata JSON, even if a document with the
+    /// same fingerprint has already been indexed
+    #[clap(long, env, parse(try_from_str), default_value = "false")]
+    pub force_metadata_refresh: bool,
+
+    /// Maximum number of times to retry a failed HTTP request.  Only
+    /// connection errors, timeouts, and 429 or 5xx responses are retried.
+    #[clap(long, env, default_value_t = 3)]
+    pub http_max_retries: u32,
+
+    /// Delay, in milliseconds, before the first retry of a failed HTTP
+    /// request.  The delay doubles with each subsequent retry, and is jittered
+    /// to avoid retrying many requests in lockstep.
+    #[clap(long, env, default_value_t = 250)]
+    pub http_base_backoff_ms: u64,
+
+    /// Maximum number of HTTP requests to have in flight at once.  Workers
+    /// wait for a free slot rather than failing, holding their unacknowledged
+    /// delivery while they do, so memory remains bounded by the AMQP prefetch
+    /// and outbound connections by this value.
+    #[clap(long, env, default_value_t = 64)]
+    pub http_concurrency: usize,
+
+    /// Interval, in seconds, between recomputations of the rarity scores of
+    /// collections with NFTs indexed since the previous recomputation
+    #[clap(long, env, default_value_t = 60)]
+    pub rarity_recompute_interval: u64,
 }
 
 /// Wrapper for handling networking logic
@@ -29,24 +94,52 @@ pub struct Args {
 pub struct Client {
     db: Pool,
     http: Mutex<(u8, reqwest::Client)>,
-    ipfs_cdn: Url,
+    http_permits: Semaphore,
+    ipfs_gateways: Vec<Url>,
     arweave_cdn: Url,
     timeout: Duration,
+    max_raw_content_size: usize,
+    max_json_bytes: usize,
+    max_file_bytes: u64,
+    normalize_trait_types: bool,
+    metadata_json_max_age: Option<chrono::Duration>,
+    force_metadata_refresh: bool,
+    max_retries: u32,
+    base_backoff: Duration,
+    stale_rarity: DashSet<String>,
+    rarity_recompute_interval: Duration,
 }
 
 impl Client {
     /// Construct a new client, wrapped in an `Arc`.
     ///
     /// # Errors
-    /// This function fails if an invalid URL is given for `ipfs_cdn` or
-    /// `arweave_cdn`.
+    /// This function fails if an invalid URL is given for `ipfs_cdn`,
+    /// `ipfs_gateways`, or `arweave_cdn`, or if `http_concurrency` is zero.
     pub fn new_rc(db: Pool, args: Args) -> Result<Arc<Self>> {
         let Args {
             ipfs_cdn,
+            ipfs_gateways,
             arweave_cdn,
             timeout,
+            max_raw_content_size,
+            max_json_bytes,
+            max_file_bytes,
+            normalize_trait_types,
+            metadata_json_max_age,
+            force_metadata_refresh,
+            http_max_retries,
+            http_base_backoff_ms,
+            http_concurrency,
+            rarity_recompute_interval,
         } = args;
 
+        ensure!(http_concurrency > 0, "HTTP concurrency must be nonzero");
+        ensure!(
+            rarity_recompute_interval > 0,
+            "Rarity recompute interval must be nonzero"
+        );
+
         let ipfs_cdn: Url = ipfs_cdn.parse().context("Failed to parse IPFS CDN URL")?;
         let arweave_cdn: Url = arweave_cdn
             .parse()
@@ -55,14 +148,42 @@ impl Client {
         ensure!(!ipfs_cdn.cannot_be_a_base(), "Invalid IPFS CDN URL");
         ensure!(!arweave_cdn.cannot_be_a_base(), "Invalid Arweave CDN URL");
 
+        let ipfs_gateways = Some(Ok(ipfs_cdn))
+            .into_iter()
+            .chain(ipfs_gateways.into_iter().flatten().map(|g| {
+                let url: Url = g
+                    .parse()
+                    .with_context(|| format!("Failed to parse IPFS gateway URL {:?}", g))?;
+
+                ensure!(!url.cannot_be_a_base(), "Invalid IPFS gateway URL {:?}", g);
+
+                Ok(url)
+            }))
+            .collect::<Result<Vec<_>>>()?;
+
         let timeout = Duration::from_secs_f64(timeout);
+        let metadata_json_max_age = metadata_json_max_age
+            .map(|s| chrono::Duration::from_std(Duration::from_secs(s)))
+            .transpose()
+            .context("Invalid metadata JSON max age")?;
 
         Ok(Arc::new(Self {
             db,
             http: Mutex::new((0, Self::build_client(timeout)?)),
-            ipfs_cdn,
+            http_permits: Semaphore::new(http_concurrency),
+            ipfs_gateways,
             arweave_cdn,
             timeout,
+            max_raw_content_size,
+            max_json_bytes,
+            max_file_bytes,
+            normalize_trait_types,
+            metadata_json_max_age,
+            force_metadata_refresh,
+            max_retries: http_max_retries,
+            base_backoff: Duration::from_millis(http_base_backoff_ms),
+            stale_rarity: DashSet::default(),
+            rarity_recompute_interval: Duration::from_secs(rarity_recompute_interval),
         }))
     }
 
@@ -72,6 +193,88 @@ impl Client {
         &self.db
     }
 
+    /// Get the maximum size, in bytes, of raw JSON content to store
+    #[must_use]
+    pub fn max_raw_content_size(&self) -> usize {
+        self.max_raw_content_size
+    }
+
+    /// Get the maximum size, in bytes, a non-JSON file may advertise
+    #[must_use]
+    pub fn max_file_bytes(&self) -> u64 {
+        self.max_file_bytes
+    }
+
+    /// Returns true if attribute trait types should be normalized on write
+    #[must_use]
+    pub fn normalize_trait_types(&self) -> bool {
+        self.normalize_trait_types
+    }
+
+    /// Get the maximum age of indexed metadata JSON to skip downloading again
+    /// when its fingerprint is unchanged, if any
+    #[must_use]
+    pub fn metadata_json_max_age(&self) -> Option<chrono::Duration> {
+        self.metadata_json_max_age
+    }
+
+    /// Returns true if metadata JSON should be downloaded again regardless of
+    /// its fingerprint
+    #[must_use]
+    pub fn force_metadata_refresh(&self) -> bool {
+        self.force_metadata_refresh
+    }
+
+    /// Mark the rarity scores of the collection with the given first verified
+    /// creator as stale, to be recomputed by [`Self::recompute_rarity`]
+    ///
+    /// Collections which are still minting see many NFTs indexed in quick
+    /// succession, so rather than rescoring a collection for each one, its
+    /// rescoring is deferred and done once for all NFTs indexed in between.
+    pub fn invalidate_rarity(&self, first_verified_creator: String) {
+        self.stale_rarity.insert(first_verified_creator);
+    }
+
+    /// Recompute the rarity scores of every collection marked stale since the
+    /// last recomputation.  Collections which fail to be rescored are marked
+    /// stale again to be retried.
+    pub async fn recompute_rarity(&self) {
+        let creators: Vec<String> = self.stale_rarity.iter().map(|c| c.key().clone()).collect();
+
+        for creator in creators {
+            // Unmark the collection before rescoring it, so NFTs indexed
+            // while it is rescored mark it stale again
+            self.stale_rarity.remove(&creator);
+
+            let res = self
+                .db
+                .run({
+                    let creator = creator.clone();
+                    move |db| queries::rarity_scores::recompute(db, &creator)
+                })
+                .await;
+
+            match res {
+                Ok(n) => debug!("Rescored {} NFT(s) in collection {}", n, creator),
+                Err(e) => {
+                    error!("Failed to rescore collection {}: {:?}", creator, e);
+                    self.stale_rarity.insert(creator);
+                },
+            }
+        }
+    }
+
+    /// Periodically recompute the rarity scores of stale collections with
+    /// [`Self::recompute_rarity`], until the returned future is dropped
+    pub async fn run_rarity_recompute(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(self.rarity_recompute_interval);
+
+        loop {
+            interval.tick().await;
+            self.recompute_rarity().await;
+        }
+    }
+
     fn build_client(timeout: Duration) -> Result<reqwest::Client> {
         reqwest::ClientBuilder::new()
             .timeout(timeout)
@@ -85,69 +288,232 @@ impl Client {
             .context("Failed to build HTTP client")
     }
 
-    /// Acquire an HTTP client
+    /// Returns true if a failed request may succeed if attempted again
+    fn is_transient(e: &reqwest::Error) -> bool {
+        e.is_connect()
+            || e.is_timeout()
+            || e.status().map_or(false, |s| {
+                s.is_server_error() || s == reqwest::StatusCode::TOO_MANY_REQUESTS
+            })
+    }
+
+    /// Compute the delay before retrying a request that has failed `attempt`
+    /// times, doubling the base delay each time and jittering the result
+    /// between half and all of the computed delay
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_backoff
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)));
+        let half = delay / 2;
+
+        half + half.mul_f64(rand::random())
+    }
+
+    /// Acquire an HTTP client and perform a request with it, retrying
+    /// transient failures with exponential backoff
+    ///
+    /// Each attempt waits for one of the slots limiting concurrent requests,
+    /// which is released while waiting to retry.
+    ///
+    /// The closure is called once per attempt.  For status errors to be
+    /// retried, the closure should check the response status (e.g. with
+    /// [`reqwest::Response::error_for_status`]).
     ///
     /// # Errors
     /// This function does not generate errors, it simply passes any errors
-    /// raised by the given closure through to the function return.
+    /// raised by the final attempt of the given closure through to the
+    /// function return.
     #[inline]
     pub async fn http<F: std::future::Future<Output = reqwest::Result<T>>, T>(
         &self,
-        f: impl FnOnce(reqwest::Client) -> F,
+        f: impl Fn(reqwest::Client) -> F,
     ) -> Result<T> {
-        let (hint, http) = self.http.lock().await.clone();
-
-        match f(http).await {
-            Ok(v) => Ok(v),
-            Err(e) => {
-                if e.is_connect()
-                    || !(e.is_redirect()
-                        || e.is_status()
-                        || e.is_timeout()
-                        || e.is_body()
-                        || e.is_decode())
-                {
-                    // Something may have happened, close the connection pool
-                    let (ref mut hint2, ref mut http) = *self.http.lock().await;
-
-                    if *hint2 == hint {
-                        warn!("Connection error detected, rotating HTTP client");
-
-                        match Self::build_client(self.timeout) {
-                            Ok(client) => {
-                                *hint2 = hint2.wrapping_add(1);
-                                *http = client;
-                            },
-                            Err(e) => error!("Failed to rotate HTTP client: {:?}", e),
-                        }
+        let mut attempt = 0;
+
+        loop {
+            let (hint, http) = self.http.lock().await.clone();
+
+            let res = {
+                let _permit = self
+                    .http_permits
+                    .acquire()
+                    .await
+                    .context("HTTP request semaphore closed")?;
+
+                f(http).await
+            };
+
+            let e = match res {
+                Ok(v) => return Ok(v),
+                Err(e) => e,
+            };
+
+            if e.is_connect()
+                || !(e.is_redirect()
+                    || e.is_status()
+                    || e.is_timeout()
+                    || e.is_body()
+                    || e.is_decode())
+            {
+                // Something may have happened, close the connection pool
+                let (ref mut hint2, ref mut http) = *self.http.lock().await;
+
+                if *hint2 == hint {
+                    warn!("Connection error detected, rotating HTTP client");
+
+                    match Self::build_client(self.timeout) {
+                        Ok(client) => {
+                            *hint2 = hint2.wrapping_add(1);
+                            *http = client;
+                        },
+                        Err(e) => error!("Failed to rotate HTTP client: {:?}", e),
                     }
                 }
+            }
+
+            if attempt >= self.max_retries || !Self::is_transient(&e) {
+                return Err(e).context("HTTP request failed");
+            }
+
+            attempt += 1;
+
+            let delay = self.backoff(attempt);
+            debug!(
+                "Retrying HTTP request in {:?} (attempt {} of {}): {}",
+                delay, attempt, self.max_retries, e
+            );
 
-                Err(e).context("HTTP request failed")
-            },
+            tokio::time::sleep(delay).await;
         }
     }
 
-    /// Construct an IPFS link from an IPFS CID
+    /// Download a JSON document with [`Self::http`], rejecting it without
+    /// buffering the rest of the body if it is larger than the configured
+    /// maximum JSON size
+    ///
+    /// # Errors
+    /// This function fails if the request fails or if the response body is
+    /// too large, either as advertised by its `Content-Length` or as read.
+    pub async fn get_json_bytes(&self, url: &Url) -> Result<Vec<u8>> {
+        let max = self.max_json_bytes;
+
+        self.http(|h| {
+            let url = url.clone();
+            async move {
+                let mut res = h.get(url).send().await?.error_for_status()?;
+
+                if let Some(len) = res.content_length() {
+                    if usize::try_from(len).map_or(true, |l| l > max) {
+                        return Ok(Err(anyhow!(
+                            "Response advertised {} bytes, exceeding the limit of {}",
+                            len,
+                            max
+                        )));
+                    }
+                }
+
+                let mut bytes = Vec::new();
+
+                while let Some(chunk) = res.chunk().await? {
+                    if bytes.len() + chunk.len() > max {
+                        return Ok(Err(anyhow!(
+                            "Response body exceeded the limit of {} bytes",
+                            max
+                        )));
+                    }
+
+                    bytes.extend_from_slice(&chunk);
+                }
+
+                Ok(Ok(bytes))
+            }
+        })
+        .await?
+    }
+
+    /// Check the size of a non-JSON file with a `HEAD` request, returning the
+    /// length it advertises, if any
     ///
     /// # Errors
-    /// This function fails if the CID provided is not URL safe.
-    pub fn ipfs_link(&self, cid: &Cid, path: &str) -> Result<Url> {
-        let mut ret = self.ipfs_cdn.clone();
+    /// This function fails if the request fails.
+    pub async fn head_file(&self, url: &Url) -> Result<Option<u64>> {
+        self.http(|h| {
+            let url = url.clone();
+            async move {
+                let res = h.head(url).send().await?.error_for_status()?;
+
+                // The body of a HEAD response is always empty, so read
+                // the header rather than the body's size hint
+                Ok(res
+                    .headers()
+                    .get(reqwest::header::CONTENT_LENGTH)
+                    .and_then(|l| l.to_str().ok()?.parse::<u64>().ok()))
+            }
+        })
+        .await
+    }
+
+    /// Construct IPFS links from an IPFS CID, one for each configured gateway
+    /// in the order they should be tried, starting with the IPFS CDN
+    pub fn ipfs_links<'a>(
+        &'a self,
+        cid: &'a Cid,
+        path: &'a str,
+    ) -> impl Iterator<Item = Result<Url>> + 'a {
+        self.ipfs_gateways.iter().map(move |gateway| {
+            let mut ret = gateway.clone();
 
-        {
-            let mut parts = ret
-                .path_segments_mut()
-                .map_err(|_| anyhow!("Invalid IPFS CDN URL"))?;
+            {
+                let mut parts = ret
+                    .path_segments_mut()
+                    .map_err(|_| anyhow!("Invalid IPFS gateway URL"))?;
 
-            parts.push(&cid.to_string());
+                parts.push(&cid.to_string());
 
-            if !path.is_empty() {
-                parts.extend(path.split('/'));
+                if !path.is_empty() {
+                    parts.extend(path.split('/'));
+                }
             }
+
+            Ok(ret)
+        })
+    }
+
+    /// Decode the payload of a `data:` URI, which carries its content inline
+    /// rather than referring to a network resource.  Returns `None` if the URL
+    /// is not a data URI.
+    ///
+    /// Payloads are percent-decoded, then base64-decoded if the URI's media
+    /// type is marked `;base64`.
+    ///
+    /// # Errors
+    /// This function fails if the URI has no payload or its base64 payload is
+    /// invalid.
+    #[must_use]
+    pub fn decode_data_uri(url: &Url) -> Option<Result<Vec<u8>>> {
+        if url.scheme() != "data" {
+            return None;
         }
 
-        Ok(ret)
+        // Use the serialized URL rather than the path, since a payload may
+        // contain characters parsed as a query or fragment delimiter
+        let (header, payload) = match url.as_str().split_once(',') {
+            Some(p) => p,
+            None => return Some(Err(anyhow!("Data URI is missing a payload"))),
+        };
+
+        let bytes: Vec<u8> = percent_encoding::percent_decode_str(payload).collect();
+
+        Some(if header.to_ascii_lowercase().ends_with(";base64") {
+            let bytes: Vec<u8> = bytes
+                .into_iter()
+                .filter(|b| !b.is_ascii_whitespace())
+                .collect();
+
+            base64::decode(&bytes).context("Failed to decode base64 data URI payload")
+        } else {
+            Ok(bytes)
+        })
     }
 
     /// Construct an Arweave link from a valid Arweave transaction ID
@@ -160,3 +526,262 @@ impl Client {
             .map_err(Into::into)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use hyper::StatusCode;
+
+    use super::*;
+    use crate::http::testing::{self, MockServer};
+
+    async fn get_text(client: &Client, url: &Url) -> Result<String> {
+        client
+            .http(|h| {
+                let url = url.clone();
+                async move { h.get(url).send().await?.error_for_status()?.text().await }
+            })
+            .await
+    }
+
+    async fn status_error(status: StatusCode) -> reqwest::Error {
+        let server = MockServer::start(move |_, _| async move { testing::respond(status, "") });
+
+        reqwest::get(server.url("/"))
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures() {
+        let server = MockServer::start(|n, _| async move {
+            if n < 2 {
+                testing::respond(StatusCode::SERVICE_UNAVAILABLE, "")
+            } else {
+                testing::respond(StatusCode::OK, "done")
+            }
+        });
+        let client = testing::client(testing::args(&server.url("/")));
+
+        assert_eq!(
+            get_text(&client, &server.url("/doc")).await.unwrap(),
+            "done"
+        );
+        assert_eq!(server.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn permanent_failures_are_not_retried() {
+        let server =
+            MockServer::start(|_, _| async { testing::respond(StatusCode::NOT_FOUND, "") });
+        let client = testing::client(testing::args(&server.url("/")));
+
+        assert!(get_text(&client, &server.url("/doc")).await.is_err());
+        assert_eq!(server.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_are_bounded() {
+        let server = MockServer::start(|_, _| async {
+            testing::respond(StatusCode::INTERNAL_SERVER_ERROR, "")
+        });
+        let client = testing::client(Args {
+            http_max_retries: 2,
+            ..testing::args(&server.url("/"))
+        });
+
+        assert!(get_text(&client, &server.url("/doc")).await.is_err());
+        assert_eq!(server.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn transient_errors() {
+        for status in [
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::BAD_GATEWAY,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ] {
+            assert!(
+                Client::is_transient(&status_error(status).await),
+                "{}",
+                status
+            );
+        }
+
+        for status in [
+            StatusCode::BAD_REQUEST,
+            StatusCode::FORBIDDEN,
+            StatusCode::NOT_FOUND,
+        ] {
+            assert!(
+                !Client::is_transient(&status_error(status).await),
+                "{}",
+                status
+            );
+        }
+
+        // Nothing listens on port 1, so connecting is refused
+        let refused = reqwest::get("http://127.0.0.1:1/").await.unwrap_err();
+
+        assert!(Client::is_transient(&refused));
+    }
+
+    #[test]
+    fn backoff_doubles_with_jitter() {
+        let client = testing::client(Args {
+            http_base_backoff_ms: 100,
+            ..testing::args(&"http://127.0.0.1:1/".parse().unwrap())
+        });
+
+        for (attempt, max_ms) in [(1, 100), (2, 200), (3, 400), (4, 800)] {
+            for _ in 0..20 {
+                let delay = client.backoff(attempt);
+
+                assert!(delay >= Duration::from_millis(max_ms / 2), "{:?}", delay);
+                assert!(delay <= Duration::from_millis(max_ms), "{:?}", delay);
+            }
+        }
+
+        // Large attempt counts saturate rather than overflowing
+        assert!(client.backoff(u32::MAX) > Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_are_bounded() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let server = MockServer::start({
+            let in_flight = Arc::clone(&in_flight);
+            let peak = Arc::clone(&peak);
+
+            move |_, _| {
+                let in_flight = Arc::clone(&in_flight);
+                let peak = Arc::clone(&peak);
+
+                async move {
+                    let n = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(n, Ordering::SeqCst);
+
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                    testing::respond(StatusCode::OK, "done")
+                }
+            }
+        });
+        let client = testing::client(Args {
+            http_concurrency: 2,
+            ..testing::args(&server.url("/"))
+        });
+
+        let url = server.url("/doc");
+        let results = futures_util::future::join_all((0..6).map(|_| get_text(&client, &url))).await;
+
+        assert!(results.into_iter().all(|r| r.unwrap() == "done"));
+        assert_eq!(server.requests(), 6);
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn oversized_json_is_rejected() {
+        let server = MockServer::start(|_, req| async move {
+            let len = if req.uri().path() == "/big.json" {
+                4096
+            } else {
+                512
+            };
+
+            testing::respond(StatusCode::OK, vec![b' '; len])
+        });
+        let client = testing::client(Args {
+            max_json_bytes: 1024,
+            ..testing::args(&server.url("/"))
+        });
+
+        let err = client
+            .get_json_bytes(&server.url("/big.json"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("exceeding the limit"), "{:?}", err);
+        assert_eq!(server.requests(), 1);
+
+        assert_eq!(
+            client
+                .get_json_bytes(&server.url("/small.json"))
+                .await
+                .unwrap()
+                .len(),
+            512
+        );
+    }
+
+    #[tokio::test]
+    async fn files_have_a_larger_limit() {
+        let server =
+            MockServer::start(
+                |_, _| async move { testing::respond(StatusCode::OK, vec![0_u8; 4096]) },
+            );
+        let client = testing::client(Args {
+            max_json_bytes: 1024,
+            max_file_bytes: 8192,
+            ..testing::args(&server.url("/"))
+        });
+
+        assert!(
+            client
+                .get_json_bytes(&server.url("/nft.png"))
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            client.head_file(&server.url("/nft.png")).await.unwrap(),
+            Some(4096)
+        );
+    }
+
+    fn decode(uri: &str) -> Option<Result<Vec<u8>>> {
+        Client::decode_data_uri(&uri.parse().unwrap())
+    }
+
+    #[test]
+    fn data_uris() {
+        let json = r#"{"name":"NFT"}"#;
+        let encoded = base64::encode(json);
+
+        assert_eq!(
+            decode(&format!("data:application/json;base64,{}", encoded))
+                .unwrap()
+                .unwrap(),
+            json.as_bytes()
+        );
+        // Whitespace and percent-encoding within base64 payloads are tolerated
+        assert_eq!(
+            decode(&format!(
+                "data:application/json;BASE64,{}%0A{}",
+                &encoded[..8],
+                &encoded[8..]
+            ))
+            .unwrap()
+            .unwrap(),
+            json.as_bytes()
+        );
+        assert_eq!(
+            decode("data:application/json,%7B%22name%22%3A%22NFT%22%7D")
+                .unwrap()
+                .unwrap(),
+            json.as_bytes()
+        );
+
+        assert!(
+            decode("data:application/json;base64,not*base64")
+                .unwrap()
+                .is_err()
+        );
+        assert!(decode("data:application/json").unwrap().is_err());
+        assert!(decode("https://example.com/nft.json").is_none());
+    }
+}
@@ -0,0 +1,119 @@
+use indexer_core::db::{insert_into, models::CollectionJson, tables::collection_jsons};
+use reqwest::Url;
+use serde::Deserialize;
+
+use super::Client;
+use crate::prelude::*;
+
+#[derive(Deserialize, Debug)]
+struct CollectionJsonData {
+    name: Option<String>,
+    image: Option<String>,
+    description: Option<String>,
+}
+
+pub async fn process(client: &Client, collection_mint: Pubkey, uri_str: String) -> Result<()> {
+    let url = match Url::parse(&uri_str) {
+        Ok(u) => u,
+        Err(e) => {
+            debug!("Couldn't parse collection URL: {:?}", e);
+            return Ok(());
+        },
+    };
+
+    debug!(
+        "Attempting to fetch collection JSON: {:?}, with uri: {:?}",
+        collection_mint, uri_str
+    );
+
+    let bytes = client
+        .get_json_bytes(&url)
+        .await
+        .context("Collection JSON request failed")?;
+    let json: CollectionJsonData =
+        serde_json::from_slice(&bytes).context("Failed to parse collection JSON")?;
+
+    let row = CollectionJson {
+        collection_mint: Owned(bs58::encode(collection_mint).into_string()),
+        fetch_uri: Owned(uri_str),
+        name: json.name.map(Owned),
+        image: json.image.map(Owned),
+        description: json.description.map(Owned),
+    };
+
+    client
+        .db()
+        .run(move |db| {
+            insert_into(collection_jsons::table)
+                .values(&row)
+                .on_conflict(collection_jsons::collection_mint)
+                .do_update()
+                .set(&row)
+                .execute(db)
+        })
+        .await
+        .context("Failed to insert collection JSON")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::StatusCode;
+
+    use super::*;
+    use crate::http::testing::{self, MockServer};
+
+    #[tokio::test]
+    async fn stores_collection_json() {
+        if !testing::has_database() {
+            return;
+        }
+
+        let server = MockServer::start(|_, _| async {
+            testing::respond(
+                StatusCode::OK,
+                r#"{
+                    "name": "Degens",
+                    "image": "https://example.com/degens.png",
+                    "description": "A collection",
+                    "extra": { "ignored": true }
+                }"#,
+            )
+        });
+        let client = testing::client(testing::args(&server.url("/")));
+        let mint = Pubkey::new_unique();
+        let uri = server.url("/collection.json").to_string();
+
+        process(&client, mint, uri.clone()).await.unwrap();
+
+        let mint = bs58::encode(mint).into_string();
+        let row: CollectionJson<'static> = client
+            .db()
+            .run(move |db| {
+                collection_jsons::table
+                    .filter(collection_jsons::collection_mint.eq(mint))
+                    .first(db)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(server.requests(), 1);
+        assert_eq!(row.fetch_uri, uri);
+        assert_eq!(row.name.as_deref(), Some("Degens"));
+        assert_eq!(row.image.as_deref(), Some("https://example.com/degens.png"));
+        assert_eq!(row.description.as_deref(), Some("A collection"));
+    }
+
+    #[tokio::test]
+    async fn invalid_urls_are_skipped() {
+        let server = MockServer::start(|_, _| async { testing::respond(StatusCode::OK, "{}") });
+        let client = testing::client(testing::args(&server.url("/")));
+
+        process(&client, Pubkey::new_unique(), "not a url".to_owned())
+            .await
+            .unwrap();
+
+        assert_eq!(server.requests(), 0);
+    }
+}
@@ -1,16 +1,19 @@
-use std::fmt::{self, Debug, Display};
+use std::{
+    collections::hash_map::Entry,
+    fmt::{self, Debug, Display},
+};
 
 use indexer_core::{
     assets::{AssetHint, AssetIdentifier},
     db::{
-        insert_into,
+        delete, excluded, insert_into,
         models::{
             File as DbFile, MetadataAttributeWrite, MetadataCollection,
             MetadataJson as DbMetadataJson,
         },
-        select,
+        queries, select,
         tables::{attributes, files, metadata_collections, metadata_jsons},
-        update, Connection,
+        update, upsert_batch, Connection,
     },
     hash::HashMap,
 };
@@ -21,12 +24,111 @@ use serde_json::Value;
 use super::Client;
 use crate::prelude::*;
 
+/// Source name used when logging metadata JSON decoded from a data URI, to
+/// avoid logging the entire URI
+const DATA_URI_SOURCE: &str = "<data URI>";
+
+/// An entry in `properties.files`, given either as an object or as a bare URI
 #[derive(Serialize, Deserialize, Debug)]
-struct File {
-    uri: Option<String>,
-    #[serde(rename = "type")]
-    ty: Option<String>,
+#[serde(untagged)]
+enum File {
+    Object {
+        uri: Option<String>,
+        #[serde(rename = "type")]
+        ty: Option<String>,
+    },
+    Uri(String),
 }
+
+impl File {
+    /// Get the URI of this file, if one was given
+    fn uri(&self) -> Option<&str> {
+        match self {
+            Self::Object { uri, .. } => uri.as_deref(),
+            Self::Uri(uri) => Some(uri),
+        }
+    }
+
+    /// Get the MIME type of this file, guessing it from the URI's extension if
+    /// none was given
+    fn file_type(&self) -> Option<&str> {
+        match self {
+            Self::Object { ty: Some(ty), .. } => Some(ty.as_str()),
+            Self::Object { uri, ty: None } => uri.as_deref().and_then(guess_file_type),
+            Self::Uri(uri) => guess_file_type(uri),
+        }
+    }
+
+    /// Get the URI and MIME type of this file, guessing the type from the
+    /// URI's extension if none was given
+    fn into_parts(self) -> Option<(String, String)> {
+        let (uri, ty) = match self {
+            Self::Object { uri, ty } => (uri?, ty),
+            Self::Uri(uri) => (uri, None),
+        };
+
+        let ty = ty.or_else(|| guess_file_type(&uri).map(Into::into))?;
+
+        Some((uri, ty))
+    }
+}
+
+fn guess_file_type(uri: &str) -> Option<&'static str> {
+    let path = uri.split(|c| c == '?' || c == '#').next()?;
+    let (_, ext) = path.rsplit_once('.')?;
+
+    Some(match ext.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        "webm" => "video/webm",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "glb" => "model/gltf-binary",
+        "gltf" => "model/gltf+json",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        _ => return None,
+    })
+}
+
+/// Infer the Metaplex category of an NFT from the MIME types of its files,
+/// preferring the richest media present, since NFTs with rich media usually
+/// also list a still image as a preview
+fn infer_category(files: &[File]) -> Option<&'static str> {
+    files
+        .iter()
+        .filter_map(|f| {
+            let ty = f.file_type()?;
+            let (major, _) = ty.split_once('/')?;
+
+            Some(match (major, ty) {
+                ("model", _) => (0, "vr"),
+                ("video", _) => (1, "video"),
+                ("audio", _) => (2, "audio"),
+                (_, "text/html") => (3, "html"),
+                ("image", _) => (4, "image"),
+                _ => return None,
+            })
+        })
+        .min()
+        .map(|(_, c)| c)
+}
+
+/// Pick the category of an NFT, taking the one given in its JSON if present
+/// and inferring it from its files otherwise
+fn category(given: Option<String>, files: Option<&[File]>) -> Option<String> {
+    given
+        .map(|c| c.trim().to_owned())
+        .filter(|c| !c.is_empty())
+        .or_else(|| files.and_then(infer_category).map(Into::into))
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct Creator {
     address: String,
@@ -102,11 +204,155 @@ struct MetadataJsonMinimal {
     extra: HashMap<String, Value>,
 }
 
+/// Known shapes of off-chain metadata JSON, recorded in `metadata_jsons.model`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::IntoStaticStr)]
+enum MetadataModel {
+    /// The original Metaplex standard, listing creators under `properties`
+    #[strum(serialize = "metaplex")]
+    Metaplex,
+    /// The v1.1 Metaplex standard, which leaves creators on-chain
+    #[strum(serialize = "metaplex-v1.1")]
+    MetaplexV1_1,
+    /// JSON generated by the Candy Machine upload tooling
+    #[strum(serialize = "candy-machine")]
+    CandyMachine,
+    /// Any other shape
+    #[strum(serialize = "unknown")]
+    Unknown,
+    /// A document too large to store whole, reduced before being stored.  The
+    /// shape of the original document is not recorded.
+    #[strum(serialize = "truncated")]
+    Truncated,
+}
+
+impl MetadataModel {
+    /// Guess the schema of a metadata JSON document from the fields present
+    fn detect(json: &Value) -> Self {
+        let properties = json.get("properties").filter(|p| p.is_object());
+        let has_creators = properties
+            .and_then(|p| p.get("creators"))
+            .map_or(false, Value::is_array);
+        let has_files = properties
+            .and_then(|p| p.get("files"))
+            .map_or(false, Value::is_array);
+        let has_family = json
+            .get("collection")
+            .and_then(|c| c.get("family"))
+            .map_or(false, |f| !f.is_null());
+        let has_fee = json
+            .get("seller_fee_basis_points")
+            .map_or(false, Value::is_number);
+
+        match (json.get("name").and_then(Value::as_str), properties) {
+            (Some(_), Some(_)) if has_creators && has_family && has_fee => Self::CandyMachine,
+            (Some(_), Some(_)) if has_creators => Self::Metaplex,
+            (Some(_), Some(_)) if has_files => Self::MetaplexV1_1,
+            _ => Self::Unknown,
+        }
+    }
+}
+
 enum MetadataJsonResult {
     Full(MetadataJson),
     Minimal(MetadataJsonMinimal),
 }
 
+/// Top-level keys retained when an oversized document is reduced before being
+/// stored
+const STANDARD_FIELDS: &[&str] = &[
+    "name",
+    "symbol",
+    "description",
+    "seller_fee_basis_points",
+    "image",
+    "animation_url",
+    "external_url",
+    "category",
+    "collection",
+    "attributes",
+    "properties",
+];
+
+/// Measure the size of a JSON value as it will be stored
+fn json_size(value: &Value) -> Result<usize> {
+    serde_json::to_vec(value)
+        .map(|v| v.len())
+        .context("Failed to measure metadata JSON")
+}
+
+/// Shorten a string to at most `max` bytes, respecting character boundaries
+fn truncate_str(s: &mut String, max: usize) {
+    if s.len() > max {
+        let end = (0..=max)
+            .rev()
+            .find(|&i| s.is_char_boundary(i))
+            .unwrap_or(0);
+        s.truncate(end);
+    }
+}
+
+/// Cap the size of JSON content stored in `raw_content` at `max_size` bytes,
+/// returning the value to store and whether it had to be reduced
+///
+/// Oversized documents are reduced to their standard fields, then to the
+/// scalar ones, then have their strings shortened to share the limit.  If
+/// none of these fit, an empty object is stored.
+fn limit_raw_content(addr: &str, raw_content: Value, max_size: usize) -> Result<(Value, bool)> {
+    let size = json_size(&raw_content)?;
+
+    if size <= max_size {
+        return Ok((raw_content, false));
+    }
+
+    let mut map = match raw_content {
+        Value::Object(m) => m,
+        _ => serde_json::Map::new(),
+    };
+
+    map.retain(|k, _| STANDARD_FIELDS.contains(&k.as_str()));
+
+    let mut reduced = Value::Object(map);
+    let mut reduced_size = json_size(&reduced)?;
+
+    if reduced_size > max_size {
+        // Still too large, drop nested values entirely
+        if let Value::Object(ref mut map) = reduced {
+            map.retain(|_, v| !(v.is_array() || v.is_object()));
+        }
+
+        reduced_size = json_size(&reduced)?;
+    }
+
+    if reduced_size > max_size {
+        // Still too large, most likely from a huge string such as the
+        // description, so split the limit evenly between the fields
+        if let Value::Object(ref mut map) = reduced {
+            let share = max_size / map.len().max(1);
+
+            for value in map.values_mut() {
+                if let Value::String(ref mut s) = value {
+                    truncate_str(s, share);
+                }
+            }
+        }
+
+        reduced_size = json_size(&reduced)?;
+    }
+
+    if reduced_size > max_size {
+        // Escaping may still push the strings over the limit
+        reduced = Value::Object(serde_json::Map::new());
+        reduced_size = json_size(&reduced)?;
+    }
+
+    warn!(
+        "Truncating metadata JSON for {} ({} bytes, limit {}) to {} bytes",
+        addr, size, max_size, reduced_size
+    );
+
+    Ok((reduced, true))
+}
+
 async fn fetch_json(
     client: &Client,
     meta_key: Pubkey,
@@ -116,43 +362,53 @@ async fn fetch_json(
     let url = url.context("Failed to create asset URL")?;
 
     let bytes = client
-        .http(|h| {
-            let url = url.clone();
-            async move { h.get(url).send().await?.bytes().await }
-        })
+        .get_json_bytes(&url)
         .await
         .context("Failed to download metadata JSON")?;
 
     let end_time = Local::now();
 
     debug!(
-        "Metadata JSON URI {:?} for {} fetched in {}",
+        "Metadata JSON URI {:?} for {} fetched in {} ({} bytes)",
         url.as_str(),
         meta_key,
-        indexer_core::util::duration_hhmmssfff(end_time - start_time)
+        indexer_core::util::duration_hhmmssfff(end_time - start_time),
+        bytes.len(),
     );
 
-    if let Ok(full) = serde_json::from_slice(&bytes).map_err(|e| {
+    parse_json(url.as_str(), &bytes)
+}
+
+/// Fingerprint metadata JSON decoded from a data URI by the SHA-256 hash of
+/// its content
+fn fingerprint_inline(bytes: &[u8]) -> Vec<u8> {
+    use cid::multihash::{Sha2_256, StatefulHasher};
+
+    let mut h = Sha2_256::default();
+    h.update(bytes);
+
+    h.finalize().as_ref().to_vec()
+}
+
+/// Parse a downloaded or decoded metadata JSON document, falling back to the
+/// minimal schema if the document does not match the full one
+fn parse_json(source: &str, bytes: &[u8]) -> Result<MetadataJsonResult> {
+    if let Ok(full) = serde_json::from_slice(bytes).map_err(|e| {
         debug!(
             "Failed to parse full metadata JSON for {:?}: {:?}",
-            url.as_str(),
-            e
+            source, e
         );
     }) {
         Ok(MetadataJsonResult::Full(full))
-    } else if let Ok(min) = serde_json::from_slice(&bytes).map_err(|e| {
+    } else if let Ok(min) = serde_json::from_slice(bytes).map_err(|e| {
         debug!(
             "Failed to parse minimal metadata JSON for {:?}: {:?}",
-            url.as_str(),
-            e
+            source, e
         );
     }) {
         Ok(MetadataJsonResult::Minimal(min))
     } else {
-        Err(anyhow!(
-            "Failed to parse JSON response from {:?}",
-            url.as_str()
-        ))
+        Err(anyhow!("Failed to parse JSON response from {:?}", source))
     }
 }
 
@@ -170,7 +426,7 @@ async fn try_locate_json(
     for (url, hint) in id
         .ipfs
         .iter()
-        .map(|(c, p)| (client.ipfs_link(c, p), AssetHint::Ipfs))
+        .flat_map(|(c, p)| client.ipfs_links(c, p).map(|u| (u, AssetHint::Ipfs)))
         .chain(
             id.arweave
                 .iter()
@@ -182,7 +438,15 @@ async fn try_locate_json(
 
         match fetch_json(client, meta_key, url).await {
             Ok(j) => {
-                debug!("Using fetch from {:?} for metadata {}", url_str, meta_key);
+                if matches!(hint, AssetHint::Ipfs) {
+                    info!(
+                        "Fetched metadata {} from IPFS gateway {:?}",
+                        meta_key, url_str
+                    );
+                } else {
+                    debug!("Using fetch from {:?} for metadata {}", url_str, meta_key);
+                }
+
                 resp = Ok(Some((j, fingerprint)));
                 break;
             },
@@ -239,6 +503,14 @@ async fn process_full(
 ) -> Result<()> {
     let raw_content: Value =
         serde_json::value::to_value(&json).context("Failed to upcast metadata JSON")?;
+    let model = MetadataModel::detect(&raw_content);
+    let (raw_content, truncated) =
+        limit_raw_content(&addr, raw_content, client.max_raw_content_size())?;
+    let model = if truncated {
+        MetadataModel::Truncated
+    } else {
+        model
+    };
 
     let MetadataJson {
         description,
@@ -256,6 +528,11 @@ async fn process_full(
              creators,
          }| (files, category, creators),
     );
+    let files = match files {
+        Some(files) => Some(check_file_sizes(client, &addr, files).await),
+        None => None,
+    };
+    let category = self::category(category, files.as_deref());
 
     let row = DbMetadataJson {
         metadata_address: Owned(addr.clone()),
@@ -267,9 +544,12 @@ async fn process_full(
         external_url: external_url.map(Owned),
         category: category.map(Owned),
         raw_content: Owned(raw_content),
-        model: Some(Borrowed("full")),
+        model: Some(Borrowed(model.into())),
     };
 
+    let normalize_trait_types = client.normalize_trait_types();
+    let rescore = first_verified_creator.clone();
+
     client
         .db()
         .run(move |db| {
@@ -281,19 +561,30 @@ async fn process_full(
                 .execute(db)
                 .context("Failed to insert metadata")?;
 
+            process_files(db, &addr, files)?;
+
             // TODO: if the row updates the following functions do not clear the
             //       previous rows from the old metadata JSON:
 
-            process_files(db, &addr, files)?;
             process_attributes(
                 db,
                 &addr,
                 first_verified_creator.as_deref(),
                 json.attributes,
+                normalize_trait_types,
             )?;
+
             process_collection(db, &addr, json.collection)
         })
-        .await
+        .await?;
+
+    // Adding an NFT changes the trait frequencies of its collection, so the
+    // whole collection is rescored once its pending NFTs are indexed
+    if let Some(creator) = rescore {
+        client.invalidate_rarity(creator);
+    }
+
+    Ok(())
 }
 
 async fn process_minimal(
@@ -314,6 +605,14 @@ async fn process_minimal(
 
     let raw_content: Value =
         serde_json::value::to_value(&json).context("Failed to upcast minimal metadata JSON")?;
+    let model = MetadataModel::detect(&raw_content);
+    let (raw_content, truncated) =
+        limit_raw_content(&addr, raw_content, client.max_raw_content_size())?;
+    let model = if truncated {
+        MetadataModel::Truncated
+    } else {
+        model
+    };
 
     let MetadataJsonMinimal {
         name: _,
@@ -321,10 +620,27 @@ async fn process_minimal(
         image,
         animation_url,
         external_url,
-        category,
-        extra: _,
+        category: given_category,
+        extra,
     } = json;
 
+    // Minimal documents may still carry a well-formed category or file list
+    // under `properties`, even if the document as a whole is malformed
+    let properties = extra.get("properties");
+    let files: Option<Vec<File>> = properties
+        .and_then(|p| p.get("files"))
+        .and_then(|f| serde_json::from_value(f.clone()).ok());
+    let category = self::category(
+        to_opt_string(&given_category)
+            .or_else(|| {
+                properties
+                    .and_then(|p| p.get("category"))
+                    .and_then(to_opt_string)
+            })
+            .map(Cow::into_owned),
+        files.as_deref(),
+    );
+
     let row = DbMetadataJson {
         metadata_address: Owned(addr.clone()),
         fingerprint: Owned(fingerprint),
@@ -333,9 +649,9 @@ async fn process_minimal(
         image: to_opt_string(&image),
         animation_url: to_opt_string(&animation_url),
         external_url: to_opt_string(&external_url),
-        category: to_opt_string(&category),
+        category: category.map(Owned),
         raw_content: Owned(raw_content),
-        model: Some(Borrowed("minimal")),
+        model: Some(Borrowed(model.into())),
     };
 
     client
@@ -354,29 +670,88 @@ async fn process_minimal(
     Ok(())
 }
 
+/// Drop files advertising a size larger than the configured maximum file size
+/// in response to a `HEAD` request.  Files which cannot be checked, such as
+/// those without an HTTP URL or whose server does not answer, are kept.
+async fn check_file_sizes(client: &Client, addr: &str, files: Vec<File>) -> Vec<File> {
+    let max = client.max_file_bytes();
+
+    let sizes = futures_util::future::join_all(files.iter().map(|file| async move {
+        let url = file
+            .uri()
+            .and_then(|u| Url::parse(u).ok())
+            .filter(|u| matches!(u.scheme(), "http" | "https"))?;
+
+        client
+            .head_file(&url)
+            .await
+            .map_err(|e| debug!("Failed to check size of file {:?}: {:?}", url.as_str(), e))
+            .ok()?
+    }))
+    .await;
+
+    files
+        .into_iter()
+        .zip(sizes)
+        .filter(|(file, size)| match *size {
+            Some(size) if size > max => {
+                warn!(
+                    "Skipping file {:?} for {} advertising {} bytes, exceeding the limit of {}",
+                    file.uri().unwrap_or_default(),
+                    addr,
+                    size,
+                    max
+                );
+
+                false
+            },
+            _ => true,
+        })
+        .map(|(file, _)| file)
+        .collect()
+}
+
+/// Replace the stored files for a metadata address with those listed in its
+/// JSON, removing any files no longer present
 fn process_files(db: &Connection, addr: &str, files: Option<Vec<File>>) -> Result<()> {
-    for File { uri, ty } in files.unwrap_or_else(Vec::new) {
-        let (uri, ty) = if let Some(v) = uri.zip(ty) {
+    let mut rows = Vec::new();
+
+    for file in files.unwrap_or_else(Vec::new) {
+        let (uri, ty) = if let Some(v) = file.into_parts() {
             v
         } else {
             debug!("Skipping malformed file in JSON");
             continue;
         };
 
-        let row = DbFile {
+        rows.push(DbFile {
             metadata_address: Borrowed(addr),
             uri: Owned(uri),
             file_type: Owned(ty),
-        };
-
-        insert_into(files::table)
-            .values(&row)
-            .on_conflict_do_nothing()
-            .execute(db)
-            .context("Failed to insert file!")?;
+        });
     }
 
-    Ok(())
+    db.transaction(|| {
+        delete(
+            files::table
+                .filter(files::metadata_address.eq(addr))
+                .filter(files::uri.ne_all(rows.iter().map(|r| r.uri.as_ref()))),
+        )
+        .execute(db)
+        .context("Failed to delete stale files")?;
+
+        for row in &rows {
+            insert_into(files::table)
+                .values(row)
+                .on_conflict((files::metadata_address, files::uri))
+                .do_update()
+                .set(files::file_type.eq(excluded(files::file_type)))
+                .execute(db)
+                .context("Failed to insert file!")?;
+        }
+
+        Ok(())
+    })
 }
 
 #[inline]
@@ -385,27 +760,52 @@ fn process_attributes(
     addr: &str,
     first_verified_creator: Option<&str>,
     attributes: Option<Vec<Attribute>>,
+    normalize_trait_types: bool,
 ) -> Result<()> {
+    let mut rows: Vec<MetadataAttributeWrite> = Vec::new();
+    let mut indices = HashMap::default();
+
     for Attribute { trait_type, value } in attributes.unwrap_or_else(Vec::new) {
+        let trait_type_normalized = trait_type
+            .as_deref()
+            .filter(|_| normalize_trait_types)
+            .map(|t| Owned(queries::metadatas::normalize_trait_type(t)));
+
         let row = MetadataAttributeWrite {
             metadata_address: Borrowed(addr),
             trait_type: trait_type.map(Owned),
             value: value.as_ref().map(|v| Owned(v.to_string())),
             first_verified_creator: first_verified_creator.map(Borrowed),
+            trait_type_normalized,
         };
 
+        // A single upsert statement cannot touch the same row twice, so keep
+        // only the last of any duplicated trait-value pairs
+        match indices.entry((row.trait_type.clone(), row.value.clone())) {
+            Entry::Occupied(o) => rows[*o.get()] = row,
+            Entry::Vacant(v) => {
+                v.insert(rows.len());
+                rows.push(row);
+            },
+        }
+    }
+
+    upsert_batch(db, &rows, |db, chunk| {
         insert_into(attributes::table)
-            .values(&row)
+            .values(chunk)
             .on_conflict((
                 attributes::metadata_address,
                 attributes::value,
                 attributes::trait_type,
             ))
             .do_update()
-            .set(&row)
+            .set((
+                attributes::first_verified_creator.eq(excluded(attributes::first_verified_creator)),
+                attributes::trait_type_normalized.eq(excluded(attributes::trait_type_normalized)),
+            ))
             .execute(db)
-            .context("Failed to insert attribute!")?;
-    }
+    })
+    .context("Failed to insert attributes!")?;
 
     Ok(())
 }
@@ -434,6 +834,8 @@ async fn reprocess_attributes(
     addr: String,
     first_verified_creator: Option<String>,
 ) -> Result<()> {
+    let rescore = first_verified_creator.clone();
+
     client
         .db()
         .run(move |db| {
@@ -444,6 +846,10 @@ async fn reprocess_attributes(
         .await
         .context("Failed to update attributes")?;
 
+    if let Some(creator) = rescore {
+        client.invalidate_rarity(creator);
+    }
+
     Ok(())
 }
 
@@ -463,26 +869,51 @@ pub async fn process<'a>(
     };
     let id = AssetIdentifier::new(&url);
 
-    let possible_fingerprints: Vec<_> = id.fingerprints().map(Cow::into_owned).collect();
+    // Data URIs carry their content inline, so they are decoded up front and
+    // fingerprinted by content in place of an IPFS CID or Arweave TXID
+    let inline = match Client::decode_data_uri(&url).transpose() {
+        Ok(i) => i.map(|bytes| {
+            let fingerprint = fingerprint_inline(&bytes);
+            (bytes, fingerprint)
+        }),
+        Err(e) => {
+            debug!("Couldn't decode metadata data URI: {:?}", e);
+            return Ok(());
+        },
+    };
+
+    let possible_fingerprints: Vec<_> = match inline {
+        Some((_, ref fingerprint)) => vec![fingerprint.clone()],
+        None => id.fingerprints().map(Cow::into_owned).collect(),
+    };
     let addr = bs58::encode(meta_key).into_string();
 
-    let is_present = client
-        .db()
-        .run({
-            let addr = addr.clone();
-            move |db| {
-                select(exists(
-                    metadata_jsons::table.filter(
-                        metadata_jsons::metadata_address
-                            .eq(addr)
-                            .and(metadata_jsons::fingerprint.eq(any(possible_fingerprints))),
-                    ),
-                ))
-                .get_result(db)
-            }
-        })
-        .await
-        .context("Failed to check for already-indexed metadata JSON")?;
+    // Documents with an unchanged fingerprint have unchanged content, so
+    // they only need downloading again if forced or if they are too old
+    let fresh_after = client
+        .metadata_json_max_age()
+        .map(|a| Local::now().naive_utc() - a);
+    let is_present = !client.force_metadata_refresh()
+        && client
+            .db()
+            .run({
+                let addr = addr.clone();
+                move |db| {
+                    let query = metadata_jsons::table
+                        .filter(metadata_jsons::metadata_address.eq(addr))
+                        .filter(metadata_jsons::fingerprint.eq(any(possible_fingerprints)))
+                        .into_boxed();
+
+                    let query = match fresh_after {
+                        Some(t) => query.filter(metadata_jsons::updated_at.gt(t)),
+                        None => query,
+                    };
+
+                    select(exists(query)).get_result(db)
+                }
+            })
+            .await
+            .context("Failed to check for already-indexed metadata JSON")?;
 
     let first_verified_creator =
         first_verified_creator.map(|address| bs58::encode(address).into_string());
@@ -503,9 +934,24 @@ pub async fn process<'a>(
         return Ok(());
     }
 
-    debug!("{:?} -> {:?}", url.as_str(), id);
+    let located = if let Some((bytes, fingerprint)) = inline {
+        debug!("Decoding inline metadata JSON for {}", meta_key);
 
-    if let Some((json, fingerprint)) = try_locate_json(client, &url, &id, meta_key).await? {
+        match parse_json(DATA_URI_SOURCE, &bytes) {
+            Ok(json) => Some((json, fingerprint)),
+            Err(e) => {
+                // The document can never change, so don't retry the job
+                warn!("Inline metadata JSON for {} is invalid: {:?}", meta_key, e);
+                return Ok(());
+            },
+        }
+    } else {
+        debug!("{:?} -> {:?}", url.as_str(), id);
+
+        try_locate_json(client, &url, &id, meta_key).await?
+    };
+
+    if let Some((json, fingerprint)) = located {
         match json {
             MetadataJsonResult::Full(f) => {
                 process_full(client, addr, first_verified_creator, f, fingerprint).await?;
@@ -518,3 +964,384 @@ pub async fn process<'a>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use hyper::StatusCode;
+    use serde_json::json;
+
+    use super::*;
+    use crate::http::{
+        testing::{self, MockServer},
+        ClientArgs,
+    };
+
+    #[test]
+    fn small_content_is_kept() {
+        let doc = json!({ "name": "NFT", "extra": [1, 2, 3] });
+
+        assert_eq!(
+            limit_raw_content("addr", doc.clone(), 1024).unwrap(),
+            (doc, false)
+        );
+    }
+
+    #[test]
+    fn oversized_content_keeps_standard_fields() {
+        let doc = json!({
+            "name": "NFT",
+            "image": "https://example.com/nft.png",
+            "blob": "x".repeat(1024),
+        });
+
+        assert_eq!(
+            limit_raw_content("addr", doc, 256).unwrap(),
+            (
+                json!({ "name": "NFT", "image": "https://example.com/nft.png" }),
+                true
+            )
+        );
+    }
+
+    #[test]
+    fn oversized_standard_fields_are_truncated() {
+        let doc = json!({
+            "name": "NFT",
+            "description": "é".repeat(4096),
+            "attributes": [{ "trait_type": "Background", "value": "Blue" }],
+        });
+
+        let (limited, truncated) = limit_raw_content("addr", doc, 256).unwrap();
+
+        assert!(truncated);
+        assert!(json_size(&limited).unwrap() <= 256);
+        assert_eq!(limited["name"], "NFT");
+        assert!(limited.get("attributes").is_none());
+        assert!(limited["description"].as_str().unwrap().starts_with('é'));
+    }
+
+    #[test]
+    fn unfittable_content_is_emptied() {
+        let doc = json!({ "name": "\"".repeat(64) });
+        let limited = limit_raw_content("addr", doc, 16).unwrap();
+
+        assert_eq!(limited, (json!({}), true));
+    }
+
+    #[test]
+    fn detect_metaplex() {
+        let doc = json!({
+            "name": "Degen Ape #1",
+            "symbol": "DAPE",
+            "seller_fee_basis_points": 420,
+            "image": "https://arweave.net/ape.png",
+            "properties": {
+                "files": [{ "uri": "https://arweave.net/ape.png", "type": "image/png" }],
+                "creators": [{ "address": "creator", "share": 100 }],
+            },
+        });
+
+        assert_eq!(MetadataModel::detect(&doc), MetadataModel::Metaplex);
+    }
+
+    #[test]
+    fn detect_metaplex_v1_1() {
+        let doc = json!({
+            "name": "Degen Ape #2",
+            "image": "https://arweave.net/ape.png",
+            "properties": {
+                "files": ["https://arweave.net/ape.png"],
+                "category": "image",
+            },
+        });
+
+        assert_eq!(MetadataModel::detect(&doc), MetadataModel::MetaplexV1_1);
+    }
+
+    #[test]
+    fn detect_candy_machine() {
+        let doc = json!({
+            "name": "Number #3",
+            "symbol": "NB",
+            "seller_fee_basis_points": 500,
+            "image": "3.png",
+            "collection": { "name": "Numbers", "family": "Numbers" },
+            "properties": {
+                "files": [{ "uri": "3.png", "type": "image/png" }],
+                "category": "image",
+                "creators": [{ "address": "creator", "share": 100 }],
+            },
+        });
+
+        assert_eq!(MetadataModel::detect(&doc), MetadataModel::CandyMachine);
+    }
+
+    #[test]
+    fn detect_unknown() {
+        assert_eq!(
+            MetadataModel::detect(&json!({ "title": "Not an NFT" })),
+            MetadataModel::Unknown
+        );
+        assert_eq!(
+            MetadataModel::detect(&json!({ "name": "No properties" })),
+            MetadataModel::Unknown
+        );
+    }
+
+    /// An arbitrary valid CIDv0
+    const CID: &str = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+
+    #[tokio::test]
+    async fn falls_back_through_ipfs_gateways() {
+        let server = MockServer::start(|_, req| async move {
+            if req.uri().path().starts_with("/gw2/") {
+                testing::respond(
+                    StatusCode::OK,
+                    r#"{ "name": "NFT", "seller_fee_basis_points": 500 }"#,
+                )
+            } else {
+                testing::respond(StatusCode::GATEWAY_TIMEOUT, "")
+            }
+        });
+        let client = testing::client(ClientArgs {
+            ipfs_gateways: Some(vec![
+                server.url("/gw1/").to_string(),
+                server.url("/gw2/").to_string(),
+            ]),
+            http_max_retries: 0,
+            ..testing::args(&server.url("/cdn/"))
+        });
+
+        let url: Url = format!("https://ipfs.example.com/ipfs/{}/1.json", CID)
+            .parse()
+            .unwrap();
+        let id = AssetIdentifier::new(&url);
+        let (json, fingerprint) = try_locate_json(&client, &url, &id, Pubkey::new_unique())
+            .await
+            .unwrap()
+            .unwrap();
+
+        match json {
+            MetadataJsonResult::Full(j) => assert_eq!(j.name, "NFT"),
+            MetadataJsonResult::Minimal(_) => panic!("Expected a full metadata JSON"),
+        }
+
+        assert_eq!(
+            fingerprint,
+            id.fingerprint(Some(AssetHint::Ipfs)).unwrap().into_owned()
+        );
+        // The CDN and first gateway each time out once before the second
+        // gateway succeeds
+        assert_eq!(server.requests(), 3);
+    }
+
+    /// Index a stale copy of the document at `url` for `meta_key`, with the
+    /// fingerprint of its IPFS CID
+    async fn seed_indexed(client: &Client, meta_key: Pubkey, url: &Url) {
+        let row = DbMetadataJson {
+            metadata_address: Owned(meta_key.to_string()),
+            fingerprint: Owned(
+                AssetIdentifier::new(url)
+                    .fingerprint(Some(AssetHint::Ipfs))
+                    .unwrap()
+                    .into_owned(),
+            ),
+            ..indexer_core::db::testing::metadata_json("", "")
+        };
+
+        client
+            .db()
+            .run(move |db| insert_into(metadata_jsons::table).values(&row).execute(db))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn unchanged_fingerprint_skips_download() {
+        if !testing::has_database() {
+            return;
+        }
+
+        let server = MockServer::start(|_, _| async {
+            testing::respond(
+                StatusCode::OK,
+                r#"{ "name": "NFT", "seller_fee_basis_points": 500 }"#,
+            )
+        });
+        let url: Url = format!("https://ipfs.example.com/ipfs/{}/1.json", CID)
+            .parse()
+            .unwrap();
+        let meta_key = Pubkey::new_unique();
+
+        let client = testing::client(testing::args(&server.url("/cdn/")));
+        seed_indexed(&client, meta_key, &url).await;
+        process(&client, meta_key, None, url.to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(server.requests(), 0);
+
+        // The seeded document is older than any maximum age
+        let client = testing::client(ClientArgs {
+            metadata_json_max_age: Some(60),
+            ..testing::args(&server.url("/cdn/"))
+        });
+        seed_indexed(&client, meta_key, &url).await;
+        process(&client, meta_key, None, url.to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(server.requests(), 1);
+
+        let client = testing::client(ClientArgs {
+            force_metadata_refresh: true,
+            ..testing::args(&server.url("/cdn/"))
+        });
+        seed_indexed(&client, meta_key, &url).await;
+        process(&client, meta_key, None, url.to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(server.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn oversized_files_are_skipped() {
+        let server = MockServer::start(|_, req| async move {
+            match req.uri().path() {
+                "/big.mp4" => testing::respond(StatusCode::OK, vec![0_u8; 4096]),
+                "/small.png" => testing::respond(StatusCode::OK, vec![0_u8; 512]),
+                _ => testing::respond(StatusCode::NOT_FOUND, ""),
+            }
+        });
+        let client = testing::client(ClientArgs {
+            max_file_bytes: 2048,
+            ..testing::args(&server.url("/cdn/"))
+        });
+
+        let files = parse_files(json!([
+            server.url("/big.mp4").as_str(),
+            { "uri": server.url("/small.png").as_str(), "type": "image/png" },
+            { "uri": server.url("/missing.png").as_str(), "type": "image/png" },
+            "3.png",
+        ]));
+        let kept: Vec<_> = check_file_sizes(&client, "addr", files)
+            .await
+            .into_iter()
+            .filter_map(|f| f.uri().map(ToOwned::to_owned))
+            .collect();
+
+        assert_eq!(kept, vec![
+            server.url("/small.png").to_string(),
+            server.url("/missing.png").to_string(),
+            "3.png".to_owned(),
+        ]);
+    }
+
+    fn files() -> Vec<File> {
+        serde_json::from_value(json!([
+            { "uri": "https://example.com/image", "type": "image/png" },
+            "https://example.com/video.MP4?v=2",
+            { "uri": "https://example.com/unknown" },
+            { "type": "image/png" },
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn file_parts() {
+        let parts: Vec<_> = files().into_iter().map(File::into_parts).collect();
+
+        assert_eq!(parts, vec![
+            Some((
+                "https://example.com/image".to_owned(),
+                "image/png".to_owned()
+            )),
+            Some((
+                "https://example.com/video.MP4?v=2".to_owned(),
+                "video/mp4".to_owned()
+            )),
+            None,
+            None,
+        ]);
+    }
+
+    #[test]
+    fn stores_files_of_each_form() {
+        let pool = match indexer_core::db::testing::test_pool() {
+            Some(p) => p,
+            None => return,
+        };
+        let conn = pool.get().unwrap();
+
+        insert_into(files::table)
+            .values(&DbFile {
+                metadata_address: Borrowed("addr"),
+                uri: Borrowed("https://example.com/stale.png"),
+                file_type: Borrowed("image/png"),
+            })
+            .execute(&conn)
+            .unwrap();
+
+        process_files(&conn, "addr", Some(files())).unwrap();
+
+        let rows: Vec<(String, String)> = files::table
+            .filter(files::metadata_address.eq("addr"))
+            .select((files::uri, files::file_type))
+            .order(files::uri)
+            .load(&conn)
+            .unwrap();
+
+        assert_eq!(rows, vec![
+            (
+                "https://example.com/image".to_owned(),
+                "image/png".to_owned()
+            ),
+            (
+                "https://example.com/video.MP4?v=2".to_owned(),
+                "video/mp4".to_owned()
+            ),
+        ]);
+    }
+
+    fn parse_files(files: serde_json::Value) -> Vec<File> {
+        serde_json::from_value(files).unwrap()
+    }
+
+    #[test]
+    fn video_and_audio_categories() {
+        let video = parse_files(json!([
+            { "uri": "https://example.com/preview.png", "type": "image/png" },
+            "https://example.com/clip.webm",
+            { "uri": "https://example.com/track.mp3" },
+        ]));
+        let audio = parse_files(json!([
+            "https://example.com/cover.jpg",
+            { "uri": "https://example.com/track", "type": "audio/wav" },
+        ]));
+
+        assert_eq!(infer_category(&video), Some("video"));
+        assert_eq!(infer_category(&audio), Some("audio"));
+        assert_eq!(category(None, Some(&video)), Some("video".to_owned()));
+        assert_eq!(category(None, Some(&audio)), Some("audio".to_owned()));
+    }
+
+    #[test]
+    fn given_category_wins() {
+        let video = parse_files(json!(["https://example.com/clip.mp4"]));
+
+        assert_eq!(
+            category(Some(" audio ".to_owned()), Some(&video)),
+            Some("audio".to_owned())
+        );
+        assert_eq!(
+            category(Some(" ".to_owned()), Some(&video)),
+            Some("video".to_owned())
+        );
+        assert_eq!(category(None, None), None);
+        assert_eq!(
+            infer_category(&parse_files(json!(["https://example.com/unknown"]))),
+            None
+        );
+    }
+}
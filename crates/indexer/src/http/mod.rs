@@ -1,11 +1,14 @@
 //! Support features for the HTTP indexer
 
 pub(self) mod client;
+mod collection_json;
 mod metadata_json;
 mod store_config;
+#[cfg(test)]
+pub(crate) mod testing;
 
 pub use client::{Args as ClientArgs, Client};
-use indexer_rabbitmq::http_indexer::{Entity, MetadataJson, StoreConfig};
+use indexer_rabbitmq::http_indexer::{CollectionJson, Entity, MetadataJson, StoreConfig};
 
 use crate::prelude::*;
 
@@ -40,3 +43,15 @@ impl Process for StoreConfig {
         store_config::process(client, config_address, uri).await
     }
 }
+
+#[async_trait::async_trait]
+impl Process for CollectionJson {
+    async fn process(self, client: &Client) -> Result<()> {
+        let CollectionJson {
+            collection_mint,
+            uri,
+        } = self;
+
+        collection_json::process(client, collection_mint, uri).await
+    }
+}
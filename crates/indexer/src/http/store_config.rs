@@ -65,10 +65,12 @@ pub async fn process(client: &Client, config_key: Pubkey, uri_str: String) -> Re
     );
 
     // TODO: parse failure shouldn't be an error, this stuff will be unstructured
-    let json = client
-        .http(|h| async move { h.get(url).send().await?.json::<SettingUri>().await })
+    let bytes = client
+        .get_json_bytes(&url)
         .await
         .context("Store config JSON request failed")?;
+    let json: SettingUri =
+        serde_json::from_slice(&bytes).context("Failed to parse store config JSON")?;
 
     let addr = bs58::encode(config_key).into_string();
 
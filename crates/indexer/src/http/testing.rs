@@ -0,0 +1,149 @@
+//! Fixtures for exercising HTTP indexer processing against a local server
+//!
+//! [`MockServer`] serves responses computed by a closure from a background
+//! task, counting the requests it receives.  [`client`] constructs a
+//! [`Client`] pointed at such a server, backed by the scratch database named
+//! by [`TEST_DATABASE_URL`](indexer_core::db::testing::TEST_DATABASE_URL) if
+//! one is set, or else by a pool which fails to connect.
+
+use std::{
+    convert::Infallible,
+    future::Future,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use indexer_core::db::{self, testing};
+use reqwest::Url;
+
+use super::{client::Args, Client};
+use crate::db::Pool;
+
+/// A local HTTP server whose responses are computed by a closure
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    requests: Arc<AtomicUsize>,
+}
+
+impl MockServer {
+    /// Start serving on a random local port.  The handler is called with the
+    /// zero-based index of each request and the request itself.
+    ///
+    /// # Panics
+    /// This function panics if the server cannot be bound, and must be called
+    /// from within a Tokio runtime.
+    pub fn start<F, R>(handler: F) -> Self
+    where
+        F: Fn(usize, Request<Body>) -> R + Send + Sync + 'static,
+        R: Future<Output = Response<Body>> + Send + 'static,
+    {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let handler = Arc::new(handler);
+
+        let make_service = make_service_fn({
+            let requests = Arc::clone(&requests);
+
+            move |_| {
+                let handler = Arc::clone(&handler);
+                let requests = Arc::clone(&requests);
+
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |req| {
+                        let res = handler(requests.fetch_add(1, Ordering::SeqCst), req);
+
+                        async move { Ok::<_, Infallible>(res.await) }
+                    }))
+                }
+            }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let addr = server.local_addr();
+
+        tokio::spawn(server);
+
+        Self { addr, requests }
+    }
+
+    /// Get the URL of the given path on this server
+    #[must_use]
+    pub fn url(&self, path: &str) -> Url {
+        format!("http://{}{}", self.addr, path)
+            .parse()
+            .unwrap_or_else(|e| panic!("Invalid mock server path {:?}: {}", path, e))
+    }
+
+    /// Get the number of requests received so far
+    #[must_use]
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+/// Construct a response with the given status and body
+#[must_use]
+pub fn respond(status: StatusCode, body: impl Into<Body>) -> Response<Body> {
+    let mut res = Response::new(body.into());
+    *res.status_mut() = status;
+
+    res
+}
+
+/// Construct client arguments with small timeouts and no retry delay, using
+/// the given base URL for both IPFS and Arweave links
+#[must_use]
+pub fn args(cdn: &Url) -> Args {
+    Args {
+        ipfs_cdn: cdn.to_string(),
+        ipfs_gateways: None,
+        arweave_cdn: cdn.to_string(),
+        timeout: 5.0,
+        max_raw_content_size: 4 * 1024 * 1024,
+        max_json_bytes: 32 * 1024 * 1024,
+        max_file_bytes: 512 * 1024 * 1024,
+        normalize_trait_types: true,
+        metadata_json_max_age: None,
+        force_metadata_refresh: false,
+        http_max_retries: 3,
+        http_base_backoff_ms: 1,
+        http_concurrency: 64,
+        rarity_recompute_interval: 60,
+    }
+}
+
+/// Returns true if a scratch database is configured, in which case clients
+/// returned by [`client`] can write to it
+#[must_use]
+pub fn has_database() -> bool {
+    std::env::var(testing::TEST_DATABASE_URL).map_or(false, |u| !u.trim().is_empty())
+}
+
+/// Construct a client with the given arguments, backed by the scratch
+/// database if [`has_database`] or else by a pool which never connects
+///
+/// # Panics
+/// This function panics if the arguments are invalid or the scratch database
+/// cannot be opened.
+#[must_use]
+pub fn client(args: Args) -> Arc<Client> {
+    let pool = if has_database() {
+        testing::test_pool().unwrap_or_else(|| unreachable!())
+    } else {
+        db::Pool::builder()
+            .max_size(1)
+            .connection_timeout(Duration::from_millis(100))
+            .build_unchecked(db::ConnectionManager::new("postgres://localhost:1/none"))
+    };
+
+    Client::new_rc(Pool::new((pool, db::ConnectionType::Default)), args)
+        .expect("Failed to construct test client")
+}
@@ -15,6 +15,10 @@ pub mod geyser;
 pub mod http;
 #[cfg(feature = "http")]
 pub mod legacy_storefronts;
+pub(crate) mod metrics;
+pub mod rollback;
+#[cfg(feature = "seed")]
+pub mod seed;
 pub(crate) mod util;
 
 pub use runtime::*;
@@ -26,13 +30,20 @@ pub mod prelude {
 }
 
 mod runtime {
-    use std::{fmt::Debug, future::Future};
+    use std::{
+        fmt::Debug,
+        future::Future,
+        net::SocketAddr,
+        sync::Arc,
+        time::{Duration, Instant},
+    };
 
-    use futures_util::{FutureExt, StreamExt};
+    use futures_util::{stream::FuturesUnordered, FutureExt, StreamExt};
     use indexer_core::{
         clap,
         clap::{Args, Parser},
         db,
+        logging::{LogOpts, WithFieldsExt},
     };
     use indexer_rabbitmq::{
         consumer::Consumer,
@@ -40,9 +51,9 @@ mod runtime {
         lapin::options::{BasicAckOptions, BasicRejectOptions},
         QueueType,
     };
-    use tokio::sync::{broadcast, broadcast::error::RecvError};
+    use tokio::sync::{broadcast, broadcast::error::RecvError, watch};
 
-    use super::{db::Pool, prelude::*};
+    use super::{db::Pool, metrics, prelude::*};
 
     #[derive(Debug, Parser)]
     struct Opts<T: Debug + Args> {
@@ -50,28 +61,104 @@ mod runtime {
         #[clap(short = 'j', env)]
         thread_count: Option<usize>,
 
+        /// The number of times a message may fail to process before it is
+        /// moved to its queue's dead-letter queue.  Defaults to a per-queue
+        /// limit.
+        #[clap(long, env)]
+        amqp_max_tries: Option<u64>,
+
+        /// The maximum number of unacknowledged AMQP deliveries to buffer.
+        /// Defaults to a per-queue limit.
+        ///
+        /// Only as many messages as there are threads are processed at once,
+        /// each holding at most one database connection, so the database pool
+        /// is sized by the thread count rather than by this value.  Raising
+        /// the prefetch past the thread count smooths out bursts at the cost
+        /// of memory; lowering it toward the thread count reduces memory use
+        /// but may leave workers idle waiting on the broker.
+        #[clap(long, env)]
+        amqp_prefetch: Option<u16>,
+
+        /// Seconds to wait for in-flight messages to finish processing after
+        /// receiving a shutdown signal
+        #[clap(long, env, default_value_t = 30.0)]
+        shutdown_timeout: f64,
+
+        /// An optional address to serve Prometheus metrics on, at `/metrics`
+        #[clap(long, env)]
+        metrics_addr: Option<SocketAddr>,
+
+        #[clap(flatten)]
+        log: LogOpts,
+
         #[clap(flatten)]
         extra: T,
     }
 
+    impl<T: Debug + Args> AsRef<LogOpts> for Opts<T> {
+        fn as_ref(&self) -> &LogOpts {
+            &self.log
+        }
+    }
+
     /// Common parameters for all indexers
     #[allow(missing_copy_implementations)]
     #[derive(Debug)]
     pub struct Params {
         concurrency: usize,
+        max_tries: Option<u64>,
+        prefetch: Option<u16>,
+        shutdown_timeout: Duration,
+        shutdown: watch::Receiver<bool>,
+    }
+
+    /// Wait for SIGINT, or SIGTERM on Unix platforms
+    async fn shutdown_signal() -> Result<()> {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+
+            let mut term =
+                signal(SignalKind::terminate()).context("Failed to register SIGTERM handler")?;
+
+            tokio::select! {
+                r = tokio::signal::ctrl_c() => r.context("Failed to wait for SIGINT")?,
+                _ = term.recv() => (),
+            }
+        }
+
+        #[cfg(not(unix))]
+        tokio::signal::ctrl_c()
+            .await
+            .context("Failed to wait for Ctrl-C")?;
+
+        Ok(())
+    }
+
+    /// Wait until the shutdown flag is raised.  If the signal handler goes
+    /// away without raising it this never returns.
+    async fn wait_for_shutdown(mut rx: watch::Receiver<bool>) {
+        while !*rx.borrow() {
+            if rx.changed().await.is_err() {
+                futures_util::future::pending::<()>().await;
+            }
+        }
     }
 
     /// Entrypoint for `holaplex-indexer` binaries
     pub fn run<T: Debug + Args, F: Future<Output = Result<()>>>(
         f: impl FnOnce(T, Params, Pool) -> F,
     ) -> ! {
-        indexer_core::run(|| {
-            let opts = Opts::parse();
-
+        indexer_core::run(|opts: Opts<T>| {
             debug!("{:#?}", opts);
 
             let Opts {
                 thread_count,
+                amqp_max_tries,
+                amqp_prefetch,
+                shutdown_timeout,
+                metrics_addr,
+                log: _,
                 extra,
             } = opts;
 
@@ -93,7 +180,37 @@ mod runtime {
 
             let concurrency = thread_count.unwrap_or_else(indexer_core::num_cpus::get);
 
-            rt.block_on(f(extra, Params { concurrency }, db))
+            if let Some(addr) = metrics_addr {
+                rt.spawn(metrics::serve(addr, db.inner()));
+            }
+
+            let (shutdown_tx, shutdown) = watch::channel(false);
+
+            rt.spawn(async move {
+                match shutdown_signal().await {
+                    Ok(()) => {
+                        info!("Shutdown signal received, finishing in-flight messages...");
+                        shutdown_tx.send(true).ok();
+
+                        // Keep the sender alive so receivers don't mistake it
+                        // for a dropped handler
+                        futures_util::future::pending::<()>().await;
+                    },
+                    Err(e) => error!("Failed to listen for shutdown signals: {:?}", e),
+                }
+            });
+
+            rt.block_on(f(
+                extra,
+                Params {
+                    concurrency,
+                    max_tries: amqp_max_tries,
+                    prefetch: amqp_prefetch,
+                    shutdown_timeout: Duration::from_secs_f64(shutdown_timeout),
+                    shutdown,
+                },
+                db,
+            ))
         })
     }
 
@@ -126,23 +243,56 @@ mod runtime {
         .context("Failed to connect to the AMQP server")
     }
 
-    /// Consume messages from an AMQP consumer until the connection closes
+    /// Name the variant of a message for tagging logs and error reports,
+    /// without including any of its contents
+    fn message_kind(msg: &impl Debug) -> String {
+        format!("{:?}", msg)
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect()
+    }
+
+    /// Wait up to `timeout` for the remaining workers to finish, returning the
+    /// number which did not
+    async fn drain_workers<F: Future<Output = ()>>(
+        workers: &mut FuturesUnordered<F>,
+        timeout: Duration,
+    ) -> usize {
+        match tokio::time::timeout(timeout, async {
+            while let Some(()) = workers.next().await {}
+        })
+        .await
+        {
+            Ok(()) => 0,
+            Err(_) => workers.len(),
+        }
+    }
+
+    /// Create a consumer for the given queue type and consume messages from it
+    /// until the connection closes
+    ///
+    /// On receiving a shutdown signal, workers stop accepting new deliveries
+    /// and the consumer is cancelled.  Messages already being processed are
+    /// given up to the configured shutdown timeout to finish before the AMQP
+    /// connection is closed, returning any unacknowledged deliveries to the
+    /// queue.
     ///
     /// # Errors
-    /// This function fails if a message cannot be received, but _does not_ fail
-    /// if a received message fails to process.
+    /// This function fails if the consumer cannot be created or a message
+    /// cannot be received, but _does not_ fail if a received message fails to
+    /// process.
     ///
     /// # Panics
     /// This function will panic if the internal scheduler enters a deadlock
     /// state.
     pub async fn amqp_consume<
-        Q: QueueType + Send + Sync + 'static,
+        Q: QueueType + Clone + Send + Sync + 'static,
         F: Send + Future<Output = Result<()>> + 'static,
     >(
         params: &Params,
         conn: indexer_rabbitmq::lapin::Connection,
-        consumer: Consumer<Q>,
         queue_type: Q,
+        tag: &str,
         process: impl Fn(Q::Message) -> F + Send + Sync + Clone + 'static,
     ) -> Result<()>
     where
@@ -164,7 +314,7 @@ mod runtime {
         {
             // Ideally T would be ! but ! is unstable.
             enum Delivery<T> {
-                Message(Option<(T, lapin::acker::Acker)>),
+                Message(Option<(T, lapin::acker::Acker, lapin::types::DeliveryTag)>),
                 Stop,
             }
 
@@ -183,37 +333,71 @@ mod runtime {
                     r = stop_rx.recv() => handle_stop(r)?,
                 };
 
-                let (msg, acker) = match del {
+                let (msg, acker, tag) = match del {
                     Delivery::Message(Some(d)) => d,
                     Delivery::Message(None) => break Ok(StopType::Hangup),
                     Delivery::Stop => break Ok(StopType::Stopped),
                 };
 
-                trace!("Worker {}: {:?}", worker_id, msg);
-
-                match process(msg).await {
-                    Ok(()) => acker
-                        .ack(BasicAckOptions::default())
-                        .await
-                        .context("Failed to send ACK for delivery")?,
-                    Err(e) => {
-                        warn!("Failed to process message: {:?}", e);
-
-                        acker
-                            .reject(BasicRejectOptions { requeue: false })
-                            .await
-                            .context("Failed to send NAK for delivery")?;
-                    },
+                let kind = message_kind(&msg);
+
+                async {
+                    trace!("Worker {}: {:?}", worker_id, msg);
+
+                    let start = Instant::now();
+                    let res = process(msg).await;
+                    metrics::PROCESS_DURATION.observe(start.elapsed().as_secs_f64());
+
+                    match res {
+                        Ok(()) => {
+                            metrics::PROCESSED.inc();
+
+                            acker
+                                .ack(BasicAckOptions::default())
+                                .await
+                                .context("Failed to send ACK for delivery")
+                        },
+                        Err(e) => {
+                            warn!("Failed to process message: {:?}", e);
+                            indexer_core::reporting::capture_error(&e);
+                            metrics::FAILED.inc();
+
+                            acker
+                                .reject(BasicRejectOptions { requeue: false })
+                                .await
+                                .context("Failed to send NAK for delivery")
+                        },
+                    }
                 }
+                .with_log_fields(vec![
+                    ("worker_id", worker_id.to_string()),
+                    ("delivery_tag", tag.to_string()),
+                    ("message", kind),
+                ])
+                .await?;
             }
         }
 
-        let Params { concurrency } = *params;
+        let Params {
+            concurrency,
+            max_tries,
+            prefetch,
+            shutdown_timeout,
+            ref shutdown,
+        } = *params;
+
+        let conn = Arc::new(conn);
+
+        let consumer = Consumer::new(&conn, queue_type.clone(), tag, prefetch)
+            .await
+            .context("Failed to create queue consumer")?;
 
         let dl_task = tokio::spawn(indexer_rabbitmq::dl_consumer::run(
-            conn,
+            Arc::clone(&conn),
             queue_type,
+            max_tries,
             tokio::time::sleep,
+            || metrics::DEAD_LETTERED.inc(),
         ));
 
         let (stop_tx, _stop_rx) = broadcast::channel(1);
@@ -233,18 +417,37 @@ mod runtime {
                     Err(e) => error!("Worker terminated unexpectedly: {:?}", e),
                 })
             })
-            .collect::<futures_util::stream::FuturesUnordered<_>>();
+            .collect::<FuturesUnordered<_>>();
 
-        q_tasks.next().await; // Everything past this point is graceful failure
+        // Everything past this point is graceful failure
+        let signaled = tokio::select! {
+            _ = q_tasks.next() => false,
+            () = wait_for_shutdown(shutdown.clone()) => true,
+        };
 
         stop_tx.send(()).unwrap();
         dl_task.abort();
 
+        if signaled {
+            consumer
+                .cancel()
+                .await
+                .map_err(|e| warn!("Failed to cancel AMQP consumer: {:?}", e))
+                .ok();
+        }
+
         if !q_tasks.is_empty() {
             info!("Waiting for additional jobs to finish...");
         }
 
-        while let Some(()) = q_tasks.next().await {}
+        let abandoned = drain_workers(&mut q_tasks, shutdown_timeout).await;
+
+        if abandoned > 0 {
+            warn!(
+                "{} worker(s) did not finish within {:?}, abandoning them",
+                abandoned, shutdown_timeout
+            );
+        }
 
         std::mem::drop(stop_tx);
 
@@ -253,6 +456,52 @@ mod runtime {
             .map_err(|e| error!("DLX consumer cleanup failed: {:?}", e))
             .unwrap_or(());
 
+        conn.close(200, "Shutting down")
+            .await
+            .map_err(|e| warn!("Failed to close AMQP connection: {:?}", e))
+            .ok();
+
         Ok(())
     }
+
+    #[cfg(test)]
+    mod tests {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use super::*;
+
+        #[tokio::test]
+        async fn in_flight_work_finishes_before_shutdown() {
+            let finished = Arc::new(AtomicUsize::new(0));
+
+            let mut workers = (1..=3)
+                .map(|i| {
+                    let finished = Arc::clone(&finished);
+
+                    tokio::spawn(async move {
+                        tokio::time::sleep(Duration::from_millis(20 * i)).await;
+                        finished.fetch_add(1, Ordering::SeqCst);
+                    })
+                    .map(|r| r.unwrap())
+                })
+                .collect::<FuturesUnordered<_>>();
+
+            assert_eq!(drain_workers(&mut workers, Duration::from_secs(5)).await, 0);
+            assert_eq!(finished.load(Ordering::SeqCst), 3);
+            assert!(workers.is_empty());
+        }
+
+        #[tokio::test]
+        async fn stuck_workers_are_abandoned() {
+            let mut workers = [10, 10_000]
+                .into_iter()
+                .map(|ms| tokio::time::sleep(Duration::from_millis(ms)))
+                .collect::<FuturesUnordered<_>>();
+
+            assert_eq!(
+                drain_workers(&mut workers, Duration::from_millis(200)).await,
+                1
+            );
+        }
+    }
 }
@@ -0,0 +1,167 @@
+//! Prometheus metrics for indexer consumers, served over HTTP
+
+use std::{convert::Infallible, net::SocketAddr};
+
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use indexer_core::{
+    db,
+    metrics::{
+        self,
+        prometheus::{Histogram, HistogramOpts, IntCounter, IntGauge},
+    },
+};
+use lazy_static::lazy_static;
+
+use crate::prelude::*;
+
+lazy_static! {
+    /// Messages processed successfully
+    pub(crate) static ref PROCESSED: IntCounter = metrics::register(
+        IntCounter::new("indexer_messages_processed_total", "Messages processed successfully")
+            .unwrap_or_else(|_| unreachable!()),
+    );
+    /// Messages which failed to process and were rejected for retry
+    pub(crate) static ref FAILED: IntCounter = metrics::register(
+        IntCounter::new("indexer_messages_failed_total", "Messages which failed to process")
+            .unwrap_or_else(|_| unreachable!()),
+    );
+    /// Messages moved to a dead-letter queue after exhausting their retries
+    pub(crate) static ref DEAD_LETTERED: IntCounter = metrics::register(
+        IntCounter::new(
+            "indexer_messages_dead_lettered_total",
+            "Messages moved to a dead-letter queue after exhausting their retries",
+        )
+        .unwrap_or_else(|_| unreachable!()),
+    );
+    /// Time taken to process a message, in seconds
+    pub(crate) static ref PROCESS_DURATION: Histogram = metrics::register(
+        Histogram::with_opts(HistogramOpts::new(
+            "indexer_message_process_seconds",
+            "Time taken to process a message",
+        ))
+        .unwrap_or_else(|_| unreachable!()),
+    );
+    static ref DB_IDLE: IntGauge = metrics::register(
+        IntGauge::new("indexer_db_pool_idle_connections", "Idle database connections")
+            .unwrap_or_else(|_| unreachable!()),
+    );
+    static ref DB_ACTIVE: IntGauge = metrics::register(
+        IntGauge::new("indexer_db_pool_active_connections", "Database connections in use")
+            .unwrap_or_else(|_| unreachable!()),
+    );
+}
+
+/// Register all consumer metrics so they are reported before first use
+fn init() {
+    lazy_static::initialize(&PROCESSED);
+    lazy_static::initialize(&FAILED);
+    lazy_static::initialize(&DEAD_LETTERED);
+    lazy_static::initialize(&PROCESS_DURATION);
+    lazy_static::initialize(&DB_IDLE);
+    lazy_static::initialize(&DB_ACTIVE);
+}
+
+fn scrape(db: &db::Pool) -> Result<Response<Body>> {
+    let state = db.state();
+
+    DB_IDLE.set(state.idle_connections.into());
+    DB_ACTIVE.set((state.connections - state.idle_connections).into());
+
+    Response::builder()
+        .header(CONTENT_TYPE, metrics::content_type())
+        .body(metrics::encode()?.into())
+        .context("Failed to build metrics response")
+}
+
+fn status(code: StatusCode) -> Response<Body> {
+    let mut res = Response::new(Body::empty());
+    *res.status_mut() = code;
+
+    res
+}
+
+async fn handle(db: db::Pool, req: Request<Body>) -> Result<Response<Body>, Infallible> {
+    if req.method() != Method::GET || req.uri().path() != "/metrics" {
+        return Ok(status(StatusCode::NOT_FOUND));
+    }
+
+    Ok(scrape(&db).unwrap_or_else(|e| {
+        error!("Failed to scrape metrics: {:?}", e);
+        status(StatusCode::INTERNAL_SERVER_ERROR)
+    }))
+}
+
+/// Serve registered metrics at `/metrics` on the given address until the
+/// server fails
+pub(crate) async fn serve(addr: SocketAddr, db: db::Pool) {
+    let make_svc = make_service_fn(move |_| {
+        let db = db.clone();
+
+        async move { Ok::<_, Infallible>(service_fn(move |req| handle(db.clone(), req))) }
+    });
+
+    init();
+
+    info!("Serving metrics on {}", addr);
+
+    if let Err(e) = Server::bind(&addr).serve(make_svc).await {
+        error!("Metrics server failed: {:?}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn pool() -> db::Pool {
+        db::Pool::builder()
+            .max_size(1)
+            .connection_timeout(Duration::from_millis(100))
+            .build_unchecked(db::ConnectionManager::new("postgres://localhost:1/none"))
+    }
+
+    async fn get(path: &str) -> Response<Body> {
+        let req = Request::get(path).body(Body::empty()).unwrap();
+
+        handle(pool(), req).await.unwrap()
+    }
+
+    fn counter(text: &str, name: &str) -> u64 {
+        text.lines()
+            .find_map(|l| l.strip_prefix(name)?.strip_prefix(' '))
+            .unwrap_or_else(|| panic!("{} missing from scrape", name))
+            .parse()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn scrape_reports_counters() {
+        init();
+        PROCESSED.inc();
+        DEAD_LETTERED.inc_by(2);
+
+        let res = get("/metrics").await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        let text = std::str::from_utf8(&body).unwrap();
+
+        assert!(counter(text, "indexer_messages_processed_total") >= 1);
+        assert!(counter(text, "indexer_messages_dead_lettered_total") >= 2);
+        assert!(text.contains("\nindexer_messages_failed_total "));
+        assert!(text.contains("\nindexer_message_process_seconds_bucket"));
+        assert_eq!(counter(text, "indexer_db_pool_active_connections"), 0);
+    }
+
+    #[tokio::test]
+    async fn other_paths_are_not_found() {
+        assert_eq!(get("/").await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(get("/metrics/x").await.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,36 @@
+//! Support for discarding indexed data from slots rolled back by a fork
+
+use indexer_core::db::rollback_above_slot;
+
+use crate::{db::Pool, prelude::*};
+
+/// Delete rows written at any slot above `slot` and log how many were removed
+///
+/// Deleted rows are not reverted to their previous state, so nothing is
+/// removed unless `confirmed` is set.
+///
+/// # Errors
+/// This function fails if the slot is out of range, if the deletion was not
+/// confirmed, or if the rollback fails.
+pub async fn run(db: &Pool, slot: u64, confirmed: bool) -> Result<()> {
+    let slot: i64 = slot.try_into().context("Slot was too big to store")?;
+
+    ensure!(
+        confirmed,
+        "Rolling back deletes rows above slot {} rather than reverting them; pass --yes to \
+         confirm",
+        slot
+    );
+
+    let counts = db
+        .run(move |db| rollback_above_slot(db, slot))
+        .await
+        .context("Failed to roll back rows")?;
+
+    info!(
+        "Rolled back to slot {}: removed {} token account(s) and {} Twitter handle(s)",
+        slot, counts.token_accounts, counts.twitter_handle_name_services
+    );
+
+    Ok(())
+}
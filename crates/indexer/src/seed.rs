@@ -0,0 +1,289 @@
+//! Generation of representative sample data for local development
+//!
+//! Seeded NFTs share a single randomly generated verified creator, so they
+//! form one collection with a small set of traits, and are listed and sold on
+//! the seeded auction houses.  All addresses are random, so seeding the same
+//! database repeatedly adds new rows rather than conflicting with old ones.
+
+use indexer_core::db::{
+    insert_into,
+    models::{
+        AuctionHouse, ListingReceipt, Metadata, MetadataAttributeWrite, MetadataJson,
+        PurchaseReceipt,
+    },
+    queries,
+    tables::{
+        attributes, auction_houses, listing_receipts, metadata_jsons, metadatas, purchase_receipts,
+    },
+    upsert_batch, Connection,
+};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{db::Pool, prelude::*};
+
+/// Address of the wrapped SOL mint, used as the treasury mint of every seeded
+/// auction house
+const NATIVE_MINT: &str = "So11111111111111111111111111111111111111112";
+
+/// Trait types and values assigned to seeded NFTs
+const TRAITS: &[(&str, &[&str])] = &[
+    ("Background", &["Red", "Green", "Blue", "Gold"]),
+    ("Eyes", &["Open", "Closed", "Laser"]),
+    ("Hat", &["None", "Cap", "Crown"]),
+];
+
+/// Number of rows to seed in each table
+#[derive(Debug, Clone, Copy)]
+pub struct Counts {
+    /// Number of auction houses
+    pub auction_houses: usize,
+    /// Number of NFTs, each with a metadata JSON document and attributes
+    pub nfts: usize,
+    /// Number of NFTs with an active listing
+    pub listings: usize,
+    /// Number of sales
+    pub purchases: usize,
+}
+
+/// Counts of rows written by [`run`]
+#[derive(Debug, Clone, Copy, Default)]
+#[allow(missing_docs)]
+pub struct SeedCounts {
+    pub auction_houses: usize,
+    pub metadatas: usize,
+    pub metadata_jsons: usize,
+    pub attributes: usize,
+    pub listing_receipts: usize,
+    pub purchase_receipts: usize,
+}
+
+fn address() -> String {
+    Pubkey::new_from_array(rand::random()).to_string()
+}
+
+fn auction_house() -> AuctionHouse<'static> {
+    AuctionHouse {
+        address: Owned(address()),
+        treasury_mint: Borrowed(NATIVE_MINT),
+        auction_house_treasury: Owned(address()),
+        treasury_withdrawal_destination: Owned(address()),
+        fee_withdrawal_destination: Owned(address()),
+        authority: Owned(address()),
+        creator: Owned(address()),
+        bump: 255,
+        treasury_bump: 254,
+        fee_payer_bump: 253,
+        seller_fee_basis_points: 200,
+        requires_sign_off: false,
+        can_change_sale_price: false,
+        auction_house_fee_account: Owned(address()),
+    }
+}
+
+fn metadata(index: usize) -> Metadata<'static> {
+    Metadata {
+        address: Owned(address()),
+        name: Owned(format!("Seed #{}", index + 1)),
+        symbol: Borrowed("SEED"),
+        uri: Owned(format!("https://example.com/seed/{}.json", index + 1)),
+        seller_fee_basis_points: 500,
+        update_authority_address: Owned(address()),
+        mint_address: Owned(address()),
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+        edition_pda: Owned(address()),
+        token_standard: None,
+    }
+}
+
+fn seed(conn: &Connection, counts: Counts) -> Result<SeedCounts> {
+    let mut rng = rand::thread_rng();
+    let now = Local::now().naive_utc();
+    let creator = address();
+
+    let houses: Vec<_> = (0..counts.auction_houses.max(1))
+        .map(|_| auction_house())
+        .collect();
+    let metas: Vec<_> = (0..counts.nfts).map(metadata).collect();
+
+    let mut jsons = Vec::with_capacity(metas.len());
+    let mut attrs = Vec::with_capacity(metas.len() * TRAITS.len());
+
+    for meta in &metas {
+        let mut json_attrs = Vec::with_capacity(TRAITS.len());
+
+        for &(trait_type, values) in TRAITS {
+            let value = *values.choose(&mut rng).unwrap_or_else(|| unreachable!());
+
+            json_attrs.push(serde_json::json!({ "trait_type": trait_type, "value": value }));
+            attrs.push(MetadataAttributeWrite {
+                metadata_address: meta.address.clone(),
+                value: Some(Borrowed(value)),
+                trait_type: Some(Borrowed(trait_type)),
+                first_verified_creator: Some(Owned(creator.clone())),
+                trait_type_normalized: Some(Owned(queries::metadatas::normalize_trait_type(
+                    trait_type,
+                ))),
+            });
+        }
+
+        let image = format!("https://example.com/seed/{}.png", meta.address);
+
+        jsons.push(MetadataJson {
+            metadata_address: meta.address.clone(),
+            fingerprint: Owned(rand::random::<[u8; 32]>().to_vec()),
+            updated_at: now,
+            description: Some(Borrowed("Seeded for local development")),
+            image: Some(Owned(image.clone())),
+            animation_url: None,
+            external_url: None,
+            category: Some(Borrowed("image")),
+            raw_content: Owned(serde_json::json!({
+                "name": meta.name,
+                "symbol": meta.symbol,
+                "description": "Seeded for local development",
+                "image": image,
+                "attributes": json_attrs,
+            })),
+            model: Some(Borrowed("metaplex")),
+        });
+    }
+
+    let house_for = |rng: &mut rand::rngs::ThreadRng| {
+        houses
+            .choose(rng)
+            .unwrap_or_else(|| unreachable!())
+            .address
+            .clone()
+    };
+
+    let listings: Vec<_> = metas
+        .iter()
+        .take(counts.listings)
+        .map(|meta| ListingReceipt {
+            address: Owned(address()),
+            trade_state: Owned(address()),
+            bookkeeper: Owned(address()),
+            auction_house: house_for(&mut rng),
+            seller: Owned(address()),
+            metadata: meta.address.clone(),
+            purchase_receipt: None,
+            price: rng.gen_range(100_000_000..100_000_000_000),
+            token_size: 1,
+            bump: 255,
+            trade_state_bump: 254,
+            created_at: now,
+            canceled_at: None,
+        })
+        .collect();
+
+    let mut purchases = Vec::with_capacity(counts.purchases);
+
+    for _ in 0..counts.purchases {
+        let meta = match metas.choose(&mut rng) {
+            Some(m) => m,
+            None => break,
+        };
+
+        purchases.push(PurchaseReceipt {
+            address: Owned(address()),
+            bookkeeper: Owned(address()),
+            buyer: Owned(address()),
+            seller: Owned(address()),
+            auction_house: house_for(&mut rng),
+            metadata: meta.address.clone(),
+            token_size: 1,
+            price: rng.gen_range(100_000_000..100_000_000_000),
+            bump: 255,
+            created_at: now - chrono::Duration::minutes(rng.gen_range(0..60 * 24 * 30)),
+        });
+    }
+
+    conn.transaction(|| {
+        Ok(SeedCounts {
+            auction_houses: upsert_batch(conn, &houses, |db, chunk| {
+                insert_into(auction_houses::table).values(chunk).execute(db)
+            })?,
+            metadatas: upsert_batch(conn, &metas, |db, chunk| {
+                insert_into(metadatas::table).values(chunk).execute(db)
+            })?,
+            metadata_jsons: upsert_batch(conn, &jsons, |db, chunk| {
+                insert_into(metadata_jsons::table).values(chunk).execute(db)
+            })?,
+            attributes: upsert_batch(conn, &attrs, |db, chunk| {
+                insert_into(attributes::table).values(chunk).execute(db)
+            })?,
+            listing_receipts: upsert_batch(conn, &listings, |db, chunk| {
+                insert_into(listing_receipts::table)
+                    .values(chunk)
+                    .execute(db)
+            })?,
+            purchase_receipts: upsert_batch(conn, &purchases, |db, chunk| {
+                insert_into(purchase_receipts::table)
+                    .values(chunk)
+                    .execute(db)
+            })?,
+        })
+    })
+}
+
+/// Insert sample data into the database in a single transaction and log how
+/// many rows were written
+///
+/// # Errors
+/// This function fails if any insert fails, in which case nothing is written.
+pub async fn run(db: &Pool, counts: Counts) -> Result<()> {
+    let written = db
+        .run(move |db| seed(db, counts))
+        .await
+        .context("Failed to seed database")?;
+
+    info!("Seeded database: {:?}", written);
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "http"))]
+mod tests {
+    use super::*;
+    use crate::http::testing;
+
+    fn row_counts(db: &Connection) -> Result<[i64; 6]> {
+        Ok([
+            auction_houses::table.count().get_result(db)?,
+            metadatas::table.count().get_result(db)?,
+            metadata_jsons::table.count().get_result(db)?,
+            attributes::table.count().get_result(db)?,
+            listing_receipts::table.count().get_result(db)?,
+            purchase_receipts::table.count().get_result(db)?,
+        ])
+    }
+
+    #[tokio::test]
+    async fn seeds_requested_rows() {
+        if !testing::has_database() {
+            return;
+        }
+
+        let client = testing::client(testing::args(&"http://localhost/".parse().unwrap()));
+        let db = client.db();
+
+        let before = db.run(|db| row_counts(db)).await.unwrap();
+
+        run(db, Counts {
+            auction_houses: 2,
+            nfts: 5,
+            listings: 3,
+            purchases: 4,
+        })
+        .await
+        .unwrap();
+
+        let after = db.run(|db| row_counts(db)).await.unwrap();
+        let seeded: Vec<_> = after.iter().zip(before).map(|(a, b)| a - b).collect();
+
+        // Each NFT is assigned one value of every trait
+        assert_eq!(seeded, [2, 5, 5, 15, 3, 4]);
+    }
+}
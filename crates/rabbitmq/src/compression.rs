@@ -0,0 +1,138 @@
+//! Optional compression of AMQP message bodies
+//!
+//! The codec used for a message is recorded in its `content-encoding`
+//! property, allowing consumers to accept both compressed and uncompressed
+//! messages from a mixed fleet of producers.
+
+#[cfg(feature = "consumer")]
+use std::io::Read;
+#[cfg(feature = "producer")]
+use std::io::Write;
+
+#[cfg(any(feature = "producer", feature = "consumer"))]
+use crate::{Error, Result};
+
+/// A compression codec for message bodies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumString, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Compression {
+    /// Send message bodies uncompressed
+    None,
+    /// Compress message bodies with gzip
+    Gzip,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+impl Compression {
+    /// The `content-encoding` property value identifying this codec, if any
+    #[must_use]
+    pub fn content_encoding(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some("gzip"),
+        }
+    }
+
+    /// Look up a codec from a message's `content-encoding` property
+    ///
+    /// # Errors
+    /// This function fails if the encoding is not recognized.
+    #[cfg(feature = "consumer")]
+    pub fn from_content_encoding(encoding: Option<&str>) -> Result<Self> {
+        match encoding {
+            None | Some("" | "identity") => Ok(Self::None),
+            Some("gzip") => Ok(Self::Gzip),
+            Some(e) => Err(Error::UnsupportedEncoding(e.to_owned())),
+        }
+    }
+
+    /// Compress a serialized message body
+    #[cfg(feature = "producer")]
+    pub(crate) fn compress(self, data: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(data),
+            Self::Gzip => {
+                let mut enc =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+
+                enc.write_all(&data).map_err(Error::Compression)?;
+
+                enc.finish().map_err(Error::Compression)
+            },
+        }
+    }
+
+    /// Decompress a received message body
+    #[cfg(feature = "consumer")]
+    pub(crate) fn decompress(self, data: Vec<u8>) -> Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(data),
+            Self::Gzip => {
+                let mut vec = Vec::new();
+
+                flate2::read::GzDecoder::new(data.as_slice())
+                    .read_to_end(&mut vec)
+                    .map_err(Error::Compression)?;
+
+                Ok(vec)
+            },
+        }
+    }
+}
+
+#[cfg(all(test, feature = "producer", feature = "consumer"))]
+mod tests {
+    use super::Compression;
+
+    #[test]
+    fn round_trip() {
+        let body = br#"{"key":"value","data":[1,2,3,4,5,6,7,8]}"#.repeat(32);
+
+        for codec in [Compression::None, Compression::Gzip] {
+            let sent = codec.compress(body.clone()).unwrap();
+            let encoding = codec.content_encoding();
+            let received = Compression::from_content_encoding(encoding)
+                .unwrap()
+                .decompress(sent)
+                .unwrap();
+
+            assert_eq!(received, body, "{} round-trip", codec);
+        }
+    }
+
+    #[test]
+    fn gzip_shrinks_repetitive_bodies() {
+        let body = vec![b'a'; 4096];
+
+        assert!(Compression::Gzip.compress(body.clone()).unwrap().len() < body.len());
+    }
+
+    #[test]
+    fn content_encodings() {
+        assert_eq!(
+            Compression::from_content_encoding(None).unwrap(),
+            Compression::None
+        );
+        assert_eq!(
+            Compression::from_content_encoding(Some("identity")).unwrap(),
+            Compression::None
+        );
+        assert_eq!(
+            Compression::from_content_encoding(Some("gzip")).unwrap(),
+            Compression::Gzip
+        );
+        assert!(Compression::from_content_encoding(Some("br")).is_err());
+    }
+
+    #[test]
+    fn parse_codec_names() {
+        assert_eq!("gzip".parse::<Compression>().unwrap(), Compression::Gzip);
+        assert_eq!("none".parse::<Compression>().unwrap(), Compression::None);
+        assert!("zstd".parse::<Compression>().is_err());
+    }
+}
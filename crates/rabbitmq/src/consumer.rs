@@ -3,14 +3,20 @@
 use std::marker::PhantomData;
 
 use futures_util::StreamExt;
-use lapin::{acker::Acker, Connection};
+use lapin::{
+    acker::Acker,
+    options::{BasicCancelOptions, BasicRejectOptions},
+    types::DeliveryTag,
+    BasicProperties, Channel, Connection,
+};
+use log::warn;
 
-use crate::{serialize::deserialize, QueueType, Result};
+use crate::{serialize::deserialize, Compression, QueueType, Result};
 
 /// A consumer consisting of a configured AMQP consumer and queue config
 #[derive(Debug)]
 pub struct Consumer<Q> {
-    // chan: Channel,
+    chan: Channel,
     consumer: lapin::Consumer,
     // ty: Q,
     _p: PhantomData<Q>,
@@ -18,50 +24,99 @@ pub struct Consumer<Q> {
 
 impl<Q> Clone for Consumer<Q> {
     fn clone(&self) -> Self {
-        let Self { consumer, .. } = self;
+        let Self { chan, consumer, .. } = self;
 
         Self {
+            chan: chan.clone(),
             consumer: consumer.clone(),
             ..*self
         }
     }
 }
 
+impl<Q> Consumer<Q> {
+    /// Ask the server to stop sending deliveries to this consumer.  Deliveries
+    /// already received may still be read and acknowledged.
+    ///
+    /// # Errors
+    /// This function fails if the cancellation cannot be sent.
+    pub async fn cancel(&self) -> Result<()> {
+        self.chan
+            .basic_cancel(self.consumer.tag().as_str(), BasicCancelOptions::default())
+            .await
+            .map_err(Into::into)
+    }
+}
+
 impl<Q: QueueType> Consumer<Q>
 where
     Q::Message: for<'a> serde::Deserialize<'a>,
 {
     /// Construct a new consumer from a [`QueueType`]
     ///
+    /// If `prefetch` is given it overrides the queue type's default limit on
+    /// unacknowledged deliveries.
+    ///
     /// # Errors
     /// This function fails if the consumer cannot be created and configured
     /// successfully.
-    pub async fn new(conn: &Connection, ty: Q, tag: impl AsRef<str>) -> Result<Self> {
+    pub async fn new(
+        conn: &Connection,
+        ty: Q,
+        tag: impl AsRef<str>,
+        prefetch: Option<u16>,
+    ) -> Result<Self> {
         let chan = conn.create_channel().await?;
 
-        let consumer = ty.info().init_consumer(&chan, tag).await?;
+        let consumer = ty.info().init_consumer(&chan, tag, prefetch).await?;
 
         Ok(Self {
-            // chan,
+            chan,
             consumer,
             // ty,
             _p: PhantomData::default(),
         })
     }
 
-    /// Receive a single message from this consumer
+    /// Receive a single message from this consumer, along with the tag
+    /// identifying its delivery on this consumer's channel
+    ///
+    /// Deliveries whose payload cannot be decompressed or deserialized are
+    /// rejected without requeueing, sending them to the dead-letter exchange
+    /// for retry and eventual parking, and are skipped.
     ///
     /// # Errors
     /// This function fails if the delivery cannot be successfully performed or
-    /// the payload cannot be deserialized.
-    pub async fn read(&mut self) -> Result<Option<(Q::Message, Acker)>> {
-        let delivery = match self.consumer.next().await {
-            Some(d) => d?,
-            None => return Ok(None),
-        };
+    /// a malformed delivery cannot be rejected.
+    pub async fn read(&mut self) -> Result<Option<(Q::Message, Acker, DeliveryTag)>> {
+        loop {
+            let delivery = match self.consumer.next().await {
+                Some(d) => d?,
+                None => return Ok(None),
+            };
+
+            match Self::decode(&delivery.properties, delivery.data) {
+                Ok(data) => return Ok(Some((data, delivery.acker, delivery.delivery_tag))),
+                Err(e) => {
+                    warn!("Rejecting malformed AMQP delivery: {:?}", e);
+
+                    delivery
+                        .acker
+                        .reject(BasicRejectOptions { requeue: false })
+                        .await?;
+                },
+            }
+        }
+    }
 
-        let data = deserialize(std::io::Cursor::new(delivery.data))?;
+    fn decode(properties: &BasicProperties, data: Vec<u8>) -> Result<Q::Message> {
+        let compression = Compression::from_content_encoding(
+            properties
+                .content_encoding()
+                .as_ref()
+                .map(lapin::types::ShortString::as_str),
+        )?;
 
-        Ok(Some((data, delivery.acker)))
+        deserialize(std::io::Cursor::new(compression.decompress(data)?)).map_err(Into::into)
     }
 }
@@ -1,6 +1,9 @@
 //! Handler for an AMQP dead-letter consumer configured from a [`QueueType`]
 
-use std::{collections::BTreeMap, time::Duration};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use futures_util::StreamExt;
 use lapin::{
@@ -12,10 +15,11 @@ use lapin::{
 use log::{debug, error, trace, warn};
 
 use crate::{
-    queue_type::{DLX_DEAD_KEY, DLX_LIVE_KEY},
+    queue_type::{DLX_DEAD_KEY, DLX_LIVE_KEY, DLX_PARKED_KEY},
     QueueType, Result,
 };
 
+#[derive(Debug, PartialEq, Eq)]
 enum RetryAction {
     DropUnexpected,
     DropMaxlen,
@@ -83,9 +87,48 @@ fn parse_x_death(
     }
 }
 
-async fn try_consume<Q: QueueType>(conn: &Connection, ty: &Q) -> Result<()> {
+/// Attach headers describing why a message was parked, preserving any headers
+/// it already carries
+fn failure_headers(
+    headers: Option<&FieldTable>,
+    queue: &str,
+    deaths: u64,
+    reason: &str,
+) -> FieldTable {
+    let mut headers = headers.cloned().unwrap_or_default();
+
+    headers.insert(
+        "x-failure-count".into(),
+        AMQPValue::LongLongInt(deaths.try_into().unwrap_or(i64::MAX)),
+    );
+    headers.insert(
+        "x-failure-queue".into(),
+        AMQPValue::LongString(queue.into()),
+    );
+    headers.insert(
+        "x-failure-reason".into(),
+        AMQPValue::LongString(reason.into()),
+    );
+    headers.insert(
+        "x-parked-at".into(),
+        AMQPValue::Timestamp(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        ),
+    );
+
+    headers
+}
+
+async fn try_consume<Q: QueueType>(
+    conn: &Connection,
+    ty: &Q,
+    max_tries: Option<u64>,
+    on_parked: &impl Fn(),
+) -> Result<()> {
     let chan = conn.create_channel().await?;
-    let (mut consumer, inf) = ty.info().init_dl_consumer(&chan).await?;
+    let (mut consumer, inf) = ty.info().init_dl_consumer(&chan, max_tries).await?;
 
     while let Some(del) = consumer.next().await {
         let del = del?;
@@ -128,8 +171,27 @@ async fn try_consume<Q: QueueType>(conn: &Connection, ty: &Q) -> Result<()> {
                 }
             },
             RetryAction::Retry(r) => {
-                // We hit the retry limit.  Bye-bye!
-                trace!("Dropping dead letter after {} deaths", r);
+                // We hit the retry limit, park the message for manual inspection
+                warn!("Parking dead letter after {} deaths", r);
+
+                let headers = failure_headers(
+                    properties.headers().as_ref(),
+                    inf.queue(),
+                    r,
+                    "max_tries_exceeded",
+                );
+                properties = properties.with_headers(headers);
+
+                chan.basic_publish(
+                    inf.exchange(),
+                    DLX_PARKED_KEY,
+                    BasicPublishOptions::default(),
+                    &data,
+                    properties,
+                )
+                .await?;
+
+                on_parked();
             },
             RetryAction::RedeliverLive => {
                 trace!("Redelivering dead letter");
@@ -152,13 +214,20 @@ async fn try_consume<Q: QueueType>(conn: &Connection, ty: &Q) -> Result<()> {
 }
 
 /// Run the dead-letter consumer for a [`QueueType`]
+///
+/// Failed messages are retried with an increasing delay until they have
+/// failed `max_tries` times (or the queue type's default if `None`), after
+/// which they are moved to the `<queue>.dlx` queue with `x-failure-*` headers
+/// describing the failure, and `on_parked` is called.
 pub async fn run<Q: QueueType, S: std::future::Future<Output = ()>>(
     conn: impl std::borrow::Borrow<Connection>,
     ty: Q,
+    max_tries: Option<u64>,
     sleep: impl Fn(Duration) -> S,
+    on_parked: impl Fn(),
 ) {
     loop {
-        match try_consume(conn.borrow(), &ty).await {
+        match try_consume(conn.borrow(), &ty, max_tries, &on_parked).await {
             Ok(()) => (),
             Err(e) => {
                 log::error!("Dead-letter consumer failed: {:?}", e);
@@ -167,3 +236,105 @@ pub async fn run<Q: QueueType, S: std::future::Future<Output = ()>>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use lapin::types::FieldArray;
+
+    use super::*;
+
+    fn death(reason: &str, queue: &str, count: i64) -> AMQPValue {
+        let mut table = FieldTable::default();
+        table.insert("reason".into(), AMQPValue::LongString(reason.into()));
+        table.insert("queue".into(), AMQPValue::LongString(queue.into()));
+        table.insert("count".into(), AMQPValue::LongLongInt(count));
+
+        AMQPValue::FieldTable(table)
+    }
+
+    fn headers(deaths: Vec<AMQPValue>) -> FieldTable {
+        let mut headers = FieldTable::default();
+        headers.insert(
+            "x-death".into(),
+            AMQPValue::FieldArray(FieldArray::from(deaths)),
+        );
+
+        headers
+    }
+
+    fn action(deaths: Vec<AMQPValue>) -> RetryAction {
+        parse_x_death(Some(headers(deaths).inner()), "live", "live.dlq")
+    }
+
+    #[test]
+    fn retries_count_live_deaths() {
+        assert_eq!(
+            action(vec![death("rejected", "live", 1)]),
+            RetryAction::Retry(1)
+        );
+        assert_eq!(
+            action(vec![
+                death("rejected", "live", 2),
+                death("delivery_limit", "live", 1),
+                death("expired", "live.dlq", 2),
+            ]),
+            RetryAction::Retry(3)
+        );
+    }
+
+    #[test]
+    fn expired_retries_are_redelivered() {
+        assert_eq!(
+            action(vec![
+                death("rejected", "live", 2),
+                death("expired", "live.dlq", 2),
+            ]),
+            RetryAction::RedeliverLive
+        );
+        assert_eq!(
+            parse_x_death(None, "live", "live.dlq"),
+            RetryAction::RedeliverLive
+        );
+    }
+
+    #[test]
+    fn unexpected_deaths_are_dropped() {
+        assert_eq!(
+            action(vec![death("maxlen", "live", 1)]),
+            RetryAction::DropMaxlen
+        );
+        assert_eq!(
+            action(vec![death("rejected", "elsewhere", 1)]),
+            RetryAction::DropUnexpected
+        );
+    }
+
+    #[test]
+    fn failure_headers_record_the_retry_count() {
+        let existing = headers(vec![death("rejected", "live", 5)]);
+        let headers = failure_headers(Some(&existing), "live", 5, "max_tries_exceeded");
+        let headers = headers.inner();
+
+        assert!(headers.contains_key("x-death"));
+        assert_eq!(
+            headers.get("x-failure-count"),
+            Some(&AMQPValue::LongLongInt(5))
+        );
+        assert_eq!(
+            headers.get("x-failure-queue"),
+            Some(&AMQPValue::LongString("live".into()))
+        );
+        assert_eq!(
+            headers.get("x-failure-reason"),
+            Some(&AMQPValue::LongString("max_tries_exceeded".into()))
+        );
+        assert!(matches!(
+            headers.get("x-parked-at"),
+            Some(AMQPValue::Timestamp(t)) if *t > 0
+        ));
+        assert_eq!(
+            parse_x_death(Some(headers), "live", "live.dlq"),
+            RetryAction::Retry(5)
+        );
+    }
+}
@@ -34,6 +34,8 @@ pub enum EntityId {
     MetadataJson,
     /// Identifier for [StoreConfig] entities
     StoreConfig,
+    /// Identifier for [CollectionJson] entities
+    CollectionJson,
 }
 
 /// Type hints for declaring and using entity-specific exchanges and queues
@@ -77,6 +79,21 @@ impl Entity for StoreConfig {
     const ID: EntityId = EntityId::StoreConfig;
 }
 
+/// Fetch the off-chain JSON for a Metaplex collection NFT
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionJson {
+    /// The mint of the collection NFT
+    pub collection_mint: Pubkey,
+    /// The URI to retrieve the file from
+    pub uri: String,
+}
+
+impl Entity for CollectionJson {
+    type Id = EntityId;
+
+    const ID: EntityId = EntityId::CollectionJson;
+}
+
 impl<E: Entity> QueueType<E> {
     /// Construct a new queue configuration given an optional queue suffix
     #[must_use]
@@ -32,11 +32,18 @@ pub enum Error {
     /// An error occurred related to a queue's configuration
     #[error("Invalid queue type for operation: {0}")]
     InvalidQueueType(&'static str),
+    /// An I/O error occurred while compressing or decompressing a message
+    #[error("Compression error: {0:?}")]
+    Compression(std::io::Error),
+    /// A message was received with an unknown `content-encoding`
+    #[error("Unsupported message encoding: {0:?}")]
+    UnsupportedEncoding(String),
 }
 
 #[allow(dead_code)]
 pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
 
+pub mod compression;
 #[cfg(feature = "consumer")]
 pub mod consumer;
 #[cfg(feature = "consumer")]
@@ -50,4 +57,5 @@ pub mod producer;
 mod queue_type;
 mod serialize;
 
+pub use compression::Compression;
 pub use queue_type::QueueType;
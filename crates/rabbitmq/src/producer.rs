@@ -2,13 +2,14 @@
 
 use lapin::{Channel, Connection};
 
-use crate::{serialize::serialize, QueueType, Result};
+use crate::{serialize::serialize, Compression, QueueType, Result};
 
 /// A producer consisting of a configured channel and additional queue config
 #[derive(Debug)]
 pub struct Producer<Q> {
     chan: Channel,
     ty: Q,
+    compression: Compression,
 }
 
 impl<Q: QueueType> Producer<Q>
@@ -25,21 +26,39 @@ where
 
         ty.info().init_producer(&chan).await?;
 
-        Ok(Self { chan, ty })
+        Ok(Self {
+            chan,
+            ty,
+            compression: Compression::default(),
+        })
+    }
+
+    /// Set the codec used to compress message bodies sent by this producer
+    #[must_use]
+    pub fn with_compression(self, compression: Compression) -> Self {
+        Self {
+            compression,
+            ..self
+        }
     }
 
     /// Write a single message to this producer
     ///
     /// # Errors
-    /// This function fails if the value cannot be serialized or the serialized
-    /// payload cannot be transmitted.
+    /// This function fails if the value cannot be serialized or compressed, or
+    /// the resulting payload cannot be transmitted.
     pub async fn write(&self, val: impl std::borrow::Borrow<Q::Message>) -> Result<()> {
         let val = val.borrow();
 
         let mut vec = Vec::new();
         serialize(&mut vec, val)?;
+        let vec = self.compression.compress(vec)?;
 
-        self.ty.info().publish(&self.chan, &vec).await?.await?;
+        self.ty
+            .info()
+            .publish(&self.chan, &vec, self.compression)
+            .await?
+            .await?;
 
         Ok(())
     }
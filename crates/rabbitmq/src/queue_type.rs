@@ -1,7 +1,10 @@
 use std::time::Duration;
 
 #[cfg(feature = "producer")]
-use lapin::{options::BasicPublishOptions, publisher_confirm::PublisherConfirm, BasicProperties};
+use {
+    crate::Compression,
+    lapin::{options::BasicPublishOptions, publisher_confirm::PublisherConfirm, BasicProperties},
+};
 #[cfg(feature = "consumer")]
 use {
     crate::Error,
@@ -78,6 +81,8 @@ pub const DLX_DEAD_KEY: &str = "dead";
 pub const DLX_LIVE_KEY: &str = "live";
 #[cfg(feature = "consumer")]
 pub const DLX_TRIAGE_KEY: &str = "triage";
+#[cfg(feature = "consumer")]
+pub const DLX_PARKED_KEY: &str = "parked";
 
 #[cfg(any(feature = "producer", feature = "consumer"))]
 impl<'a> QueueInfo<'a> {
@@ -105,13 +110,24 @@ impl<'a> QueueInfo<'a> {
         Ok(())
     }
 
-    pub(crate) async fn publish(self, chan: &Channel, data: &[u8]) -> Result<PublisherConfirm> {
+    pub(crate) async fn publish(
+        self,
+        chan: &Channel,
+        data: &[u8],
+        compression: Compression,
+    ) -> Result<PublisherConfirm> {
+        let mut properties = BasicProperties::default();
+
+        if let Some(encoding) = compression.content_encoding() {
+            properties = properties.with_content_encoding(encoding.into());
+        }
+
         chan.basic_publish(
             self.0.exchange.as_ref(),
             self.0.queue.as_ref(),
             BasicPublishOptions::default(),
             data,
-            BasicProperties::default(),
+            properties,
         )
         .await
         .map_err(Into::into)
@@ -132,6 +148,10 @@ impl<'a> QueueInfo<'a> {
         format!("triage.dlq.{}", self.0.queue)
     }
 
+    fn dl_parked_queue(self) -> String {
+        format!("{}.dlx", self.0.queue)
+    }
+
     async fn queue_declare(self, chan: &Channel) -> Result<()> {
         let mut queue_fields = FieldTable::default();
 
@@ -197,10 +217,17 @@ impl<'a> QueueInfo<'a> {
         Ok((exchg, self.dl_queue(), self.dl_triage_queue()))
     }
 
+    /// Get the number of unacknowledged deliveries a consumer may hold, which
+    /// is `prefetch` if given or else the queue type's default
+    fn prefetch_count(self, prefetch: Option<u16>) -> u16 {
+        prefetch.unwrap_or(self.0.prefetch)
+    }
+
     pub(crate) async fn init_consumer(
         self,
         chan: &Channel,
         tag: impl AsRef<str>,
+        prefetch: Option<u16>,
     ) -> Result<Consumer> {
         self.dl_exchange_declare(chan).await?;
         self.exchange_declare(chan).await?;
@@ -215,7 +242,7 @@ impl<'a> QueueInfo<'a> {
         )
         .await?;
 
-        chan.basic_qos(self.0.prefetch, BasicQosOptions::default())
+        chan.basic_qos(self.prefetch_count(prefetch), BasicQosOptions::default())
             .await?;
 
         chan.basic_consume(
@@ -231,6 +258,7 @@ impl<'a> QueueInfo<'a> {
     pub(crate) async fn init_dl_consumer(
         self,
         chan: &Channel,
+        max_tries: Option<u64>,
     ) -> Result<(Consumer, DlConsumerInfo)> {
         let (exchange, queue, triage_queue) = self.dl_exchange_declare(chan).await?;
 
@@ -280,8 +308,6 @@ impl<'a> QueueInfo<'a> {
                 AMQPValue::LongLongInt(self.0.max_len_bytes.min(100 * 1024 * 1024)),
             );
 
-            // TODO: add a true DL queue
-
             chan.queue_declare(
                 triage_queue.as_ref(),
                 QueueDeclareOptions {
@@ -302,6 +328,35 @@ impl<'a> QueueInfo<'a> {
             .await?;
         }
 
+        {
+            let mut queue_fields = FieldTable::default();
+            queue_fields.insert(
+                "x-max-length-bytes".into(),
+                AMQPValue::LongLongInt(self.0.max_len_bytes.min(100 * 1024 * 1024)),
+            );
+
+            let parked_queue = self.dl_parked_queue();
+
+            chan.queue_declare(
+                parked_queue.as_ref(),
+                QueueDeclareOptions {
+                    auto_delete: self.0.auto_delete,
+                    ..QueueDeclareOptions::default()
+                },
+                queue_fields,
+            )
+            .await?;
+
+            chan.queue_bind(
+                parked_queue.as_ref(),
+                exchange.as_ref(),
+                DLX_PARKED_KEY,
+                QueueBindOptions::default(),
+                FieldTable::default(),
+            )
+            .await?;
+        }
+
         self.queue_declare(chan).await?;
         chan.queue_bind(
             self.0.queue.as_ref(),
@@ -324,11 +379,15 @@ impl<'a> QueueInfo<'a> {
             )
             .await?;
 
-        let retry = self
+        let mut retry = self
             .0
             .retry
             .ok_or(crate::Error::InvalidQueueType("Missing retry properties"))?;
 
+        if let Some(max_tries) = max_tries {
+            retry.max_tries = max_tries;
+        }
+
         Ok((consumer, DlConsumerInfo {
             exchange,
             retry,
@@ -373,3 +432,29 @@ impl DlConsumerInfo {
         millis.try_into().ok()
     }
 }
+
+#[cfg(all(test, feature = "consumer"))]
+mod tests {
+    use super::*;
+
+    fn props() -> QueueProps {
+        QueueProps {
+            exchange: "exchange".into(),
+            queue: "queue".into(),
+            binding: Binding::Fanout,
+            prefetch: 1024,
+            max_len_bytes: 100 * 1024 * 1024,
+            auto_delete: false,
+            retry: None,
+        }
+    }
+
+    #[test]
+    fn prefetch_overrides_default() {
+        let props = props();
+        let info = QueueInfo::from(&props);
+
+        assert_eq!(info.prefetch_count(None), 1024);
+        assert_eq!(info.prefetch_count(Some(16)), 16);
+    }
+}